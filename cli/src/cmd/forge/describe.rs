@@ -0,0 +1,172 @@
+//! Describes the matched tests as a test plan, without running them
+//!
+//! `forge test --describe` prints the matched tests grouped by source file and test contract,
+//! annotated with their kind and the `@notice` of their NatSpec, if any:
+//!
+//! ```text
+//! test/Vault.t.sol
+//!   VaultTest
+//!     - testDeposit() [unit]
+//!         Deposits are credited to the sender
+//!     - testWithdraw(uint256) [fuzz]
+//! ```
+//!
+//! The notices are taken from the `userdoc` the compiler outputs for the test contracts, so
+//! doc comment lines without a tag are notices and inherited tests keep their notices.
+use ethers::solc::ProjectCompileOutput;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// A test in the [TestPlan]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DescribedTest {
+    /// The signature of the test
+    pub signature: String,
+    /// Whether the test is a fuzz test
+    pub is_fuzz: bool,
+    /// The `@notice` of the test, if any
+    pub notice: Option<String>,
+}
+
+/// The matched tests, by source file and test contract
+#[derive(Debug, Clone, Default)]
+pub struct TestPlan {
+    files: BTreeMap<PathBuf, BTreeMap<String, Vec<DescribedTest>>>,
+}
+
+impl TestPlan {
+    /// Adds a test of the contract in the source file
+    pub fn add(&mut self, source: &Path, contract: &str, test: DescribedTest) {
+        self.files
+            .entry(source.to_path_buf())
+            .or_default()
+            .entry(contract.to_string())
+            .or_default()
+            .push(test);
+    }
+
+    /// Returns the number of tests in the plan
+    pub fn len(&self) -> usize {
+        self.files.values().flat_map(BTreeMap::values).map(Vec::len).sum()
+    }
+
+    /// Returns `true` if the plan has no tests
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Display for TestPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (source, contracts) in &self.files {
+            writeln!(f, "{}", source.display())?;
+            for (contract, tests) in contracts {
+                writeln!(f, "  {}", contract)?;
+                for test in tests {
+                    let kind = if test.is_fuzz { "fuzz" } else { "unit" };
+                    writeln!(f, "    - {} [{}]", test.signature, kind)?;
+                    if let Some(ref notice) = test.notice {
+                        for line in notice.lines() {
+                            writeln!(f, "        {}", line)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the `@notice` of every documented function of the compiled contracts, by contract
+/// identifier (`<path>:<contract>`, relative to `root`) and function signature
+///
+/// The notices are read from the `userdoc` output of the compiler, which is only part of the
+/// artifacts if it was selected as extra output.
+pub fn function_notices(
+    output: ProjectCompileOutput,
+    root: &Path,
+) -> BTreeMap<String, BTreeMap<String, String>> {
+    output
+        .with_stripped_file_prefixes(root)
+        .into_artifacts()
+        .map(|(id, artifact)| (id.identifier(), method_notices(&artifact)))
+        .filter(|(_, notices)| !notices.is_empty())
+        .collect()
+}
+
+/// Returns the `@notice` of the functions in the `userdoc` of the artifact, by signature
+fn method_notices(artifact: &impl Serialize) -> BTreeMap<String, String> {
+    let artifact = serde_json::to_value(artifact).unwrap_or_default();
+    artifact["userdoc"]["methods"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(signature, method)| {
+            let notice = method["notice"].as_str()?.trim();
+            (!notice.is_empty()).then(|| (signature.clone(), notice.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_read_method_notices() {
+        let artifact = serde_json::json!({
+            "abi": [],
+            "userdoc": {
+                "kind": "user",
+                "methods": {
+                    "testDeposit()": { "notice": "Deposits are credited\nto the sender" },
+                    "testWithdraw(uint256)": { "notice": " " },
+                },
+                "notice": "Tests of the vault",
+                "version": 1
+            }
+        });
+        assert_eq!(
+            method_notices(&artifact),
+            BTreeMap::from([(
+                "testDeposit()".to_string(),
+                "Deposits are credited\nto the sender".to_string()
+            )])
+        );
+        assert!(method_notices(&serde_json::json!({ "abi": [] })).is_empty());
+    }
+
+    #[test]
+    fn can_display_test_plan() {
+        let mut plan = TestPlan::default();
+        let source = Path::new("test/Vault.t.sol");
+        plan.add(
+            source,
+            "VaultTest",
+            DescribedTest {
+                signature: "testDeposit()".to_string(),
+                is_fuzz: false,
+                notice: Some("Deposits are credited".to_string()),
+            },
+        );
+        plan.add(
+            source,
+            "VaultTest",
+            DescribedTest {
+                signature: "testWithdraw(uint256)".to_string(),
+                is_fuzz: true,
+                notice: None,
+            },
+        );
+        assert_eq!(plan.len(), 2);
+        assert_eq!(
+            plan.to_string(),
+            "test/Vault.t.sol\n  VaultTest\n    - testDeposit() [unit]\n        Deposits are \
+             credited\n    - testWithdraw(uint256) [fuzz]\n"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! Runs example code from the NatSpec of contracts as tests
+//!
+//! Only `@custom:example` tags in the doc comments of contracts, libraries and interfaces are
+//! recognized, tags on functions or other declarations are ignored. The example extends until the
+//! next tag or the end of the comment and may be wrapped in a ```` ``` ```` fence:
+//!
+//! ```solidity
+//! /// @title A counter
+//! /// @custom:example
+//! /// Counter counter = new Counter();
+//! /// counter.increment();
+//! /// assert(counter.number() == 1);
+//! contract Counter { .. }
+//! ```
+//!
+//! Every example becomes the body of a test function in a test contract that imports the source
+//! file of the documented contract. The example passes if it does not revert.
+use crate::utils::FoundryPathExt;
+use eyre::WrapErr;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// An example from the NatSpec of a contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocExample {
+    /// The name of the documented contract
+    pub contract: String,
+    /// The code of the example
+    pub code: String,
+}
+
+/// Extracts the `@custom:example` blocks of all contracts in the given source code
+pub fn extract_examples(source: &str) -> Vec<DocExample> {
+    let lines = source.lines().map(str::trim).collect::<Vec<_>>();
+    let mut examples = Vec::new();
+    for (decl, line) in lines.iter().enumerate() {
+        let contract = match declared_contract(line) {
+            Some(contract) => contract,
+            None => continue,
+        };
+
+        // the doc comment consists of the comment lines directly above the declaration
+        let mut doc = lines[..decl]
+            .iter()
+            .rev()
+            .take_while(|line| {
+                line.starts_with("///") || line.starts_with("/**") || line.starts_with('*')
+            })
+            .map(|line| strip_comment(line))
+            .collect::<Vec<_>>();
+        doc.reverse();
+
+        let mut code: Option<Vec<&str>> = None;
+        for line in doc {
+            if let Some(rest) = line.strip_prefix("@custom:example") {
+                finish_example(&mut examples, contract, code.take());
+                code = Some(vec![rest.trim()]);
+            } else if line.starts_with('@') {
+                finish_example(&mut examples, contract, code.take());
+            } else if let Some(ref mut code) = code {
+                code.push(line);
+            }
+        }
+        finish_example(&mut examples, contract, code);
+    }
+    examples
+}
+
+/// Returns the name of the contract, library or interface declared in the line, if any
+fn declared_contract(line: &str) -> Option<&str> {
+    let mut tokens = line.split_whitespace().skip_while(|token| *token == "abstract");
+    match tokens.next() {
+        Some("contract") | Some("library") | Some("interface") => {
+            tokens.next().map(|token| token.trim_end_matches('{'))
+        }
+        _ => None,
+    }
+}
+
+/// Strips the comment markers of a doc comment line
+fn strip_comment(line: &str) -> &str {
+    let line = line.trim_end_matches("*/");
+    let line = line
+        .strip_prefix("///")
+        .or_else(|| line.strip_prefix("/**"))
+        .or_else(|| line.strip_prefix('*'))
+        .unwrap_or(line);
+    line.trim()
+}
+
+/// Adds the example with the given lines, if it has any code
+fn finish_example(examples: &mut Vec<DocExample>, contract: &str, lines: Option<Vec<&str>>) {
+    let code = lines
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|line| !line.is_empty() && !line.starts_with("```"))
+        .collect::<Vec<_>>();
+    if !code.is_empty() {
+        examples.push(DocExample { contract: contract.to_string(), code: code.join("\n") });
+    }
+}
+
+/// Returns the `pragma solidity` directive of the source, if any
+fn solidity_pragma(source: &str) -> Option<&str> {
+    source.lines().map(str::trim).find(|line| line.starts_with("pragma solidity"))
+}
+
+/// Writes a test contract for every example in the non-test sources of `sources` to `dir`, the
+/// directory is cleared first.
+///
+/// Returns the paths of the written test files.
+pub fn write_doctests(sources: &Path, dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir)
+        .wrap_err_with(|| format!("Failed to create doctest directory {}", dir.display()))?;
+
+    let mut files = Vec::new();
+    for path in ethers::solc::utils::source_files(sources) {
+        if path.is_sol_test() {
+            continue
+        }
+        let source = fs::read_to_string(&path)?;
+        for example in extract_examples(&source) {
+            let name = format!("{}Example{}", example.contract, files.len());
+            let test = format!(
+                "// SPDX-License-Identifier: UNLICENSED\n\
+                 // Generated from the NatSpec of {} in {}\n\
+                 {}\n\
+                 import \"{}\";\n\n\
+                 contract {} {{\n    function testExample() public {{\n        {}\n    }}\n}}\n",
+                example.contract,
+                path.display(),
+                solidity_pragma(&source).unwrap_or("pragma solidity >=0.0.0;"),
+                path.display(),
+                name,
+                example.code.replace('\n', "\n        ")
+            );
+            let file = dir.join(format!("{}.t.sol", name));
+            fs::write(&file, test)?;
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_extract_examples() {
+        let source = r#"
+pragma solidity ^0.8.0;
+
+/// @title A counter
+/// @custom:example
+/// Counter counter = new Counter();
+/// counter.increment();
+/// @custom:example assert(true);
+/// @author someone
+contract Counter {
+    /// @custom:example ignored();
+    function increment() public {}
+}
+
+/**
+ * @custom:example
+ * ```
+ * Math.add(1, 2);
+ * ```
+ */
+library Math {}
+"#;
+        assert_eq!(
+            extract_examples(source),
+            vec![
+                DocExample {
+                    contract: "Counter".to_string(),
+                    code: "Counter counter = new Counter();\ncounter.increment();".to_string()
+                },
+                DocExample { contract: "Counter".to_string(), code: "assert(true);".to_string() },
+                DocExample { contract: "Math".to_string(), code: "Math.add(1, 2);".to_string() },
+            ]
+        );
+        assert_eq!(solidity_pragma(source), Some("pragma solidity ^0.8.0;"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Reports metrics of test runs to StatsD or a webhook as the test contracts complete
+//!
+//! `forge test --metrics-endpoint <endpoint>` sends the metrics of every test contract as soon as
+//! its tests completed:
+//!
+//! - `statsd://<host>:<port>`: StatsD metrics over UDP with DogStatsD style `suite` and `test`
+//!   tags, see [SuiteMetrics::statsd_lines()]
+//! - `http://...` or `https://...`: the [SuiteMetrics] of the contract as JSON, POSTed to the URL
+//!
+//! Metrics are sent from a background thread, failures to send them are logged and never affect
+//! the run. Requires forge to be built with the `metrics` feature.
+use forge::SuiteResult;
+use serde::Serialize;
+use std::{
+    fmt,
+    str::FromStr,
+    sync::mpsc::{channel, Sender},
+    thread::{self, JoinHandle},
+};
+
+/// Where metrics are sent to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricsEndpoint {
+    /// A StatsD server, `host:port`
+    StatsD(String),
+    /// A webhook URL
+    Webhook(String),
+}
+
+impl FromStr for MetricsEndpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(addr) = s.strip_prefix("statsd://") {
+            if addr
+                .rsplit_once(':')
+                .map_or(true, |(host, port)| host.is_empty() || port.parse::<u16>().is_err())
+            {
+                return Err(format!("Invalid StatsD endpoint `{}`, expected `host:port`", s))
+            }
+            Ok(MetricsEndpoint::StatsD(addr.to_string()))
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(MetricsEndpoint::Webhook(s.to_string()))
+        } else {
+            Err(format!(
+                "Invalid metrics endpoint `{}`, expected `statsd://<host>:<port>` or an HTTP URL",
+                s
+            ))
+        }
+    }
+}
+
+impl fmt::Display for MetricsEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricsEndpoint::StatsD(addr) => write!(f, "statsd://{}", addr),
+            MetricsEndpoint::Webhook(url) => f.write_str(url),
+        }
+    }
+}
+
+/// The metrics of a single test
+#[derive(Debug, Clone, Serialize)]
+pub struct TestMetrics {
+    /// The signature of the test
+    pub test: String,
+    pub success: bool,
+    /// The gas used by the test, the median for fuzz tests
+    pub gas: u64,
+    pub duration_ms: u128,
+}
+
+/// The metrics of a test contract
+#[derive(Debug, Clone, Serialize)]
+pub struct SuiteMetrics {
+    /// The identifier of the test contract, `<path>:<contract>`
+    pub suite: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub duration_ms: u128,
+    pub tests: Vec<TestMetrics>,
+}
+
+impl SuiteMetrics {
+    pub fn new(suite: &str, result: &SuiteResult) -> Self {
+        let tests = result
+            .test_results
+            .iter()
+            .map(|(test, result)| TestMetrics {
+                test: test.clone(),
+                success: result.success,
+                gas: result.kind.gas_used().gas(),
+                duration_ms: result.duration.as_millis(),
+            })
+            .collect::<Vec<_>>();
+        let passed = tests.iter().filter(|test| test.success).count();
+        Self {
+            suite: suite.to_string(),
+            passed,
+            failed: tests.len() - passed,
+            duration_ms: result.duration.as_millis(),
+            tests,
+        }
+    }
+
+    /// Returns the StatsD lines of the metrics:
+    ///
+    /// - `forge.suite.duration` in ms, tagged with the name of the contract
+    /// - `forge.test.passed` or `forge.test.failed` as a counter, `forge.test.gas` as a gauge and
+    ///   `forge.test.duration` in ms for every test, tagged with the names of the contract and the
+    ///   test
+    pub fn statsd_lines(&self) -> Vec<String> {
+        let contract = tag_value(self.suite.rsplit(':').next().unwrap_or(&self.suite));
+        let mut lines =
+            vec![format!("forge.suite.duration:{}|ms|#suite:{}", self.duration_ms, contract)];
+        for test in &self.tests {
+            let name = test.test.split('(').next().unwrap_or(&test.test);
+            let tags = format!("#suite:{},test:{}", contract, tag_value(name));
+            let outcome = if test.success { "passed" } else { "failed" };
+            lines.push(format!("forge.test.{}:1|c|{}", outcome, tags));
+            lines.push(format!("forge.test.gas:{}|g|{}", test.gas, tags));
+            lines.push(format!("forge.test.duration:{}|ms|{}", test.duration_ms, tags));
+        }
+        lines
+    }
+}
+
+/// Replaces the characters that delimit StatsD fields and tags
+fn tag_value(value: &str) -> String {
+    value.replace(|c| matches!(c, ',' | '|' | '#' | ':' | '\n'), "_")
+}
+
+/// Sends the metrics of test contracts to an endpoint from a background thread.
+///
+/// Dropping the reporter waits for the pending metrics to be sent.
+pub struct MetricsReporter {
+    tx: Option<Sender<SuiteMetrics>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MetricsReporter {
+    /// Starts the background thread that sends the metrics to the endpoint
+    pub fn new(endpoint: MetricsEndpoint) -> eyre::Result<Self> {
+        let send = sender(endpoint)?;
+        let (tx, rx) = channel::<SuiteMetrics>();
+        let handle = thread::spawn(move || {
+            for metrics in rx {
+                send(&metrics);
+            }
+        });
+        Ok(Self { tx: Some(tx), handle: Some(handle) })
+    }
+
+    /// Queues the metrics of the completed test contract
+    pub fn report(&self, suite: &str, result: &SuiteResult) {
+        if let Some(ref tx) = self.tx {
+            let _ = tx.send(SuiteMetrics::new(suite, result));
+        }
+    }
+}
+
+impl Drop for MetricsReporter {
+    fn drop(&mut self) {
+        self.tx.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+type SendMetrics = Box<dyn Fn(&SuiteMetrics) + Send>;
+
+/// Returns the function that sends metrics to the endpoint, failures are logged
+#[cfg(feature = "metrics")]
+fn sender(endpoint: MetricsEndpoint) -> eyre::Result<SendMetrics> {
+    use std::{net::UdpSocket, time::Duration};
+
+    match endpoint {
+        MetricsEndpoint::StatsD(addr) => {
+            let socket = UdpSocket::bind("0.0.0.0:0")?;
+            Ok(Box::new(move |metrics| {
+                for line in metrics.statsd_lines() {
+                    if let Err(err) = socket.send_to(line.as_bytes(), &addr) {
+                        tracing::warn!(?err, %addr, "failed to send metrics to StatsD");
+                        return
+                    }
+                }
+            }))
+        }
+        MetricsEndpoint::Webhook(url) => {
+            let client =
+                reqwest::blocking::Client::builder().timeout(Duration::from_secs(5)).build()?;
+            Ok(Box::new(move |metrics| {
+                let res =
+                    client.post(&url).json(metrics).send().and_then(|res| res.error_for_status());
+                if let Err(err) = res {
+                    tracing::warn!(?err, %url, "failed to send metrics to webhook");
+                }
+            }))
+        }
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+fn sender(_: MetricsEndpoint) -> eyre::Result<SendMetrics> {
+    eyre::bail!(
+        "forge was built without metrics support, rebuild it with `--features metrics` to use \
+         --metrics-endpoint"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use forge::{TestKind, TestResult};
+    use std::{collections::BTreeMap, time::Duration};
+
+    #[test]
+    fn can_parse_endpoint() {
+        assert_eq!(
+            "statsd://localhost:8125".parse::<MetricsEndpoint>().unwrap(),
+            MetricsEndpoint::StatsD("localhost:8125".to_string())
+        );
+        assert_eq!(
+            "https://example.com/hook".parse::<MetricsEndpoint>().unwrap(),
+            MetricsEndpoint::Webhook("https://example.com/hook".to_string())
+        );
+        assert!("statsd://localhost".parse::<MetricsEndpoint>().is_err());
+        assert!("localhost:8125".parse::<MetricsEndpoint>().is_err());
+    }
+
+    #[test]
+    fn can_format_statsd_lines() {
+        let result = |success, gas| TestResult {
+            success,
+            kind: TestKind::Standard(gas),
+            duration: Duration::from_millis(2),
+            ..Default::default()
+        };
+        let suite = SuiteResult::new(
+            Duration::from_millis(5),
+            BTreeMap::from([
+                ("testDeposit(uint256,address)".to_string(), result(true, 42_000)),
+                ("testWithdraw()".to_string(), result(false, 21_000)),
+            ]),
+        );
+        let metrics = SuiteMetrics::new("test/Vault.t.sol:VaultTest", &suite);
+        assert_eq!((metrics.passed, metrics.failed), (1, 1));
+        assert_eq!(
+            metrics.statsd_lines(),
+            vec![
+                "forge.suite.duration:5|ms|#suite:VaultTest",
+                "forge.test.passed:1|c|#suite:VaultTest,test:testDeposit",
+                "forge.test.gas:42000|g|#suite:VaultTest,test:testDeposit",
+                "forge.test.duration:2|ms|#suite:VaultTest,test:testDeposit",
+                "forge.test.failed:1|c|#suite:VaultTest,test:testWithdraw",
+                "forge.test.gas:21000|g|#suite:VaultTest,test:testWithdraw",
+                "forge.test.duration:2|ms|#suite:VaultTest,test:testWithdraw",
+            ]
+        );
+    }
+}
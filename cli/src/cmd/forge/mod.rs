@@ -41,11 +41,15 @@ pub mod bind;
 pub mod build;
 pub mod config;
 pub mod create;
+pub mod describe;
+pub mod doctest;
 pub mod flatten;
 pub mod fmt;
 pub mod init;
 pub mod inspect;
 pub mod install;
+pub mod metrics;
+pub mod record;
 pub mod remappings;
 pub mod run;
 pub mod snapshot;
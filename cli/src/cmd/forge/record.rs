@@ -0,0 +1,117 @@
+//! Records test runs as fixtures that reproduce them deterministically
+//!
+//! `forge test --record <dir>` writes everything a run depends on besides the sources to `<dir>`:
+//!
+//! - `record.json`: the solc versions, the EVM environment, the fork and the fuzz and shuffle seeds
+//!   of the run, see [RunRecord]
+//! - `foundry.toml`: the resolved configuration of the run, for reference
+//! - `storage.json`: the state the tests read from the fork, in the format of the fork cache
+//!
+//! `forge test --replay-record <dir>` runs the tests again in the recorded environment. The state
+//! of the fork is served from the recorded cache, so a replay needs no RPC access unless the tests
+//! read state the recorded run did not read. Forks declared by test contracts are not recorded.
+use eyre::WrapErr;
+use forge::executor::Env;
+use foundry_config::Config;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The name of the file in the record directory the [RunRecord] is stored in
+pub const RECORD_FILE: &str = "record.json";
+
+/// The name of the file in the record directory the resolved configuration is stored in
+pub const RECORD_CONFIG_FILE: &str = "foundry.toml";
+
+/// The name of the file in the record directory the state read from the fork is stored in
+pub const RECORD_STORAGE_FILE: &str = "storage.json";
+
+/// The fork a recorded run ran against
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedFork {
+    /// The URL of the node the state was fetched from
+    pub url: String,
+    /// The block the fork was pinned to
+    pub block: u64,
+    /// The chain id of the fork
+    pub chain_id: u64,
+}
+
+/// Everything a test run depends on besides the sources and the state read from the fork
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// The versions of solc the contracts were compiled with
+    pub solc_versions: BTreeSet<Version>,
+    /// The EVM environment the tests ran in
+    pub env: Env,
+    /// The fork the tests ran against, if any
+    pub fork: Option<RecordedFork>,
+    /// The seed of the fuzzer
+    pub fuzz_seed: u64,
+    /// The seed the tests were shuffled with, if they were
+    pub shuffle_seed: Option<u64>,
+}
+
+impl RunRecord {
+    /// Reads the record from the record directory
+    pub fn read(dir: &Path) -> eyre::Result<Self> {
+        let path = dir.join(RECORD_FILE);
+        let content = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("Failed to read the recorded run {}", path.display()))?;
+        serde_json::from_str(&content)
+            .wrap_err_with(|| format!("Invalid recorded run {}", path.display()))
+    }
+
+    /// Writes the record and the resolved configuration to the record directory
+    pub fn write(&self, dir: &Path, config: &Config) -> eyre::Result<()> {
+        fs::create_dir_all(dir)
+            .wrap_err_with(|| format!("Failed to create record directory {}", dir.display()))?;
+        fs::write(dir.join(RECORD_FILE), serde_json::to_string_pretty(self)?)?;
+        fs::write(dir.join(RECORD_CONFIG_FILE), config.to_string_pretty()?)?;
+        Ok(())
+    }
+
+    /// Returns the solc version to compile with when replaying the run, if the contracts were
+    /// compiled with a single version
+    pub fn solc_version(&self) -> Option<&Version> {
+        match self.solc_versions.len() {
+            1 => self.solc_versions.iter().next(),
+            _ => None,
+        }
+    }
+
+    /// Returns a warning if the configuration differs from the recorded one
+    pub fn config_warning(dir: &Path, config: &Config) -> Option<String> {
+        let recorded = fs::read_to_string(dir.join(RECORD_CONFIG_FILE)).ok()?;
+        let current = config.to_string_pretty().ok()?;
+        (recorded.trim() != current.trim()).then(|| {
+            format!(
+                "The configuration differs from the recorded one in {}, the replay may not \
+                 reproduce the run",
+                dir.join(RECORD_CONFIG_FILE).display()
+            )
+        })
+    }
+}
+
+/// Returns the path of the recorded fork state in the record directory
+pub fn storage_file(dir: &Path) -> PathBuf {
+    dir.join(RECORD_STORAGE_FILE)
+}
+
+/// Returns a new seed for the fuzzer of a recorded run
+pub fn new_fuzz_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64
+}
+
+/// Returns the 32 byte seed of the fuzzer's RNG for the recorded seed
+pub fn fuzz_rng_seed(seed: u64) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes[..8].copy_from_slice(&seed.to_be_bytes());
+    bytes
+}
@@ -26,7 +26,7 @@ use forge::{
     CALLER,
 };
 use foundry_config::{figment::Figment, Config};
-use foundry_utils::{encode_args, IntoFunction, PostLinkInput, RuntimeOrHandle};
+use foundry_utils::{encode_args, IntoFunction, OutputEncoding, PostLinkInput, RuntimeOrHandle};
 use std::{collections::BTreeMap, path::PathBuf};
 use ui::{TUIExitReason, Tui, Ui};
 
@@ -222,7 +222,7 @@ impl Cmd for RunArgs {
 
             println!("Gas used: {}", result.gas);
             println!("== Logs ==");
-            let console_logs = decode_console_logs(&result.logs);
+            let console_logs = decode_console_logs(&result.logs, OutputEncoding::default());
             if !console_logs.is_empty() {
                 for log in console_logs {
                     println!("  {}", log);
@@ -4,7 +4,7 @@ use crate::cmd::{
     forge::{
         build::BuildArgs,
         test,
-        test::{custom_run, Test, TestOutcome},
+        test::{custom_run, with_interrupt, Test, TestOutcome},
     },
     Cmd,
 };
@@ -97,24 +97,28 @@ impl Cmd for SnapshotArgs {
     type Output = ();
 
     fn run(self) -> eyre::Result<()> {
-        let outcome = custom_run(self.test, self.include_fuzz_tests)?;
+        let granularity = self.test.gas_granularity;
+        let include_fuzz_tests = self.include_fuzz_tests;
+        let outcome =
+            with_interrupt(|interrupt| custom_run(self.test, include_fuzz_tests, interrupt))?;
+        outcome.exit_if_interrupted();
         outcome.ensure_ok()?;
         let tests = self.config.apply(outcome);
 
         if let Some(path) = self.diff {
             let snap = path.as_ref().unwrap_or(&self.snap);
             let snaps = read_snapshot(snap)?;
-            diff(tests, snaps)?;
+            diff(tests, snaps, granularity)?;
         } else if let Some(path) = self.check {
             let snap = path.as_ref().unwrap_or(&self.snap);
             let snaps = read_snapshot(snap)?;
-            if check(tests, snaps) {
+            if check(tests, snaps, granularity) {
                 std::process::exit(0)
             } else {
                 std::process::exit(1)
             }
         } else {
-            write_to_snapshot_file(&tests, self.snap, self.format)?;
+            write_to_snapshot_file(&tests, self.snap, self.format, granularity)?;
         }
         Ok(())
     }
@@ -229,6 +233,14 @@ impl FromStr for SnapshotEntry {
     }
 }
 
+/// Rounds the gas to the nearest multiple of the granularity, if any
+fn round(gas: TestKindGas, granularity: Option<u64>) -> TestKindGas {
+    match granularity {
+        Some(granularity) => gas.rounded(granularity),
+        None => gas,
+    }
+}
+
 /// Reads a list of snapshot entries from a snapshot file
 fn read_snapshot(path: impl AsRef<Path>) -> eyre::Result<Vec<SnapshotEntry>> {
     let path = path.as_ref();
@@ -250,6 +262,7 @@ fn write_to_snapshot_file(
     tests: &[Test],
     path: impl AsRef<Path>,
     _format: Option<Format>,
+    granularity: Option<u64>,
 ) -> eyre::Result<()> {
     let mut out = String::new();
     for test in tests {
@@ -258,7 +271,7 @@ fn write_to_snapshot_file(
             "{}:{} {}",
             test.contract_name(),
             test.signature,
-            test.result.kind.gas_used()
+            round(test.result.kind.gas_used(), granularity)
         )?;
     }
     Ok(fs::write(path, out)?)
@@ -289,18 +302,19 @@ impl SnapshotDiff {
 
 /// Compares the set of tests with an existing snapshot
 ///
-/// Returns true all tests match
-fn check(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> bool {
+/// Returns true all tests match. With a granularity, both the gas of the tests and of the snapshot
+/// are rounded before they are compared.
+fn check(tests: Vec<Test>, snaps: Vec<SnapshotEntry>, granularity: Option<u64>) -> bool {
     let snaps = snaps
         .into_iter()
-        .map(|s| ((s.contract_name, s.signature), s.gas_used))
+        .map(|s| ((s.contract_name, s.signature), round(s.gas_used, granularity)))
         .collect::<HashMap<_, _>>();
     let mut has_diff = false;
     for test in tests {
         if let Some(target_gas) =
             snaps.get(&(test.contract_name().to_string(), test.signature.clone())).cloned()
         {
-            let source_gas = test.result.kind.gas_used();
+            let source_gas = round(test.result.kind.gas_used(), granularity);
             if source_gas.gas() != target_gas.gas() {
                 eprintln!(
                     "Diff in \"{}::{}\": consumed \"{}\" gas, expected \"{}\" gas ",
@@ -323,11 +337,11 @@ fn check(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> bool {
     !has_diff
 }
 
-/// Compare the set of tests with an existing snapshot
-fn diff(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> eyre::Result<()> {
+/// Compare the set of tests with an existing snapshot, after rounding both to the granularity
+fn diff(tests: Vec<Test>, snaps: Vec<SnapshotEntry>, granularity: Option<u64>) -> eyre::Result<()> {
     let snaps = snaps
         .into_iter()
-        .map(|s| ((s.contract_name, s.signature), s.gas_used))
+        .map(|s| ((s.contract_name, s.signature), round(s.gas_used, granularity)))
         .collect::<HashMap<_, _>>();
     let mut diffs = Vec::with_capacity(tests.len());
     for test in tests.into_iter() {
@@ -342,7 +356,7 @@ fn diff(tests: Vec<Test>, snaps: Vec<SnapshotEntry>) -> eyre::Result<()> {
             })?;
 
         diffs.push(SnapshotDiff {
-            source_gas_used: test.result.kind.gas_used(),
+            source_gas_used: round(test.result.kind.gas_used(), granularity),
             signature: test.signature,
             target_gas_used,
         });
@@ -426,4 +440,11 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn can_round_snapshot_entry() {
+        let entry = SnapshotEntry::from_str("Test:deposit() (gas: 7251)").unwrap();
+        assert_eq!(round(entry.gas_used.clone(), Some(100)), TestKindGas::Standard(7300));
+        assert_eq!(round(entry.gas_used, None), TestKindGas::Standard(7251));
+    }
 }
@@ -1,9 +1,14 @@
 //! Support for compiling [ethers::solc::Project]
 
 use crate::term;
-use ethers::solc::{report::NoReporter, Artifact, FileFilter, Project, ProjectCompileOutput};
+use ethers::solc::{
+    report::NoReporter, Artifact, FileFilter, Graph, Project, ProjectCompileOutput,
+};
 use foundry_utils::to_table;
-use std::{collections::BTreeMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    path::PathBuf,
+};
 
 /// Compiles the provided [`Project`], throws if there's any compiler error and logs whether
 /// compilation was successful or if there was a cache hit.
@@ -136,6 +141,23 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
 /// compilation was successful or if there was a cache hit.
 /// Doesn't print anything to stdout, thus is "suppressed".
 pub fn suppress_compile(project: &Project) -> eyre::Result<ProjectCompileOutput> {
+    suppress_compile_with(project, |prj| Ok(prj.compile()?))
+}
+
+/// Compiles the provided [`Project`] like [`suppress_compile`], but only emits artifacts for the
+/// files that match the filter, see [`ProjectCompiler::compile_sparse`]
+pub fn suppress_compile_sparse<F: FileFilter + 'static>(
+    project: &Project,
+    filter: F,
+) -> eyre::Result<ProjectCompileOutput> {
+    suppress_compile_with(project, |prj| Ok(prj.compile_sparse(filter)?))
+}
+
+/// Compiles the provided [`Project`] with the given closure without printing anything to stdout
+fn suppress_compile_with<F>(project: &Project, f: F) -> eyre::Result<ProjectCompileOutput>
+where
+    F: FnOnce(&Project) -> eyre::Result<ProjectCompileOutput>,
+{
     if !project.paths.sources.exists() {
         eyre::bail!(
             r#"no contracts to compile, contracts folder "{}" does not exist.
@@ -149,7 +171,7 @@ If you are in a subdirectory in a Git repository, try adding `--root .`"#,
 
     let output = ethers::solc::report::with_scoped(
         &ethers::solc::report::Report::new(NoReporter::default()),
-        || project.compile(),
+        || f(project),
     )?;
 
     if output.has_compiler_errors() {
@@ -169,3 +191,49 @@ pub fn compile_files(project: &Project, files: Vec<PathBuf>) -> eyre::Result<Pro
     println!("{}", output);
     Ok(output)
 }
+
+/// Prints the import graph that a sparse compilation with the given `filter` would select.
+///
+/// Starting from every source file that matches the filter, this walks all (transitive) imports
+/// and prints one `<file> -> <import>` edge per line. Files without any imports are printed on
+/// their own.
+pub fn print_import_graph<F: FileFilter>(project: &Project, filter: &F) -> eyre::Result<()> {
+    let graph = Graph::resolve(&project.paths)?;
+    let root = &project.paths.root;
+    let display = |idx: usize| {
+        let path = graph.node(idx).path();
+        path.strip_prefix(root).unwrap_or(path).display().to_string()
+    };
+
+    let mut queue = graph
+        .files()
+        .iter()
+        .filter(|(path, _)| filter.is_match(path))
+        .map(|(_, idx)| *idx)
+        .collect::<VecDeque<_>>();
+    let mut visited = BTreeSet::new();
+    let mut edges = BTreeSet::new();
+
+    while let Some(idx) = queue.pop_front() {
+        if !visited.insert(idx) {
+            continue
+        }
+        let imports = graph.imported_nodes(idx);
+        if imports.is_empty() {
+            edges.insert((display(idx), None));
+        }
+        for import in imports {
+            edges.insert((display(idx), Some(display(*import))));
+            queue.push_back(*import);
+        }
+    }
+
+    println!("Compiled files: {}", visited.len());
+    for (file, import) in edges {
+        match import {
+            Some(import) => println!("{} -> {}", file, import),
+            None => println!("{}", file),
+        }
+    }
+    Ok(())
+}
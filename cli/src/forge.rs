@@ -13,15 +13,23 @@ use clap_complete::generate;
 
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
-    utils::subscriber();
 
     let opts = Opts::parse();
+    let trace_log = match opts.sub {
+        Subcommands::Test(ref cmd) => cmd.trace_log(),
+        _ => None,
+    };
+    match trace_log {
+        Some(directives) => utils::filtered_subscriber(directives)?,
+        None => utils::subscriber(),
+    }
     match opts.sub {
         Subcommands::Test(cmd) => {
             if cmd.build_args().is_watch() {
                 utils::block_on(watch::watch_test(cmd))?;
             } else {
                 let outcome = cmd.run()?;
+                outcome.exit_if_interrupted();
                 outcome.ensure_ok()?;
             }
         }
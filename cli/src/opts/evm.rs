@@ -12,6 +12,8 @@ use foundry_config::{
 };
 use serde::Serialize;
 
+use crate::utils::parse_u256;
+
 // `EvmArgs` and `EnvArgs` take the highest precedence in the Config/Figment hierarchy.
 // All vars are opt-in, their default values are expected to be set by the
 // [`foundry_config::Config`], and are always present ([`foundry_config::Config::default`])
@@ -71,6 +73,20 @@ pub struct EvmArgs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sender: Option<Address>,
 
+    /// The memory limit of the EVM in bytes.
+    ///
+    /// Calls that exceed the limit fail with an out of gas error. Defaults to 16 MiB.
+    #[clap(long)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_limit: Option<u64>,
+
+    /// The gas the calls of tests are given, instead of the gas limit.
+    ///
+    /// Deployments and `setUp` still get the gas limit.
+    #[clap(long, value_name = "GAS")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gas_stipend: Option<u64>,
+
     /// Enable the FFI cheatcode.
     #[clap(help = "enables the FFI cheatcode", long)]
     #[serde(skip)]
@@ -151,6 +167,9 @@ pub struct EnvArgs {
     pub block_base_fee_per_gas: Option<u64>,
 
     /// The transaction origin.
+    ///
+    /// In tests this is `tx.origin` of the calls, independently of the sender. Defaults to the
+    /// sender.
     #[clap(long)]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tx_origin: Option<Address>,
@@ -175,6 +194,14 @@ pub struct EnvArgs {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub block_difficulty: Option<u64>,
 
+    /// The prevrandao value of the block, returned by `block.prevrandao` (`block.difficulty`
+    /// before the merge).
+    ///
+    /// This takes precedence over the block difficulty and the prevrandao of a forked block.
+    #[clap(long, value_name = "VALUE", parse(try_from_str = parse_u256))]
+    #[serde(skip)]
+    pub block_prevrandao: Option<U256>,
+
     /// The block gas limit.
     #[clap(long)]
     #[serde(skip_serializing_if = "Option::is_none")]
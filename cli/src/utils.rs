@@ -1,13 +1,18 @@
 use std::{
     future::Future,
-    path::{Path, PathBuf},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    process::{Child, Command, Stdio},
     str::FromStr,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use ethers::{solc::EvmVersion, types::U256};
-use forge::executor::{opts::EvmOpts, Fork, SpecId};
-use foundry_config::{caching::StorageCachingConfig, Config};
+use ethers::{
+    providers::{Middleware, Provider},
+    types::U256,
+};
+use eyre::WrapErr;
+use forge::executor::opts::EvmOpts;
 use tracing_error::ErrorLayer;
 use tracing_subscriber::prelude::*;
 
@@ -15,6 +20,8 @@ use tracing_subscriber::prelude::*;
 #[doc(hidden)]
 pub use foundry_config::utils::*;
 
+pub use forge::run::{evm_spec, get_fork};
+
 /// The version message for the current program, like
 /// `forge 0.1.0 (f01b232bc 2022-01-22T23:28:39.493201+00:00)`
 pub(crate) const VERSION_MESSAGE: &str = concat!(
@@ -66,13 +73,18 @@ pub fn subscriber() {
         .init()
 }
 
-pub fn evm_spec(evm: &EvmVersion) -> SpecId {
-    match evm {
-        EvmVersion::Istanbul => SpecId::ISTANBUL,
-        EvmVersion::Berlin => SpecId::BERLIN,
-        EvmVersion::London => SpecId::LONDON,
-        _ => panic!("Unsupported EVM version"),
-    }
+/// Initializes a tracing Subscriber for logging to stderr, filtered by the given directives (e.g.
+/// `forge=debug,foundry_evm=trace`) instead of `RUST_LOG`
+#[allow(dead_code)]
+pub fn filtered_subscriber(directives: &str) -> eyre::Result<()> {
+    let filter = tracing_subscriber::EnvFilter::try_new(directives)
+        .wrap_err_with(|| format!("Invalid tracing filter `{}`", directives))?;
+    tracing_subscriber::Registry::default()
+        .with(filter)
+        .with(ErrorLayer::default())
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+    Ok(())
 }
 
 /// Securely reads a secret from stdin, or proceeds to return a fallback value
@@ -150,58 +162,89 @@ pub fn block_on<F: Future>(future: F) -> F::Output {
     rt.block_on(future)
 }
 
-/// Helper function that returns the [Fork] to use, if any.
-///
-/// storage caching for the [Fork] will be enabled if
-///   - `fork_url` is present
-///   - `fork_block_number` is present
-///   - [StorageCachingConfig] allows the `fork_url` +  chain id pair
-///   - storage is allowed (`no_storage_caching = false`)
-///
-/// If all these criteria are met, then storage caching is enabled and storage info will be written
-/// to [Config::foundry_cache_dir()]/<str(chainid)>/<block>/storage.json
+/// Parses a duration like `90s`, `30m`, `2h` or `1d`, a plain number is interpreted as seconds
+pub fn parse_duration(s: &str) -> eyre::Result<Duration> {
+    let s = s.trim();
+    let (num, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let num: u64 = num.parse().wrap_err_with(|| format!("Invalid duration `{}`", s))?;
+    let secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => eyre::bail!("Invalid duration unit `{}`, expected one of s, m, h, d", unit),
+    };
+    Ok(Duration::from_secs(num * secs))
+}
+
+/// A local `anvil` node forking a remote endpoint, spawned as a child process.
 ///
-/// for `mainnet` and `--fork-block-number 14435000` on mac the corresponding storage cache will be
-/// at `~/.foundry/cache/mainnet/14435000/storage.json`
-pub fn get_fork(evm_opts: &EvmOpts, config: &StorageCachingConfig) -> Option<Fork> {
-    /// Returns the path where the cache file should be stored
-    ///
-    /// or `None` if caching should not be enabled
-    ///
-    /// See also [ Config::foundry_block_cache_file()]
-    fn get_block_storage_path(
-        evm_opts: &EvmOpts,
-        config: &StorageCachingConfig,
-        chain_id: u64,
-    ) -> Option<PathBuf> {
-        if evm_opts.no_storage_caching {
-            // storage caching explicitly opted out of
-            return None
-        }
-        let url = evm_opts.fork_url.as_ref()?;
-        // cache only if block explicitly pinned
-        let block = evm_opts.fork_block_number?;
+/// The node is killed when this is dropped, including when unwinding from a panic. On Ctrl-C the
+/// node receives the signal as well since it shares the terminal's process group.
+pub struct LocalFork {
+    child: Child,
+    /// The endpoint of the local node
+    pub url: String,
+    /// The block the local node forks at
+    pub block: u64,
+}
 
-        if config.enable_for_endpoint(url) && config.enable_for_chain_id(chain_id) {
-            return Config::foundry_block_cache_file(chain_id, block)
-        }
+impl LocalFork {
+    /// How long to wait for the node to accept connections
+    const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Spawns an `anvil` node forking `fork_url` at `block`, or the latest block if unset, and
+    /// waits until it accepts connections
+    pub fn spawn(fork_url: &str, block: Option<u64>) -> eyre::Result<Self> {
+        let block = match block {
+            Some(block) => block,
+            None => {
+                let provider = Provider::try_from(fork_url)?;
+                block_on(provider.get_block_number())?.as_u64()
+            }
+        };
 
-        None
+        // let the OS pick a free port
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+
+        let child = Command::new("anvil")
+            .arg("--fork-url")
+            .arg(fork_url)
+            .arg("--fork-block-number")
+            .arg(block.to_string())
+            .arg("--port")
+            .arg(port.to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .wrap_err("Failed to spawn anvil, make sure it is installed and in your PATH")?;
+        let mut fork = Self { child, url: format!("http://127.0.0.1:{}", port), block };
+
+        let start = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            if let Some(status) = fork.child.try_wait()? {
+                eyre::bail!("anvil exited before accepting connections: {}", status)
+            }
+            if start.elapsed() > Self::STARTUP_TIMEOUT {
+                eyre::bail!("anvil did not accept connections within {:?}", Self::STARTUP_TIMEOUT)
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        Ok(fork)
     }
 
-    if let Some(ref url) = evm_opts.fork_url {
-        let chain_id = evm_opts.get_chain_id();
-        let cache_storage = get_block_storage_path(evm_opts, config, chain_id);
-        let fork = Fork {
-            url: url.clone(),
-            pin_block: evm_opts.fork_block_number,
-            cache_path: cache_storage,
-            chain_id,
-        };
-        return Some(fork)
+    /// Points `evm_opts` at the local node, at the block it forks at
+    pub fn redirect(&self, evm_opts: &mut EvmOpts) {
+        evm_opts.fork_url = Some(self.url.clone());
+        evm_opts.fork_block_number = Some(self.block);
     }
+}
 
-    None
+impl Drop for LocalFork {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
 }
 
 /// Conditionally print a message
@@ -225,6 +268,17 @@ pub(crate) use p_println;
 mod tests {
     use super::*;
 
+    #[test]
+    fn can_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(2 * 60 * 60));
+        assert_eq!(parse_duration("1d").unwrap(), Duration::from_secs(24 * 60 * 60));
+        assert!(parse_duration("1w").is_err());
+        assert!(parse_duration("m").is_err());
+    }
+
     #[test]
     fn foundry_path_ext_works() {
         let p = Path::new("contracts/MyTest.t.sol");
@@ -23,6 +23,10 @@ use std::{
 
 static CURRENT_DIR_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
+/// The environment variable that overrides the directory of the `forge` and `cast` binaries the
+/// tests run, see [TestProject::with_bin_dir()]
+pub const BIN_DIR_ENV: &str = "FORGE_TEST_BIN_DIR";
+
 /// Contains a `forge init` initialized project
 pub static FORGE_INITIALIZED: Lazy<TestProject> = Lazy::new(|| {
     let (prj, mut cmd) = setup_forge("init-template", PathStyle::Dapptools);
@@ -84,8 +88,8 @@ pub fn setup_cast_project(test: TestProject) -> (TestProject, TestCommand) {
 /// Test projects are created from a global atomic counter to avoid duplicates.
 #[derive(Clone, Debug)]
 pub struct TestProject<T: ArtifactOutput = ConfigurableArtifacts> {
-    /// The directory in which this test executable is running.
-    root: PathBuf,
+    /// The directory of the `forge` and `cast` binaries.
+    bin_dir: PathBuf,
     /// The project in which the test should run.
     inner: Arc<TempProject<T>>,
 }
@@ -100,10 +104,29 @@ impl TestProject {
         Self::with_project(project)
     }
 
+    /// Creates a test project for the given project.
+    ///
+    /// The `forge` and `cast` binaries are looked up in the directory set by [BIN_DIR_ENV], or
+    /// else next to the directory of the running test executable, where cargo puts them.
     pub fn with_project(project: TempProject) -> Self {
-        let root =
-            env::current_exe().unwrap().parent().expect("executable's directory").to_path_buf();
-        Self { root, inner: Arc::new(project) }
+        let bin_dir = match env::var_os(BIN_DIR_ENV) {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => default_bin_dir(),
+        };
+        Self { bin_dir, inner: Arc::new(project) }
+    }
+
+    /// Runs the `forge` and `cast` binaries in the given directory, e.g. if they are built for
+    /// another target or installed separately in a container
+    #[must_use]
+    pub fn with_bin_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.bin_dir = dir.into();
+        self
+    }
+
+    /// Returns the directory of the `forge` and `cast` binaries.
+    pub fn bin_dir(&self) -> &Path {
+        &self.bin_dir
     }
 
     /// Returns the root path of the project's workspace.
@@ -224,13 +247,13 @@ impl TestProject {
 
     /// Returns the path to the forge executable.
     pub fn forge_bin(&self) -> process::Command {
-        let forge = self.root.join(format!("../forge{}", env::consts::EXE_SUFFIX));
+        let forge = self.bin_dir.join(format!("forge{}", env::consts::EXE_SUFFIX));
         process::Command::new(forge)
     }
 
     /// Returns the path to the cast executable.
     pub fn cast_bin(&self) -> process::Command {
-        let cast = self.root.join(format!("../cast{}", env::consts::EXE_SUFFIX));
+        let cast = self.bin_dir.join(format!("cast{}", env::consts::EXE_SUFFIX));
         process::Command::new(cast)
     }
 
@@ -262,6 +285,13 @@ impl Drop for TestCommand {
     }
 }
 
+/// Returns the directory cargo puts the binaries in, the parent of the `deps` directory of the
+/// running test executable
+fn default_bin_dir() -> PathBuf {
+    let exe = env::current_exe().unwrap();
+    exe.parent().expect("executable's directory").join("..")
+}
+
 fn config_paths_exist(paths: &ProjectPathsConfig, cached: bool) {
     if cached {
         assert!(paths.cache.exists());
@@ -406,6 +436,14 @@ impl TestCommand {
         self.expect_success(output)
     }
 
+    /// Gets the output of a command, regardless of whether it succeeded.
+    ///
+    /// Unlike [TestCommand::output()] this does not panic if the command failed, so both the exit
+    /// status and the output can be inspected.
+    pub fn try_output(&mut self) -> process::Output {
+        self.cmd.output().unwrap()
+    }
+
     /// Runs the command and prints its output
     pub fn print_output(&mut self) {
         let output = self.cmd.output().unwrap();
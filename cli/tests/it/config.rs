@@ -11,9 +11,9 @@ use foundry_cli_test_utils::{
 };
 use foundry_config::{
     caching::{CachedChains, CachedEndpoints, StorageCachingConfig},
-    Config, OptimizerDetails, SolcReq,
+    Config, FuzzWeightsConfig, OptimizerDetails, SolcReq, TestGroup,
 };
-use std::{fs, path::PathBuf, str::FromStr};
+use std::{collections::BTreeMap, fs, path::PathBuf, str::FromStr};
 
 // import forge utils as mod
 #[allow(unused)]
@@ -53,7 +53,11 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         fuzz_runs: 1000,
         fuzz_max_local_rejects: 2000,
         fuzz_max_global_rejects: 100203,
+        fuzz_boundary_weight: 25,
+        fuzz_address_pool_rate: 10,
+        fuzz: FuzzWeightsConfig { random: 50, state: 30, boundary: Some(10), zero: 5, max: 5 },
         ffi: true,
+        required_env: vec!["MAINNET_RPC_URL".to_string()],
         sender: "00a329c0648769A73afAc7F9381D08FB43dBEA72".parse().unwrap(),
         tx_origin: "00a329c0648769A73afAc7F9F81E08FB43dBEA72".parse().unwrap(),
         initial_balance: U256::from(0xffffffffffffffffffffffffu128),
@@ -67,8 +71,11 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         block_timestamp: 10,
         block_difficulty: 10,
         block_gas_limit: Some(100.into()),
+        memory_limit: 2u64.pow(25),
+        gas_stipend: Some(1_000_000),
         eth_rpc_url: Some("localhost".to_string()),
         etherscan_api_key: None,
+        silence_etherscan_warning: true,
         verbosity: 4,
         remappings: vec![Remapping::from_str("ds-test=lib/ds-test/").unwrap().into()],
         libraries: vec![
@@ -83,6 +90,14 @@ forgetest!(can_extract_config_values, |prj: TestProject, mut cmd: TestCommand| {
         no_storage_caching: true,
         bytecode_hash: Default::default(),
         sparse_mode: true,
+        test_groups: BTreeMap::from([(
+            "smoke".to_string(),
+            TestGroup { contracts: vec!["CounterTest".to_string()], tests: vec![] },
+        )]),
+        test_prefixes: vec!["test".to_string(), "check_".to_string()],
+        test_dirs: vec!["test-test".into()],
+        output_encoding: "checksum,base64".parse().unwrap(),
+        post_test_hook: Some("./notify.sh".to_string()),
         __non_exhaustive: (),
     };
     prj.write_config(input.clone());
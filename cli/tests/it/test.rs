@@ -3,6 +3,7 @@ use foundry_cli_test_utils::{
     forgetest,
     util::{TestCommand, TestProject},
 };
+use foundry_config::Config;
 
 // import forge utils as mod
 #[allow(unused)]
@@ -81,3 +82,1485 @@ contract ATest is DSTest {
     cmd.args(["test", "--match-path", "*src/ATest.t.sol"]);
     cmd.stdout().contains("[PASS]")
 });
+
+// tests that `--recent` reads the modification times of the sources in the project root, also if
+// forge runs in another directory
+#[cfg(unix)]
+forgetest!(can_select_recent_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    let sources = ["OldTest", "NewTest"].map(|name| {
+        prj.inner()
+            .add_source(
+                format!("{}.t.sol", name),
+                format!(
+                    r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract {} is DSTest {{
+    function testPass() external {{}}
+}}
+   "#,
+                    name
+                ),
+            )
+            .unwrap()
+    });
+    let touched =
+        std::process::Command::new("touch").args(["-t", "200001010000"]).arg(&sources[0]).status();
+    assert!(touched.unwrap().success());
+
+    cmd.current_dir(prj.root().parent().unwrap());
+    cmd.arg("test").root_arg().args(["--recent", "1h"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("NewTest"));
+    assert!(!stdout.contains("OldTest"));
+});
+
+// tests that `forge test` fails up front if a required environment variable is missing
+forgetest!(fails_on_missing_required_env, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.write_config(Config {
+        required_env: vec!["FOUNDRY_TEST_REQUIRED_ENV_VAR".to_string()],
+        ..Default::default()
+    });
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testEnv() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.arg("test");
+    cmd.unset_env("FOUNDRY_TEST_REQUIRED_ENV_VAR");
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("FOUNDRY_TEST_REQUIRED_ENV_VAR"));
+
+    cmd.set_env("FOUNDRY_TEST_REQUIRED_ENV_VAR", "1");
+    assert!(cmd.stdout().contains("[PASS]"));
+});
+
+// tests that NatSpec examples are run as tests
+forgetest!(can_run_doctests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Counter.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+/// @custom:example
+/// Counter counter = new Counter();
+/// counter.increment();
+/// require(counter.number() == 1);
+/// @custom:example require(new Counter().number() == 1);
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--doctests"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("CounterExample0"));
+    assert!(stdout.contains("CounterExample1"));
+    assert!(stdout.contains("1 passed; 0 failed"));
+    assert!(stdout.contains("0 passed; 1 failed"));
+});
+
+// tests that labeled addresses are part of the JSON results
+forgetest!(can_output_labeled_addresses_json, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "LabelTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+interface Vm {
+    function label(address, string calldata) external;
+}
+contract LabelTest is DSTest {
+    Vm constant vm = Vm(HEVM_ADDRESS);
+    function testLabel() external {
+        vm.label(address(1337), "Alice");
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--json"]);
+    let results: serde_json::Value = serde_json::from_str(&cmd.stdout()).unwrap();
+    let result = &results["src/LabelTest.t.sol:LabelTest"]["test_results"]["testLabel()"];
+    assert_eq!(result["labeled_addresses"]["0x0000000000000000000000000000000000000539"], "Alice");
+});
+
+// tests that the resolved EVM environment is reported with the JSON results
+forgetest!(can_output_env_json, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPasses() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--json", "-v", "--chain-id", "1337"]);
+    let output = cmd.try_output();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let metadata: serde_json::Value =
+        stderr.lines().find_map(|line| serde_json::from_str(line).ok()).unwrap();
+    assert_eq!(metadata["env"]["chain_id"], "0x539");
+    assert!(metadata["env"]["coinbase"].is_string());
+});
+
+// tests that `--cache-results` reuses the results of unchanged passing tests
+forgetest!(can_cache_results, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPasses() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--cache-results"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("[PASS]"));
+    assert!(!stdout.contains("cached results"));
+
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("1 cached results of passing tests are still valid"));
+    assert!(stdout.contains("[PASS] testPasses()"));
+});
+
+// tests that `--expect-test-count` fails the run if a different number of tests matches
+forgetest!(can_expect_test_count, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testA() external {}
+
+    function testB() external {}
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--expect-test-count", "2"]);
+    assert!(cmd.stdout().contains("[PASS] testB()"));
+
+    cmd.forge_fuse().args(["test", "--expect-test-count", "3"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Expected 3 tests to match, but 2 matched"));
+});
+
+// tests that `--max-rpc-calls` reports the RPC calls the fork made and fails the run if there are
+// too many
+forgetest!(can_limit_rpc_calls, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testA() external {
+        emit log_uint(address(uint160(0x63091244180ae240c87d1f528f5f269134cb07b3)).code.length);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    // there are no RPC calls without a fork
+    cmd.args(["test", "--max-rpc-calls", "0"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("[PASS] testA()"));
+    assert!(stdout.contains("RPC calls: 0"));
+
+    // Skip fork tests if the RPC url is not set.
+    let eth_rpc_url = match std::env::var("ETH_RPC_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("Skipping the fork part of can_limit_rpc_calls. ETH_RPC_URL is not set.");
+            return
+        }
+    };
+    cmd.args(["--fork-url", eth_rpc_url.as_str(), "--fork-block-number", "14435000"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let calls = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("RPC calls: "))
+        .and_then(|calls| calls.parse::<usize>().ok())
+        .unwrap();
+    assert!(calls > 0);
+    assert!(stdout.contains("src/ATest.t.sol:ATest:testA()"));
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains(&format!("The forks made {} RPC calls, more than the maximum of 0", calls)));
+
+    cmd.forge_fuse().args(["test", "--fork-url", eth_rpc_url.as_str()]);
+    cmd.args(["--fork-block-number", "14435000", "--max-rpc-calls"]).arg(calls.to_string());
+    assert!(cmd.stdout().contains(&format!("RPC calls: {}", calls)));
+});
+
+// tests that struct arguments of counterexamples are printed with their field names
+forgetest!(can_print_struct_counterexamples, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "StructTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract StructTest is DSTest {
+    struct Inner {
+        bool flag;
+        uint8 value;
+    }
+
+    struct Outer {
+        address owner;
+        Inner inner;
+    }
+
+    function testStruct(Outer memory outer) external {
+        assertTrue(outer.inner.value > 255);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("args=[{owner: 0x"));
+    assert!(stdout.contains("inner: {flag: "));
+    assert!(stdout.contains(", value: "));
+});
+
+// tests that `--timings` prints the time spent per stage
+forgetest!(can_print_timings, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPasses() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--timings"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("Timings: compilation"));
+    assert!(stdout.contains("deployment and setup"));
+    assert!(stdout.contains("test execution"));
+});
+
+// tests that the function coverage gate reports uncovered functions and fails below the minimum
+forgetest!(can_check_min_function_coverage, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "Counter.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+    function reset() external {
+        number = 0;
+    }
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "CounterTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+import "./Counter.sol";
+contract CounterTest is DSTest {
+    function testIncrement() external {
+        Counter counter = new Counter();
+        counter.increment();
+        assertEq(counter.number(), 1);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--min-function-coverage", "80"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Function coverage: 66.67% (2/3)"));
+    assert!(stdout.contains("Counter.reset()"));
+
+    cmd.forge_fuse().args(["test", "--min-function-coverage", "60"]);
+    assert!(cmd.stdout().contains("Function coverage: 66.67% (2/3)"));
+});
+
+// tests that the branches of contracts created in fuzz cases count, but only those of the
+// functions that ran
+forgetest!(can_check_fuzz_branch_coverage, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "Vault.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Vault {
+    function check(uint256 x) external pure returns (uint256) {
+        if (uint256(keccak256(abi.encode(x))) % 1000000 == 123456) {
+            return 1;
+        }
+        return 0;
+    }
+    function other(uint256 y) external pure returns (uint256) {
+        if (y > 1) {
+            return 1;
+        }
+        return 0;
+    }
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "VaultTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+import "./Vault.sol";
+contract VaultTest is DSTest {
+    function testCheck(uint256 x) external {
+        Vault vault = new Vault();
+        vault.check(x);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--fuzz-min-coverage", "100"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("branch coverage of"));
+    assert!(stdout.contains("Vault.sol:6: jump"));
+    assert!(!stdout.contains("Vault.sol:12"));
+});
+
+// tests that repeated runs report the outcome of every test across all runs
+forgetest!(can_repeat_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPasses() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--repeat", "3", "--fail-on-flaky"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("Across 3 runs"));
+    assert!(!stdout.contains("Flaky tests:"));
+});
+
+// tests that a recorded run is replayed with the same fuzz inputs
+forgetest!(can_record_and_replay_runs, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testFuzz(uint256 x) external {
+        uint256 sum;
+        for (uint256 i = 0; i < x % 64; i++) {
+            sum += i;
+        }
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let dir = prj.root().join("record");
+    let fuzz_line = |stdout: String| {
+        stdout.lines().find(|line| line.contains("testFuzz(uint256)")).unwrap().to_string()
+    };
+
+    cmd.arg("test").arg("--record").arg(&dir);
+    let recorded = fuzz_line(cmd.stdout());
+    assert!(dir.join("record.json").exists());
+    assert!(dir.join("foundry.toml").exists());
+
+    cmd.forge_fuse().arg("test").arg("--replay-record").arg(&dir);
+    assert_eq!(fuzz_line(cmd.stdout()), recorded);
+});
+
+// tests that `--check-code-size` reports the code size of test contracts and names those that are
+// larger than the limit
+forgetest!(can_check_code_size, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPasses() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--check-code-size"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("Code size: "));
+
+    cmd.forge_fuse().args(["test", "--check-code-size", "10"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("more than the code size limit of 10 bytes"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("larger than the limit of 10 bytes"));
+    assert!(stderr.contains("ATest.t.sol:ATest"));
+});
+
+// tests that `--trace-log` logs the internals of the runner to stderr
+forgetest!(can_filter_trace_log, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    function testPasses() external {
+        assertTrue(true);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--trace-log", "forge=info"]);
+    let output = cmd.output();
+    assert!(String::from_utf8_lossy(&output.stderr).contains("starting tests"));
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("starting tests"));
+
+    cmd.forge_fuse().args(["test", "--trace-log", "forge=loud"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid tracing filter"));
+});
+
+// tests that `--covers` only runs the tests that call the function
+forgetest!(can_run_tests_covering_function, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "Vault.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Vault {
+    uint256 public balance;
+    function deposit(uint256 amount) external {
+        balance += amount;
+    }
+    function withdraw(uint256 amount) external {
+        balance -= amount;
+    }
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "VaultTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+import "./Vault.sol";
+contract VaultTest is DSTest {
+    Vault vault;
+    function setUp() public {
+        vault = new Vault();
+        vault.deposit(10);
+    }
+    function testDeposit() external {
+        vault.deposit(1);
+        assertEq(vault.balance(), 11);
+    }
+    function testWithdraw() external {
+        vault.withdraw(1);
+        assertEq(vault.balance(), 9);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--covers", "Vault.withdraw"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("1 tests call Vault.withdraw"));
+    assert!(stdout.contains("testWithdraw()"));
+    assert!(!stdout.contains("testDeposit()"));
+
+    // calls made by setUp do not count
+    cmd.forge_fuse().args(["test", "--covers", "Vault.deposit(uint256)"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("1 tests call Vault.deposit(uint256)"));
+    assert!(stdout.contains("testDeposit()"));
+    assert!(!stdout.contains("testWithdraw()"));
+
+    cmd.forge_fuse().args(["test", "--covers", "Vault.borrow"]);
+    cmd.assert_err();
+});
+
+// tests that `--describe` prints the matched tests with their notices without running them
+forgetest!(can_describe_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "ATest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ATest is DSTest {
+    /// @notice Fails if it is run
+    function testFails() external {
+        assertTrue(false);
+    }
+
+    function testFuzz(uint256 x) external {}
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--describe"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("ATest.t.sol\n  ATest\n"));
+    assert!(stdout.contains("    - testFails() [unit]\n        Fails if it is run\n"));
+    assert!(stdout.contains("    - testFuzz(uint256) [fuzz]\n"));
+    assert!(!stdout.contains("[FAIL"));
+});
+
+// tests that `--export-callgraph` writes the functions of non-test contracts each test called
+forgetest!(can_export_callgraph, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+
+    prj.inner()
+        .add_source(
+            "Counter.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "CounterTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+import "./Counter.sol";
+contract CounterTest is DSTest {
+    function testIncrementTwice() external {
+        Counter counter = new Counter();
+        counter.increment();
+        counter.increment();
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let path = prj.root().join("callgraph.dot");
+    cmd.args(["test", "--export-callgraph"]).arg(&path);
+    assert!(cmd.stdout().contains("Call graph written to"));
+    let dot = std::fs::read_to_string(&path).unwrap();
+    assert!(dot.contains(
+        "\"CounterTest.testIncrementTwice()\" -> \"Counter.increment()\" [label=\"2\"];"
+    ));
+});
+
+// tests that `--output-dir` writes all results of the run into the directory
+forgetest!(can_write_output_dir, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "OutputTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract OutputTest is DSTest {
+    function testPass() external {}
+    function testRevert() external {
+        revert("nope");
+    }
+}
+   "#,
+        )
+        .unwrap();
+    // a contract of the same name in another file
+    prj.inner()
+        .add_source(
+            "nested/OutputTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "../test.sol";
+contract OutputTest is DSTest {
+    function testRevert() external {
+        revert("nested");
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let dir = prj.root().join("out-dir");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("notes.txt"), "keep").unwrap();
+    cmd.args(["test", "--output-dir"]).arg(&dir);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("Wrote {}", dir.join("report.junit.xml").display())));
+
+    let results = std::fs::read_to_string(dir.join("results.json")).unwrap();
+    assert!(results.contains("testRevert()"));
+    let junit = std::fs::read_to_string(dir.join("report.junit.xml")).unwrap();
+    assert!(junit.contains("<failure message=\"nope\"/>"));
+    let gas = std::fs::read_to_string(dir.join("gas.json")).unwrap();
+    assert!(gas.contains("testPass()"));
+    assert!(dir.join("traces/src/OutputTest.t.sol/OutputTest.testRevert().json").exists());
+    assert!(dir.join("traces/src/nested/OutputTest.t.sol/OutputTest.testRevert().json").exists());
+    assert!(!dir.join("traces/src/OutputTest.t.sol/OutputTest.testPass().json").exists());
+    assert_eq!(std::fs::read_to_string(dir.join("notes.txt")).unwrap(), "keep");
+});
+
+// tests that `--fork-blocks` requires a fork
+forgetest!(fork_blocks_require_fork_url, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "ForkTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ForkTest is DSTest {
+    function testBlock() external {
+        assertGt(block.number, 0);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--fork-blocks", "17000000,18000000"]);
+    cmd.assert_err();
+});
+
+// tests that `--fork-blocks` runs the tests once against every block
+forgetest!(can_run_tests_against_fork_blocks, |prj: TestProject, mut cmd: TestCommand| {
+    // Skip fork tests if the RPC url is not set.
+    let eth_rpc_url = match std::env::var("ETH_RPC_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("Skipping test can_run_tests_against_fork_blocks. ETH_RPC_URL is not set.");
+            return
+        }
+    };
+
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "ForkTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract ForkTest is DSTest {
+    function testBlock() external {
+        assertTrue(block.number == 14000000 || block.number == 14000001);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--json", "--fork-url", eth_rpc_url.as_str()]);
+    cmd.args(["--fork-blocks", "14000000,14000001"]);
+    let results: serde_json::Value = serde_json::from_str(&cmd.stdout()).unwrap();
+    for block in ["14000000", "14000001"] {
+        let suite = format!("src/ForkTest.t.sol:ForkTest@{}", block);
+        assert_eq!(results[&suite]["test_results"]["testBlock()"]["success"], true);
+    }
+});
+
+// tests that a project without tests prints how to write one
+forgetest!(hints_missing_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.inner()
+        .add_source(
+            "Counter.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public number;
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.arg("test");
+    assert!(cmd.stdout().contains("test files must end in `.t.sol`"));
+
+    cmd.forge_fuse().args(["test", "--no-hints"]);
+    assert!(!cmd.stdout().contains("No tests found"));
+});
+
+// tests that mutants are run against the tests calling the contract and reported with their
+// verdict
+forgetest!(can_report_surviving_mutants, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "Threshold.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Threshold {
+    function exceeds(uint256 value) external pure returns (bool) {
+        return value > 0x04d2;
+    }
+    function limit() external pure returns (uint256) {
+        return 0xbeef;
+    }
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "ThresholdTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+import "./Threshold.sol";
+contract ThresholdTest is DSTest {
+    function testExceeds() external {
+        assertTrue(new Threshold().exceeds(0x04d3));
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--mutate", "--max-mutants", "5"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("Running 5 of "));
+    assert!(stdout.contains(" of 5 mutants killed"));
+
+    // all mutants run, `value > 0x04d3` fails the test while no test checks the limit
+    cmd.forge_fuse().args(["test", "--mutate", "--max-mutants", "1000"]);
+    let stdout = cmd.stdout();
+    let verdict = |status: &str, mutation: &str| {
+        stdout.lines().any(|line| {
+            line.starts_with(status) && line.contains(&format!("Threshold: {} at pc ", mutation))
+        })
+    };
+    assert!(verdict("KILLED ", "constant 0xd2 -> 0xd3"), "{}", stdout);
+    assert!(verdict("SURVIVED ", "constant 0xef -> 0xf0"), "{}", stdout);
+    assert!(stdout.contains("  by src/ThresholdTest.t.sol:ThresholdTest::testExceeds()"));
+});
+
+// tests that tests above their gas target are reported, and fail with `--fail-on-gas-target`
+forgetest!(can_check_gas_targets, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "GasTargetTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract GasTargetTest is DSTest {
+    uint256 value;
+    /// @custom:gas-target 100 ±5%
+    function testStore() external {
+        value = 1;
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.arg("test");
+    let stderr = cmd.stderr_lossy();
+    assert!(stderr.contains("GasTargetTest::testStore() used"));
+    assert!(stderr.contains("above its target of 100 ±5%"));
+
+    cmd.arg("--fail-on-gas-target");
+    cmd.assert_err();
+});
+
+// tests that `--json-stream` prints one line of JSON per test contract
+forgetest!(can_stream_json_results, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "StreamTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract StreamOneTest is DSTest {
+    function testOne() external {}
+}
+contract StreamTwoTest is DSTest {
+    function testTwo() external {}
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--json-stream", "-vvvv"]);
+    let stdout = cmd.stdout();
+    // nothing but the results is printed to stdout, even with traces
+    let lines = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    for line in &lines {
+        assert_eq!(line.as_object().unwrap().len(), 1);
+    }
+    assert!(lines.iter().any(|line| {
+        line["src/StreamTest.t.sol:StreamOneTest"]["test_results"]["testOne()"]["success"] == true
+    }));
+    assert!(lines.iter().any(|line| {
+        line["src/StreamTest.t.sol:StreamTwoTest"]["test_results"]["testTwo()"]["success"] == true
+    }));
+});
+
+forgetest!(can_set_output_encoding, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "EncodingTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract EncodingTest is DSTest {
+    function testLog() external {
+        emit log_named_address("owner", 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed);
+        emit log_named_bytes("data", hex"dead");
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "-vv"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("owner: 0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+    assert!(stdout.contains("data: 0xdead"));
+
+    cmd.arg("--output-encoding=checksum,base64");
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("owner: 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+    assert!(stdout.contains("data: 3q0="));
+
+    // the encoding of the config is used unless the flag overrides it
+    prj.write_config(Config {
+        output_encoding: "checksum,base64".parse().unwrap(),
+        ..Default::default()
+    });
+    cmd.forge_fuse().args(["test", "-vv"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("owner: 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+    assert!(stdout.contains("data: 3q0="));
+
+    cmd.arg("--output-encoding=lowercase");
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("owner: 0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+    assert!(stdout.contains("data: 3q0="));
+});
+
+forgetest!(can_skip_tests_at_runtime, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "SkipTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+interface Vm {
+    function skip(bool) external;
+}
+contract SkipTest is DSTest {
+    Vm constant vm = Vm(HEVM_ADDRESS);
+    function testMainnetOnly() external {
+        vm.skip(block.chainid != 1);
+        assertTrue(false);
+    }
+    function testEverywhere() external {}
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("[SKIP] testMainnetOnly()"));
+    assert!(stdout.contains("[PASS] testEverywhere()"));
+    assert!(stdout.contains("1 passed; 0 failed; 1 skipped"));
+});
+
+// tests that `--format oneline` prints one line per test
+forgetest!(can_print_oneline_format, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "OnelineTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract OnelineTest is DSTest {
+    function testPass() external {}
+    function testFail() external {
+        revert();
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--format", "oneline"]);
+    let stdout = cmd.stdout();
+    let lines = stdout.lines().filter(|line| line.contains("OnelineTest::")).collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("PASS ") && lines[0].contains("OnelineTest::testFail() gas="));
+    assert!(lines[1].starts_with("PASS ") && lines[1].contains("OnelineTest::testPass() gas="));
+});
+
+// tests that failed setups are reported separately from failed tests
+forgetest!(can_report_setup_failures_separately, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "SetupTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract BrokenSetupTest is DSTest {
+    function setUp() public {
+        require(false, "broken harness");
+    }
+    function testNeverRuns() external {}
+}
+contract BrokenTest is DSTest {
+    function testBroken() external {
+        assertTrue(false);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let setups = stdout.find("Failed setups:").unwrap();
+    let tests = stdout.find("Failed tests:").unwrap();
+    assert!(setups < tests);
+    assert!(stdout[setups..tests].contains("broken harness"));
+    assert!(!stdout[tests..].contains("setUp()"));
+    assert!(stdout.contains("failing setups and") && stdout.contains("failing tests,"));
+});
+
+// tests that `--dry-run` prints the plan of the run without running any test
+forgetest!(can_dry_run, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "DryRunTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract DryRunTest is DSTest {
+    function testFails() external {
+        assertTrue(false);
+    }
+    function testFuzz(uint256 x) external {}
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--dry-run", "--match-contract", "DryRun", "--results-file", "out.jsonl"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("fuzz runs: 256"));
+    assert!(stdout.contains("filter: --match-contract DryRun"));
+    assert!(stdout.contains("Matched 2 tests:"));
+    assert!(stdout.contains("- testFuzz(uint256) [fuzz]"));
+    assert!(stdout.contains("results of every test to out.jsonl"));
+    assert!(!stdout.contains("[FAIL"));
+    assert!(!prj.root().join("out.jsonl").exists());
+
+    // the tests are not run to find those that call the covered function either
+    cmd.forge_fuse().args(["test", "--dry-run", "--covers", "DryRunTest.testFails"]);
+    let stdout = cmd.stdout();
+    assert!(stdout.contains("Matched 2 tests:"));
+    assert!(!stdout.contains("tests call"));
+});
+
+// tests that `--coverage` writes the lines of non-test contracts the tests executed as lcov
+forgetest!(can_write_lcov_report, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "Counter.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+    function reset() external {
+        number = 0;
+    }
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "CounterTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+import "./Counter.sol";
+contract CounterTest is DSTest {
+    function testIncrementTwice() external {
+        Counter counter = new Counter();
+        counter.increment();
+        counter.increment();
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--coverage", "--report", "lcov", "--output", "coverage.lcov"]);
+    assert!(cmd.stdout().contains("Line coverage: "));
+    let lcov = std::fs::read_to_string(prj.root().join("coverage.lcov")).unwrap();
+    assert!(lcov.contains("Counter.sol\nDA:"));
+    assert!(!lcov.contains("CounterTest.t.sol"));
+    // `number++` was executed, `number = 0` was not
+    assert!(lcov.lines().any(|line| line.starts_with("DA:7,") && line != "DA:7,0"));
+    assert!(lcov.contains("DA:10,0\n"));
+    assert!(lcov.contains("end_of_record\n"));
+
+    cmd.forge_fuse().args(["test", "--report", "lcov"]);
+    assert!(!cmd.try_output().status.success());
+});
+
+// tests that the lines of contracts created in fuzz cases are covered
+forgetest!(can_write_lcov_report_for_fuzz_tests, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "Counter.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+    function reset() external {
+        number = 0;
+    }
+}
+   "#,
+        )
+        .unwrap();
+    prj.inner()
+        .add_source(
+            "CounterTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+import "./Counter.sol";
+contract CounterTest is DSTest {
+    function testIncrement(uint8 times) external {
+        Counter counter = new Counter();
+        for (uint8 i = 0; i < times % 4; i++) {
+            counter.increment();
+        }
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--coverage", "--report", "lcov", "--output", "coverage.lcov"]);
+    cmd.assert_non_empty_stdout();
+    let lcov = std::fs::read_to_string(prj.root().join("coverage.lcov")).unwrap();
+    assert!(lcov.lines().any(|line| line.starts_with("DA:7,") && line != "DA:7,0"));
+    assert!(lcov.contains("DA:10,0\n"));
+});
+
+// tests that `--expect-events` fails on unexpected and missing events
+forgetest!(can_expect_events, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "EventsTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract Vault {
+    event Deposit(address indexed owner, uint256 amount);
+    event Skim(uint256 amount);
+    function deposit(uint256 amount) external {
+        emit Deposit(msg.sender, amount);
+    }
+    function skim() external {
+        emit Skim(1);
+    }
+}
+contract EventsTest is DSTest {
+    function testDeposit() external {
+        new Vault().deposit(1);
+        emit log("deposited");
+    }
+    function testSkim() external {
+        new Vault().skim();
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let schema = prj.root().join("events.json");
+    std::fs::write(&schema, r#"{"required": ["Deposit(address, uint256)", "Skim(uint256)"]}"#)
+        .unwrap();
+    cmd.args(["test", "--expect-events"]).arg(&schema);
+    assert!(cmd.output().status.success());
+
+    std::fs::write(&schema, r#"{"required": ["Deposit(address,uint256)", "Withdrawal(uint256)"]}"#)
+        .unwrap();
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("unexpected event Skim(uint256) emitted by"));
+    assert!(stderr.contains("required event Withdrawal(uint256) was not emitted by any test"));
+});
+
+// tests that the `post_test_hook` runs after all outputs were written, with the status of the
+// whole run, and does not change the exit code
+#[cfg(unix)]
+forgetest!(runs_post_test_hook, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.write_config(Config {
+        post_test_hook: Some(
+            "echo \"$FORGE_TEST_STATUS $FORGE_TEST_PASSED $FORGE_TEST_FAILED\" > hook.txt && \
+             ls results >> hook.txt && cat >> hook.txt && exit 1"
+                .to_string(),
+        ),
+        ..Default::default()
+    });
+
+    prj.inner()
+        .add_source(
+            "HookTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract HookTest is DSTest {
+    function testPass() external {}
+    function testFail() external {}
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--output-dir", "results"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("post test hook"));
+    let hook = std::fs::read_to_string(prj.root().join("hook.txt")).unwrap();
+    assert!(hook.starts_with("failed 1 1\n"));
+    assert!(hook.contains("report.junit.xml\n"));
+    assert!(hook.contains("\"testFail()\""));
+
+    // allowed failures pass the run
+    cmd.arg("--allow-failure");
+    cmd.assert_non_empty_stdout();
+    let hook = std::fs::read_to_string(prj.root().join("hook.txt")).unwrap();
+    assert!(hook.starts_with("passed 1 1\n"));
+
+    // a check that fails after the tests ran fails the run
+    cmd.args(["--check-code-size", "1"]);
+    assert!(!cmd.try_output().status.success());
+    let hook = std::fs::read_to_string(prj.root().join("hook.txt")).unwrap();
+    assert!(hook.starts_with("failed 1 1\n"));
+});
+
+// tests that `--flamegraph` writes the gas of every call frame in the folded stack format
+forgetest!(can_write_flamegraph, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "FlamegraphTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract Counter {
+    uint256 public number;
+    function increment() external {
+        number++;
+    }
+}
+contract FlamegraphTest is DSTest {
+    Counter counter;
+    function setUp() public {
+        counter = new Counter();
+    }
+    function testIncrement() external {
+        counter.increment();
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    let path = prj.root().join("gas.folded");
+    cmd.args(["test", "--flamegraph"]).arg(&path);
+    assert!(cmd.stdout().contains("Flamegraph written to"));
+    let folded = std::fs::read_to_string(&path).unwrap();
+    assert!(folded.lines().any(|line| line.starts_with("FlamegraphTest::testIncrement ")));
+    assert!(folded
+        .lines()
+        .any(|line| line.starts_with("FlamegraphTest::testIncrement;Counter::increment ")));
+    assert!(!folded.contains("setUp"));
+
+    // a dry run lists the flamegraph without writing it
+    std::fs::remove_file(&path).unwrap();
+    cmd.arg("--dry-run");
+    let stdout = cmd.stdout();
+    assert!(stdout.contains(&format!("gas flamegraph to {}", path.display())));
+    assert!(!path.exists());
+});
+
+// tests that `--compare --json` keeps stdout a single JSON document and prints the comparison to
+// stderr
+forgetest!(can_compare_json_results, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    let source = |tests: &str| {
+        format!(
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract CompareTest is DSTest {{
+    {}
+}}
+   "#,
+            tests
+        )
+    };
+    prj.inner().add_source("CompareTest.t.sol", source("function testOld() external {}")).unwrap();
+
+    cmd.args(["test", "--json"]);
+    let previous = prj.root().join("previous.json");
+    std::fs::write(&previous, cmd.stdout()).unwrap();
+
+    prj.inner()
+        .add_source(
+            "CompareTest.t.sol",
+            source("function testOld() external {}\n    function testNew() external {}"),
+        )
+        .unwrap();
+    cmd.arg("--compare").arg(&previous);
+    let output = cmd.output();
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(results["src/CompareTest.t.sol:CompareTest"]["test_results"]["testNew()"].is_object());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let diff: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(diff["added"][0]["test"], "testNew()");
+});
+
+// tests that `--bail` only reports skipped tests if it skipped any
+forgetest!(can_bail_after_failures, |prj: TestProject, mut cmd: TestCommand| {
+    prj.insert_ds_test();
+    prj.inner()
+        .add_source(
+            "BailTest.t.sol",
+            r#"
+// SPDX-License-Identifier: UNLICENSED
+pragma solidity 0.8.10;
+import "./test.sol";
+contract BailTest is DSTest {
+    function testA() external {
+        assertTrue(false);
+    }
+    function testB() external {
+        assertTrue(false);
+    }
+}
+   "#,
+        )
+        .unwrap();
+
+    cmd.args(["test", "--bail", "2"]);
+    let output = cmd.try_output();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("testA()"));
+    assert!(stdout.contains("testB()"));
+    assert!(!stdout.contains("remaining tests were skipped"));
+});
@@ -3,6 +3,7 @@ extern crate core;
 
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -23,6 +24,7 @@ use figment::{
     value::{Dict, Map, Value},
     Error, Figment, Metadata, Profile, Provider,
 };
+use foundry_utils::OutputEncoding;
 use inflector::Inflector;
 use semver::Version;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -136,12 +138,18 @@ pub struct Config {
     pub eth_rpc_url: Option<String>,
     /// etherscan API key
     pub etherscan_api_key: Option<String>,
+    /// Whether to silence the warning that traces of a forked chain can not be fully decoded
+    /// without an `etherscan_api_key`
+    pub silence_etherscan_warning: bool,
     /// list of solidity error codes to always silence in the compiler output
     pub ignored_error_codes: Vec<SolidityErrorCode>,
     /// The number of test cases that must execute for each property test
     pub fuzz_runs: u32,
     /// Whether to allow ffi cheatcodes in test
     pub ffi: bool,
+    /// Environment variables that must be set before running tests, e.g. RPC keys used by fork
+    /// tests
+    pub required_env: Vec<String>,
     /// The address which will be executing all tests
     pub sender: Address,
     /// The tx.origin value during EVM execution
@@ -168,6 +176,16 @@ pub struct Config {
     pub block_difficulty: u64,
     /// the `block.gaslimit` value during EVM execution
     pub block_gas_limit: Option<GasLimit>,
+    /// The memory limit of the EVM in bytes (16 MiB by default)
+    ///
+    /// Exceeding the limit makes the call fail with an out of gas error instead of aborting the
+    /// test run.
+    pub memory_limit: u64,
+    /// The gas the calls of tests are given, instead of the gas limit
+    ///
+    /// Deployments and `setUp` still get the gas limit. Tests that use more gas fail with an out
+    /// of gas error.
+    pub gas_stipend: Option<u64>,
     /// Additional output selection for all contracts
     /// such as "ir", "devodc", "storageLayout", etc.
     /// See [Solc Compiler Api](https://docs.soliditylang.org/en/latest/using-the-compiler.html#compiler-api)
@@ -202,6 +220,21 @@ pub struct Config {
     /// by proptest, to be encountered during usage of `vm.assume`
     /// cheatcode.
     pub fuzz_max_global_rejects: u32,
+    /// The weight with which fuzz inputs are drawn from the boundary values of their types, like
+    /// `0`, `type(uint256).max` or `address(0)`.
+    ///
+    /// The weight is relative to the combined weight (100) of all other input strategies, `0`
+    /// disables boundary values. The `boundary` weight of [FuzzWeightsConfig] takes precedence if
+    /// it is set.
+    pub fuzz_boundary_weight: u32,
+    /// The percentage of randomly generated `address` fuzz inputs that are drawn from a pool of
+    /// known addresses instead, like the test contract, the sender, deployed contracts,
+    /// `address(0)` and the precompiles.
+    ///
+    /// `0` disables the pool.
+    pub fuzz_address_pool_rate: u32,
+    /// The weights of the strategies fuzz inputs are drawn from, the `fuzz` table of a profile
+    pub fuzz: FuzzWeightsConfig,
     /// Print the names of the compiled contracts
     pub names: bool,
     /// Print the sizes of the compiled contracts
@@ -225,6 +258,29 @@ pub struct Config {
     /// included in solc's output selection, see also
     /// [OutputSelection](ethers_solc::artifacts::output_selection::OutputSelection)
     pub sparse_mode: bool,
+    /// Named subsets of the tests, run with `forge test --group <name>`
+    pub test_groups: BTreeMap<String, TestGroup>,
+    /// The prefixes of test functions, e.g. `["test", "check_"]`
+    ///
+    /// A test is expected to fail if its name continues with `Fail` after the prefix. An empty
+    /// prefix is rejected, it would make every function a test.
+    #[serde(deserialize_with = "deserialize_test_prefixes")]
+    pub test_prefixes: Vec<String>,
+    /// The directories test files are discovered in, e.g. `["test"]`. If empty, `.t.sol` files
+    /// in any directory are test files
+    pub test_dirs: Vec<PathBuf>,
+    /// How addresses and bytes are printed in logs and traces, a comma separated list of
+    /// `checksum` or `lowercase` for addresses and `hex` or `base64` for bytes
+    pub output_encoding: OutputEncoding,
+    /// A shell command that is run in the project root after `forge test` completed and wrote
+    /// all of its outputs, whether the run passed or not
+    ///
+    /// The command gets the results as JSON on stdin and the outcome in the `FORGE_TEST_STATUS`
+    /// (`failed` if a test or a check of the run failed, or the run errored, otherwise
+    /// `passed`), `FORGE_TEST_PASSED`, `FORGE_TEST_FAILED` and `FORGE_TEST_SKIPPED`
+    /// environment variables, and the path of the `--results-file` in `FORGE_TEST_RESULTS_FILE`
+    /// if one was written.
+    pub post_test_hook: Option<String>,
     /// The root path where the config detection started from, `Config::with_root`
     #[doc(hidden)]
     //  We're skipping serialization here, so it won't be included in the [`Config::to_string()`]
@@ -361,6 +417,7 @@ impl Config {
         self.out = p(&root, &self.out);
 
         self.libs = self.libs.into_iter().map(|lib| p(&root, &lib)).collect();
+        self.test_dirs = self.test_dirs.into_iter().map(|dir| p(&root, &dir)).collect();
 
         self.remappings =
             self.remappings.into_iter().map(|r| RelativeRemapping::new(r.into(), &root)).collect();
@@ -701,6 +758,8 @@ impl Config {
                 );
         }
         s = s.replace("[rpc_storage_caching]", &format!("[{}.rpc_storage_caching]", self.profile));
+        s = s.replace("[test_groups", &format!("[{}.test_groups", self.profile));
+        s = s.replace("[fuzz]", &format!("[{}.fuzz]", self.profile));
 
         Ok(format!(
             r#"[{}]
@@ -911,7 +970,11 @@ impl Default for Config {
             fuzz_runs: 256,
             fuzz_max_local_rejects: 1024,
             fuzz_max_global_rejects: 65536,
+            fuzz_boundary_weight: 0,
+            fuzz_address_pool_rate: 0,
+            fuzz: Default::default(),
             ffi: false,
+            required_env: vec![],
             sender: "00a329c0648769A73afAc7F9381E08FB43dBEA72".parse().unwrap(),
             tx_origin: "00a329c0648769A73afAc7F9381E08FB43dBEA72".parse().unwrap(),
             initial_balance: U256::from(0xffffffffffffffffffffffffu128),
@@ -925,8 +988,11 @@ impl Default for Config {
             block_timestamp: 0,
             block_difficulty: 0,
             block_gas_limit: None,
+            memory_limit: 2u64.pow(24),
+            gas_stipend: None,
             eth_rpc_url: None,
             etherscan_api_key: None,
+            silence_etherscan_warning: false,
             verbosity: 0,
             remappings: vec![],
             libraries: vec![],
@@ -937,10 +1003,75 @@ impl Default for Config {
             no_storage_caching: false,
             bytecode_hash: BytecodeHash::Ipfs,
             sparse_mode: false,
+            test_groups: Default::default(),
+            test_prefixes: vec!["test".to_string()],
+            test_dirs: vec![],
+            output_encoding: OutputEncoding::default(),
+            post_test_hook: None,
         }
     }
 }
 
+/// A named subset of the tests, configured as
+///
+/// ```toml
+/// [default.test_groups.smoke]
+/// contracts = ["CounterTest", "Token.*Test"]
+/// tests = ["testIncrement"]
+/// ```
+///
+/// The entries are names or regular expressions that must match the whole name of a contract or
+/// test function.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestGroup {
+    /// The test contracts of the group, all contracts if empty
+    #[serde(default)]
+    pub contracts: Vec<String>,
+    /// The test functions of the group, all test functions if empty
+    #[serde(default)]
+    pub tests: Vec<String>,
+}
+
+/// The weights of the strategies the inputs of fuzz tests are drawn from, configured as
+///
+/// ```toml
+/// [default.fuzz]
+/// random = 60
+/// state = 40
+/// boundary = 10
+/// zero = 5
+/// max = 5
+/// ```
+///
+/// The weights are relative, each strategy generates its weight's share of the total weight of
+/// the inputs. A weight of `0` disables the strategy, at least one weight must be greater than
+/// `0`. The defaults only draw random and state based inputs, like previous versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FuzzWeightsConfig {
+    /// Random values of the parameter types
+    pub random: u32,
+    /// Values collected from the state, like storage values and the addresses and logs of
+    /// previous fuzz cases
+    pub state: u32,
+    /// The boundary values of the parameter types, like `0`, `type(uint256).max`,
+    /// `type(int256).min`, `-1` or `address(0)`. Takes precedence over `fuzz_boundary_weight`,
+    /// which is used if this is not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boundary: Option<u32>,
+    /// Zero for all integer and address parameters, other parameters are random
+    pub zero: u32,
+    /// The maximum value of all integer parameters and `0xff..ff` for address parameters, other
+    /// parameters are random
+    pub max: u32,
+}
+
+impl Default for FuzzWeightsConfig {
+    fn default() -> Self {
+        Self { random: 60, state: 40, boundary: None, zero: 0, max: 0 }
+    }
+}
+
 /// Wrapper for the config's `gas_limit` value necessary because toml-rs can't handle larger number because integers are stored signed: <https://github.com/alexcrichton/toml-rs/issues/256>
 ///
 /// Due to this limitation this type will be serialized/deserialized as String if it's larger than
@@ -1389,6 +1520,18 @@ pub(crate) mod from_str_lowercase {
     }
 }
 
+/// Deserializes the `test_prefixes`, rejecting empty prefixes
+fn deserialize_test_prefixes<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let prefixes = Vec::<String>::deserialize(deserializer)?;
+    if prefixes.iter().any(String::is_empty) {
+        return Err(serde::de::Error::custom("`test_prefixes` must not contain an empty prefix"))
+    }
+    Ok(prefixes)
+}
+
 fn canonic(path: impl Into<PathBuf>) -> PathBuf {
     let path = path.into();
     ethers_solc::utils::canonicalize(&path).unwrap_or(path)
@@ -1669,6 +1812,35 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_fuzz_weights() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "foundry.toml",
+                r#"
+                [default]
+                fuzz_boundary_weight = 25
+
+                [default.fuzz]
+                zero = 5
+                max = 10
+            "#,
+            )?;
+
+            let config = Config::load();
+            assert_eq!(
+                config.fuzz,
+                FuzzWeightsConfig { random: 60, state: 40, boundary: None, zero: 5, max: 10 }
+            );
+            assert_eq!(config.fuzz_boundary_weight, 25);
+
+            jail.create_file("foundry.toml", &config.to_string_pretty().unwrap())?;
+            assert_eq!(Config::load().fuzz, config.fuzz);
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn test_solc_req() {
         figment::Jail::expect_with(|jail| {
@@ -1925,6 +2097,32 @@ mod tests {
         });
     }
 
+    #[test]
+    fn rejects_empty_test_prefix() {
+        figment::Jail::expect_with(|jail| {
+            jail.create_file(
+                "foundry.toml",
+                r#"
+                [default]
+                test_prefixes = ["test", "check_"]
+            "#,
+            )?;
+            let config = Config::try_from(Config::figment()).unwrap();
+            assert_eq!(config.test_prefixes, vec!["test".to_string(), "check_".to_string()]);
+
+            jail.create_file(
+                "foundry.toml",
+                r#"
+                [default]
+                test_prefixes = ["test", ""]
+            "#,
+            )?;
+            assert!(Config::try_from(Config::figment()).is_err());
+
+            Ok(())
+        });
+    }
+
     #[test]
     fn config_roundtrip() {
         figment::Jail::expect_with(|jail| {
@@ -1,24 +1,28 @@
 //! Various utilities to decode test results
 use crate::abi::ConsoleEvents::{self, *};
 use ethers::{abi::RawLog, contract::EthLogDecode};
+use foundry_utils::OutputEncoding;
+use std::collections::BTreeMap;
 
 /// Decode a set of logs, only returning logs from DSTest logging events and Hardhat's `console.log`
-pub fn decode_console_logs(logs: &[RawLog]) -> Vec<String> {
-    logs.iter().filter_map(decode_console_log).collect()
+///
+/// Addresses and bytes are printed in the given encoding.
+pub fn decode_console_logs(logs: &[RawLog], encoding: OutputEncoding) -> Vec<String> {
+    logs.iter().filter_map(|log| decode_console_log(log, encoding)).collect()
 }
 
 /// Decode a single log.
 ///
 /// This function returns [None] if it is not a DSTest log or the result of a Hardhat
 /// `console.log`.
-pub fn decode_console_log(log: &RawLog) -> Option<String> {
+pub fn decode_console_log(log: &RawLog, encoding: OutputEncoding) -> Option<String> {
     let decoded = match ConsoleEvents::decode_log(log).ok()? {
-        LogsFilter(inner) => format!("{}", inner.0),
-        LogBytesFilter(inner) => format!("{}", inner.0),
-        LogNamedAddressFilter(inner) => format!("{}: {:?}", inner.key, inner.val),
-        LogNamedBytes32Filter(inner) => {
-            format!("{}: 0x{}", inner.key, hex::encode(inner.val))
-        }
+        LogsFilter(inner) => encoding.bytes(&inner.0),
+        LogBytesFilter(inner) => encoding.bytes(&inner.0),
+        LogAddressFilter(inner) => encoding.address(&inner.0),
+        LogBytes32Filter(inner) => encoding.bytes(&inner.0),
+        LogNamedAddressFilter(inner) => format!("{}: {}", inner.key, encoding.address(&inner.val)),
+        LogNamedBytes32Filter(inner) => format!("{}: {}", inner.key, encoding.bytes(&inner.val)),
         LogNamedDecimalIntFilter(inner) => {
             let (sign, val) = inner.val.into_sign_and_abs();
             format!(
@@ -37,12 +41,83 @@ pub fn decode_console_log(log: &RawLog) -> Option<String> {
         }
         LogNamedIntFilter(inner) => format!("{}: {:?}", inner.key, inner.val),
         LogNamedUintFilter(inner) => format!("{}: {:?}", inner.key, inner.val),
-        LogNamedBytesFilter(inner) => {
-            format!("{}: 0x{}", inner.key, hex::encode(inner.val))
-        }
+        LogNamedBytesFilter(inner) => format!("{}: {}", inner.key, encoding.bytes(&inner.val)),
         LogNamedStringFilter(inner) => format!("{}: {}", inner.key, inner.val),
 
         e => e.to_string(),
     };
     Some(decoded)
 }
+
+/// Post-processes decoded console logs into structured fields
+pub trait LogProcessor: Send + Sync {
+    /// Returns the fields of the log, or [None] if this processor does not handle the log
+    fn process(&self, log: &str) -> Option<BTreeMap<String, String>>;
+}
+
+/// A [LogProcessor] for logs that are JSON objects, e.g. `console.log('{"swaps": 3}')`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonLogProcessor;
+
+impl LogProcessor for JsonLogProcessor {
+    fn process(&self, log: &str) -> Option<BTreeMap<String, String>> {
+        let object =
+            serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(log.trim()).ok()?;
+        Some(
+            object
+                .into_iter()
+                .map(|(key, value)| match value {
+                    serde_json::Value::String(value) => (key, value),
+                    value => (key, value.to_string()),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Runs the decoded console logs through the processors.
+///
+/// Returns the logs that no processor handled and the fields extracted from all handled logs,
+/// where fields of later logs overwrite fields of earlier logs with the same key.
+pub fn process_console_logs(
+    logs: Vec<String>,
+    processors: &[Box<dyn LogProcessor>],
+) -> (Vec<String>, BTreeMap<String, String>) {
+    let mut fields = BTreeMap::new();
+    let unmatched = logs
+        .into_iter()
+        .filter(|log| match processors.iter().find_map(|processor| processor.process(log)) {
+            Some(log_fields) => {
+                fields.extend(log_fields);
+                false
+            }
+            None => true,
+        })
+        .collect();
+    (unmatched, fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_process_json_logs() {
+        let processors: Vec<Box<dyn LogProcessor>> = vec![Box::new(JsonLogProcessor)];
+        let logs = vec![
+            "plain log".to_string(),
+            r#"{"swaps": 3, "pool": "WETH/DAI"}"#.to_string(),
+            "[1, 2]".to_string(),
+        ];
+
+        let (unmatched, fields) = process_console_logs(logs, &processors);
+        assert_eq!(unmatched, vec!["plain log".to_string(), "[1, 2]".to_string()]);
+        assert_eq!(
+            fields,
+            BTreeMap::from([
+                ("pool".to_string(), "WETH/DAI".to_string()),
+                ("swaps".to_string(), "3".to_string())
+            ])
+        );
+    }
+}
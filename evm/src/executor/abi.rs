@@ -41,6 +41,7 @@ ethers::contract::abigen!(
             getCode(string)
             label(address,string)
             assume(bool)
+            skip(bool)
             setNonce(address,uint64)
             getNonce(address)
     ]"#,
@@ -3,17 +3,27 @@ use revm::{
     db::{DatabaseRef, EmptyDB},
     Env, SpecId,
 };
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use super::{
     fork::SharedBackend,
-    inspector::{Cheatcodes, InspectorStackConfig},
+    inspector::{
+        CallGuard, Cheatcodes, CustomPrecompiles, InspectorStackConfig, OpcodeGuard, TxOrigin,
+    },
     Executor,
 };
 
-use ethers::types::{H160, H256, U256};
+use ethers::{
+    abi::Event,
+    types::{H160, H256, U256},
+};
 
 use crate::executor::fork::{BlockchainDb, BlockchainDbMeta};
+use foundry_utils::OutputEncoding;
 
 use revm::AccountInfo;
 
@@ -137,6 +147,29 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Records the names of the cheatcodes invoked in calls, see [Cheatcodes::used_cheatcodes].
+    ///
+    /// This has no effect if cheatcodes are not enabled.
+    #[must_use]
+    pub fn with_cheatcode_recording(mut self) -> Self {
+        if let Some(cheatcodes) = self.inspector_config.cheatcodes.as_mut() {
+            cheatcodes.used_cheatcodes = Some(BTreeSet::new());
+        }
+        self
+    }
+
+    /// Sets the known events by signature, used to decode the emitted and expected events in
+    /// `vm.expectEmit` failures, see [Cheatcodes::events].
+    ///
+    /// This has no effect if cheatcodes are not enabled.
+    #[must_use]
+    pub fn with_known_events(mut self, events: Arc<BTreeMap<H256, Event>>) -> Self {
+        if let Some(cheatcodes) = self.inspector_config.cheatcodes.as_mut() {
+            cheatcodes.events = events;
+        }
+        self
+    }
+
     /// Enables tracing
     #[must_use]
     pub fn with_tracing(mut self) -> Self {
@@ -151,6 +184,77 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Reverts calls to contracts that were not deployed during execution, unless they are in
+    /// `allowed`
+    #[must_use]
+    pub fn with_call_guard(mut self, allowed: BTreeSet<H160>) -> Self {
+        self.inspector_config.call_guard = Some(CallGuard::new(allowed));
+        self
+    }
+
+    /// Prints addresses and bytes of Hardhat-style logs in the encoding
+    #[must_use]
+    pub fn with_output_encoding(mut self, encoding: OutputEncoding) -> Self {
+        self.inspector_config.output_encoding = encoding;
+        self
+    }
+
+    /// Answers calls to the addresses of the custom precompiles with their handlers, see
+    /// [CustomPrecompiles](super::inspector::CustomPrecompiles)
+    #[must_use]
+    pub fn with_precompiles(mut self, precompiles: CustomPrecompiles) -> Self {
+        self.inspector_config.precompiles = Some(precompiles);
+        self
+    }
+
+    /// Makes the given opcodes unavailable, executing them reverts, see
+    /// [OpcodeGuard](super::inspector::OpcodeGuard)
+    #[must_use]
+    pub fn with_disabled_opcodes(mut self, opcodes: BTreeSet<u8>) -> Self {
+        self.inspector_config.opcode_guard = Some(OpcodeGuard::new(opcodes));
+        self
+    }
+
+    /// Sets `tx.origin` of the top-level calls, which is the caller otherwise, see
+    /// [TxOrigin](super::inspector::TxOrigin)
+    #[must_use]
+    pub fn with_tx_origin(mut self, origin: H160) -> Self {
+        self.inspector_config.tx_origin = Some(TxOrigin::new(origin));
+        self
+    }
+
+    /// Records the maximum call depth reached in calls, see
+    /// [CallDepth](super::inspector::CallDepth)
+    #[must_use]
+    pub fn with_call_depth_recording(mut self) -> Self {
+        self.inspector_config.call_depth = true;
+        self
+    }
+
+    /// Records the directions taken at conditional jumps in calls, see
+    /// [BranchRecorder](super::inspector::BranchRecorder)
+    #[must_use]
+    pub fn with_branch_recording(mut self) -> Self {
+        self.inspector_config.branches = true;
+        self
+    }
+
+    /// Records the gas refunded to calls, e.g. for clearing storage, see
+    /// [GasRefunds](super::inspector::GasRefunds)
+    #[must_use]
+    pub fn with_gas_refund_recording(mut self) -> Self {
+        self.inspector_config.gas_refunds = true;
+        self
+    }
+
+    /// Counts how often every instruction is executed in calls, see
+    /// [InstructionRecorder](super::inspector::InstructionRecorder)
+    #[must_use]
+    pub fn with_instruction_recording(mut self) -> Self {
+        self.inspector_config.instructions = true;
+        self
+    }
+
     /// Sets the EVM spec to use
     #[must_use]
     pub fn with_spec(mut self, spec: SpecId) -> Self {
@@ -158,6 +262,15 @@ impl ExecutorBuilder {
         self
     }
 
+    /// Sets the memory limit of the EVM in bytes.
+    ///
+    /// Calls that exceed the memory limit fail with an out of gas error.
+    #[must_use]
+    pub fn with_memory_limit(mut self, memory_limit: u64) -> Self {
+        self.env.cfg.memory_limit = memory_limit;
+        self
+    }
+
     /// Sets the executor gas limit.
     ///
     /// See [Executor::gas_limit] for more info on why you might want to set this.
@@ -171,6 +284,7 @@ impl ExecutorBuilder {
     #[must_use]
     pub fn with_config(mut self, env: Env) -> Self {
         self.inspector_config.block = env.block.clone();
+        self.inspector_config.gas_price = env.tx.gas_price;
         self.env = env;
         self
     }
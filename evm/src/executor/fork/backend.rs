@@ -16,9 +16,14 @@ use futures::{
 };
 
 use std::{
+    cell::RefCell,
     collections::{hash_map::Entry, HashMap, VecDeque},
     pin::Pin,
-    sync::mpsc::{channel as oneshot_channel, Sender as OneshotSender},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel as oneshot_channel, Sender as OneshotSender},
+        Arc,
+    },
 };
 use tracing::{trace, warn};
 
@@ -27,6 +32,35 @@ type AccountFuture<Err> =
 type StorageFuture<Err> = Pin<Box<dyn Future<Output = (Result<U256, Err>, Address, U256)> + Send>>;
 type BlockHashFuture<Err> = Pin<Box<dyn Future<Output = (Result<H256, Err>, u64)> + Send>>;
 
+/// Counts the RPC calls made on behalf of a request, see [count_rpc_calls()]
+type RpcCallCounter = Option<Arc<AtomicUsize>>;
+
+/// The number of RPC calls needed to fetch an account: its balance, nonce and code
+const ACCOUNT_RPC_CALLS: usize = 3;
+
+thread_local! {
+    /// The counter of the innermost [count_rpc_calls()] scope of the current thread
+    static RPC_CALL_COUNTER: RefCell<RpcCallCounter> = RefCell::new(None);
+}
+
+/// Runs `f` and returns its result together with the number of RPC calls the fork backends made
+/// to serve the requests `f` made on the current thread.
+///
+/// Values that are already cached, or already being fetched for another request, do not count.
+/// Scopes can be nested, the calls of an inner scope are also counted by the outer scope.
+pub fn count_rpc_calls<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let outer = RPC_CALL_COUNTER.with(|current| current.replace(Some(counter.clone())));
+    let result = f();
+    RPC_CALL_COUNTER.with(|current| *current.borrow_mut() = outer.clone());
+
+    let calls = counter.load(Ordering::Relaxed);
+    if let Some(outer) = outer {
+        outer.fetch_add(calls, Ordering::Relaxed);
+    }
+    (result, calls)
+}
+
 /// Request variants that are executed by the provider
 enum ProviderRequest<Err> {
     Account(AccountFuture<Err>),
@@ -37,9 +71,9 @@ enum ProviderRequest<Err> {
 /// The Request type the Backend listens for
 #[derive(Debug)]
 enum BackendRequest {
-    Basic(Address, OneshotSender<AccountInfo>),
-    Storage(Address, U256, OneshotSender<U256>),
-    BlockHash(u64, OneshotSender<H256>),
+    Basic(Address, OneshotSender<AccountInfo>, RpcCallCounter),
+    Storage(Address, U256, OneshotSender<U256>, RpcCallCounter),
+    BlockHash(u64, OneshotSender<H256>, RpcCallCounter),
 }
 
 /// Handles an internal provider and listens for requests.
@@ -66,6 +100,8 @@ struct BackendHandler<M: Middleware> {
     /// The block to fetch data from.
     // This is an `Option` so that we can have less code churn in the functions below
     block_id: Option<BlockId>,
+    /// The total number of RPC calls made
+    rpc_calls: Arc<AtomicUsize>,
 }
 
 impl<M> BackendHandler<M>
@@ -77,6 +113,7 @@ where
         db: BlockchainDb,
         rx: Receiver<BackendRequest>,
         block_id: Option<BlockId>,
+        rpc_calls: Arc<AtomicUsize>,
     ) -> Self {
         Self {
             provider,
@@ -88,6 +125,15 @@ where
             queued_requests: Default::default(),
             incoming: rx,
             block_id,
+            rpc_calls,
+        }
+    }
+
+    /// Records `calls` RPC calls made on behalf of the requester owning `counter`
+    fn count_rpc_calls(&self, calls: usize, counter: RpcCallCounter) {
+        self.rpc_calls.fetch_add(calls, Ordering::Relaxed);
+        if let Some(counter) = counter {
+            counter.fetch_add(calls, Ordering::Relaxed);
         }
     }
 
@@ -99,7 +145,7 @@ where
     /// progress (e.g. another Sender just requested the same account)
     fn on_request(&mut self, req: BackendRequest) {
         match req {
-            BackendRequest::Basic(addr, sender) => {
+            BackendRequest::Basic(addr, sender, counter) => {
                 trace!(target: "backendhandler", "received request basic address={:?}", addr);
                 let lock = self.db.accounts().read();
                 let basic = lock.get(&addr).cloned();
@@ -108,10 +154,10 @@ where
                 if let Some(basic) = basic {
                     let _ = sender.send(basic);
                 } else {
-                    self.request_account(addr, sender);
+                    self.request_account(addr, sender, counter);
                 }
             }
-            BackendRequest::BlockHash(number, sender) => {
+            BackendRequest::BlockHash(number, sender, counter) => {
                 let lock = self.db.block_hashes().read();
                 let hash = lock.get(&number).cloned();
                 // release the lock
@@ -119,10 +165,10 @@ where
                 if let Some(hash) = hash {
                     let _ = sender.send(hash);
                 } else {
-                    self.request_hash(number, sender);
+                    self.request_hash(number, sender, counter);
                 }
             }
-            BackendRequest::Storage(addr, idx, sender) => {
+            BackendRequest::Storage(addr, idx, sender, counter) => {
                 let lock = self.db.storage().read();
                 let acc = lock.get(&addr);
                 let value = acc.and_then(|acc| acc.get(&idx).copied());
@@ -134,7 +180,7 @@ where
                     let _ = sender.send(value);
                 } else {
                     // account present but not storage -> fetch storage
-                    self.request_account_storage(addr, idx, sender);
+                    self.request_account_storage(addr, idx, sender, counter);
                 }
             }
         }
@@ -146,6 +192,7 @@ where
         address: Address,
         idx: U256,
         listener: OneshotSender<U256>,
+        counter: RpcCallCounter,
     ) {
         match self.storage_requests.entry((address, idx)) {
             Entry::Occupied(mut entry) => {
@@ -154,6 +201,7 @@ where
             Entry::Vacant(entry) => {
                 trace!(target: "backendhandler", "preparing storage request, address={:?}, idx={}", address, idx);
                 entry.insert(vec![listener]);
+                self.count_rpc_calls(1, counter);
                 let provider = self.provider.clone();
                 let block_id = self.block_id;
                 let fut = Box::pin(async move {
@@ -185,20 +233,31 @@ where
     }
 
     /// process a request for an account
-    fn request_account(&mut self, address: Address, listener: OneshotSender<AccountInfo>) {
+    fn request_account(
+        &mut self,
+        address: Address,
+        listener: OneshotSender<AccountInfo>,
+        counter: RpcCallCounter,
+    ) {
         match self.account_requests.entry(address) {
             Entry::Occupied(mut entry) => {
                 entry.get_mut().push(listener);
             }
             Entry::Vacant(entry) => {
                 entry.insert(vec![listener]);
+                self.count_rpc_calls(ACCOUNT_RPC_CALLS, counter);
                 self.pending_requests.push(self.get_account_req(address));
             }
         }
     }
 
     /// process a request for a block hash
-    fn request_hash(&mut self, number: u64, listener: OneshotSender<H256>) {
+    fn request_hash(
+        &mut self,
+        number: u64,
+        listener: OneshotSender<H256>,
+        counter: RpcCallCounter,
+    ) {
         match self.block_requests.entry(number) {
             Entry::Occupied(mut entry) => {
                 entry.get_mut().push(listener);
@@ -206,6 +265,7 @@ where
             Entry::Vacant(entry) => {
                 trace!(target: "backendhandler", "preparing block hash request, number={}", number);
                 entry.insert(vec![listener]);
+                self.count_rpc_calls(1, counter);
                 let provider = self.provider.clone();
                 let fut = Box::pin(async move {
                     let res = provider.get_block(number).await;
@@ -372,6 +432,8 @@ impl<M: Middleware> Drop for BackendHandler<M> {
 pub struct SharedBackend {
     /// channel used for sending commands related to database operations
     backend: Sender<BackendRequest>,
+    /// The total number of RPC calls made by the `BackendHandler`
+    rpc_calls: Arc<AtomicUsize>,
 }
 
 impl SharedBackend {
@@ -387,31 +449,43 @@ impl SharedBackend {
         M: Middleware + Unpin + 'static + Clone,
     {
         let (backend, backend_rx) = channel(1);
-        let handler = BackendHandler::new(provider, db, backend_rx, pin_block);
+        let rpc_calls = Arc::new(AtomicUsize::new(0));
+        let handler = BackendHandler::new(provider, db, backend_rx, pin_block, rpc_calls.clone());
         // spawn the provider handler to background
         trace!(target: "backendhandler", "spawning Backendhandler");
         tokio::spawn(handler);
 
-        Self { backend }
+        Self { backend, rpc_calls }
+    }
+
+    /// Returns the total number of RPC calls made to fetch missing data so far, by this backend
+    /// and all its clones
+    pub fn rpc_calls(&self) -> usize {
+        self.rpc_calls.load(Ordering::Relaxed)
+    }
+
+    /// Returns the counter of the current [count_rpc_calls()] scope, if any
+    fn rpc_call_counter() -> RpcCallCounter {
+        RPC_CALL_COUNTER.with(|counter| counter.borrow().clone())
     }
 
     fn do_get_basic(&self, address: Address) -> eyre::Result<AccountInfo> {
         let (sender, rx) = oneshot_channel();
-        let req = BackendRequest::Basic(address, sender);
+        let req = BackendRequest::Basic(address, sender, Self::rpc_call_counter());
         self.backend.clone().try_send(req).map_err(|e| eyre::eyre!("{:?}", e))?;
         Ok(rx.recv()?)
     }
 
     fn do_get_storage(&self, address: Address, index: U256) -> eyre::Result<U256> {
         let (sender, rx) = oneshot_channel();
-        let req = BackendRequest::Storage(address, index, sender);
+        let req = BackendRequest::Storage(address, index, sender, Self::rpc_call_counter());
         self.backend.clone().try_send(req).map_err(|e| eyre::eyre!("{:?}", e))?;
         Ok(rx.recv()?)
     }
 
     fn do_get_block_hash(&self, number: u64) -> eyre::Result<H256> {
         let (sender, rx) = oneshot_channel();
-        let req = BackendRequest::BlockHash(number, sender);
+        let req = BackendRequest::BlockHash(number, sender, Self::rpc_call_counter());
         self.backend.clone().try_send(req).map_err(|e| eyre::eyre!("{:?}", e))?;
         Ok(rx.recv()?)
     }
@@ -452,8 +526,9 @@ impl DatabaseRef for SharedBackend {
 #[cfg(test)]
 mod tests {
     use crate::executor::{
+        builder::Backend,
         fork::{BlockchainDbMeta, JsonBlockCacheDB},
-        Fork,
+        ExecutorBuilder, Fork,
     };
     use ethers::{
         providers::{Http, Provider},
@@ -484,8 +559,13 @@ mod tests {
         let address: Address = "63091244180ae240c87d1f528f5f269134cb07b3".parse().unwrap();
 
         let idx = U256::from(0u64);
-        let value = backend.storage(address, idx);
+        let (value, calls) = count_rpc_calls(|| backend.storage(address, idx));
+        assert_eq!(calls, 1);
         let account = backend.basic(address);
+        // cached values are not fetched again
+        let (_, calls) = count_rpc_calls(|| backend.storage(address, idx));
+        assert_eq!(calls, 0);
+        assert_eq!(backend.rpc_calls(), 1 + ACCOUNT_RPC_CALLS);
 
         let mem_acc = db.accounts().read().get(&address).unwrap().clone();
         assert_eq!(account.balance, mem_acc.balance);
@@ -511,6 +591,37 @@ mod tests {
         assert_eq!(slots.len() as u64, max_slots);
     }
 
+    #[test]
+    fn forked_executors_share_reads_but_not_writes() {
+        let provider = Provider::<Http>::try_from(ENDPOINT).unwrap();
+        let meta = BlockchainDbMeta {
+            cfg_env: Default::default(),
+            block_env: Default::default(),
+            hosts: BTreeSet::from([ENDPOINT.to_string()]),
+        };
+        let db = BlockchainDb::new(meta, None);
+        let runtime = RuntimeOrHandle::new();
+        let backend =
+            runtime.block_on(SharedBackend::spawn_backend(Arc::new(provider), db.clone(), None));
+
+        // test contracts that declare the same fork run in executors built on clones of its backend
+        let mut writer = ExecutorBuilder::new().build(Backend::Forked(backend.clone()));
+        let reader = ExecutorBuilder::new().build(Backend::Forked(backend));
+
+        let address: Address = "63091244180ae240c87d1f528f5f269134cb07b3".parse().unwrap();
+        let idx = U256::from(0u64);
+        let original = DatabaseRef::storage(&writer.db, address, idx);
+        writer.set_storage(address, idx, original + 1);
+        assert_eq!(DatabaseRef::storage(&writer.db, address, idx), original + 1);
+
+        // the write stays in the writer's database, the reader gets the fetched value from the
+        // shared cache without fetching it again
+        let (value, calls) = count_rpc_calls(|| DatabaseRef::storage(&reader.db, address, idx));
+        assert_eq!(value, original);
+        assert_eq!(calls, 0);
+        assert_eq!(db.storage().read()[&address][&idx], original);
+    }
+
     #[test]
     fn can_read_cache() {
         let cache_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-data/storage.json");
@@ -1,13 +1,17 @@
-use ethers::{providers::Middleware, types::Address};
+use ethers::{
+    providers::Middleware,
+    types::{Address, U256},
+};
 use revm::{BlockEnv, CfgEnv, Env, TxEnv};
 
 /// Initializes a REVM block environment based on a forked
-/// ethereum provider.
+/// ethereum provider, with the given EVM memory limit in bytes.
 pub async fn environment<M: Middleware>(
     provider: &M,
     override_chain_id: Option<u64>,
     pin_block: Option<u64>,
     origin: Address,
+    memory_limit: u64,
 ) -> Result<Env, M::Error> {
     let block_number = if let Some(pin_block) = pin_block {
         pin_block
@@ -24,15 +28,21 @@ pub async fn environment<M: Middleware>(
     Ok(Env {
         cfg: CfgEnv {
             chain_id: override_chain_id.unwrap_or(rpc_chain_id.as_u64()).into(),
-            // 16 MiB
-            memory_limit: 2u64.pow(24),
+            memory_limit,
             ..Default::default()
         },
         block: BlockEnv {
             number: block.number.expect("block number not found").as_u64().into(),
             timestamp: block.timestamp,
             coinbase: block.author,
-            difficulty: block.difficulty,
+            // the `DIFFICULTY` opcode returns the prevrandao of post-merge blocks, which is their
+            // mix hash
+            difficulty: match block.mix_hash {
+                Some(mix_hash) if block.difficulty.is_zero() => {
+                    U256::from_big_endian(mix_hash.as_bytes())
+                }
+                _ => block.difficulty,
+            },
             basefee: block.base_fee_per_gas.unwrap_or_default(),
             gas_limit: block.gas_limit,
         },
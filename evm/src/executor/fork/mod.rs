@@ -1,5 +1,5 @@
 mod backend;
-pub use backend::SharedBackend;
+pub use backend::{count_rpc_calls, SharedBackend};
 
 mod init;
 pub use init::environment;
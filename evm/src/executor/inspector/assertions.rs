@@ -0,0 +1,42 @@
+use ethers::types::Address;
+use revm::{db::Database, EVMData, Inspector, Interpreter, Return};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+/// An inspector that counts the assertions executed by contracts.
+///
+/// Assertions like DSTest's `assertEq` are internal functions, calling them is a jump into the
+/// function. The program counters of these jumps are determined from the source maps of the
+/// contracts before execution, the inspector counts how often they are executed.
+#[derive(Clone, Debug, Default)]
+pub struct AssertionCounter {
+    /// The program counters of the jumps into assertion functions, by contract address
+    jumps: BTreeMap<Address, Arc<BTreeSet<usize>>>,
+    /// The number of executed assertions
+    pub count: usize,
+}
+
+impl AssertionCounter {
+    /// Counts the assertions of the contract at `address`, given the program counters of the
+    /// jumps into assertion functions in its code
+    pub fn insert(&mut self, address: Address, jumps: BTreeSet<usize>) {
+        self.jumps.insert(address, Arc::new(jumps));
+    }
+}
+
+impl<DB> Inspector<DB> for AssertionCounter
+where
+    DB: Database,
+{
+    fn step(&mut self, interpreter: &mut Interpreter, _: &mut EVMData<'_, DB>, _: bool) -> Return {
+        if let Some(jumps) = self.jumps.get(&interpreter.contract.address) {
+            if jumps.contains(&interpreter.program_counter()) {
+                self.count += 1;
+            }
+        }
+
+        Return::Continue
+    }
+}
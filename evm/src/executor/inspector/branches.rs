@@ -0,0 +1,76 @@
+use bytes::Bytes;
+use ethers::types::Address;
+use revm::{db::Database, opcode, CreateInputs, EVMData, Gas, Inspector, Interpreter, Return};
+use std::collections::BTreeMap;
+
+/// The directions taken at conditional jumps, by contract address and program counter of the
+/// `JUMPI` instruction. The first direction is the jump, the second falling through.
+pub type BranchHits = BTreeMap<Address, BTreeMap<usize, [bool; 2]>>;
+
+/// Adds the directions taken in `other` to `hits`
+pub fn merge_branch_hits(hits: &mut BranchHits, other: &BranchHits) {
+    for (address, branches) in other {
+        let merged = hits.entry(*address).or_default();
+        for (pc, taken) in branches {
+            let directions = merged.entry(*pc).or_default();
+            directions[0] |= taken[0];
+            directions[1] |= taken[1];
+        }
+    }
+}
+
+/// An inspector that records which direction conditional jumps take.
+///
+/// Jumps are recorded by the address of the contract that executes them, so code run with
+/// `DELEGATECALL` is attributed to the calling contract. Constructors are not recorded.
+#[derive(Clone, Debug, Default)]
+pub struct BranchRecorder {
+    /// The recorded directions
+    pub hits: BranchHits,
+    /// The number of contract creations in progress
+    creating: usize,
+}
+
+impl<DB> Inspector<DB> for BranchRecorder
+where
+    DB: Database,
+{
+    fn step(&mut self, interpreter: &mut Interpreter, _: &mut EVMData<'_, DB>, _: bool) -> Return {
+        let pc = interpreter.program_counter();
+        if self.creating > 0 || interpreter.contract.code[pc] != opcode::JUMPI {
+            return Return::Continue
+        }
+        // the condition is below the jump destination on the stack
+        if let Ok(condition) = interpreter.stack().peek(1) {
+            let direction = if condition.is_zero() { 1 } else { 0 };
+            let branches = self.hits.entry(interpreter.contract.address).or_default();
+            branches.entry(pc).or_default()[direction] = true;
+        }
+
+        Return::Continue
+    }
+
+    fn create(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &mut CreateInputs,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        self.creating += 1;
+
+        (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn create_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CreateInputs,
+        status: Return,
+        address: Option<Address>,
+        remaining_gas: Gas,
+        retdata: Bytes,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        self.creating = self.creating.saturating_sub(1);
+
+        (status, address, remaining_gas, retdata)
+    }
+}
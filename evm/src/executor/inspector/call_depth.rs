@@ -0,0 +1,48 @@
+use crate::executor::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS};
+use bytes::Bytes;
+use ethers::types::Address;
+use revm::{db::Database, CallInputs, CreateInputs, EVMData, Gas, Inspector, Return};
+
+/// An inspector that records the maximum call depth reached during execution.
+///
+/// The top-level call is at depth 0 and every nested call or contract creation is one level
+/// deeper. Calls to the cheatcode and console addresses are not counted.
+#[derive(Clone, Debug, Default)]
+pub struct CallDepth {
+    /// The maximum depth reached so far
+    pub max_depth: usize,
+}
+
+impl CallDepth {
+    fn record(&mut self, depth: usize) {
+        self.max_depth = self.max_depth.max(depth);
+    }
+}
+
+impl<DB> Inspector<DB> for CallDepth
+where
+    DB: Database,
+{
+    fn call(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &mut CallInputs,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        if call.contract != CHEATCODE_ADDRESS && call.contract != HARDHAT_CONSOLE_ADDRESS {
+            self.record(data.subroutine.depth() as usize);
+        }
+
+        (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn create(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &mut CreateInputs,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        self.record(data.subroutine.depth() as usize);
+
+        (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{
+    abi::HEVMCalls,
+    executor::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
+};
+use bytes::Bytes;
+use ethers::{
+    abi::{AbiDecode, AbiEncode, Address},
+    types::H256,
+};
+use revm::{db::Database, CallInputs, CreateInputs, EVMData, Gas, Inspector, Return, KECCAK_EMPTY};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+};
+
+/// An inspector that reverts calls to contracts that were not deployed during execution and are
+/// not explicitly allowed.
+///
+/// This keeps tests hermetic: contracts that exist only on a forked chain can not be called by
+/// accident. Calls to addresses without code (e.g. plain transfers) are always allowed, and code
+/// placed with the `etch` cheatcode counts as deployed.
+#[derive(Clone, Debug, Default)]
+pub struct CallGuard {
+    /// Contracts that may be called even though they were not deployed during execution
+    pub allowed: BTreeSet<Address>,
+    /// Contracts deployed or etched during execution, shared by all copies of the guard
+    deployed: Arc<Mutex<BTreeSet<Address>>>,
+}
+
+impl CallGuard {
+    pub fn new(allowed: BTreeSet<Address>) -> Self {
+        Self { allowed, ..Default::default() }
+    }
+
+    /// Records the contract a cheatcode call places code at, if it does.
+    ///
+    /// Cheatcode calls are handled by the cheatcodes inspector and do not reach the guard, so they
+    /// have to be passed to it before.
+    pub fn record_cheatcode_call(&mut self, call: &CallInputs) {
+        if call.contract != CHEATCODE_ADDRESS {
+            return
+        }
+        if let Ok(HEVMCalls::Etch(inner)) = HEVMCalls::decode(&call.input) {
+            self.deployed.lock().unwrap().insert(inner.0);
+        }
+    }
+
+    fn is_allowed(&self, address: &Address) -> bool {
+        *address == CHEATCODE_ADDRESS ||
+            *address == HARDHAT_CONSOLE_ADDRESS ||
+            self.allowed.contains(address) ||
+            self.deployed.lock().unwrap().contains(address)
+    }
+}
+
+impl<DB> Inspector<DB> for CallGuard
+where
+    DB: Database,
+{
+    fn call(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &mut CallInputs,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        if !self.is_allowed(&call.contract) {
+            data.subroutine.load_account(call.contract, data.db);
+            let info = &data.subroutine.account(call.contract).info;
+            let has_code = match info.code {
+                Some(ref code) => !code.is_empty(),
+                None => info.code_hash != KECCAK_EMPTY && info.code_hash != H256::zero(),
+            };
+
+            if has_code {
+                return (
+                    Return::Revert,
+                    Gas::new(call.gas_limit),
+                    format!(
+                        "Call to external contract {:?} which was not deployed in the test and is not allowed",
+                        call.contract
+                    )
+                    .encode()
+                    .into(),
+                )
+            }
+        }
+
+        (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn create_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CreateInputs,
+        status: Return,
+        address: Option<Address>,
+        remaining_gas: Gas,
+        retdata: Bytes,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        if let Some(address) = address {
+            self.deployed.lock().unwrap().insert(address);
+        }
+
+        (status, address, remaining_gas, retdata)
+    }
+}
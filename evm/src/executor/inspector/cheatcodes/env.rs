@@ -1,6 +1,6 @@
 use std::collections::BTreeMap;
 
-use super::Cheatcodes;
+use super::{Cheatcodes, SKIP_REASON};
 use crate::abi::HEVMCalls;
 use bytes::Bytes;
 use ethers::{
@@ -180,6 +180,13 @@ pub fn apply<DB: Database>(
             let account = data.subroutine.state().get(&inner.0).unwrap();
             Ok(abi::encode(&[Token::Uint(account.info.nonce.into())]).into())
         }
+        HEVMCalls::Skip(inner) => {
+            if inner.0 {
+                Err(SKIP_REASON.to_string().encode().into())
+            } else {
+                Ok(Bytes::new())
+            }
+        }
         _ => return None,
     })
 }
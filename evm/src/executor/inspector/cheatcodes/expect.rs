@@ -2,10 +2,12 @@ use super::Cheatcodes;
 use crate::abi::HEVMCalls;
 use bytes::Bytes;
 use ethers::{
-    abi::{AbiEncode, RawLog},
-    types::{Address, H160},
+    abi::{AbiEncode, Event, ParamType, RawLog},
+    types::{Address, H160, H256},
 };
+use foundry_utils::format_token;
 use revm::{return_ok, Database, EVMData, Return};
+use std::collections::BTreeMap;
 
 /// For some cheatcodes we may internally change the status of the call, i.e. in `expectRevert`.
 /// Solidity will see a successful call and attempt to decode the return data. Therefore, we need
@@ -39,6 +41,18 @@ fn expect_revert(state: &mut Cheatcodes, reason: Bytes, depth: u64) -> Result<By
     }
 }
 
+/// Formats revert data for failure messages, as a string if it is valid UTF-8 or as hex otherwise
+fn format_revert_data(data: &[u8]) -> String {
+    match std::str::from_utf8(data) {
+        Ok(s) => format!("'{}'", s),
+        Err(_) => format!("0x{}", hex::encode(data)),
+    }
+}
+
+/// Checks whether a call matches the expectation set by `vm.expectRevert`.
+///
+/// If the expectation is not met, the returned error is an encoded failure reason that either
+/// states that the call did not revert at all, or what it reverted with and what was expected.
 pub fn handle_expect_revert(
     is_create: bool,
     expected_revert: &Bytes,
@@ -46,11 +60,21 @@ pub fn handle_expect_revert(
     retdata: Bytes,
 ) -> Result<(Option<Address>, Bytes), Bytes> {
     if matches!(status, return_ok!()) {
-        return Err("Call did not revert as expected".to_string().encode().into())
+        return Err(format!(
+            "{} did not revert as expected by vm.expectRevert",
+            if is_create { "contract creation" } else { "call" }
+        )
+        .encode()
+        .into())
     }
 
     if !expected_revert.is_empty() && retdata.is_empty() {
-        return Err("Call reverted as expected, but without data".to_string().encode().into())
+        return Err(format!(
+            "reverted without data but expected {}",
+            format_revert_data(expected_revert)
+        )
+        .encode()
+        .into())
     }
 
     let (err, actual_revert): (_, Bytes) = match retdata {
@@ -66,13 +90,9 @@ pub fn handle_expect_revert(
 
             (
                 format!(
-                    "Error != expected error: '{}' != '{}'",
-                    String::from_utf8(decoded_data.to_vec())
-                        .ok()
-                        .unwrap_or_else(|| hex::encode(&decoded_data)),
-                    String::from_utf8(expected_revert.to_vec())
-                        .ok()
-                        .unwrap_or_else(|| hex::encode(&expected_revert))
+                    "reverted with {} but expected {}",
+                    format_revert_data(&decoded_data),
+                    format_revert_data(expected_revert)
                 )
                 .encode()
                 .into(),
@@ -81,7 +101,7 @@ pub fn handle_expect_revert(
         }
         _ => (
             format!(
-                "Error != expected error: 0x{} != 0x{}",
+                "reverted with 0x{} but expected 0x{}",
                 hex::encode(&retdata),
                 hex::encode(&expected_revert)
             )
@@ -116,6 +136,131 @@ pub struct ExpectedEmit {
     pub checks: [bool; 4],
     /// Whether the log was actually found in the subcalls
     pub found: bool,
+    /// How the last emitted log with the same event signature differed from the expected log
+    pub mismatch: Option<String>,
+}
+
+/// Returns the canonical signature of the event, e.g. `Transfer(address,address,uint256)`
+fn event_signature(event: &Event) -> String {
+    let params = event.inputs.iter().map(|param| param.kind.to_string()).collect::<Vec<_>>();
+    format!("{}({})", event.name, params.join(","))
+}
+
+/// Describes the first checked topic or data field in which the emitted log differs from the
+/// expected log with the same event signature, or returns `None` if the logs match.
+///
+/// The differing values are decoded with the ABI of the event if it is known, and shown as hex
+/// otherwise.
+fn emit_mismatch(
+    expected: &RawLog,
+    log: &RawLog,
+    checks: &[bool; 4],
+    event: Option<&Event>,
+) -> Option<String> {
+    if expected.topics.len() != log.topics.len() {
+        return Some(format!(
+            "expected {} topics but the emitted event has {}",
+            expected.topics.len(),
+            log.topics.len()
+        ))
+    }
+
+    // Only decode with the event if it matches the number of topics
+    let inputs = event
+        .map(|event| event.inputs.as_slice())
+        .filter(|inputs| {
+            inputs.iter().filter(|input| input.indexed).count() + 1 == log.topics.len()
+        })
+        .unwrap_or_default();
+
+    let topic = expected
+        .topics
+        .iter()
+        .zip(&log.topics)
+        .enumerate()
+        .skip(1)
+        .find(|(i, (expected, topic))| checks[*i - 1] && expected != topic);
+    if let Some((i, (expected, topic))) = topic {
+        return Some(match inputs.iter().filter(|input| input.indexed).nth(i - 1) {
+            Some(input) => format!(
+                "topic {} (`{}`) differs: expected {} but got {}",
+                i,
+                input.name,
+                decode_topic(&input.kind, expected),
+                decode_topic(&input.kind, topic)
+            ),
+            None => format!("topic {} differs: expected {:?} but got {:?}", i, expected, topic),
+        })
+    }
+
+    if checks[3] && expected.data != log.data {
+        let data_inputs = inputs.iter().filter(|input| !input.indexed).collect::<Vec<_>>();
+        let kinds = data_inputs.iter().map(|input| input.kind.clone()).collect::<Vec<_>>();
+        if !inputs.is_empty() {
+            let expected_tokens = ethers::abi::decode(&kinds, &expected.data);
+            if let (Ok(expected_tokens), Ok(tokens)) =
+                (expected_tokens, ethers::abi::decode(&kinds, &log.data))
+            {
+                let field = data_inputs
+                    .iter()
+                    .zip(expected_tokens.iter().zip(&tokens))
+                    .find(|(_, (expected, token))| expected != token);
+                if let Some((input, (expected, token))) = field {
+                    return Some(format!(
+                        "data field `{}` differs: expected {} but got {}",
+                        input.name,
+                        format_token(expected),
+                        format_token(token)
+                    ))
+                }
+            }
+        }
+        return Some(format!(
+            "data differs: expected 0x{} but got 0x{}",
+            hex::encode(&expected.data),
+            hex::encode(&log.data)
+        ))
+    }
+    None
+}
+
+/// Decodes an indexed event parameter from its topic. Parameters of dynamic types are stored as
+/// their hash, so their topics are returned as is.
+fn decode_topic(kind: &ParamType, topic: &H256) -> String {
+    let decoded = match kind {
+        ParamType::Bytes |
+        ParamType::String |
+        ParamType::Array(_) |
+        ParamType::FixedArray(..) |
+        ParamType::Tuple(_) => None,
+        _ => ethers::abi::decode(&[kind.clone()], topic.as_bytes()).ok().and_then(|mut t| t.pop()),
+    };
+    match decoded {
+        Some(token) => format_token(&token),
+        None => format!("{:?}", topic),
+    }
+}
+
+/// Returns the failure reason for an expected emit that was not found.
+///
+/// The reason names the expected event and, if an event with the same signature was emitted, how
+/// it differed from the expected one.
+pub fn expect_emit_failure(expected: &ExpectedEmit, events: &BTreeMap<H256, Event>) -> String {
+    let log = match expected.log {
+        Some(ref log) => log,
+        None => return "Expected an emit, but no logs were emitted afterward".to_string(),
+    };
+    let event = match log.topics.first() {
+        Some(topic) => match events.get(topic) {
+            Some(event) => event_signature(event),
+            None => format!("{:?}", topic),
+        },
+        None => "anonymous event".to_string(),
+    };
+    match expected.mismatch {
+        Some(ref mismatch) => format!("Expected event {} was not emitted, {}", event, mismatch),
+        None => format!("Expected event {} was not emitted", event),
+    }
 }
 
 pub fn handle_expect_emit(state: &mut Cheatcodes, log: RawLog) {
@@ -131,25 +276,16 @@ pub fn handle_expect_emit(state: &mut Cheatcodes, log: RawLog) {
         let expected =
             next_expect.log.as_ref().expect("we should have a log to compare against here");
         if expected.topics[0] == log.topics[0] {
-            // Topic 0 can match, but the amount of topics can differ.
-            if expected.topics.len() != log.topics.len() {
-                next_expect.found = false;
-            } else {
-                let topics_match = log
-                    .topics
-                    .iter()
-                    .skip(1)
-                    .enumerate()
-                    .filter(|(i, _)| next_expect.checks[*i])
-                    .all(|(i, topic)| topic == &expected.topics[i + 1]);
-
-                // Maybe check data
-                next_expect.found = if next_expect.checks[3] {
-                    expected.data == log.data && topics_match
-                } else {
-                    topics_match
-                };
-            }
+            // Topic 0 can match, but the amount of topics, the checked topics or the data can
+            // differ
+            let mismatch = emit_mismatch(
+                expected,
+                &log,
+                &next_expect.checks,
+                state.events.get(&log.topics[0]),
+            );
+            next_expect.found = mismatch.is_none();
+            next_expect.mismatch = mismatch;
         }
     }
 }
@@ -194,3 +330,53 @@ pub fn apply<DB: Database>(
         _ => return None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{AbiParser, Token};
+
+    #[test]
+    fn describes_expect_emit_mismatches() {
+        let event = AbiParser::default()
+            .parse_event("event Deposit(address indexed from, uint256 indexed id, uint256 amount)")
+            .unwrap();
+        let log = |id: u64, amount: u64| RawLog {
+            topics: vec![event.signature(), H256::from_low_u64_be(1), H256::from_low_u64_be(id)],
+            data: ethers::abi::encode(&[Token::Uint(amount.into())]),
+        };
+        let expected = log(2, 100);
+        let checks = [true; 4];
+
+        assert_eq!(emit_mismatch(&expected, &log(2, 100), &checks, Some(&event)), None);
+        assert_eq!(
+            emit_mismatch(&expected, &log(3, 100), &checks, Some(&event)).unwrap(),
+            "topic 2 (`id`) differs: expected 2 but got 3"
+        );
+        assert_eq!(
+            emit_mismatch(&expected, &log(2, 99), &checks, Some(&event)).unwrap(),
+            "data field `amount` differs: expected 100 but got 99"
+        );
+        assert_eq!(emit_mismatch(&expected, &log(3, 99), &[true, false, true, false], None), None);
+        assert!(emit_mismatch(&expected, &log(2, 99), &checks, None)
+            .unwrap()
+            .starts_with("data differs: expected 0x"));
+
+        let expect = ExpectedEmit {
+            log: Some(expected),
+            checks,
+            mismatch: emit_mismatch(&log(2, 100), &log(2, 99), &checks, Some(&event)),
+            ..Default::default()
+        };
+        let events = BTreeMap::from([(event.signature(), event)]);
+        assert_eq!(
+            expect_emit_failure(&expect, &events),
+            "Expected event Deposit(address,uint256,uint256) was not emitted, data field `amount` \
+             differs: expected 100 but got 99"
+        );
+        assert_eq!(
+            expect_emit_failure(&ExpectedEmit::default(), &events),
+            "Expected an emit, but no logs were emitted afterward"
+        );
+    }
+}
@@ -11,21 +11,27 @@ mod fuzz;
 /// Utility cheatcodes (`sign` etc.)
 mod util;
 
-use self::expect::{handle_expect_emit, handle_expect_revert};
+use self::expect::{expect_emit_failure, handle_expect_emit, handle_expect_revert};
 use crate::{
-    abi::HEVMCalls,
+    abi::{HEVMCalls, HEVM_ABI},
     executor::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
 };
 use bytes::Bytes;
 use ethers::{
-    abi::{AbiDecode, AbiEncode, RawLog},
-    types::{Address, H256},
+    abi::{AbiDecode, AbiEncode, Event, RawLog},
+    types::{Address, H256, U256},
 };
 use revm::{
     opcode, BlockEnv, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Interpreter,
     Return,
 };
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+/// The revert reason of the `skip` cheatcode, a test that reverts with it is skipped
+pub const SKIP_REASON: &str = "FOUNDRY::SKIP";
 
 /// An inspector that handles calls to various cheatcodes, each with their own behavior.
 ///
@@ -42,6 +48,11 @@ pub struct Cheatcodes {
     /// execution block environment.
     pub block: Option<BlockEnv>,
 
+    /// The gas price
+    ///
+    /// Used in a similar manner to [Cheatcodes::block]
+    pub gas_price: Option<U256>,
+
     /// Address labels
     pub labels: BTreeMap<Address, String>,
 
@@ -62,6 +73,15 @@ pub struct Cheatcodes {
 
     /// Expected emits
     pub expected_emits: Vec<ExpectedEmit>,
+
+    /// Whether the FFI cheatcode was invoked
+    pub used_ffi: bool,
+
+    /// The names of the invoked cheatcodes, only recorded if this is `Some`
+    pub used_cheatcodes: Option<BTreeSet<String>>,
+
+    /// Known events by signature, used to decode `expectEmit` failures
+    pub events: Arc<BTreeMap<H256, Event>>,
 }
 
 impl Cheatcodes {
@@ -83,7 +103,7 @@ impl Cheatcodes {
             .or_else(|| util::apply(self, data, &decoded))
             .or_else(|| expect::apply(self, data, &decoded))
             .or_else(|| fuzz::apply(data, &decoded))
-            .or_else(|| ext::apply(self.ffi, &decoded))
+            .or_else(|| ext::apply(self, &decoded))
             .ok_or_else(|| "Cheatcode was unhandled. This is a bug.".to_string().encode())?
     }
 }
@@ -99,6 +119,14 @@ where
         _: bool,
     ) -> (Return, Gas, Bytes) {
         if call.contract == CHEATCODE_ADDRESS {
+            if let Some(used_cheatcodes) = self.used_cheatcodes.as_mut() {
+                if let Some(func) = call.input.get(..4).and_then(|selector| {
+                    HEVM_ABI.functions().find(|func| func.short_signature()[..] == selector[..])
+                }) {
+                    used_cheatcodes.insert(func.name.clone());
+                }
+            }
+
             match self.apply_cheatcode(data, call.context.caller, call) {
                 Ok(retdata) => (Return::Return, Gas::new(call.gas_limit), retdata),
                 Err(err) => (Return::Revert, Gas::new(call.gas_limit), err),
@@ -159,6 +187,9 @@ where
         if let Some(block) = self.block.take() {
             data.env.block = block;
         }
+        if let Some(gas_price) = self.gas_price.take() {
+            data.env.tx.gas_price = gas_price;
+        }
 
         Return::Continue
     }
@@ -239,16 +270,15 @@ where
         }
 
         // Handle expected emits at current depth
-        if !self
+        if let Some(expected) = self
             .expected_emits
             .iter()
-            .filter(|expected| expected.depth == data.subroutine.depth())
-            .all(|expected| expected.found)
+            .find(|expected| expected.depth == data.subroutine.depth() && !expected.found)
         {
             return (
                 Return::Revert,
                 remaining_gas,
-                "Log != expected log".to_string().encode().into(),
+                expect_emit_failure(expected, &self.events).encode().into(),
             )
         } else {
             // Clear the emits we expected at this depth that have been found
@@ -275,14 +305,11 @@ where
             }
 
             // Check if we have any leftover expected emits
-            if !self.expected_emits.is_empty() {
+            if let Some(expected) = self.expected_emits.first() {
                 return (
                     Return::Revert,
                     remaining_gas,
-                    "Expected an emit, but no logs were emitted afterward"
-                        .to_string()
-                        .encode()
-                        .into(),
+                    expect_emit_failure(expected, &self.events).encode().into(),
                 )
             }
         }
@@ -0,0 +1,67 @@
+use bytes::Bytes;
+use ethers::types::Address;
+use revm::{db::Database, CreateInputs, EVMData, Gas, Inspector, Interpreter, Return};
+use std::collections::BTreeMap;
+
+/// How often instructions were executed, by contract address and program counter
+pub type InstructionHits = BTreeMap<Address, BTreeMap<usize, u64>>;
+
+/// Adds the executions in `other` to `hits`
+pub fn merge_instruction_hits(hits: &mut InstructionHits, other: &InstructionHits) {
+    for (address, instructions) in other {
+        let merged = hits.entry(*address).or_default();
+        for (pc, count) in instructions {
+            *merged.entry(*pc).or_default() += count;
+        }
+    }
+}
+
+/// An inspector that counts how often every instruction is executed.
+///
+/// Instructions are recorded by the address of the contract that executes them, so code run with
+/// `DELEGATECALL` is attributed to the calling contract. Constructors are not recorded.
+#[derive(Clone, Debug, Default)]
+pub struct InstructionRecorder {
+    /// The recorded executions
+    pub hits: InstructionHits,
+    /// The number of contract creations in progress
+    creating: usize,
+}
+
+impl<DB> Inspector<DB> for InstructionRecorder
+where
+    DB: Database,
+{
+    fn step(&mut self, interpreter: &mut Interpreter, _: &mut EVMData<'_, DB>, _: bool) -> Return {
+        if self.creating == 0 {
+            let instructions = self.hits.entry(interpreter.contract.address).or_default();
+            *instructions.entry(interpreter.program_counter()).or_default() += 1;
+        }
+
+        Return::Continue
+    }
+
+    fn create(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &mut CreateInputs,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        self.creating += 1;
+
+        (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn create_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CreateInputs,
+        status: Return,
+        address: Option<Address>,
+        remaining_gas: Gas,
+        retdata: Bytes,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        self.creating = self.creating.saturating_sub(1);
+
+        (status, address, remaining_gas, retdata)
+    }
+}
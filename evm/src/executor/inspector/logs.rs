@@ -1,11 +1,13 @@
 use crate::executor::{
-    patch_hardhat_console_selector, HardhatConsoleCalls, HARDHAT_CONSOLE_ADDRESS,
+    patch_hardhat_console_selector, HardhatConsoleCalls, HARDHAT_CONSOLE_ABI,
+    HARDHAT_CONSOLE_ADDRESS,
 };
 use bytes::Bytes;
 use ethers::{
     abi::{AbiDecode, RawLog, Token},
     types::{Address, H256},
 };
+use foundry_utils::OutputEncoding;
 use revm::{db::Database, CallInputs, EVMData, Gas, Inspector, Return};
 
 /// An inspector that collects logs during execution.
@@ -14,9 +16,16 @@ use revm::{db::Database, CallInputs, EVMData, Gas, Inspector, Return};
 #[derive(Default)]
 pub struct LogCollector {
     pub logs: Vec<RawLog>,
+    /// How addresses and bytes are printed in Hardhat-style logs
+    pub output_encoding: OutputEncoding,
 }
 
 impl LogCollector {
+    /// Creates a collector that prints addresses and bytes of Hardhat-style logs in the encoding
+    pub fn new(output_encoding: OutputEncoding) -> Self {
+        Self { logs: vec![], output_encoding }
+    }
+
     fn hardhat_log(&mut self, input: Vec<u8>) -> (Return, Bytes) {
         // Patch the Hardhat-style selectors
         let input = patch_hardhat_console_selector(input.to_vec());
@@ -31,7 +40,7 @@ impl LogCollector {
         };
 
         // Convert it to a DS-style `emit log(string)` event
-        self.logs.push(convert_hh_log_to_event(decoded));
+        self.logs.push(convert_hh_log_to_event(decoded, &input, self.output_encoding));
 
         (Return::Continue, Bytes::new())
     }
@@ -61,7 +70,11 @@ where
 }
 
 /// Converts a call to Hardhat's `console.log` to a DSTest `log(string)` event.
-fn convert_hh_log_to_event(call: HardhatConsoleCalls) -> RawLog {
+fn convert_hh_log_to_event(
+    call: HardhatConsoleCalls,
+    input: &[u8],
+    encoding: OutputEncoding,
+) -> RawLog {
     RawLog {
         // This is topic 0 of DSTest's `log(string)`
         topics: vec![H256::from_slice(
@@ -69,6 +82,29 @@ fn convert_hh_log_to_event(call: HardhatConsoleCalls) -> RawLog {
                 .unwrap(),
         )],
         // Convert the parameters of the call to their string representation for the log
-        data: ethers::abi::encode(&[Token::String(call.to_string())]),
+        data: ethers::abi::encode(&[Token::String(format_hh_log(call, input, encoding))]),
+    }
+}
+
+/// Returns the parameters of a call to Hardhat's `console.log` as a string, with addresses and
+/// bytes in the encoding if it is not the default
+fn format_hh_log(call: HardhatConsoleCalls, input: &[u8], encoding: OutputEncoding) -> String {
+    if encoding == OutputEncoding::default() {
+        return call.to_string()
+    }
+    let tokens = HARDHAT_CONSOLE_ABI
+        .functions()
+        .find(|func| input.get(..4) == Some(&func.short_signature()[..]))
+        .and_then(|func| func.decode_input(&input[4..]).ok());
+    match tokens {
+        Some(tokens) => tokens
+            .iter()
+            .map(|token| match token {
+                Token::String(s) => s.clone(),
+                token => encoding.token(token),
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        None => call.to_string(),
     }
 }
@@ -14,8 +14,40 @@ mod stack;
 pub use stack::{InspectorData, InspectorStack};
 
 mod cheatcodes;
-pub use cheatcodes::Cheatcodes;
+pub use cheatcodes::{Cheatcodes, SKIP_REASON};
 
+mod call_guard;
+pub use call_guard::CallGuard;
+
+mod call_depth;
+pub use call_depth::CallDepth;
+
+mod precompiles;
+pub use precompiles::{CustomPrecompiles, PrecompileHandler, PrecompileStub};
+
+mod assertions;
+pub use assertions::AssertionCounter;
+
+mod opcodes;
+pub use opcodes::{opcode_name, parse_opcode, OpcodeGuard};
+
+mod origin;
+pub use origin::TxOrigin;
+
+mod branches;
+pub use branches::{merge_branch_hits, BranchHits, BranchRecorder};
+
+mod refunds;
+pub use refunds::{capped_refund, GasRefunds};
+
+mod instructions;
+pub use instructions::{merge_instruction_hits, InstructionHits, InstructionRecorder};
+
+mod out_of_gas;
+pub use out_of_gas::{failure_reason, OutOfGas, OutOfGasRecorder};
+
+use ethers::types::U256;
+use foundry_utils::OutputEncoding;
 use revm::BlockEnv;
 
 #[derive(Default, Clone, Debug)]
@@ -28,20 +60,49 @@ pub struct InspectorStackConfig {
     /// Used in the cheatcode handler to overwrite the block environment separately from the
     /// execution block environment.
     pub block: BlockEnv,
+    /// The gas price
+    ///
+    /// Used in the cheatcode handler to overwrite the gas price separately from the execution
+    /// gas price.
+    pub gas_price: U256,
     /// Whether or not tracing is enabled
     pub tracing: bool,
     /// Whether or not the debugger is enabled
     pub debugger: bool,
+    /// The guard against calls to external contracts, if enabled
+    pub call_guard: Option<CallGuard>,
+    /// Whether or not the maximum call depth is recorded
+    pub call_depth: bool,
+    /// The custom precompiles, if any
+    pub precompiles: Option<CustomPrecompiles>,
+    /// The counter of executed assertions, if assertions are counted
+    pub assertions: Option<AssertionCounter>,
+    /// The guard against disabled opcodes, if any opcodes are disabled
+    pub opcode_guard: Option<OpcodeGuard>,
+    /// The `tx.origin` of top-level calls, if it differs from the caller
+    pub tx_origin: Option<TxOrigin>,
+    /// Whether or not the directions of conditional jumps are recorded
+    pub branches: bool,
+    /// Whether or not the gas refunds of top-level calls are recorded
+    pub gas_refunds: bool,
+    /// Whether or not the executions of instructions are counted
+    pub instructions: bool,
+    /// How addresses and bytes are printed in Hardhat-style logs
+    pub output_encoding: OutputEncoding,
 }
 
 impl InspectorStackConfig {
     pub fn stack(&self) -> InspectorStack {
-        let mut stack =
-            InspectorStack { logs: Some(LogCollector::default()), ..Default::default() };
+        let mut stack = InspectorStack {
+            logs: Some(LogCollector::new(self.output_encoding)),
+            out_of_gas: Some(OutOfGasRecorder::default()),
+            ..Default::default()
+        };
 
         stack.cheatcodes = self.cheatcodes.clone();
         if let Some(ref mut cheatcodes) = stack.cheatcodes {
             cheatcodes.block = Some(self.block.clone());
+            cheatcodes.gas_price = Some(self.gas_price);
         }
 
         if self.tracing {
@@ -50,6 +111,23 @@ impl InspectorStackConfig {
         if self.debugger {
             stack.debugger = Some(Debugger::default());
         }
+        stack.call_guard = self.call_guard.clone();
+        stack.precompiles = self.precompiles.clone();
+        stack.assertions = self.assertions.clone();
+        stack.opcode_guard = self.opcode_guard.clone();
+        stack.tx_origin = self.tx_origin.clone();
+        if self.call_depth {
+            stack.call_depth = Some(CallDepth::default());
+        }
+        if self.branches {
+            stack.branches = Some(BranchRecorder::default());
+        }
+        if self.gas_refunds {
+            stack.gas_refunds = Some(GasRefunds::default());
+        }
+        if self.instructions {
+            stack.instructions = Some(InstructionRecorder::default());
+        }
         stack
     }
 }
@@ -0,0 +1,127 @@
+use bytes::Bytes;
+use ethers::{abi::AbiEncode, types::Address};
+use revm::{
+    db::Database, CallInputs, CreateInputs, EVMData, Gas, Inspector, Interpreter, OpCode, Return,
+};
+use std::collections::BTreeSet;
+
+/// Opcodes of hardforks later than the ones supported by the EVM, by name
+const LATER_OPCODES: &[(&str, u8)] =
+    &[("TLOAD", 0x5c), ("TSTORE", 0x5d), ("MCOPY", 0x5e), ("PUSH0", 0x5f), ("BLOBHASH", 0x49)];
+
+/// Returns the opcode with the given name, e.g. `PUSH0`, or given as hex, e.g. `0x5f`
+pub fn parse_opcode(name: &str) -> Option<u8> {
+    if let Some(hex) = name.strip_prefix("0x") {
+        return u8::from_str_radix(hex, 16).ok()
+    }
+    let name = name.to_uppercase();
+    LATER_OPCODES
+        .iter()
+        .find(|(later, _)| *later == name)
+        .map(|(_, op)| *op)
+        .or_else(|| (0..=u8::MAX).find(|op| opcode_name(*op) == name))
+}
+
+/// Returns the name of the opcode, e.g. `PUSH0`, or its hex value if it is unknown
+pub fn opcode_name(op: u8) -> String {
+    match LATER_OPCODES.iter().find(|(_, later)| *later == op) {
+        Some((name, _)) => name.to_string(),
+        None => OpCode::try_from_u8(op)
+            .map_or_else(|| format!("0x{:02x}", op), |opcode| opcode.as_str().to_string()),
+    }
+}
+
+/// An inspector that makes opcodes unavailable, e.g. to check that contracts run on chains that
+/// did not activate a hardfork yet.
+///
+/// Executing a disabled opcode halts the call, which then reverts with the message
+/// `opcode <name> not available under this configuration`.
+#[derive(Clone, Debug, Default)]
+pub struct OpcodeGuard {
+    /// The disabled opcodes
+    disabled: BTreeSet<u8>,
+    /// The disabled opcode that halted the current call, if any
+    halted_at: Option<u8>,
+}
+
+impl OpcodeGuard {
+    pub fn new(disabled: BTreeSet<u8>) -> Self {
+        Self { disabled, halted_at: None }
+    }
+
+    /// Returns the revert data of a call that executed a disabled opcode, if the call halted
+    /// because of it
+    fn revert_data(&mut self, status: Return) -> Option<Bytes> {
+        if status != Return::OpcodeNotFound {
+            return None
+        }
+        let op = self.halted_at.take()?;
+        Some(
+            format!("opcode {} not available under this configuration", opcode_name(op))
+                .encode()
+                .into(),
+        )
+    }
+}
+
+impl<DB> Inspector<DB> for OpcodeGuard
+where
+    DB: Database,
+{
+    fn step(&mut self, interpreter: &mut Interpreter, _: &mut EVMData<'_, DB>, _: bool) -> Return {
+        let op = interpreter.contract.code[interpreter.program_counter()];
+        if self.disabled.contains(&op) {
+            self.halted_at = Some(op);
+            return Return::OpcodeNotFound
+        }
+
+        Return::Continue
+    }
+
+    fn call_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CallInputs,
+        remaining_gas: Gas,
+        status: Return,
+        retdata: Bytes,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        match self.revert_data(status) {
+            Some(revert) => (Return::Revert, remaining_gas, revert),
+            None => (status, remaining_gas, retdata),
+        }
+    }
+
+    fn create_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CreateInputs,
+        status: Return,
+        address: Option<Address>,
+        remaining_gas: Gas,
+        retdata: Bytes,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        match self.revert_data(status) {
+            Some(revert) => (Return::Revert, None, remaining_gas, revert),
+            None => (status, address, remaining_gas, retdata),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::opcode;
+
+    #[test]
+    fn can_parse_opcodes() {
+        assert_eq!(parse_opcode("PUSH0"), Some(0x5f));
+        assert_eq!(parse_opcode("mcopy"), Some(0x5e));
+        assert_eq!(parse_opcode("SSTORE"), Some(opcode::SSTORE));
+        assert_eq!(parse_opcode("0x48"), Some(0x48));
+        assert_eq!(parse_opcode("NOPE"), None);
+        assert_eq!(opcode_name(0x5f), "PUSH0");
+        assert_eq!(opcode_name(opcode::SSTORE), "SSTORE");
+    }
+}
@@ -0,0 +1,52 @@
+use bytes::Bytes;
+use ethers::types::Address;
+use revm::{db::Database, CallInputs, CreateInputs, EVMData, Gas, Inspector, Return};
+
+/// An inspector that sets `tx.origin` of the top-level calls and contract creations, which is
+/// the caller otherwise.
+///
+/// This lets tests model calls that are initiated by a contract rather than an EOA, where
+/// `tx.origin != msg.sender`. Pranks that set `tx.origin` still take precedence in the calls
+/// they apply to.
+#[derive(Clone, Debug)]
+pub struct TxOrigin {
+    origin: Address,
+}
+
+impl TxOrigin {
+    pub fn new(origin: Address) -> Self {
+        Self { origin }
+    }
+
+    fn apply<DB: Database>(&self, data: &mut EVMData<'_, DB>) {
+        if data.subroutine.depth() == 0 {
+            data.env.tx.caller = self.origin;
+        }
+    }
+}
+
+impl<DB> Inspector<DB> for TxOrigin
+where
+    DB: Database,
+{
+    fn call(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &mut CallInputs,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.apply(data);
+
+        (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn create(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &mut CreateInputs,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        self.apply(data);
+
+        (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
+    }
+}
@@ -0,0 +1,183 @@
+use bytes::Bytes;
+use ethers::types::Address;
+use revm::{
+    db::Database, opcode, CallInputs, CreateInputs, EVMData, Gas, Inspector, Interpreter, Return,
+};
+use std::fmt;
+
+/// A call frame that ran out of gas
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfGas {
+    /// The address of the called or created contract, if it is known
+    pub address: Option<Address>,
+    /// The gas the call frame was given
+    pub gas_limit: u64,
+    /// The gas the call frame used
+    pub gas_used: u64,
+}
+
+impl fmt::Display for OutOfGas {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "out of gas (gas limit: {}, gas used: {})", self.gas_limit, self.gas_used)?;
+        if let Some(address) = self.address {
+            write!(f, " in call to {:?}", address)?;
+        }
+        Ok(())
+    }
+}
+
+/// An inspector that records the call frame that ran out of gas and made the top-level call
+/// fail.
+///
+/// Calls that run out of gas revert without revert data, so the caller usually fails with an
+/// empty revert that looks like any other. A caller propagates the failure if it reads the empty
+/// return data of the call, e.g. to revert with it, and then reverts without revert data itself.
+/// If the caller handles the failure instead, e.g. in a `try`/`catch`, and makes another call or
+/// reverts on its own, the frame that ran out of gas is not the cause of the failure.
+#[derive(Clone, Debug, Default)]
+pub struct OutOfGasRecorder {
+    /// The call frame that ran out of gas, if the top-level call failed because of it
+    pub out_of_gas: Option<OutOfGas>,
+    /// The frame that ran out of gas and the depth of the outermost frame that failed because of
+    /// it so far
+    failed: Option<(OutOfGas, u64)>,
+    /// Whether the caller of the failed frame read its return data
+    read: bool,
+}
+
+impl OutOfGasRecorder {
+    /// Records the end of the call frame at `depth`
+    fn record(
+        &mut self,
+        depth: u64,
+        status: Return,
+        retdata: &Bytes,
+        address: Option<Address>,
+        gas_limit: u64,
+        gas: &Gas,
+    ) {
+        self.failed = match self.failed.take() {
+            _ if status == Return::OutOfGas => {
+                Some((OutOfGas { address, gas_limit, gas_used: gas.spend() }, depth))
+            }
+            Some((out_of_gas, failed))
+                if failed == depth + 1 &&
+                    self.read &&
+                    status == Return::Revert &&
+                    retdata.is_empty() =>
+            {
+                Some((out_of_gas, depth))
+            }
+            _ => None,
+        };
+        self.read = false;
+        if depth == 1 {
+            self.out_of_gas = self.failed.map(|(out_of_gas, _)| out_of_gas);
+        }
+    }
+}
+
+impl<DB> Inspector<DB> for OutOfGasRecorder
+where
+    DB: Database,
+{
+    fn step(
+        &mut self,
+        interpreter: &mut Interpreter,
+        data: &mut EVMData<'_, DB>,
+        _: bool,
+    ) -> Return {
+        if let Some((_, failed)) = self.failed {
+            let op = interpreter.contract.code[interpreter.program_counter()];
+            if op == opcode::RETURNDATASIZE && data.subroutine.depth() + 1 == failed {
+                self.read = true;
+            }
+        }
+
+        Return::Continue
+    }
+
+    fn call(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &mut CallInputs,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        // the caller went on after the failed call
+        self.failed = None;
+
+        (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn call_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &CallInputs,
+        remaining_gas: Gas,
+        status: Return,
+        retdata: Bytes,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        // the frame has already been left, the caller is one level up
+        let depth = data.subroutine.depth() + 1;
+        self.record(depth, status, &retdata, Some(call.contract), call.gas_limit, &remaining_gas);
+
+        (status, remaining_gas, retdata)
+    }
+
+    fn create(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &mut CreateInputs,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        self.failed = None;
+
+        (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
+    }
+
+    fn create_end(
+        &mut self,
+        data: &mut EVMData<'_, DB>,
+        call: &CreateInputs,
+        status: Return,
+        address: Option<Address>,
+        remaining_gas: Gas,
+        retdata: Bytes,
+    ) -> (Return, Option<Address>, Gas, Bytes) {
+        let depth = data.subroutine.depth() + 1;
+        self.record(depth, status, &retdata, address, call.gas_limit, &remaining_gas);
+
+        (status, address, remaining_gas, retdata)
+    }
+}
+
+/// Returns the reason a call failed: that a call frame ran out of gas if the call reverted
+/// without revert data, otherwise the decoded revert data or the status if it can not be decoded
+pub fn failure_reason(
+    result: &[u8],
+    status: Return,
+    out_of_gas: Option<&OutOfGas>,
+    abi: Option<&ethers::abi::Abi>,
+) -> String {
+    match out_of_gas {
+        Some(out_of_gas) if result.is_empty() => out_of_gas.to_string(),
+        _ => foundry_utils::decode_revert(result, abi).unwrap_or_else(|_| format!("{:?}", status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_format_out_of_gas() {
+        let out_of_gas =
+            OutOfGas { address: Some(Address::zero()), gas_limit: 2300, gas_used: 2300 };
+        assert_eq!(
+            failure_reason(&[], Return::Revert, Some(&out_of_gas), None),
+            "out of gas (gas limit: 2300, gas used: 2300) in call to \
+             0x0000000000000000000000000000000000000000"
+        );
+        assert_eq!(failure_reason(&[], Return::Revert, None, None), "Revert");
+    }
+}
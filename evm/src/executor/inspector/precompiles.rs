@@ -0,0 +1,119 @@
+use bytes::Bytes;
+use ethers::types::Address;
+use revm::{db::Database, CallInputs, EVMData, Gas, Inspector, Return};
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+/// The handler of a custom precompile.
+///
+/// It is called with the calldata and the gas limit of a call to the precompile and returns the
+/// gas used and the output of the call, or the revert data if the call reverts. Calls that use more
+/// gas than their limit fail with out of gas.
+pub type PrecompileHandler = Arc<dyn Fn(&[u8], u64) -> Result<(u64, Bytes), Bytes> + Send + Sync>;
+
+/// An inspector that handles calls to custom precompiles, e.g. the precompiles of an L2 that do
+/// not exist in the EVM.
+///
+/// Calls to the addresses of the precompiles are answered by their [PrecompileHandler]s instead
+/// of being executed, regardless of the code at the address. Mocked calls take precedence.
+#[derive(Clone, Default)]
+pub struct CustomPrecompiles {
+    handlers: BTreeMap<Address, PrecompileHandler>,
+}
+
+impl CustomPrecompiles {
+    /// Registers the handler of the precompile at `address`, replacing any previous handler
+    pub fn register(
+        &mut self,
+        address: Address,
+        handler: impl Fn(&[u8], u64) -> Result<(u64, Bytes), Bytes> + Send + Sync + 'static,
+    ) {
+        self.handlers.insert(address, Arc::new(handler));
+    }
+
+    /// Returns the addresses of the precompiles
+    pub fn addresses(&self) -> impl Iterator<Item = &Address> {
+        self.handlers.keys()
+    }
+
+    /// Returns `true` if no precompiles are registered
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
+
+/// A stub of a precompile with fixed outputs, e.g. of `ecrecover` or `sha256`, so that the output
+/// of tests does not depend on the inputs of the precompile, see
+/// [CustomPrecompiles::register_stub()].
+///
+/// Calls whose input has a fixture return its output, all other calls return the default output.
+/// Every call uses the same gas.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrecompileStub {
+    /// The outputs of calls by input
+    pub fixtures: BTreeMap<Bytes, Bytes>,
+    /// The output of calls without a fixture
+    pub default: Bytes,
+    /// The gas used by every call
+    pub gas: u64,
+}
+
+impl PrecompileStub {
+    /// Returns a stub that returns `output` for every call
+    pub fn constant(output: impl Into<Bytes>) -> Self {
+        Self { default: output.into(), ..Default::default() }
+    }
+
+    /// Returns `output` for calls with this input
+    #[must_use]
+    pub fn with_fixture(mut self, input: impl Into<Bytes>, output: impl Into<Bytes>) -> Self {
+        self.fixtures.insert(input.into(), output.into());
+        self
+    }
+
+    /// Returns the output of a call with the given input
+    pub fn output(&self, input: &[u8]) -> Bytes {
+        self.fixtures.get(input).unwrap_or(&self.default).clone()
+    }
+}
+
+impl CustomPrecompiles {
+    /// Registers the stub as the handler of the precompile at `address`, replacing any previous
+    /// handler
+    pub fn register_stub(&mut self, address: Address, stub: PrecompileStub) {
+        self.register(address, move |input, _| Ok((stub.gas, stub.output(input))))
+    }
+}
+
+impl fmt::Debug for CustomPrecompiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.handlers.keys()).finish()
+    }
+}
+
+impl<DB> Inspector<DB> for CustomPrecompiles
+where
+    DB: Database,
+{
+    fn call(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        call: &mut CallInputs,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        let handler = match self.handlers.get(&call.contract) {
+            Some(handler) => handler,
+            None => return (Return::Continue, Gas::new(call.gas_limit), Bytes::new()),
+        };
+
+        let mut gas = Gas::new(call.gas_limit);
+        match handler(&call.input, call.gas_limit) {
+            Ok((gas_used, output)) => {
+                if !gas.record_cost(gas_used) {
+                    return (Return::OutOfGas, gas, Bytes::new())
+                }
+                (Return::Return, gas, output)
+            }
+            Err(revert) => (Return::Revert, gas, revert),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use bytes::Bytes;
+use revm::{db::Database, CallInputs, EVMData, Gas, Inspector, Return};
+
+/// An inspector that records the gas refund counter of the top-level call, e.g. for clearing
+/// storage slots.
+///
+/// The counter is the refund before it is capped at a fraction of the gas used by the
+/// transaction, see [capped_refund].
+#[derive(Clone, Debug, Default)]
+pub struct GasRefunds {
+    /// The refund counter and the gas spent at the end of the last call that ended, which is the
+    /// top-level call once the transaction finished
+    pub last: Option<(i64, u64)>,
+}
+
+impl<DB> Inspector<DB> for GasRefunds
+where
+    DB: Database,
+{
+    fn call_end(
+        &mut self,
+        _: &mut EVMData<'_, DB>,
+        _: &CallInputs,
+        remaining_gas: Gas,
+        status: Return,
+        retdata: Bytes,
+        _: bool,
+    ) -> (Return, Gas, Bytes) {
+        self.last = Some((remaining_gas.refunded(), remaining_gas.spend()));
+
+        (status, remaining_gas, retdata)
+    }
+}
+
+/// Returns the refund granted to a transaction, given the refund counter and the gas used before
+/// the refund: at most half of the gas used, or a fifth since London (EIP-3529)
+pub fn capped_refund(refunded: i64, gas_used: u64, london: bool) -> u64 {
+    let quotient = if london { 5 } else { 2 };
+    (refunded.max(0) as u64).min(gas_used / quotient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_cap_refunds() {
+        assert_eq!(capped_refund(4800, 100_000, true), 4800);
+        assert_eq!(capped_refund(24_000, 100_000, true), 20_000);
+        assert_eq!(capped_refund(24_000, 100_000, false), 24_000);
+        assert_eq!(capped_refund(-100, 100_000, true), 0);
+    }
+}
@@ -1,4 +1,8 @@
-use super::{Cheatcodes, Debugger, LogCollector, Tracer};
+use super::{
+    AssertionCounter, BranchHits, BranchRecorder, CallDepth, CallGuard, Cheatcodes,
+    CustomPrecompiles, Debugger, GasRefunds, InstructionHits, InstructionRecorder, LogCollector,
+    OpcodeGuard, OutOfGas, OutOfGasRecorder, Tracer, TxOrigin,
+};
 use crate::{debug::DebugArena, trace::CallTraceArena};
 use bytes::Bytes;
 use ethers::{
@@ -6,7 +10,7 @@ use ethers::{
     types::{Address, H256},
 };
 use revm::{db::Database, CallInputs, CreateInputs, EVMData, Gas, Inspector, Interpreter, Return};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Helper macro to call the same method on multiple inspectors without resorting to dynamic
 /// dispatch
@@ -23,6 +27,14 @@ macro_rules! call_inspectors {
 pub struct InspectorData {
     pub logs: Vec<RawLog>,
     pub labels: BTreeMap<Address, String>,
+    pub used_ffi: bool,
+    pub used_cheatcodes: BTreeSet<String>,
+    pub max_call_depth: Option<usize>,
+    pub assertions: Option<usize>,
+    pub branches: Option<BranchHits>,
+    pub gas_refund: Option<(i64, u64)>,
+    pub instructions: Option<InstructionHits>,
+    pub out_of_gas: Option<OutOfGas>,
     pub traces: Option<CallTraceArena>,
     pub debug: Option<DebugArena>,
     pub cheatcodes: Option<Cheatcodes>,
@@ -38,6 +50,16 @@ pub struct InspectorStack {
     pub logs: Option<LogCollector>,
     pub cheatcodes: Option<Cheatcodes>,
     pub debugger: Option<Debugger>,
+    pub call_guard: Option<CallGuard>,
+    pub call_depth: Option<CallDepth>,
+    pub precompiles: Option<CustomPrecompiles>,
+    pub assertions: Option<AssertionCounter>,
+    pub opcode_guard: Option<OpcodeGuard>,
+    pub tx_origin: Option<TxOrigin>,
+    pub branches: Option<BranchRecorder>,
+    pub gas_refunds: Option<GasRefunds>,
+    pub instructions: Option<InstructionRecorder>,
+    pub out_of_gas: Option<OutOfGasRecorder>,
 }
 
 impl InspectorStack {
@@ -49,6 +71,22 @@ impl InspectorStack {
                 .as_ref()
                 .map(|cheatcodes| cheatcodes.labels.clone())
                 .unwrap_or_default(),
+            used_ffi: self
+                .cheatcodes
+                .as_ref()
+                .map(|cheatcodes| cheatcodes.used_ffi)
+                .unwrap_or_default(),
+            used_cheatcodes: self
+                .cheatcodes
+                .as_ref()
+                .and_then(|cheatcodes| cheatcodes.used_cheatcodes.clone())
+                .unwrap_or_default(),
+            max_call_depth: self.call_depth.map(|call_depth| call_depth.max_depth),
+            assertions: self.assertions.map(|assertions| assertions.count),
+            branches: self.branches.map(|branches| branches.hits),
+            gas_refund: self.gas_refunds.and_then(|refunds| refunds.last),
+            instructions: self.instructions.map(|instructions| instructions.hits),
+            out_of_gas: self.out_of_gas.and_then(|recorder| recorder.out_of_gas),
             traces: self.tracer.map(|tracer| tracer.traces),
             debug: self.debugger.map(|debugger| debugger.arena),
             cheatcodes: self.cheatcodes,
@@ -90,7 +128,17 @@ where
     ) -> Return {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.debugger,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes,
+                &mut self.assertions,
+                &mut self.opcode_guard,
+                &mut self.branches,
+                &mut self.instructions,
+                &mut self.out_of_gas
+            ],
             {
                 let status = inspector.step(interpreter, data, is_static);
 
@@ -145,9 +193,23 @@ where
         call: &mut CallInputs,
         is_static: bool,
     ) -> (Return, Gas, Bytes) {
+        if let Some(ref mut call_guard) = self.call_guard {
+            call_guard.record_cheatcode_call(call);
+        }
+
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.out_of_gas,
+                &mut self.tx_origin,
+                &mut self.debugger,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes,
+                &mut self.precompiles,
+                &mut self.call_guard,
+                &mut self.call_depth
+            ],
             {
                 let (status, gas, retdata) = inspector.call(data, call, is_static);
 
@@ -172,7 +234,15 @@ where
     ) -> (Return, Gas, Bytes) {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.gas_refunds,
+                &mut self.out_of_gas,
+                &mut self.debugger,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes,
+                &mut self.opcode_guard
+            ],
             {
                 let (new_status, new_gas, new_retdata) = inspector.call_end(
                     data,
@@ -201,7 +271,17 @@ where
     ) -> (Return, Option<Address>, Gas, Bytes) {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.out_of_gas,
+                &mut self.tx_origin,
+                &mut self.debugger,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes,
+                &mut self.call_depth,
+                &mut self.branches,
+                &mut self.instructions
+            ],
             {
                 let (status, addr, gas, retdata) = inspector.create(data, call);
 
@@ -226,7 +306,17 @@ where
     ) -> (Return, Option<Address>, Gas, Bytes) {
         call_inspectors!(
             inspector,
-            [&mut self.debugger, &mut self.tracer, &mut self.logs, &mut self.cheatcodes],
+            [
+                &mut self.out_of_gas,
+                &mut self.debugger,
+                &mut self.tracer,
+                &mut self.logs,
+                &mut self.cheatcodes,
+                &mut self.call_guard,
+                &mut self.opcode_guard,
+                &mut self.branches,
+                &mut self.instructions
+            ],
             {
                 let (new_status, new_address, new_gas, new_retdata) = inspector.create_end(
                     data,
@@ -4,7 +4,7 @@ use crate::{
         HARDHAT_CONSOLE_ADDRESS,
     },
     trace::{
-        CallTrace, CallTraceArena, LogCallOrder, RawOrDecodedCall, RawOrDecodedLog,
+        CallTrace, CallTraceArena, Create2, LogCallOrder, RawOrDecodedCall, RawOrDecodedLog,
         RawOrDecodedReturnData,
     },
     CallKind,
@@ -13,8 +13,12 @@ use bytes::Bytes;
 use ethers::{
     abi::RawLog,
     types::{Address, H256, U256},
+    utils::keccak256,
+};
+use revm::{
+    opcode, return_ok, CallInputs, CreateInputs, CreateScheme, Database, EVMData, Gas, Inspector,
+    Interpreter, Return,
 };
-use revm::{return_ok, CallInputs, CreateInputs, Database, EVMData, Gas, Inspector, Return};
 
 /// An inspector that collects call traces.
 #[derive(Default, Debug)]
@@ -88,6 +92,22 @@ where
         (Return::Continue, Gas::new(call.gas_limit), Bytes::new())
     }
 
+    fn step(&mut self, interpreter: &mut Interpreter, _: &mut EVMData<'_, DB>, _: bool) -> Return {
+        // Record the beneficiary of a `SELFDESTRUCT` in the trace of the current call
+        if interpreter.contract.code[interpreter.program_counter()] == opcode::SELFDESTRUCT {
+            if let (Some(trace), Ok(beneficiary)) =
+                (self.trace_stack.last(), interpreter.stack().peek(0))
+            {
+                let mut word = [0u8; 32];
+                beneficiary.to_big_endian(&mut word);
+                self.traces.arena[*trace].trace.selfdestruct =
+                    Some(Address::from_slice(&word[12..]));
+            }
+        }
+
+        Return::Continue
+    }
+
     fn log(&mut self, _: &mut EVMData<'_, DB>, _: &Address, topics: &[H256], data: &Bytes) {
         let node = &mut self.traces.arena[*self.trace_stack.last().expect("no ongoing trace")];
         node.ordering.push(LogCallOrder::Log(node.logs.len()));
@@ -132,6 +152,18 @@ where
             CallKind::Create,
         );
 
+        // Record the parameters of `CREATE2`, so the resulting address can be verified
+        if let CreateScheme::Create2 { salt } = call.scheme {
+            let mut buffer = [0u8; 32];
+            salt.to_big_endian(&mut buffer);
+            let trace = *self.trace_stack.last().expect("trace was started");
+            self.traces.arena[trace].trace.create2 = Some(Create2 {
+                deployer: call.caller,
+                salt: H256(buffer),
+                init_code_hash: H256(keccak256(&call.init_code)),
+            });
+        }
+
         (Return::Continue, None, Gas::new(call.gas_limit), Bytes::new())
     }
 
@@ -26,12 +26,16 @@ pub use revm::db::DatabaseRef;
 
 pub use revm::Env;
 
-use self::inspector::{InspectorData, InspectorStackConfig};
+use self::inspector::{
+    capped_refund, failure_reason, BranchHits, InspectorData, InspectorStackConfig,
+    InstructionHits, OutOfGas,
+};
 use crate::{debug::DebugArena, trace::CallTraceArena, CALLER};
 use bytes::Bytes;
 use ethers::{
     abi::{Abi, Detokenize, RawLog, Tokenize},
-    prelude::{decode_function_data, encode_function_data, Address, U256},
+    prelude::{decode_function_data, encode_function_data, Address, H256, U256},
+    utils::keccak256,
 };
 use eyre::Result;
 use foundry_utils::IntoFunction;
@@ -40,7 +44,7 @@ use revm::{
     db::{CacheDB, DatabaseCommit, EmptyDB},
     return_ok, Account, BlockEnv, CreateScheme, Return, TransactOut, TransactTo, TxEnv, EVM,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// A mapping of addresses to their changed state.
 pub type StateChangeset = HashMap<Address, Account>;
@@ -58,6 +62,11 @@ pub enum EvmError {
         traces: Option<CallTraceArena>,
         debug: Option<DebugArena>,
         labels: BTreeMap<Address, String>,
+        used_ffi: bool,
+        used_cheatcodes: BTreeSet<String>,
+        max_call_depth: Option<usize>,
+        assertions: Option<usize>,
+        instructions: Option<InstructionHits>,
         state_changeset: Option<StateChangeset>,
     },
     /// Error which occurred during ABI encoding/decoding
@@ -98,6 +107,18 @@ pub struct CallResult<D: Detokenize> {
     pub logs: Vec<RawLog>,
     /// The labels assigned to addresses during the call
     pub labels: BTreeMap<Address, String>,
+    /// Whether the FFI cheatcode was invoked
+    pub used_ffi: bool,
+    /// The names of the invoked cheatcodes, if they were recorded
+    pub used_cheatcodes: BTreeSet<String>,
+    /// The maximum call depth reached, if it was recorded
+    pub max_call_depth: Option<usize>,
+    /// The number of executed assertions, if they were counted
+    pub assertions: Option<usize>,
+    /// The gas refunded to the call, if it was recorded. The gas of the call is net of it
+    pub gas_refund: Option<u64>,
+    /// How often the instructions of the called contracts were executed, if it was recorded
+    pub instructions: Option<InstructionHits>,
     /// The traces of the call
     pub traces: Option<CallTraceArena>,
     /// The debug nodes of the call
@@ -126,6 +147,22 @@ pub struct RawCallResult {
     pub logs: Vec<RawLog>,
     /// The labels assigned to addresses during the call
     pub labels: BTreeMap<Address, String>,
+    /// Whether the FFI cheatcode was invoked
+    pub used_ffi: bool,
+    /// The names of the invoked cheatcodes, if they were recorded
+    pub used_cheatcodes: BTreeSet<String>,
+    /// The maximum call depth reached, if it was recorded
+    pub max_call_depth: Option<usize>,
+    /// The number of executed assertions, if they were counted
+    pub assertions: Option<usize>,
+    /// The directions taken at conditional jumps, if they were recorded
+    pub branches: Option<BranchHits>,
+    /// The gas refunded to the call, if it was recorded. The gas of the call is net of it
+    pub gas_refund: Option<u64>,
+    /// How often the instructions of the called contracts were executed, if it was recorded
+    pub instructions: Option<InstructionHits>,
+    /// The first call frame that ran out of gas, if any
+    pub out_of_gas: Option<OutOfGas>,
     /// The traces of the call
     pub traces: Option<CallTraceArena>,
     /// The debug nodes of the call
@@ -147,6 +184,14 @@ impl Default for RawCallResult {
             stipend: 0,
             logs: Vec::new(),
             labels: BTreeMap::new(),
+            used_ffi: false,
+            used_cheatcodes: BTreeSet::new(),
+            max_call_depth: None,
+            assertions: None,
+            branches: None,
+            gas_refund: None,
+            instructions: None,
+            out_of_gas: None,
             traces: None,
             debug: None,
             state_changeset: None,
@@ -154,6 +199,7 @@ impl Default for RawCallResult {
     }
 }
 
+#[derive(Clone)]
 pub struct Executor<DB: DatabaseRef> {
     // Note: We do not store an EVM here, since we are really
     // only interested in the database. REVM's `EVM` is a thin
@@ -194,6 +240,11 @@ where
         Executor { db, env, inspector_config, gas_limit }
     }
 
+    /// Set the gas limit for calls and deployments.
+    pub fn set_gas_limit(&mut self, gas_limit: U256) {
+        self.gas_limit = gas_limit;
+    }
+
     /// Set the balance of an account.
     pub fn set_balance(&mut self, address: Address, amount: U256) {
         let mut account = self.db.basic(address);
@@ -215,11 +266,62 @@ where
         self.db.insert_cache(address, account);
     }
 
+    /// Set the code of an account.
+    pub fn set_code(&mut self, address: Address, code: Bytes) {
+        let mut account = self.db.basic(address);
+        account.code_hash = H256::from_slice(&keccak256(&code));
+        account.code = Some(code);
+
+        self.db.insert_cache(address, account);
+    }
+
+    /// Set the value of a storage slot of an account.
+    pub fn set_storage(&mut self, address: Address, slot: U256, value: U256) {
+        self.db.insert_cache_storage(address, slot, value);
+    }
+
+    /// Counts the assertions executed by the contract at `address` in calls, given the program
+    /// counters of the jumps into assertion functions in its code, see
+    /// [AssertionCounter](inspector::AssertionCounter)
+    pub fn count_assertions(&mut self, address: Address, jumps: BTreeSet<usize>) {
+        self.inspector_config
+            .assertions
+            .get_or_insert_with(Default::default)
+            .insert(address, jumps);
+    }
+
     /// Calls the `setUp()` function on a contract.
     pub fn setup(&mut self, address: Address) -> std::result::Result<CallResult<()>, EvmError> {
         self.call_committing::<(), _, _>(*CALLER, address, "setUp()", (), 0.into(), None)
     }
 
+    /// Calls the `setUpTest()` function on a contract, or `setUpTest(bytes4)` with the given
+    /// selector of the test that is about to run.
+    pub fn setup_test(
+        &mut self,
+        address: Address,
+        selector: Option<[u8; 4]>,
+    ) -> std::result::Result<CallResult<()>, EvmError> {
+        match selector {
+            Some(selector) => self.call_committing::<(), _, _>(
+                *CALLER,
+                address,
+                "setUpTest(bytes4)",
+                (selector,),
+                0.into(),
+                None,
+            ),
+            None => self.call_committing::<(), _, _>(
+                *CALLER,
+                address,
+                "setUpTest()",
+                (),
+                0.into(),
+                None,
+            ),
+        }
+    }
+
     /// Performs a call to an account on the current state of the VM.
     ///
     /// The state after the call is persisted.
@@ -242,9 +344,17 @@ where
             stipend,
             logs,
             labels,
+            used_ffi,
+            used_cheatcodes,
+            max_call_depth,
+            assertions,
+            gas_refund,
+            instructions,
+            out_of_gas,
             traces,
             debug,
             state_changeset,
+            ..
         } = self.call_raw_committing(from, to, calldata, value)?;
         match status {
             return_ok!() => {
@@ -256,14 +366,19 @@ where
                     stipend,
                     logs,
                     labels,
+                    used_ffi,
+                    used_cheatcodes,
+                    max_call_depth,
+                    assertions,
+                    gas_refund,
+                    instructions,
                     traces,
                     debug,
                     state_changeset,
                 })
             }
             _ => {
-                let reason = foundry_utils::decode_revert(result.as_ref(), abi)
-                    .unwrap_or_else(|_| format!("{:?}", status));
+                let reason = failure_reason(result.as_ref(), status, out_of_gas.as_ref(), abi);
                 Err(EvmError::Execution {
                     reverted,
                     reason,
@@ -273,6 +388,11 @@ where
                     traces,
                     debug,
                     labels,
+                    used_ffi,
+                    used_cheatcodes,
+                    max_call_depth,
+                    assertions,
+                    instructions,
                     state_changeset,
                 })
             }
@@ -304,8 +424,21 @@ where
             _ => Bytes::default(),
         };
 
-        let InspectorData { logs, labels, traces, debug, cheatcodes } =
-            inspector.collect_inspector_states();
+        let InspectorData {
+            logs,
+            labels,
+            used_ffi,
+            used_cheatcodes,
+            max_call_depth,
+            assertions,
+            branches,
+            gas_refund,
+            instructions,
+            out_of_gas,
+            traces,
+            debug,
+            cheatcodes,
+        } = inspector.collect_inspector_states();
 
         // Persist the changed block environment
         self.inspector_config.block = evm.env.block.clone();
@@ -321,6 +454,14 @@ where
             stipend,
             logs: logs.to_vec(),
             labels,
+            used_ffi,
+            used_cheatcodes,
+            max_call_depth,
+            assertions,
+            branches,
+            gas_refund: self.granted_refund(gas_refund, stipend),
+            instructions,
+            out_of_gas,
             traces,
             debug,
             state_changeset: None,
@@ -349,9 +490,17 @@ where
             stipend,
             logs,
             labels,
+            used_ffi,
+            used_cheatcodes,
+            max_call_depth,
+            assertions,
+            gas_refund,
+            instructions,
+            out_of_gas,
             traces,
             debug,
             state_changeset,
+            ..
         } = self.call_raw(from, to, calldata, value)?;
         match status {
             return_ok!() => {
@@ -363,14 +512,19 @@ where
                     stipend,
                     logs,
                     labels,
+                    used_ffi,
+                    used_cheatcodes,
+                    max_call_depth,
+                    assertions,
+                    gas_refund,
+                    instructions,
                     traces,
                     debug,
                     state_changeset,
                 })
             }
             _ => {
-                let reason = foundry_utils::decode_revert(result.as_ref(), abi)
-                    .unwrap_or_else(|_| format!("{:?}", status));
+                let reason = failure_reason(result.as_ref(), status, out_of_gas.as_ref(), abi);
                 Err(EvmError::Execution {
                     reverted,
                     reason,
@@ -380,6 +534,11 @@ where
                     traces,
                     debug,
                     labels,
+                    used_ffi,
+                    used_cheatcodes,
+                    max_call_depth,
+                    assertions,
+                    instructions,
                     state_changeset,
                 })
             }
@@ -411,8 +570,21 @@ where
             _ => Bytes::default(),
         };
 
-        let InspectorData { logs, labels, traces, debug, .. } =
-            inspector.collect_inspector_states();
+        let InspectorData {
+            logs,
+            labels,
+            used_ffi,
+            used_cheatcodes,
+            max_call_depth,
+            assertions,
+            branches,
+            gas_refund,
+            instructions,
+            out_of_gas,
+            traces,
+            debug,
+            ..
+        } = inspector.collect_inspector_states();
         Ok(RawCallResult {
             status,
             reverted: !matches!(status, return_ok!()),
@@ -421,6 +593,14 @@ where
             stipend,
             logs: logs.to_vec(),
             labels,
+            used_ffi,
+            used_cheatcodes,
+            max_call_depth,
+            assertions,
+            branches,
+            gas_refund: self.granted_refund(gas_refund, stipend),
+            instructions,
+            out_of_gas,
             traces,
             debug,
             state_changeset: Some(state_changeset),
@@ -429,8 +609,33 @@ where
 
     /// Deploys a contract and commits the new state to the underlying database.
     pub fn deploy(&mut self, from: Address, code: Bytes, value: U256) -> Result<DeployResult> {
+        self.deploy_with_scheme(from, code, value, CreateScheme::Create)
+    }
+
+    /// Deploys a contract with `CREATE2` and commits the new state to the underlying database.
+    ///
+    /// The address of the contract only depends on `from`, the salt and the code, not on the
+    /// nonce of `from`.
+    pub fn deploy_create2(
+        &mut self,
+        from: Address,
+        code: Bytes,
+        value: U256,
+        salt: H256,
+    ) -> Result<DeployResult> {
+        let salt = U256::from_big_endian(salt.as_bytes());
+        self.deploy_with_scheme(from, code, value, CreateScheme::Create2 { salt })
+    }
+
+    fn deploy_with_scheme(
+        &mut self,
+        from: Address,
+        code: Bytes,
+        value: U256,
+        scheme: CreateScheme,
+    ) -> Result<DeployResult> {
         let mut evm = EVM::new();
-        evm.env = self.build_env(from, TransactTo::Create(CreateScheme::Create), code, value);
+        evm.env = self.build_env(from, TransactTo::Create(scheme), code, value);
         evm.database(&mut self.db);
 
         let mut inspector = self.inspector_config.stack();
@@ -443,6 +648,11 @@ where
                     panic!("deployment succeeded, but we got no address. this is a bug.");
                 }
             }
+            Return::CreateContractLimit => {
+                eyre::bail!(
+                    "deployment failed: the runtime code exceeds the EIP-170 limit of 24576 bytes"
+                )
+            }
             // TODO: We should have better error handling logic in the test runner
             // regarding deployments in general
             _ => eyre::bail!("deployment failed: {:?}", status),
@@ -495,6 +705,13 @@ where
         should_fail ^ success
     }
 
+    /// Returns the refund granted to a call, given the refund counter and the gas spent by the
+    /// top-level call recorded by [GasRefunds](inspector::GasRefunds)
+    fn granted_refund(&self, recorded: Option<(i64, u64)>, stipend: u64) -> Option<u64> {
+        let london = SpecId::enabled(self.env.cfg.spec_id, SpecId::LONDON);
+        recorded.map(|(refunded, spent)| capped_refund(refunded, stipend + spent, london))
+    }
+
     fn build_env(&self, caller: Address, transact_to: TransactTo, data: Bytes, value: U256) -> Env {
         Env {
             cfg: self.env.cfg.clone(),
@@ -3,25 +3,88 @@ mod strategies;
 pub use proptest::test_runner::{Config as FuzzConfig, Reason};
 
 use crate::{
-    executor::{Executor, RawCallResult},
+    executor::{
+        inspector::{merge_branch_hits, merge_instruction_hits, BranchHits, InstructionHits},
+        Executor, RawCallResult,
+    },
     trace::CallTraceArena,
 };
 use ethers::{
     abi::{Abi, Function, RawLog, Token},
     types::{Address, Bytes},
 };
-use proptest::test_runner::{TestCaseError, TestError, TestRunner};
+use foundry_utils::ParamNames;
+use proptest::{
+    strategy::Strategy,
+    test_runner::{TestCaseError, TestError, TestRunner},
+};
 use revm::db::DatabaseRef;
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, collections::BTreeMap, fmt};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
 use strategies::{
-    build_initial_state, collect_state_from_call, fuzz_calldata, fuzz_calldata_from_state,
-    EvmFuzzState,
+    address_pool, build_initial_state, collect_state_from_call, fuzz_calldata,
+    fuzz_calldata_boundary, fuzz_calldata_from_fixtures, fuzz_calldata_from_pool,
+    fuzz_calldata_from_state, fuzz_calldata_max, fuzz_calldata_zero, EvmFuzzState,
+};
+pub use strategies::{
+    fit_fixture, fixture_name, CustomStrategies, FuzzFixtures, InputsStrategyFn, ParamStrategyFn,
 };
 
 /// Magic return code for the `assume` cheatcode
 pub const ASSUME_MAGIC_RETURN_CODE: &[u8] = b"FOUNDRY::ASSUME";
 
+/// The weights of the strategies the inputs of fuzz tests are drawn from.
+///
+/// The weights are relative, each strategy generates its weight's share of the total weight of
+/// the inputs. A weight of `0` disables the strategy. The default weights only draw random and
+/// state based inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FuzzWeights {
+    /// Random values of the parameter types
+    pub random: u32,
+    /// Values collected from the state, like storage values and the addresses and logs of
+    /// previous fuzz cases
+    pub state: u32,
+    /// The boundary values of the parameter types, like `0`, `type(uint256).max`,
+    /// `type(int256).min`, `-1` or `address(0)`
+    pub boundary: u32,
+    /// Zero for all integer and address parameters
+    pub zero: u32,
+    /// The maximum value of all integer parameters and `0xff..ff` for address parameters
+    pub max: u32,
+}
+
+impl Default for FuzzWeights {
+    fn default() -> Self {
+        Self { random: 60, state: 40, boundary: 0, zero: 0, max: 0 }
+    }
+}
+
+impl FuzzWeights {
+    /// Returns the sum of the weights
+    pub fn total(&self) -> u64 {
+        [self.random, self.state, self.boundary, self.zero, self.max]
+            .iter()
+            .map(|weight| *weight as u64)
+            .sum()
+    }
+
+    /// Ensures that at least one strategy is enabled and that the sum of the weights fits a `u32`
+    pub fn validate(&self) -> eyre::Result<()> {
+        match self.total() {
+            0 => eyre::bail!("At least one fuzz weight must be greater than 0"),
+            total if total > u32::MAX as u64 => {
+                eyre::bail!("The fuzz weights sum up to {}, at most {} is allowed", total, u32::MAX)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Wrapper around an [`Executor`] which provides fuzzing support using [`proptest`](https://docs.rs/proptest/1.0.0/proptest/).
 ///
 /// After instantiation, calling `fuzz` will proceed to hammer the deployed smart contract with
@@ -34,6 +97,14 @@ pub struct FuzzedExecutor<'a, DB: DatabaseRef> {
     runner: TestRunner,
     /// The account that calls tests
     sender: Address,
+    /// The weights of the input strategies
+    weights: FuzzWeights,
+    /// Custom strategies that replace the default strategies of matching tests
+    custom_strategies: CustomStrategies,
+    /// The percentage of random `address` inputs that are drawn from the known addresses
+    address_pool_rate: u32,
+    /// Explicit values for the parameters of the tests, by parameter name
+    fixtures: FuzzFixtures,
 }
 
 impl<'a, DB> FuzzedExecutor<'a, DB>
@@ -42,7 +113,69 @@ where
 {
     /// Instantiates a fuzzed executor given a testrunner
     pub fn new(executor: &'a Executor<DB>, runner: TestRunner, sender: Address) -> Self {
-        Self { executor, runner, sender }
+        Self {
+            executor,
+            runner,
+            sender,
+            weights: FuzzWeights::default(),
+            custom_strategies: CustomStrategies::default(),
+            address_pool_rate: 0,
+            fixtures: FuzzFixtures::default(),
+        }
+    }
+
+    /// Sets the weight with which inputs are drawn from the boundary values of their types (e.g.
+    /// `0`, `type(uint256).max` or `address(0)`).
+    ///
+    /// The weight is relative to the combined weight (100) of the random and state based inputs,
+    /// so a weight of `25` draws boundary values for roughly 20% of the cases. `0` disables
+    /// boundary values.
+    #[must_use]
+    pub fn with_boundary_weight(mut self, weight: u32) -> Self {
+        self.weights.boundary = weight;
+        self
+    }
+
+    /// Sets the weights of all input strategies, see [FuzzWeights].
+    ///
+    /// Invalid weights, see [FuzzWeights::validate()], fall back to the default weights.
+    #[must_use]
+    pub fn with_weights(mut self, weights: FuzzWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Sets custom strategies for the inputs of tests.
+    ///
+    /// If a custom strategy matches a test or any of its parameters, the inputs of the test are
+    /// only generated by the custom strategies, see [CustomStrategies::fuzz_calldata()], otherwise
+    /// the default strategies are used.
+    #[must_use]
+    pub fn with_custom_strategies(mut self, strategies: CustomStrategies) -> Self {
+        self.custom_strategies = strategies;
+        self
+    }
+
+    /// Sets the percentage of randomly generated `address` inputs that are drawn from a pool of
+    /// known addresses instead: the test contract, the sender, the deployed contracts,
+    /// `address(0)` and the precompiles, see [address_pool()].
+    ///
+    /// `0` disables the pool.
+    #[must_use]
+    pub fn with_address_pool_rate(mut self, rate: u32) -> Self {
+        self.address_pool_rate = rate.min(100);
+        self
+    }
+
+    /// Sets explicit values for parameters, by parameter name.
+    ///
+    /// Inputs drawn from the fixtures are generated in addition to the random inputs, with the
+    /// same weight. Parameters without fixtures are random. The fixtures must have the types of
+    /// the parameters, see [fit_fixture()].
+    #[must_use]
+    pub fn with_fixtures(mut self, fixtures: FuzzFixtures) -> Self {
+        self.fixtures = fixtures;
+        self
     }
 
     /// Fuzzes the provided function, assuming it is available at the contract at `address`
@@ -63,24 +196,101 @@ where
         // Stores the result and calldata of the last failed call, if any.
         let counterexample: RefCell<(Bytes, RawCallResult)> = RefCell::new(Default::default());
 
+        // Whether any of the fuzz cases invoked the FFI cheatcode
+        let used_ffi = Cell::new(false);
+
+        // The cheatcodes invoked in any of the fuzz cases, if they are recorded
+        let used_cheatcodes: RefCell<BTreeSet<String>> = RefCell::new(Default::default());
+
+        // The maximum call depth reached in any of the fuzz cases, if it is recorded
+        let max_call_depth: Cell<Option<usize>> = Cell::new(None);
+
+        // The fewest assertions executed in any of the fuzz cases, if they are counted
+        let assertions: Cell<Option<usize>> = Cell::new(None);
+
+        // The directions taken at conditional jumps in any of the fuzz cases, if they are recorded
+        let branches: RefCell<Option<BranchHits>> = RefCell::new(None);
+
+        // The executions of instructions in all fuzz cases, if they are counted
+        let instructions: RefCell<Option<InstructionHits>> = RefCell::new(None);
+
+        // The runtime code of the contracts that recorded branches or instructions, by address
+        let contract_code: RefCell<BTreeMap<Address, Vec<u8>>> = RefCell::new(BTreeMap::new());
+
+        // The total gas refunded to the passing fuzz cases and their number, if refunds are
+        // recorded
+        let refunds: Cell<Option<(u64, u64)>> = Cell::new(None);
+
         // Stores fuzz state for use with [fuzz_calldata_from_state]
         let state: EvmFuzzState = build_initial_state(&self.executor.db);
 
         // TODO: We should have a `FuzzerOpts` struct where we can configure the fuzzer. When we
         // have that, we should add a way to configure strategy weights
-        let strat = proptest::strategy::Union::new_weighted(vec![
-            (60, fuzz_calldata(func.clone())),
-            (40, fuzz_calldata_from_state(func.clone(), state.clone())),
-        ]);
+        let strat = match self.custom_strategies.fuzz_calldata(func) {
+            Some(strat) => strat,
+            None => {
+                let random = if self.address_pool_rate > 0 {
+                    let pool = address_pool(&self.executor.db, [address, self.sender]);
+                    fuzz_calldata_from_pool(func.clone(), &pool, self.address_pool_rate)
+                } else {
+                    fuzz_calldata(func.clone())
+                };
+                let weights = match self.weights.validate() {
+                    Ok(()) => self.weights,
+                    Err(_) => FuzzWeights::default(),
+                };
+                let mut strats = vec![
+                    (weights.random, random),
+                    (weights.state, fuzz_calldata_from_state(func.clone(), state.clone())),
+                    (weights.boundary, fuzz_calldata_boundary(func.clone())),
+                    (weights.zero, fuzz_calldata_zero(func.clone())),
+                    (weights.max, fuzz_calldata_max(func.clone())),
+                ];
+                if let Some(fixtures) = fuzz_calldata_from_fixtures(func.clone(), &self.fixtures) {
+                    strats.push((weights.random, fixtures));
+                }
+                let strats =
+                    strats.into_iter().filter(|(weight, _)| *weight > 0).collect::<Vec<_>>();
+                proptest::strategy::Union::new_weighted(strats).boxed()
+            }
+        };
         tracing::debug!(func = ?func.name, should_fail, "fuzzing");
         let run_result = self.runner.clone().run(&strat, |calldata| {
             let call = self
                 .executor
                 .call_raw(self.sender, address, calldata.0.clone(), 0.into())
                 .expect("could not make raw evm call");
+            used_ffi.set(used_ffi.get() || call.used_ffi);
+            used_cheatcodes.borrow_mut().extend(call.used_cheatcodes.iter().cloned());
+            max_call_depth.set(max_call_depth.get().max(call.max_call_depth));
+            assertions.set(match (assertions.get(), call.assertions) {
+                (Some(fewest), Some(count)) => Some(fewest.min(count)),
+                (fewest, count) => fewest.or(count),
+            });
+            if let Some(ref hits) = call.branches {
+                merge_branch_hits(branches.borrow_mut().get_or_insert_with(Default::default), hits);
+            }
+            if let Some(ref hits) = call.instructions {
+                let mut instructions = instructions.borrow_mut();
+                merge_instruction_hits(instructions.get_or_insert_with(Default::default), hits);
+            }
             let state_changeset =
                 call.state_changeset.as_ref().expect("we should have a state changeset");
 
+            // contracts created in a fuzz case only exist in its changeset
+            let ran = call
+                .branches
+                .iter()
+                .flat_map(|hits| hits.keys())
+                .chain(call.instructions.iter().flat_map(|hits| hits.keys()));
+            for address in ran {
+                let code =
+                    state_changeset.get(address).and_then(|account| account.info.code.as_ref());
+                if let Some(code) = code {
+                    contract_code.borrow_mut().entry(*address).or_insert_with(|| code.to_vec());
+                }
+            }
+
             // Build fuzzer state
             collect_state_from_call(&call.logs, state_changeset, state.clone());
 
@@ -97,6 +307,10 @@ where
             );
 
             if success {
+                if let Some(refund) = call.gas_refund {
+                    let (total, count) = refunds.get().unwrap_or_default();
+                    refunds.set(Some((total + refund, count + 1)));
+                }
                 cases.borrow_mut().push(FuzzCase {
                     calldata,
                     gas: call.gas,
@@ -108,16 +322,16 @@ where
                 // since that input represents the last run case, which may not correspond with our
                 // failure - when a fuzz case fails, proptest will try to run at least one more
                 // case to find a minimal failure case.
-                *counterexample.borrow_mut() = (calldata, call);
-                Err(TestCaseError::fail(
-                    match foundry_utils::decode_revert(
-                        counterexample.borrow().1.result.as_ref(),
-                        errors,
-                    ) {
+                // calls that ran out of gas revert without revert data
+                let reason = match call.out_of_gas {
+                    Some(ref out_of_gas) if call.result.is_empty() => out_of_gas.to_string(),
+                    _ => match foundry_utils::decode_revert(call.result.as_ref(), errors) {
                         Ok(e) => e,
                         Err(_) => "".to_string(),
                     },
-                ))
+                };
+                *counterexample.borrow_mut() = (calldata, call);
+                Err(TestCaseError::fail(reason))
             }
         });
 
@@ -130,6 +344,14 @@ where
             logs: call.logs,
             traces: call.traces,
             labeled_addresses: call.labels,
+            used_ffi: used_ffi.get(),
+            used_cheatcodes: used_cheatcodes.into_inner(),
+            max_call_depth: max_call_depth.get(),
+            assertions: assertions.get(),
+            branches: branches.into_inner(),
+            instructions: instructions.into_inner(),
+            contract_code: contract_code.into_inner(),
+            gas_refund: refunds.get().map(|(total, count)| total / count),
         };
 
         match run_result {
@@ -143,7 +365,7 @@ where
                 let args = func
                     .decode_input(&calldata.as_ref()[4..])
                     .expect("could not decode fuzzer inputs");
-                result.counterexample = Some(CounterExample { calldata, args });
+                result.counterexample = Some(CounterExample { calldata, args, names: Vec::new() });
             }
             _ => (),
         }
@@ -158,11 +380,34 @@ pub struct CounterExample {
 
     #[serde(skip)]
     pub args: Vec<Token>,
+
+    /// The names of the arguments and of their struct fields, if known, used to print structs
+    /// with their field names
+    #[serde(skip)]
+    pub names: Vec<ParamNames>,
+}
+
+impl CounterExample {
+    /// Sets the names of the arguments and of their struct fields
+    #[must_use]
+    pub fn with_names(mut self, names: Vec<ParamNames>) -> Self {
+        self.names = names;
+        self
+    }
 }
 
 impl fmt::Display for CounterExample {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let args = foundry_utils::format_tokens(&self.args).collect::<Vec<_>>().join(", ");
+        let args = if self.names.len() == self.args.len() {
+            self.args
+                .iter()
+                .zip(&self.names)
+                .map(|(arg, names)| foundry_utils::format_token_named(arg, names))
+                .collect::<Vec<_>>()
+        } else {
+            foundry_utils::format_tokens(&self.args).collect::<Vec<_>>()
+        }
+        .join(", ");
         write!(f, "calldata=0x{}, args=[{}]", hex::encode(&self.calldata), args)
     }
 }
@@ -194,6 +439,33 @@ pub struct FuzzTestResult {
 
     /// Labeled addresses
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// Whether the FFI cheatcode was invoked in any of the fuzz cases
+    pub used_ffi: bool,
+
+    /// The cheatcodes invoked in any of the fuzz cases, if they were recorded
+    pub used_cheatcodes: BTreeSet<String>,
+
+    /// The maximum call depth reached in any of the fuzz cases, if it was recorded
+    pub max_call_depth: Option<usize>,
+
+    /// The fewest assertions executed in any of the fuzz cases, if they were counted
+    pub assertions: Option<usize>,
+
+    /// The directions taken at conditional jumps in any of the fuzz cases, if they were recorded
+    pub branches: Option<BranchHits>,
+
+    /// The mean gas refunded to the passing fuzz cases, if refunds were recorded
+    pub gas_refund: Option<u64>,
+
+    /// The executions of instructions in all fuzz cases, if they were counted
+    pub instructions: Option<InstructionHits>,
+
+    /// The runtime code of the contracts that recorded branches or instructions, by address,
+    /// including the contracts created in the fuzz cases, which are not part of the executor's
+    /// database. The code of the first fuzz case is kept if cases created different contracts at
+    /// an address
+    pub contract_code: BTreeMap<Address, Vec<u8>>,
 }
 
 /// Container type for all successful test cases
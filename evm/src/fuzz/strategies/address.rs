@@ -0,0 +1,136 @@
+use super::{fuzz_param, param::MAX_ARRAY_LEN};
+use ethers::{
+    abi::{Function, ParamType, Token, Tokenizable},
+    types::{Address, Bytes, H256},
+};
+use proptest::prelude::{BoxedStrategy, Strategy};
+use revm::{
+    db::{CacheDB, DatabaseRef},
+    KECCAK_EMPTY,
+};
+use std::collections::BTreeSet;
+
+/// The number of precompiled contracts, these are at the addresses `0x01` to `0x09`
+const PRECOMPILES: u64 = 9;
+
+/// Returns the addresses that fuzzed `address` parameters are likely to be meaningful for: the
+/// given addresses (e.g. the test contract and the sender), all contracts deployed in `db`,
+/// `address(0)` and the precompiles.
+pub fn address_pool<DB: DatabaseRef>(
+    db: &CacheDB<DB>,
+    addresses: impl IntoIterator<Item = Address>,
+) -> Vec<Address> {
+    let mut pool: BTreeSet<Address> = addresses.into_iter().collect();
+    for (address, _) in db.storage() {
+        let info = db.basic(*address);
+        let has_code = match info.code {
+            Some(ref code) => !code.is_empty(),
+            None => info.code_hash != KECCAK_EMPTY && info.code_hash != H256::zero(),
+        };
+        if has_code {
+            pool.insert(*address);
+        }
+    }
+    pool.insert(Address::zero());
+    pool.extend((1..=PRECOMPILES).map(Address::from_low_u64_be));
+    pool.into_iter().collect()
+}
+
+/// Given a parameter type, returns a strategy where every address is drawn from `pool` with a
+/// probability of `rate` percent, and generated by [fuzz_param] otherwise.
+///
+/// Addresses in arrays and tuples are drawn from the pool as well.
+pub fn fuzz_param_from_pool(
+    param: &ParamType,
+    pool: &[Address],
+    rate: u32,
+) -> BoxedStrategy<Token> {
+    if pool.is_empty() || rate == 0 {
+        return fuzz_param(param).boxed()
+    }
+
+    match param {
+        ParamType::Address => {
+            let known = proptest::sample::select(pool.to_vec()).prop_map(|x| x.into_token());
+            if rate >= 100 {
+                return known.boxed()
+            }
+            proptest::strategy::Union::new_weighted(vec![
+                (rate, known.boxed()),
+                (100 - rate, fuzz_param(param).boxed()),
+            ])
+            .boxed()
+        }
+        ParamType::Array(param) => {
+            proptest::collection::vec(fuzz_param_from_pool(param, pool, rate), 0..MAX_ARRAY_LEN)
+                .prop_map(Token::Array)
+                .boxed()
+        }
+        ParamType::FixedArray(param, size) => {
+            std::iter::repeat_with(|| fuzz_param_from_pool(param, pool, rate))
+                .take(*size)
+                .collect::<Vec<_>>()
+                .prop_map(Token::FixedArray)
+                .boxed()
+        }
+        ParamType::Tuple(params) => params
+            .iter()
+            .map(|param| fuzz_param_from_pool(param, pool, rate))
+            .collect::<Vec<_>>()
+            .prop_map(Token::Tuple)
+            .boxed(),
+        _ => fuzz_param(param).boxed(),
+    }
+}
+
+/// Given a function, it returns a strategy which generates calldata for that function where
+/// addresses are drawn from `pool` with a probability of `rate` percent, see
+/// [fuzz_param_from_pool()].
+pub fn fuzz_calldata_from_pool(
+    func: Function,
+    pool: &[Address],
+    rate: u32,
+) -> BoxedStrategy<Bytes> {
+    let strats = func
+        .inputs
+        .iter()
+        .map(|input| fuzz_param_from_pool(&input.kind, pool, rate))
+        .collect::<Vec<_>>();
+
+    strats
+        .prop_map(move |tokens| {
+            tracing::trace!(input = ?tokens);
+            func.encode_input(&tokens).unwrap().into()
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::AbiParser;
+    use proptest::{strategy::ValueTree, test_runner::TestRunner};
+    use revm::db::EmptyDB;
+
+    #[test]
+    fn can_draw_addresses_from_pool() {
+        let sender = Address::random();
+        let pool = address_pool(&CacheDB::new(EmptyDB()), [sender]);
+        assert_eq!(pool.len(), 11);
+        assert!(pool.contains(&sender));
+        assert!(pool.contains(&Address::zero()));
+        assert!(pool.contains(&Address::from_low_u64_be(9)));
+
+        let func = AbiParser::default().parse_function("testFoo(address,address[2])").unwrap();
+        let strategy = fuzz_calldata_from_pool(func.clone(), &pool, 100);
+        let mut runner = TestRunner::default();
+        for _ in 0..10 {
+            let calldata = strategy.new_tree(&mut runner).unwrap().current();
+            let tokens = func.decode_input(&calldata[4..]).unwrap();
+            assert!(pool.contains(&tokens[0].clone().into_address().unwrap()));
+            for token in tokens[1].clone().into_fixed_array().unwrap() {
+                assert!(pool.contains(&token.into_address().unwrap()));
+            }
+        }
+    }
+}
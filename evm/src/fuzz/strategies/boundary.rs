@@ -0,0 +1,191 @@
+use super::fuzz_param;
+use ethers::{
+    abi::{Function, ParamType, Token, Tokenizable},
+    types::{Address, Bytes, I256, U256},
+};
+use proptest::prelude::{BoxedStrategy, Strategy};
+
+/// Returns the boundary values of the given parameter type, if the type has any.
+///
+/// These are the values at the edges of the type's domain, e.g. `0` and `type(uintN).max` for
+/// unsigned integers, `type(intN).min`, `-1`, `0` and `type(intN).max` for signed integers and
+/// `address(0)` for addresses.
+pub fn boundary_values(param: &ParamType) -> Option<Vec<Token>> {
+    match param {
+        ParamType::Address => Some(vec![
+            Address::zero().into_token(),
+            Address::from_low_u64_be(1).into_token(),
+            Address::repeat_byte(0xff).into_token(),
+        ]),
+        ParamType::Uint(n) => {
+            let max = if *n < 256 { (U256::one() << U256::from(*n)) - 1 } else { U256::MAX };
+            Some(
+                [U256::zero(), U256::one(), max - 1, max]
+                    .into_iter()
+                    .map(|value| value.into_token())
+                    .collect(),
+            )
+        }
+        ParamType::Int(n) => {
+            // `max` is `2^(N-1) - 1`, and in two's complement `min` is its bitwise negation
+            let max = (U256::one() << U256::from(*n - 1)) - 1;
+            let min = !max;
+            Some(
+                [min, min + 1, U256::MAX, U256::zero(), U256::one(), max - 1, max]
+                    .into_iter()
+                    .map(|value| I256::from_raw(value).into_token())
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+/// Returns the zero value, or the maximum value if `max` is set, of the given parameter type if it
+/// is an integer or address type.
+///
+/// The maximum value of an address is `0xff..ff`.
+pub fn extreme_value(param: &ParamType, max: bool) -> Option<Token> {
+    match param {
+        ParamType::Address if max => Some(Address::repeat_byte(0xff).into_token()),
+        ParamType::Address => Some(Address::zero().into_token()),
+        ParamType::Uint(n) if max => {
+            let max = if *n < 256 { (U256::one() << U256::from(*n)) - 1 } else { U256::MAX };
+            Some(max.into_token())
+        }
+        ParamType::Int(n) if max => {
+            Some(I256::from_raw((U256::one() << U256::from(*n - 1)) - 1).into_token())
+        }
+        ParamType::Uint(_) => Some(U256::zero().into_token()),
+        ParamType::Int(_) => Some(I256::from(0).into_token()),
+        _ => None,
+    }
+}
+
+/// Given a parameter type, returns a strategy that generates the type's boundary values (see
+/// [boundary_values]).
+///
+/// Tuples and fixed size arrays are composed of the boundary strategies of their members, all
+/// other types without boundary values fall back to [fuzz_param].
+pub fn fuzz_param_boundary(param: &ParamType) -> BoxedStrategy<Token> {
+    if let Some(values) = boundary_values(param) {
+        return proptest::sample::select(values).boxed()
+    }
+
+    match param {
+        ParamType::FixedArray(param, size) => std::iter::repeat_with(|| fuzz_param_boundary(param))
+            .take(*size)
+            .collect::<Vec<_>>()
+            .prop_map(Token::FixedArray)
+            .boxed(),
+        ParamType::Tuple(params) => params
+            .iter()
+            .map(fuzz_param_boundary)
+            .collect::<Vec<_>>()
+            .prop_map(Token::Tuple)
+            .boxed(),
+        _ => fuzz_param(param).boxed(),
+    }
+}
+
+/// Given a parameter type, returns a strategy that generates the type's zero or maximum value (see
+/// [extreme_value]).
+///
+/// Tuples and fixed size arrays are composed of the strategies of their members, all other types
+/// fall back to [fuzz_param].
+fn fuzz_param_extreme(param: &ParamType, max: bool) -> BoxedStrategy<Token> {
+    if let Some(value) = extreme_value(param, max) {
+        return proptest::strategy::Just(value).boxed()
+    }
+
+    match param {
+        ParamType::FixedArray(param, size) => {
+            std::iter::repeat_with(|| fuzz_param_extreme(param, max))
+                .take(*size)
+                .collect::<Vec<_>>()
+                .prop_map(Token::FixedArray)
+                .boxed()
+        }
+        ParamType::Tuple(params) => params
+            .iter()
+            .map(|param| fuzz_param_extreme(param, max))
+            .collect::<Vec<_>>()
+            .prop_map(Token::Tuple)
+            .boxed(),
+        _ => fuzz_param(param).boxed(),
+    }
+}
+
+/// Given a function, it returns a strategy which generates calldata for that function where every
+/// integer and address parameter is zero.
+pub fn fuzz_calldata_zero(func: Function) -> BoxedStrategy<Bytes> {
+    fuzz_calldata_extreme(func, false)
+}
+
+/// Given a function, it returns a strategy which generates calldata for that function where every
+/// integer and address parameter is set to its maximum value.
+pub fn fuzz_calldata_max(func: Function) -> BoxedStrategy<Bytes> {
+    fuzz_calldata_extreme(func, true)
+}
+
+fn fuzz_calldata_extreme(func: Function, max: bool) -> BoxedStrategy<Bytes> {
+    let strats =
+        func.inputs.iter().map(|input| fuzz_param_extreme(&input.kind, max)).collect::<Vec<_>>();
+
+    strats
+        .prop_map(move |tokens| {
+            tracing::trace!(input = ?tokens);
+            func.encode_input(&tokens).unwrap().into()
+        })
+        .boxed()
+}
+
+/// Given a function, it returns a strategy which generates calldata for that function where every
+/// integer and address parameter is set to one of its boundary values.
+pub fn fuzz_calldata_boundary(func: Function) -> BoxedStrategy<Bytes> {
+    let strats =
+        func.inputs.iter().map(|input| fuzz_param_boundary(&input.kind)).collect::<Vec<_>>();
+
+    strats
+        .prop_map(move |tokens| {
+            tracing::trace!(input = ?tokens);
+            func.encode_input(&tokens).unwrap().into()
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_generate_int_boundaries() {
+        let values = boundary_values(&ParamType::Int(8)).unwrap();
+        let values = values.into_iter().map(|t| I256::from_token(t).unwrap()).collect::<Vec<_>>();
+        assert_eq!(values.first().unwrap(), &I256::from(-128i32));
+        assert_eq!(values.last().unwrap(), &I256::from(127i32));
+        assert!(values.contains(&I256::from(-1i32)));
+    }
+
+    #[test]
+    fn can_generate_uint_boundaries() {
+        let values = boundary_values(&ParamType::Uint(8)).unwrap();
+        let values = values.into_iter().map(|t| U256::from_token(t).unwrap()).collect::<Vec<_>>();
+        assert_eq!(values, vec![0.into(), 1.into(), 254.into(), 255.into()]);
+
+        let values = boundary_values(&ParamType::Uint(256)).unwrap();
+        assert_eq!(values.last().unwrap(), &U256::MAX.into_token());
+    }
+
+    #[test]
+    fn can_generate_extreme_values() {
+        assert_eq!(extreme_value(&ParamType::Uint(8), true), Some(U256::from(255).into_token()));
+        assert_eq!(extreme_value(&ParamType::Int(8), true), Some(I256::from(127i32).into_token()));
+        assert_eq!(extreme_value(&ParamType::Int(8), false), Some(I256::from(0).into_token()));
+        assert_eq!(
+            extreme_value(&ParamType::Address, true),
+            Some(Address::repeat_byte(0xff).into_token())
+        );
+        assert_eq!(extreme_value(&ParamType::Bool, false), None);
+    }
+}
@@ -0,0 +1,132 @@
+use super::fuzz_param;
+use ethers::{
+    abi::{Function, ParamType, Token},
+    types::Bytes,
+};
+use proptest::prelude::{BoxedStrategy, Strategy};
+use std::{collections::BTreeMap, fmt, sync::Arc};
+
+/// Builds the strategy for a parameter of the given type
+pub type ParamStrategyFn = Arc<dyn Fn(&ParamType) -> BoxedStrategy<Token> + Send + Sync>;
+
+/// Builds the strategy for all inputs of the given function
+pub type InputsStrategyFn = Arc<dyn Fn(&Function) -> BoxedStrategy<Vec<Token>> + Send + Sync>;
+
+/// Custom strategies for the inputs of fuzz tests, e.g. to generate valid signatures.
+///
+/// Strategies are registered either for all inputs of a test, by the test's signature, or for the
+/// parameters of a type, by the type's canonical name (e.g. `bytes` or `uint256`). Only the
+/// top-level parameters of a test are matched against the types, not the elements of arrays or
+/// tuples.
+///
+/// If no custom strategy matches a test, its inputs are generated by the default strategies.
+#[derive(Clone, Default)]
+pub struct CustomStrategies {
+    /// Strategies for all inputs of a test, by test signature
+    tests: BTreeMap<String, InputsStrategyFn>,
+    /// Strategies for parameters, by canonical type name
+    params: BTreeMap<String, ParamStrategyFn>,
+}
+
+impl CustomStrategies {
+    /// Registers a strategy for all inputs of the test with the given signature, e.g.
+    /// `testVerify(bytes32,bytes)`. This takes precedence over parameter strategies.
+    pub fn add_test_strategy(
+        &mut self,
+        signature: impl Into<String>,
+        strategy: impl Fn(&Function) -> BoxedStrategy<Vec<Token>> + Send + Sync + 'static,
+    ) {
+        self.tests.insert(signature.into(), Arc::new(strategy));
+    }
+
+    /// Registers a strategy for all parameters of the given type
+    pub fn add_param_strategy(
+        &mut self,
+        param: &ParamType,
+        strategy: impl Fn(&ParamType) -> BoxedStrategy<Token> + Send + Sync + 'static,
+    ) {
+        self.params.insert(param.to_string(), Arc::new(strategy));
+    }
+
+    /// Returns `true` if no strategies are registered
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty() && self.params.is_empty()
+    }
+
+    /// Returns a strategy which generates calldata for the function, if a custom strategy matches
+    /// the function or any of its parameters.
+    ///
+    /// Parameters without a custom strategy are generated by [fuzz_param()].
+    pub fn fuzz_calldata(&self, func: &Function) -> Option<BoxedStrategy<Bytes>> {
+        let inputs = match self.tests.get(&func.signature()) {
+            Some(strategy) => strategy(func),
+            None => {
+                if !func
+                    .inputs
+                    .iter()
+                    .any(|input| self.params.contains_key(&input.kind.to_string()))
+                {
+                    return None
+                }
+                func.inputs
+                    .iter()
+                    .map(|input| match self.params.get(&input.kind.to_string()) {
+                        Some(strategy) => strategy(&input.kind),
+                        None => fuzz_param(&input.kind).boxed(),
+                    })
+                    .collect::<Vec<_>>()
+                    .boxed()
+            }
+        };
+
+        let func = func.clone();
+        Some(
+            inputs
+                .prop_map(move |tokens| {
+                    tracing::trace!(input = ?tokens);
+                    func.encode_input(&tokens).unwrap().into()
+                })
+                .boxed(),
+        )
+    }
+}
+
+impl fmt::Debug for CustomStrategies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomStrategies")
+            .field("tests", &self.tests.keys().collect::<Vec<_>>())
+            .field("params", &self.params.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{AbiParser, Tokenizable};
+    use proptest::{
+        strategy::{Just, ValueTree},
+        test_runner::TestRunner,
+    };
+
+    #[test]
+    fn can_use_custom_param_strategies() {
+        let func = AbiParser::default().parse_function("testFoo(uint256,address)").unwrap();
+        let mut strategies = CustomStrategies::default();
+        assert!(strategies.fuzz_calldata(&func).is_none());
+
+        strategies.add_param_strategy(&ParamType::Uint(256), |_| Just(7u64.into_token()).boxed());
+        let strategy = strategies.fuzz_calldata(&func).unwrap();
+        let calldata = strategy.new_tree(&mut TestRunner::default()).unwrap().current();
+        let tokens = func.decode_input(&calldata[4..]).unwrap();
+        assert_eq!(tokens[0], 7u64.into_token());
+
+        // test strategies take precedence
+        strategies.add_test_strategy("testFoo(uint256,address)", |_| {
+            Just(vec![8u64.into_token(), Token::Address(Default::default())]).boxed()
+        });
+        let strategy = strategies.fuzz_calldata(&func).unwrap();
+        let calldata = strategy.new_tree(&mut TestRunner::default()).unwrap().current();
+        assert_eq!(func.decode_input(&calldata[4..]).unwrap()[0], 8u64.into_token());
+    }
+}
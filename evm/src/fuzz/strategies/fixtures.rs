@@ -0,0 +1,134 @@
+use super::fuzz_param;
+use ethers::{
+    abi::{Function, ParamType, Token},
+    types::{Bytes, I256, U256},
+};
+use proptest::prelude::{BoxedStrategy, Strategy};
+use std::collections::BTreeMap;
+
+/// Explicit values for the parameters of fuzz tests, by parameter name.
+///
+/// Test contracts declare fixtures by naming convention: `uint256[] public fixtureAmount`, or a
+/// `fixtureAmount()` function returning an array, provides values for parameters named `amount`,
+/// see [fixture_name()].
+#[derive(Clone, Debug, Default)]
+pub struct FuzzFixtures {
+    values: BTreeMap<String, Vec<Token>>,
+}
+
+impl FuzzFixtures {
+    /// Sets the values of the parameters named `param`, empty fixtures are ignored
+    pub fn insert(&mut self, param: impl Into<String>, values: Vec<Token>) {
+        if !values.is_empty() {
+            self.values.insert(param.into(), values);
+        }
+    }
+
+    /// Returns the values of the parameters named `param`, if any
+    pub fn get(&self, param: &str) -> Option<&[Token]> {
+        self.values.get(param).map(Vec::as_slice)
+    }
+
+    /// Returns `true` if there are no fixtures
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// Returns the name of the fixture of a parameter, `fixture` followed by the capitalized name of
+/// the parameter, e.g. `fixtureAmount` for `amount`
+pub fn fixture_name(param: &str) -> String {
+    let mut chars = param.chars();
+    match chars.next() {
+        Some(first) => format!("fixture{}{}", first.to_uppercase(), chars.as_str()),
+        None => "fixture".to_string(),
+    }
+}
+
+/// Converts a fixture value to the type of the parameter.
+///
+/// Integers are converted between sizes if the value fits into the parameter type, all other
+/// values must have the type of the parameter. Returns `None` if the value does not fit.
+pub fn fit_fixture(value: &Token, param: &ParamType) -> Option<Token> {
+    match (value, param) {
+        (Token::Uint(value), ParamType::Uint(n)) => {
+            (*n >= 256 || value.bits() <= *n).then(|| Token::Uint(*value))
+        }
+        (Token::Int(value), ParamType::Int(n)) => {
+            if *n >= 256 {
+                return Some(Token::Int(*value))
+            }
+            let value = I256::from_raw(*value);
+            let max = I256::from_raw((U256::one() << U256::from(*n - 1)) - 1);
+            let min = -max - I256::from(1);
+            (value >= min && value <= max).then(|| Token::Int(value.into_raw()))
+        }
+        (value, param) => value.type_check(param).then(|| value.clone()),
+    }
+}
+
+/// Given a function, returns a strategy which generates calldata for that function where the
+/// parameters with fixtures are drawn from their fixtures, or `None` if no parameter has fixtures.
+///
+/// Parameters without fixtures are generated by [fuzz_param()]. The fixtures are expected to have
+/// the types of the parameters, see [fit_fixture()].
+pub fn fuzz_calldata_from_fixtures(
+    func: Function,
+    fixtures: &FuzzFixtures,
+) -> Option<BoxedStrategy<Bytes>> {
+    if !func.inputs.iter().any(|input| fixtures.get(&input.name).is_some()) {
+        return None
+    }
+    let strats = func
+        .inputs
+        .iter()
+        .map(|input| match fixtures.get(&input.name) {
+            Some(values) => proptest::sample::select(values.to_vec()).boxed(),
+            None => fuzz_param(&input.kind).boxed(),
+        })
+        .collect::<Vec<_>>();
+
+    Some(
+        strats
+            .prop_map(move |tokens| {
+                tracing::trace!(input = ?tokens);
+                func.encode_input(&tokens).unwrap().into()
+            })
+            .boxed(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{AbiParser, Tokenizable};
+    use proptest::{strategy::ValueTree, test_runner::TestRunner};
+
+    #[test]
+    fn can_fit_fixtures() {
+        assert_eq!(fixture_name("amount"), "fixtureAmount");
+        assert_eq!(
+            fit_fixture(&300u64.into_token(), &ParamType::Uint(256)),
+            Some(300u64.into_token())
+        );
+        assert_eq!(fit_fixture(&300u64.into_token(), &ParamType::Uint(8)), None);
+        assert_eq!(
+            fit_fixture(&I256::from(-128).into_token(), &ParamType::Int(8)),
+            Some(I256::from(-128).into_token())
+        );
+        assert_eq!(fit_fixture(&I256::from(-129).into_token(), &ParamType::Int(8)), None);
+        assert_eq!(fit_fixture(&300u64.into_token(), &ParamType::Address), None);
+    }
+
+    #[test]
+    fn can_fuzz_from_fixtures() {
+        let func = AbiParser::default().parse_function("testFoo(uint256 amount,address)").unwrap();
+        let mut fixtures = FuzzFixtures::default();
+        assert!(fuzz_calldata_from_fixtures(func.clone(), &fixtures).is_none());
+
+        fixtures.insert("amount", vec![7u64.into_token()]);
+        let strategy = fuzz_calldata_from_fixtures(func.clone(), &fixtures).unwrap();
+        let calldata = strategy.new_tree(&mut TestRunner::default()).unwrap().current();
+        assert_eq!(func.decode_input(&calldata[4..]).unwrap()[0], 7u64.into_token());
+    }
+}
@@ -7,6 +7,21 @@ pub use param::{fuzz_param, fuzz_param_from_state};
 mod calldata;
 pub use calldata::fuzz_calldata;
 
+mod boundary;
+pub use boundary::{
+    boundary_values, extreme_value, fuzz_calldata_boundary, fuzz_calldata_max, fuzz_calldata_zero,
+    fuzz_param_boundary,
+};
+
+mod address;
+pub use address::{address_pool, fuzz_calldata_from_pool, fuzz_param_from_pool};
+
+mod custom;
+pub use custom::{CustomStrategies, InputsStrategyFn, ParamStrategyFn};
+
+mod fixtures;
+pub use fixtures::{fit_fixture, fixture_name, fuzz_calldata_from_fixtures, FuzzFixtures};
+
 mod state;
 pub use state::{
     build_initial_state, collect_state_from_call, fuzz_calldata_from_state, EvmFuzzState,
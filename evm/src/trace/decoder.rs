@@ -1,14 +1,17 @@
 use super::{
-    identifier::TraceIdentifier, CallTraceArena, RawOrDecodedCall, RawOrDecodedLog,
-    RawOrDecodedReturnData,
+    identifier::{SignaturesIdentifier, TraceIdentifier},
+    CallTraceArena, RawOrDecodedCall, RawOrDecodedLog, RawOrDecodedReturnData,
 };
 use crate::abi::{CHEATCODE_ADDRESS, CONSOLE_ABI, HEVM_ABI};
 use ethers::{
     abi::{Abi, Address, Event, Function, Param, ParamType, Token},
     types::H256,
 };
-use foundry_utils::format_token;
-use std::collections::BTreeMap;
+use foundry_utils::{AbiNames, OutputEncoding, ParamNames};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
 /// The call trace decoder.
 ///
@@ -33,6 +36,12 @@ pub struct CallTraceDecoder {
     pub events: BTreeMap<(H256, usize), Vec<Event>>,
     /// All known errors
     pub errors: Abi,
+    /// The parameter names of known functions and events, used to print the fields of structs
+    pub abi_names: Arc<AbiNames>,
+    /// How addresses and bytes of decoded calls and events are printed
+    pub output_encoding: OutputEncoding,
+    /// The selectors of functions only known from extra ABIs, see [Self::add_extra_abi()]
+    extra_selectors: BTreeSet<[u8; 4]>,
 }
 
 impl CallTraceDecoder {
@@ -125,6 +134,9 @@ impl CallTraceDecoder {
                 .map(|event| ((event.signature(), indexed_inputs(event)), vec![event.clone()]))
                 .collect::<BTreeMap<(H256, usize), Vec<Event>>>(),
             errors: Abi::default(),
+            abi_names: Default::default(),
+            output_encoding: Default::default(),
+            extra_selectors: BTreeSet::new(),
         }
     }
 
@@ -162,9 +174,15 @@ impl CallTraceDecoder {
 
             if let Some(abi) = &identity.abi {
                 // Store known functions for the address
-                abi.functions()
-                    .map(|func| (func.short_signature(), func.clone()))
-                    .for_each(|(sig, func)| self.functions.entry(sig).or_default().push(func));
+                for func in abi.functions() {
+                    let sig = func.short_signature();
+                    let funcs = self.functions.entry(sig).or_default();
+                    // functions of identified contracts take precedence over extra ABIs
+                    if self.extra_selectors.remove(&sig) {
+                        funcs.clear();
+                    }
+                    funcs.push(func.clone());
+                }
 
                 // Flatten events from all ABIs
                 abi.events()
@@ -186,6 +204,54 @@ impl CallTraceDecoder {
         });
     }
 
+    /// Adds the ABI of a contract that is not part of the project, e.g. of a contract on a forked
+    /// chain, to decode calls to it.
+    ///
+    /// Its functions, events and errors are only added if none with the same selector is known
+    /// yet, and functions are replaced by the ones of contracts identified later on.
+    pub fn add_extra_abi(&mut self, abi: &Abi) {
+        for func in abi.functions() {
+            let sig = func.short_signature();
+            if !self.functions.contains_key(&sig) {
+                self.functions.insert(sig, vec![func.clone()]);
+                self.extra_selectors.insert(sig);
+            }
+        }
+        for event in abi.events() {
+            self.events
+                .entry((event.signature(), indexed_inputs(event)))
+                .or_insert_with(|| vec![event.clone()]);
+        }
+        for error in abi.errors() {
+            if !self.errors.errors().any(|known| known.signature() == error.signature()) {
+                self.errors.errors.entry(error.name.clone()).or_default().push(error.clone());
+            }
+        }
+    }
+
+    /// Resolves the selectors of calls to functions that are not known yet using the signatures
+    /// identifier.
+    ///
+    /// This gives calls to contracts without an ABI a best effort function name.
+    pub fn identify_selectors(
+        &mut self,
+        trace: &CallTraceArena,
+        identifier: &mut SignaturesIdentifier,
+    ) {
+        for node in trace.arena.iter() {
+            if self.precompiles.contains_key(&node.trace.address) {
+                continue
+            }
+            if let RawOrDecodedCall::Raw(bytes) = &node.trace.data {
+                if bytes.len() >= 4 && !self.functions.contains_key(&bytes[0..4]) {
+                    if let Some(func) = identifier.identify_function(&bytes[0..4]) {
+                        self.functions.entry(func.short_signature()).or_default().push(func);
+                    }
+                }
+            }
+        }
+    }
+
     pub fn decode(&self, traces: &mut CallTraceArena) {
         for node in traces.arena.iter_mut() {
             // Set contract name
@@ -245,7 +311,7 @@ impl CallTraceDecoder {
                             } else {
                                 match func.decode_input(&bytes[4..]) {
                                     Ok(v) => {
-                                        v.iter().map(|token| self.apply_label(token)).collect()
+                                        self.format_inputs(&v, self.abi_names.function_inputs(func))
                                     }
                                     Err(_) => Vec::new(),
                                 }
@@ -258,8 +324,10 @@ impl CallTraceDecoder {
                         if let RawOrDecodedReturnData::Raw(bytes) = &node.trace.output {
                             if !bytes.is_empty() {
                                 if node.trace.success {
+                                    // functions resolved by selector have no known outputs
                                     if let Some(tokens) = funcs
                                         .iter()
+                                        .filter(|func| !func.outputs.is_empty())
                                         .find_map(|func| func.decode_output(&bytes[..]).ok())
                                     {
                                         node.trace.output = RawOrDecodedReturnData::Decoded(
@@ -307,13 +375,16 @@ impl CallTraceDecoder {
                     {
                         for event in events {
                             if let Ok(decoded) = event.parse_log(raw_log.clone()) {
+                                let (names, tokens): (Vec<_>, Vec<_>) = decoded
+                                    .params
+                                    .into_iter()
+                                    .map(|param| (param.name, param.value))
+                                    .unzip();
+                                let values =
+                                    self.format_inputs(&tokens, self.abi_names.event_inputs(event));
                                 *log = RawOrDecodedLog::Decoded(
                                     event.name.clone(),
-                                    decoded
-                                        .params
-                                        .into_iter()
-                                        .map(|param| (param.name, self.apply_label(&param.value)))
-                                        .collect(),
+                                    names.into_iter().zip(values).collect(),
                                 );
                                 break
                             }
@@ -324,16 +395,32 @@ impl CallTraceDecoder {
         }
     }
 
+    /// Pretty prints the inputs of a call or event, structs are printed with the names of their
+    /// fields if they are known, see [AbiNames]
+    fn format_inputs(&self, tokens: &[Token], names: Option<&[ParamNames]>) -> Vec<String> {
+        let names = names.filter(|names| names.len() == tokens.len());
+        tokens
+            .iter()
+            .enumerate()
+            .map(|(i, token)| match names.map(|names| &names[i]) {
+                Some(names) if !names.components.is_empty() => {
+                    self.output_encoding.token_named(token, names)
+                }
+                _ => self.apply_label(token),
+            })
+            .collect()
+    }
+
     fn apply_label(&self, token: &Token) -> String {
         match token {
             Token::Address(addr) => {
                 if let Some(label) = self.labels.get(addr) {
-                    format!("{}: [{:?}]", label, addr)
+                    format!("{}: [{}]", label, self.output_encoding.address(addr))
                 } else {
-                    format_token(token)
+                    self.output_encoding.token(token)
                 }
             }
-            _ => format_token(token),
+            _ => self.output_encoding.token(token),
         }
     }
 
@@ -10,7 +10,15 @@ use futures::{
     stream::{FuturesUnordered, Stream, StreamExt},
     task::{Context, Poll},
 };
-use std::{borrow::Cow, path::PathBuf, pin::Pin};
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    fs, io,
+    path::PathBuf,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
 use tokio::time::{Duration, Interval};
 use tracing::{trace, warn};
 
@@ -18,20 +26,24 @@ use tracing::{trace, warn};
 pub struct EtherscanIdentifier {
     /// The Etherscan client
     client: Option<etherscan::Client>,
+    /// The cache of identified contracts
+    cache: Option<ContractCache>,
 }
 
 impl EtherscanIdentifier {
     /// Creates a new Etherscan identifier.
     ///
-    /// The identifier is a noop if either `chain` or `etherscan_api_key` are `None`.
+    /// The identifier is a noop if either `chain` or `etherscan_api_key` are `None`. Identified
+    /// contracts are cached in `cache_path` for `ttl`, the cache can be shared by concurrent runs.
     pub fn new(
         chain: Option<impl Into<Chain>>,
         etherscan_api_key: Option<String>,
         cache_path: Option<PathBuf>,
         ttl: Duration,
     ) -> Self {
-        if let Some(cache_path) = &cache_path {
-            if let Err(err) = std::fs::create_dir_all(cache_path.join("sources")) {
+        let cache = cache_path.map(|path| ContractCache { dir: path.join("contracts"), ttl });
+        if let Some(cache) = &cache {
+            if let Err(err) = fs::create_dir_all(&cache.dir) {
                 warn!(target: "etherscanidentifier", "could not create etherscan cache dir: {:?}", err);
             }
         }
@@ -39,9 +51,10 @@ impl EtherscanIdentifier {
         Self {
             client: chain.and_then(|chain| {
                 etherscan_api_key.and_then(|key| {
-                    etherscan::Client::new_cached(chain.into(), key, cache_path, ttl).ok()
+                    etherscan::Client::new_cached(chain.into(), key, None, ttl).ok()
                 })
             }),
+            cache,
         }
     }
 }
@@ -51,27 +64,105 @@ impl TraceIdentifier for EtherscanIdentifier {
         &self,
         addresses: Vec<(&Address, Option<&Vec<u8>>)>,
     ) -> Vec<AddressIdentity> {
-        self.client.as_ref().map_or(Default::default(), |client| {
-            let mut fetcher = EtherscanFetcher::new(client.clone(), Duration::from_secs(1), 5);
+        let client = match self.client {
+            Some(ref client) => client,
+            None => return Vec::new(),
+        };
+        let identity = |address, label: String, abi| AddressIdentity {
+            address,
+            label: Some(label.clone()),
+            contract: Some(label),
+            abi: Some(Cow::Owned(abi)),
+        };
 
-            for (addr, _) in addresses {
-                fetcher.push(*addr);
+        let mut identities = Vec::new();
+        let mut fetcher = EtherscanFetcher::new(client.clone(), Duration::from_secs(1), 5);
+        for (addr, _) in addresses {
+            match self.cache.as_ref().and_then(|cache| cache.get(addr)) {
+                Some((label, abi)) => identities.push(identity(*addr, label, abi)),
+                None => fetcher.push(*addr),
             }
+        }
 
-            let fut = fetcher
-                .map(|(address, label, abi)| AddressIdentity {
-                    address,
-                    label: Some(label.clone()),
-                    contract: Some(label),
-                    abi: Some(Cow::Owned(abi)),
-                })
-                .collect();
+        let fetched: Vec<(Address, String, Abi)> =
+            foundry_utils::RuntimeOrHandle::new().block_on(fetcher.collect());
+        for (address, label, abi) in fetched {
+            if let Some(cache) = &self.cache {
+                cache.insert(&address, &label, &abi);
+            }
+            identities.push(identity(address, label, abi));
+        }
+        identities
+    }
+}
+
+/// Distinguishes the temporary files of concurrent writes of the same process
+static TMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A cache of the contracts identified by Etherscan, with a file `<address>.json` per contract.
+///
+/// Files are written to a temporary file in the same directory and then renamed into place, so
+/// runs that share the directory, e.g. CI jobs, never read a partially written file.
+struct ContractCache {
+    /// The directory of the cache files
+    dir: PathBuf,
+    /// How long a cached contract is valid
+    ttl: Duration,
+}
+
+/// A contract in the [ContractCache]
+#[derive(Serialize, Deserialize)]
+struct CachedContract {
+    /// The time the entry expires, in seconds since the Unix epoch
+    expiry: u64,
+    name: String,
+    abi: Abi,
+}
+
+impl ContractCache {
+    fn path(&self, address: &Address) -> PathBuf {
+        self.dir.join(format!("{:?}.json", address))
+    }
+
+    /// Returns the name and ABI of the contract at the address, if it is cached and not expired
+    fn get(&self, address: &Address) -> Option<(String, Abi)> {
+        let content = fs::read_to_string(self.path(address)).ok()?;
+        let cached: CachedContract = serde_json::from_str(&content).ok()?;
+        (cached.expiry > now()).then(|| (cached.name, cached.abi))
+    }
 
-            foundry_utils::RuntimeOrHandle::new().block_on(fut)
+    /// Caches the name and ABI of the contract at the address, failures are only logged
+    fn insert(&self, address: &Address, name: &str, abi: &Abi) {
+        let cached = CachedContract {
+            expiry: now().saturating_add(self.ttl.as_secs()),
+            name: name.to_string(),
+            abi: abi.clone(),
+        };
+        if let Err(err) = self.write(address, &cached) {
+            warn!(target: "etherscanidentifier", "could not write etherscan cache: {:?}", err);
+        }
+    }
+
+    fn write(&self, address: &Address, cached: &CachedContract) -> io::Result<()> {
+        let tmp = self.dir.join(format!(
+            ".{:?}.{}.{}.tmp",
+            address,
+            std::process::id(),
+            TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp, serde_json::to_vec(cached)?)?;
+        fs::rename(&tmp, self.path(address)).map_err(|err| {
+            let _ = fs::remove_file(&tmp);
+            err
         })
     }
 }
 
+/// Returns the current time in seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
 type EtherscanFuture =
     Pin<Box<dyn Future<Output = (Address, Result<ContractMetadata, EtherscanError>)>>>;
 
@@ -173,3 +264,26 @@ impl Stream for EtherscanFetcher {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_cache_contracts() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ContractCache { dir: dir.path().to_path_buf(), ttl: Duration::from_secs(60) };
+        let address = Address::from_low_u64_be(1);
+        let abi: Abi = serde_json::from_str("[]").unwrap();
+        assert!(cache.get(&address).is_none());
+
+        cache.insert(&address, "Token", &abi);
+        assert_eq!(cache.get(&address), Some(("Token".to_string(), abi.clone())));
+        // only the cache file is left, no temporary files
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let expired = ContractCache { dir: dir.path().to_path_buf(), ttl: Duration::ZERO };
+        expired.insert(&address, "Token", &abi);
+        assert!(expired.get(&address).is_none());
+    }
+}
@@ -0,0 +1,37 @@
+use super::{AddressIdentity, TraceIdentifier};
+use ethers::abi::{Abi, Address};
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// A trace identifier for contracts whose addresses are known up front, e.g. contracts that are
+/// placed into the state before the tests run.
+#[derive(Debug, Clone, Default)]
+pub struct KnownAddressIdentifier {
+    contracts: BTreeMap<Address, (String, Option<Abi>)>,
+}
+
+impl KnownAddressIdentifier {
+    /// Creates an identifier for the given contract names and ABIs, by address
+    pub fn new(contracts: BTreeMap<Address, (String, Option<Abi>)>) -> Self {
+        Self { contracts }
+    }
+}
+
+impl TraceIdentifier for KnownAddressIdentifier {
+    fn identify_addresses(
+        &self,
+        addresses: Vec<(&Address, Option<&Vec<u8>>)>,
+    ) -> Vec<AddressIdentity> {
+        addresses
+            .into_iter()
+            .filter_map(|(address, _)| {
+                let (name, abi) = self.contracts.get(address)?;
+                Some(AddressIdentity {
+                    address: *address,
+                    contract: Some(name.clone()),
+                    label: Some(name.clone()),
+                    abi: abi.as_ref().map(Cow::Borrowed),
+                })
+            })
+            .collect()
+    }
+}
@@ -4,6 +4,12 @@ pub use local::LocalTraceIdentifier;
 mod etherscan;
 pub use etherscan::EtherscanIdentifier;
 
+mod signatures;
+pub use signatures::SignaturesIdentifier;
+
+mod known;
+pub use known::KnownAddressIdentifier;
+
 use ethers::abi::{Abi, Address};
 use std::borrow::Cow;
 
@@ -0,0 +1,118 @@
+use ethers::abi::{AbiParser, Function};
+use foundry_utils::RuntimeOrHandle;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+};
+use tracing::warn;
+
+/// Resolves function selectors of unknown contracts to their signatures using the
+/// [4byte.directory](https://www.4byte.directory).
+///
+/// Resolved selectors, including selectors the directory has no signature for, are cached in
+/// memory and, if a cache path is set, persisted to disk when the identifier is dropped. Failed
+/// lookups are not cached, they are retried by the next run.
+pub struct SignaturesIdentifier {
+    /// Cached signatures by hex encoded selector, `None` if the selector is unknown
+    cached: BTreeMap<String, Option<String>>,
+    /// Selectors whose lookup failed in this run, they are not looked up again until the next run
+    failed: BTreeSet<String>,
+    /// Whether signatures were added to the cache since it was loaded
+    dirty: bool,
+    /// Where the cache is persisted
+    cache_path: Option<PathBuf>,
+    /// Whether only the cache is consulted
+    offline: bool,
+    /// The runtime the lookups are run on, created by the first lookup
+    runtime: Option<RuntimeOrHandle>,
+}
+
+impl SignaturesIdentifier {
+    /// Creates a new identifier, loading previously cached signatures from `cache_path`.
+    ///
+    /// If `offline` is set, only cached signatures are used.
+    pub fn new(cache_path: Option<PathBuf>, offline: bool) -> Self {
+        let cached = cache_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { cached, failed: BTreeSet::new(), dirty: false, cache_path, offline, runtime: None }
+    }
+
+    /// Returns the function with the given selector, if its signature can be resolved.
+    ///
+    /// If the 4byte directory knows multiple signatures for the selector, the earliest submitted
+    /// one is used. The returned function has no outputs.
+    pub fn identify_function(&mut self, selector: &[u8]) -> Option<Function> {
+        let selector = hex::encode(selector.get(..4)?);
+        if !self.cached.contains_key(&selector) {
+            if self.offline || self.failed.contains(&selector) {
+                return None
+            }
+            let lookup = self
+                .runtime
+                .get_or_insert_with(RuntimeOrHandle::new)
+                .block_on(foundry_utils::fourbyte(&selector));
+            match lookup {
+                Ok(signatures) => {
+                    let signature = signatures
+                        .into_iter()
+                        .min_by_key(|(_, id)| *id)
+                        .map(|(signature, _)| signature);
+                    self.cached.insert(selector.clone(), signature);
+                    self.dirty = true;
+                }
+                Err(err) => {
+                    warn!(target: "signaturesidentifier", ?selector, "lookup failed: {:?}", err);
+                    self.failed.insert(selector);
+                    return None
+                }
+            }
+        }
+
+        let signature = self.cached.get(&selector)?.as_ref()?;
+        AbiParser::default().parse_function(signature).ok()
+    }
+}
+
+impl Drop for SignaturesIdentifier {
+    fn drop(&mut self) {
+        if !self.dirty {
+            return
+        }
+        if let Some(path) = &self.cache_path {
+            if let Some(parent) = path.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            let content = serde_json::to_string(&self.cached).expect("cache is serializable");
+            if let Err(err) = fs::write(path, content) {
+                warn!(target: "signaturesidentifier", "could not write cache: {:?}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_writes_changed_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signatures.json");
+        fs::write(&path, r#"{"a9059cbb":"transfer(address,uint256)","deadbeef":null}"#).unwrap();
+
+        let mut identifier = SignaturesIdentifier::new(Some(path.clone()), true);
+        let transfer = identifier.identify_function(&[0xa9, 0x05, 0x9c, 0xbb]).unwrap();
+        assert_eq!(transfer.signature(), "transfer(address,uint256)");
+        assert!(identifier.identify_function(&[0xde, 0xad, 0xbe, 0xef]).is_none());
+        assert!(identifier.identify_function(&[0x12, 0x34, 0x56, 0x78]).is_none());
+
+        // nothing was looked up, so the cache is left untouched
+        fs::remove_file(&path).unwrap();
+        drop(identifier);
+        assert!(!path.exists());
+    }
+}
@@ -10,8 +10,10 @@ use crate::{abi::CHEATCODE_ADDRESS, CallKind};
 use ansi_term::Colour;
 use ethers::{
     abi::{Address, RawLog},
-    types::U256,
+    types::{H256, U256},
+    utils::get_create2_address_from_hash,
 };
+use foundry_utils::OutputEncoding;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashSet,
@@ -62,6 +64,11 @@ impl CallTraceArena {
         }
     }
 
+    /// Returns a value that displays the trace with its addresses and bytes in the encoding
+    pub fn display(&self, encoding: OutputEncoding) -> Encoded<'_, Self> {
+        Encoded(self, encoding)
+    }
+
     pub fn addresses(&self) -> HashSet<(&Address, Option<&Vec<u8>>)> {
         self.arena
             .iter()
@@ -76,6 +83,33 @@ impl CallTraceArena {
             })
             .collect()
     }
+
+    /// Returns the names of the calls leading from the entry call to the call a revert originated
+    /// in, see [CallTrace::call_name()].
+    ///
+    /// This is empty if the entry call did not revert.
+    pub fn revert_path(&self) -> Vec<String> {
+        let mut path = Vec::new();
+        let mut node = &self.arena[0];
+        if node.trace.success {
+            return path
+        }
+        loop {
+            path.push(node.trace.call_name());
+            // the revert bubbled up from the last reverting subcall, if any
+            match node
+                .children
+                .iter()
+                .rev()
+                .map(|idx| &self.arena[*idx])
+                .find(|child| !child.trace.success)
+            {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+        path
+    }
 }
 
 const PIPE: &str = "  │ ";
@@ -84,10 +118,21 @@ const BRANCH: &str = "  ├─ ";
 const CALL: &str = "→ ";
 const RETURN: &str = "← ";
 
+/// Displays a trace, or a part of it, with addresses and bytes in an [OutputEncoding], see
+/// [CallTraceArena::display()]
+pub struct Encoded<'a, T>(&'a T, OutputEncoding);
+
 impl fmt::Display for CallTraceArena {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Encoded(self, OutputEncoding::default()))
+    }
+}
+
+impl fmt::Display for Encoded<'_, CallTraceArena> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fn inner(
             arena: &CallTraceArena,
+            encoding: OutputEncoding,
             writer: &mut (impl Write + ?Sized),
             idx: usize,
             left: &str,
@@ -96,7 +141,7 @@ impl fmt::Display for CallTraceArena {
             let node = &arena.arena[idx];
 
             // Display trace header
-            writeln!(writer, "{}{}", left, node.trace)?;
+            writeln!(writer, "{}{}", left, Encoded(&node.trace, encoding))?;
 
             // Display logs and subcalls
             let left_prefix = format!("{}{}", child, BRANCH);
@@ -105,7 +150,7 @@ impl fmt::Display for CallTraceArena {
                 match child {
                     LogCallOrder::Log(index) => {
                         let mut log = String::new();
-                        write!(log, "{}", node.logs[*index])?;
+                        write!(log, "{}", Encoded(&node.logs[*index], encoding))?;
 
                         // Prepend our tree structure symbols to each line of the displayed log
                         log.lines().enumerate().try_for_each(|(i, line)| {
@@ -118,7 +163,14 @@ impl fmt::Display for CallTraceArena {
                         })?;
                     }
                     LogCallOrder::Call(index) => {
-                        inner(arena, writer, node.children[*index], &left_prefix, &right_prefix)?;
+                        inner(
+                            arena,
+                            encoding,
+                            writer,
+                            node.children[*index],
+                            &left_prefix,
+                            &right_prefix,
+                        )?;
                     }
                 }
             }
@@ -134,13 +186,13 @@ impl fmt::Display for CallTraceArena {
                     unreachable!("We should never have decoded calldata for contract creations");
                 }
             } else {
-                writeln!(writer, "{}", node.trace.output)?;
+                writeln!(writer, "{}", Encoded(&node.trace.output, encoding))?;
             }
 
             Ok(())
         }
 
-        inner(self, f, 0, "  ", "  ")
+        inner(self.0, self.1, f, 0, "  ", "  ")
     }
 }
 
@@ -158,7 +210,13 @@ pub enum RawOrDecodedLog {
 
 impl fmt::Display for RawOrDecodedLog {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
+        write!(f, "{}", Encoded(self, OutputEncoding::default()))
+    }
+}
+
+impl fmt::Display for Encoded<'_, RawOrDecodedLog> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
             RawOrDecodedLog::Raw(log) => {
                 for (i, topic) in log.topics.iter().enumerate() {
                     writeln!(
@@ -169,11 +227,7 @@ impl fmt::Display for RawOrDecodedLog {
                     )?;
                 }
 
-                write!(
-                    f,
-                    "          data: {}",
-                    Colour::Cyan.paint(format!("0x{}", hex::encode(&log.data)))
-                )
+                write!(f, "          data: {}", Colour::Cyan.paint(self.1.bytes(&log.data)))
             }
             RawOrDecodedLog::Decoded(name, params) => {
                 let params = params
@@ -252,12 +306,18 @@ impl Default for RawOrDecodedReturnData {
 
 impl fmt::Display for RawOrDecodedReturnData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self {
+        write!(f, "{}", Encoded(self, OutputEncoding::default()))
+    }
+}
+
+impl fmt::Display for Encoded<'_, RawOrDecodedReturnData> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
             RawOrDecodedReturnData::Raw(bytes) => {
                 if bytes.is_empty() {
                     write!(f, "()")
                 } else {
-                    write!(f, "0x{}", hex::encode(&bytes))
+                    write!(f, "{}", self.1.bytes(bytes))
                 }
             }
             RawOrDecodedReturnData::Decoded(decoded) => write!(f, "{}", decoded.clone()),
@@ -295,6 +355,30 @@ pub struct CallTrace {
     pub output: RawOrDecodedReturnData,
     /// The gas cost of the call
     pub gas_cost: u64,
+    /// The parameters of the contract creation, if it was created with `CREATE2`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub create2: Option<Create2>,
+    /// The beneficiary of the `SELFDESTRUCT` the call executed, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selfdestruct: Option<Address>,
+}
+
+/// The parameters of a `CREATE2` contract creation, which determine the address of the contract
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Create2 {
+    /// The address that executed `CREATE2`
+    pub deployer: Address,
+    /// The salt
+    pub salt: H256,
+    /// The keccak256 hash of the init code
+    pub init_code_hash: H256,
+}
+
+impl Create2 {
+    /// Returns the address the parameters result in
+    pub fn address(&self) -> Address {
+        get_create2_address_from_hash(self.deployer, self.salt, self.init_code_hash)
+    }
 }
 
 impl CallTrace {
@@ -308,6 +392,8 @@ impl CallTrace {
         self.output = new_trace.output;
         self.address = new_trace.address;
         self.gas_cost = new_trace.gas_cost;
+        self.create2 = new_trace.create2;
+        self.selfdestruct = new_trace.selfdestruct;
     }
 
     /// Whether this is a contract creation or not
@@ -316,20 +402,67 @@ impl CallTrace {
     }
 }
 
+impl CallTrace {
+    /// Returns a short name for the call, like `Token.transfer`
+    pub fn call_name(&self) -> String {
+        let contract = self.label.clone().unwrap_or_else(|| format!("{:?}", self.address));
+        if self.created() {
+            return format!("new {}", contract)
+        }
+        let func = match &self.data {
+            RawOrDecodedCall::Raw(bytes) if bytes.len() >= 4 => {
+                format!("0x{}", hex::encode(&bytes[0..4]))
+            }
+            RawOrDecodedCall::Raw(_) => "fallback".to_string(),
+            RawOrDecodedCall::Decoded(func, _) => func.clone(),
+        };
+        format!("{}.{}", contract, func)
+    }
+}
+
 impl fmt::Display for CallTrace {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.created() {
+        write!(f, "{}", Encoded(self, OutputEncoding::default()))
+    }
+}
+
+impl fmt::Display for Encoded<'_, CallTrace> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Encoded(trace, encoding) = *self;
+        if trace.created() {
             write!(
                 f,
-                "[{}] {}{} {}@{:?}",
-                self.gas_cost,
+                "[{}] {}{} {}@{}",
+                trace.gas_cost,
                 Colour::Yellow.paint(CALL),
                 Colour::Yellow.paint("new"),
-                self.label.as_ref().unwrap_or(&"<Unknown>".to_string()),
-                self.address
+                trace.label.as_ref().unwrap_or(&"<Unknown>".to_string()),
+                encoding.address(&trace.address)
             )?;
+            if let Some(create2) = trace.create2 {
+                write!(
+                    f,
+                    " {}",
+                    Colour::Yellow.paint(format!(
+                        "[create2 salt: {:?}, init code hash: {:?}]",
+                        create2.salt, create2.init_code_hash
+                    ))
+                )?;
+                // the address differs if e.g. the deployer was pranked
+                let expected = create2.address();
+                if trace.success && expected != trace.address {
+                    write!(
+                        f,
+                        " {}",
+                        Colour::Red.paint(format!(
+                            "(expected address {:?} for deployer {:?})",
+                            expected, create2.deployer
+                        ))
+                    )?;
+                }
+            }
         } else {
-            let (func, inputs) = match &self.data {
+            let (func, inputs) = match &trace.data {
                 RawOrDecodedCall::Raw(bytes) => {
                     // We assume that the fallback function (`data.len() < 4`) counts as decoded
                     // calldata
@@ -339,7 +472,7 @@ impl fmt::Display for CallTrace {
                 RawOrDecodedCall::Decoded(func, inputs) => (func.clone(), inputs.join(", ")),
             };
 
-            let action = match self.kind {
+            let action = match trace.kind {
                 // do not show anything for CALLs
                 CallKind::Call => "",
                 CallKind::StaticCall => "[staticcall]",
@@ -348,15 +481,21 @@ impl fmt::Display for CallTrace {
                 _ => unreachable!(),
             };
 
-            let color = trace_color(self);
+            // unlabelled addresses are abbreviated unless they are checksummed
+            let address = if encoding.checksum_addresses {
+                encoding.address(&trace.address)
+            } else {
+                trace.address.to_string()
+            };
+            let color = trace_color(trace);
             write!(
                 f,
                 "[{}] {}::{}{}({}) {}",
-                self.gas_cost,
-                color.paint(self.label.as_ref().unwrap_or(&self.address.to_string())),
+                trace.gas_cost,
+                color.paint(trace.label.as_ref().unwrap_or(&address)),
                 color.paint(func),
-                if !self.value.is_zero() {
-                    format!("{{value: {}}}", self.value)
+                if !trace.value.is_zero() {
+                    format!("{{value: {}}}", trace.value)
                 } else {
                     "".to_string()
                 },
@@ -387,3 +526,167 @@ fn trace_color(trace: &CallTrace) -> Colour {
         Colour::Red
     }
 }
+
+/// Hex values with more digits than an address are elided in lines that are too long
+const MAX_HEX_DIGITS: usize = 40;
+
+/// The number of leading and trailing digits that are kept of elided hex values
+const ELIDED_HEX_DIGITS: usize = 8;
+
+/// Fits every line of a displayed trace into `width` characters, see [fit_line()]
+pub fn fit_lines(text: &str, width: usize) -> String {
+    text.lines().map(|line| fit_line(line, width) + "\n").collect()
+}
+
+/// Fits a line of a displayed trace into `width` characters.
+///
+/// Long hex values, like calldata, are elided to their first and last digits first. If the line
+/// is still too long, it is truncated. Both are marked with `…`, color codes do not count towards
+/// the width.
+pub fn fit_line(line: &str, width: usize) -> String {
+    if visible_width(line) <= width {
+        return line.to_string()
+    }
+    let line = elide_hex(line);
+    if visible_width(&line) <= width {
+        return line
+    }
+    truncate(&line, width)
+}
+
+/// Returns the number of characters of the line without ANSI color codes
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut in_escape = false;
+    for c in line.chars() {
+        if in_escape {
+            in_escape = c != 'm';
+        } else if c == '\x1b' {
+            in_escape = true;
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// Elides the middle of hex values with more than [MAX_HEX_DIGITS] digits
+fn elide_hex(line: &str) -> String {
+    let mut elided = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("0x") {
+        let (before, hex) = rest.split_at(start + 2);
+        elided.push_str(before);
+        let digits = hex.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(hex.len());
+        if digits > MAX_HEX_DIGITS {
+            elided.push_str(&hex[..ELIDED_HEX_DIGITS]);
+            elided.push('…');
+            elided.push_str(&hex[digits - ELIDED_HEX_DIGITS..digits]);
+        } else {
+            elided.push_str(&hex[..digits]);
+        }
+        rest = &hex[digits..];
+    }
+    elided.push_str(rest);
+    elided
+}
+
+/// Truncates the line to `width` visible characters, including the trailing `…`
+fn truncate(line: &str, width: usize) -> String {
+    let mut truncated = String::with_capacity(line.len());
+    let mut visible = 0;
+    let mut in_escape = false;
+    let mut colored = false;
+    for c in line.chars() {
+        if in_escape {
+            in_escape = c != 'm';
+        } else if c == '\x1b' {
+            in_escape = true;
+            colored = true;
+        } else if visible + 1 >= width {
+            break
+        } else {
+            visible += 1;
+        }
+        truncated.push(c);
+    }
+    truncated.push('…');
+    if colored {
+        // reset the color in case the line was cut in a colored part
+        truncated.push_str("\x1b[0m");
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(depth: usize, success: bool, contract: &str, func: &str) -> CallTrace {
+        CallTrace {
+            depth,
+            success,
+            label: Some(contract.to_string()),
+            data: RawOrDecodedCall::Decoded(func.to_string(), vec![]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn finds_revert_path() {
+        let mut arena = CallTraceArena::default();
+        arena.push_trace(0, call(0, false, "VaultTest", "testWithdraw"));
+        arena.push_trace(0, call(1, true, "Token", "balanceOf"));
+        arena.push_trace(0, call(1, false, "Vault", "withdraw"));
+        arena.push_trace(0, call(2, false, "Token", "transfer"));
+        assert_eq!(
+            arena.revert_path(),
+            vec!["VaultTest.testWithdraw", "Vault.withdraw", "Token.transfer"]
+        );
+
+        let mut arena = CallTraceArena::default();
+        arena.push_trace(0, call(0, true, "VaultTest", "testWithdraw"));
+        assert!(arena.revert_path().is_empty());
+    }
+
+    #[test]
+    fn verifies_create2_addresses() {
+        // example 1 of EIP-1014
+        let create2 = Create2 {
+            deployer: Address::zero(),
+            salt: H256::zero(),
+            init_code_hash: H256(ethers::utils::keccak256([0u8])),
+        };
+        let address = "0x4D1A2e2bB4F88F0250f26Ffff098B0b30B26BF38".parse().unwrap();
+        assert_eq!(create2.address(), address);
+
+        let mut trace = CallTrace {
+            success: true,
+            kind: CallKind::Create,
+            address,
+            create2: Some(create2),
+            data: RawOrDecodedCall::Raw(vec![0]),
+            ..Default::default()
+        };
+        assert!(trace.to_string().contains("create2 salt"));
+        assert!(!trace.to_string().contains("expected address"));
+
+        trace.address = Address::random();
+        assert!(trace.to_string().contains("expected address"));
+    }
+
+    #[test]
+    fn fits_lines_into_width() {
+        let calldata = format!("0x{}", "ab".repeat(36));
+        let line = format!("  ├─ [2000] Vault::fallback({})", calldata);
+        assert_eq!(fit_line(&line, 200), line);
+        assert_eq!(fit_line(&line, 60), "  ├─ [2000] Vault::fallback(0xabababab…abababab)");
+
+        let line = format!("  ├─ [2000] {}::deposit()", Colour::Green.paint("Vault"));
+        let fitted = fit_line(&line, 16);
+        assert_eq!(visible_width(&fitted), 16);
+        assert!(fitted.ends_with("…\x1b[0m"));
+
+        assert_eq!(fit_lines("short\nlonger line\n", 8), "short\nlonger …\n");
+    }
+}
@@ -0,0 +1,96 @@
+//! Counting the assertions executed by tests.
+//!
+//! Assertions like DSTest's `assertEq` are internal functions that only emit events if they fail,
+//! so they are counted from the source maps of the test contracts instead: every call to a
+//! function whose name starts with `assert` compiles to a jump into the function, the
+//! [AssertionCounter](foundry_evm::executor::inspector::AssertionCounter) counts how often these
+//! jumps are executed.
+//!
+//! Calls inside the assertion library itself, e.g. `assertEq` with a message calling `assertEq`,
+//! are not counted. Counting requires the sources of the test contracts to be compiled in the
+//! same run.
+use ethers::solc::sourcemap::{Jump, SourceMap};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Returns the program counters of the jumps into assertion functions in the runtime code, given
+/// its source map and the source code by source id
+pub fn assertion_jumps(
+    code: &[u8],
+    source_map: &SourceMap,
+    sources: &BTreeMap<u32, String>,
+) -> BTreeSet<usize> {
+    instruction_pcs(code)
+        .into_iter()
+        .zip(source_map)
+        .filter(|(pc, element)| {
+            if code[*pc] != JUMP || !matches!(element.jump, Jump::In) {
+                return false
+            }
+            let source = match element.index.and_then(|index| sources.get(&index)) {
+                Some(source) if !is_assertion_library(source) => source,
+                _ => return false,
+            };
+            source
+                .get(element.offset..element.offset + element.length)
+                .map(|call| call.trim_start().starts_with("assert"))
+                .unwrap_or_default()
+        })
+        .map(|(pc, _)| pc)
+        .collect()
+}
+
+const JUMP: u8 = 0x56;
+const PUSH1: u8 = 0x60;
+const PUSH32: u8 = 0x7f;
+
+/// Returns the program counter of every instruction of the code, in order, skipping the data of
+/// `PUSH` instructions
+pub(crate) fn instruction_pcs(code: &[u8]) -> Vec<usize> {
+    let mut pcs = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        pcs.push(pc);
+        let op = code[pc];
+        if (PUSH1..=PUSH32).contains(&op) {
+            pc += (op - PUSH1 + 1) as usize;
+        }
+        pc += 1;
+    }
+    pcs
+}
+
+/// Returns `true` if the source declares DSTest, whose assertions call each other
+fn is_assertion_library(source: &str) -> bool {
+    source.lines().any(|line| {
+        let mut tokens = line.split_whitespace();
+        tokens.next() == Some("contract") && tokens.next() == Some("DSTest")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::solc::sourcemap::parse;
+
+    #[test]
+    fn can_find_assertion_jumps() {
+        let source = "contract T is DSTest {\n    function testA() public { check(); assertEq(a, \
+                      b); }\n}";
+        let check = source.find("check").unwrap();
+        let assert_eq = source.find("assertEq").unwrap();
+        // PUSH1 0x10, JUMP (check), PUSH2 0x0020, JUMP (assertEq), JUMP (out of assertEq)
+        let code = [0x60, 0x10, 0x56, 0x61, 0x00, 0x20, 0x56, 0x56];
+        let source_map = parse(&format!(
+            "{}:7:0:-;{}:7:0:i;{}:14:0:-;{}:14:0:i;0:1:0:o",
+            check, check, assert_eq, assert_eq
+        ))
+        .unwrap();
+
+        let sources = BTreeMap::from([(0, source.to_string())]);
+        assert_eq!(assertion_jumps(&code, &source_map, &sources), BTreeSet::from([6]));
+
+        let library = "contract DSTest {\n}".to_string() + source;
+        let sources = BTreeMap::from([(0, library)]);
+        assert!(assertion_jumps(&code, &source_map, &sources).is_empty());
+    }
+}
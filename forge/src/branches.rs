@@ -0,0 +1,272 @@
+//! Branch coverage of fuzz tests.
+//!
+//! The conditional jumps (`JUMPI`) in the runtime code of the compiled contracts are mapped to
+//! their location in the source code with the source maps. While a fuzz test runs, the
+//! [BranchRecorder](foundry_evm::executor::inspector::BranchRecorder) records the directions
+//! every jump took in any of the fuzz cases. Each direction of a jump is a branch.
+//!
+//! Only jumps in the functions and modifiers of the project sources are counted, jumps in code
+//! generated by the compiler, e.g. for ABI decoding or checked arithmetic, are left out. Contracts
+//! are matched to the addresses they run at by their runtime code, the test contract itself is not
+//! counted.
+//!
+//! Only the functions under test are counted, which are the functions that ran in any of the
+//! fuzz cases, i.e. that reached one of their jumps. The other functions of the contracts the test
+//! called do not lower the coverage.
+use crate::assertions::instruction_pcs;
+use ethers::{solc::sourcemap::SourceMap, types::Address};
+use foundry_evm::executor::inspector::BranchHits;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+const JUMPI: u8 = 0x57;
+
+/// A conditional jump in the runtime code of a contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    /// The program counter of the `JUMPI` instruction
+    pub pc: usize,
+    /// The location of the jump in the source code, `<path>:<line>`
+    pub location: String,
+    /// The source range of the function or modifier the jump is in, `(offset, length)`
+    pub function: (usize, usize),
+}
+
+/// The runtime code of a contract and its conditional jumps, see [contract_branches]
+#[derive(Debug, Clone, Default)]
+pub struct ContractBranches {
+    /// The name of the contract
+    pub name: String,
+    pub code: Vec<u8>,
+    /// The byte ranges of the values of immutable variables in the code, `(start, length)`
+    pub immutables: Vec<(usize, usize)>,
+    pub branches: Vec<Branch>,
+}
+
+/// Returns the conditional jumps of the runtime code that belong to the project sources, given
+/// its source map and the path and source code by source id
+pub fn contract_branches(
+    code: &[u8],
+    source_map: &SourceMap,
+    sources: &BTreeMap<u32, (PathBuf, String)>,
+) -> Vec<Branch> {
+    // the entry and exit of functions and modifiers are mapped to their whole definition
+    let definitions = source_map
+        .iter()
+        .filter_map(|element| {
+            let index = element.index?;
+            let (_, source) = sources.get(&index)?;
+            let text = source.get(element.offset..element.offset + element.length)?;
+            let is_definition = text.starts_with("function") || text.starts_with("modifier");
+            is_definition.then(|| (index, element.offset, element.length))
+        })
+        .collect::<BTreeSet<_>>();
+
+    instruction_pcs(code)
+        .into_iter()
+        .zip(source_map)
+        .filter(|(pc, _)| code[*pc] == JUMPI)
+        .filter_map(|(pc, element)| {
+            let index = element.index?;
+            let (path, source) = sources.get(&index)?;
+            // the innermost definition the jump is in
+            let function = definitions
+                .iter()
+                .filter(|(source, offset, length)| {
+                    *source == index &&
+                        *offset <= element.offset &&
+                        element.offset + element.length <= offset + length
+                })
+                .min_by_key(|(_, _, length)| *length)
+                .map(|(_, offset, length)| (*offset, *length))?;
+            let line = source.get(..element.offset)?.matches('\n').count() + 1;
+            Some(Branch { pc, location: location(path, line), function })
+        })
+        .collect()
+}
+
+fn location(path: &Path, line: usize) -> String {
+    format!("{}:{}", path.display(), line)
+}
+
+/// The branch coverage of a fuzz test
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BranchCoverage {
+    /// The number of branches taken in any of the fuzz cases
+    pub hit: usize,
+    /// The number of branches, two for every conditional jump
+    pub total: usize,
+    /// The conditional jumps with a branch that was never taken, e.g.
+    /// `Vault at src/Vault.sol:42: jump never taken`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uncovered: Vec<String>,
+}
+
+impl BranchCoverage {
+    /// Computes the coverage of the branches of the functions that ran during the fuzz test.
+    ///
+    /// `code_at` returns the runtime code at an address, `test_address` is the address of the
+    /// test contract.
+    pub fn new<K: Ord>(
+        hits: &BranchHits,
+        code_at: impl Fn(&Address) -> Option<Vec<u8>>,
+        contracts: &BTreeMap<K, ContractBranches>,
+        test_address: Address,
+    ) -> Self {
+        // contracts deployed more than once are covered by the branches taken in any instance
+        let mut by_contract: BTreeMap<&K, BTreeMap<usize, [bool; 2]>> = BTreeMap::new();
+        for (address, branches) in hits.iter().filter(|(address, _)| **address != test_address) {
+            let code = match code_at(address) {
+                Some(code) => code,
+                None => continue,
+            };
+            let id = match contracts
+                .iter()
+                .find(|(_, known)| same_code(&known.code, &known.immutables, &code))
+            {
+                Some((id, _)) => id,
+                None => continue,
+            };
+            let taken = by_contract.entry(id).or_default();
+            for (pc, directions) in branches {
+                let merged = taken.entry(*pc).or_default();
+                merged[0] |= directions[0];
+                merged[1] |= directions[1];
+            }
+        }
+
+        let mut coverage = Self::default();
+        for (id, taken) in by_contract {
+            let contract = &contracts[id];
+            let tested = contract
+                .branches
+                .iter()
+                .filter(|branch| taken.contains_key(&branch.pc))
+                .map(|branch| branch.function)
+                .collect::<BTreeSet<_>>();
+            let branches =
+                contract.branches.iter().filter(|branch| tested.contains(&branch.function));
+            for branch in branches {
+                let directions = taken.get(&branch.pc).copied().unwrap_or_default();
+                coverage.total += 2;
+                coverage.hit += directions.iter().filter(|taken| **taken).count();
+                let never = match directions {
+                    [false, false] => "never reached",
+                    [false, true] => "jump never taken",
+                    [true, false] => "jump always taken",
+                    [true, true] => continue,
+                };
+                coverage
+                    .uncovered
+                    .push(format!("{} at {}: {}", contract.name, branch.location, never));
+            }
+        }
+        coverage
+    }
+
+    /// Returns the percentage of branches taken, 100 if there are no branches
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            return 100.0
+        }
+        self.hit as f64 / self.total as f64 * 100.0
+    }
+}
+
+/// Returns `true` if the deployed code is the known runtime code, except for the values of
+/// immutable variables at the byte ranges `immutables`, which are only set on deployment, and zero
+/// padding added by the EVM
+pub(crate) fn same_code(known: &[u8], immutables: &[(usize, usize)], deployed: &[u8]) -> bool {
+    if known.is_empty() || known.len() > deployed.len() {
+        return false
+    }
+    let (deployed, padding) = deployed.split_at(known.len());
+    if padding.iter().any(|byte| *byte != 0) {
+        return false
+    }
+    let is_immutable = |index: usize| {
+        immutables.iter().any(|(start, length)| (*start..start + length).contains(&index))
+    };
+    known.iter().zip(deployed).enumerate().all(|(index, (a, b))| a == b || is_immutable(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::solc::sourcemap::parse;
+
+    #[test]
+    fn can_compute_branch_coverage() {
+        let source = "contract Vault {\n    function f(uint x) external {\n        if (x > 1) \
+                      {}\n    }\n    function g(uint x) external {\n        if (x > 2) {}\n    \
+                      }\n}";
+        let definition = |name: &str| {
+            let offset = source.find(name).unwrap();
+            (offset, source[offset..].find("\n    }").unwrap() + 6)
+        };
+        let (f, g) = (definition("function f"), definition("function g"));
+        let f_condition = source.find("x > 1").unwrap();
+        let g_condition = source.find("x > 2").unwrap();
+        // f: JUMPDEST, PUSH1 0x09, JUMPI, PUSH1 0x09, JUMPI (generated)
+        // g: JUMPDEST, PUSH1 0x09, JUMPI
+        let code = vec![0x5b, 0x60, 0x09, 0x57, 0x60, 0x09, 0x57, 0x5b, 0x60, 0x09, 0x57];
+        let source_map = parse(&format!(
+            "{}:{}:0:-;{}:5:0:-;{}:5:0:-;0:1:1:-;0:1:1:-;{}:{}:0:-;{}:5:0:-;{}:5:0:-",
+            f.0, f.1, f_condition, f_condition, g.0, g.1, g_condition, g_condition
+        ))
+        .unwrap();
+        let sources = BTreeMap::from([(0, (PathBuf::from("src/Vault.sol"), source.to_string()))]);
+        let branches = contract_branches(&code, &source_map, &sources);
+        assert_eq!(
+            branches,
+            vec![
+                Branch { pc: 3, location: "src/Vault.sol:3".to_string(), function: f },
+                Branch { pc: 10, location: "src/Vault.sol:6".to_string(), function: g },
+            ]
+        );
+
+        let vault_branches = ContractBranches {
+            name: "Vault".to_string(),
+            code: code.clone(),
+            immutables: vec![],
+            branches,
+        };
+        let contracts = BTreeMap::from([("src/Vault.sol:Vault", vault_branches)]);
+        let vault = Address::from_low_u64_be(1);
+        let test = Address::from_low_u64_be(2);
+        // g never ran, so its branches are not counted
+        let hits = BranchHits::from([
+            (vault, BTreeMap::from([(3, [false, true])])),
+            (test, BTreeMap::from([(10, [true, true])])),
+        ]);
+        let code_at = |_: &Address| Some(code.clone());
+        let coverage = BranchCoverage::new(&hits, code_at, &contracts, test);
+        assert_eq!(coverage.hit, 1);
+        assert_eq!(coverage.total, 2);
+        assert_eq!(coverage.percentage(), 50.0);
+        assert_eq!(coverage.uncovered, vec!["Vault at src/Vault.sol:3: jump never taken"]);
+    }
+
+    #[test]
+    fn can_match_deployed_code() {
+        // PUSH32 <immutable>, STOP
+        let mut known = vec![0x7f];
+        known.extend([0; 32]);
+        known.push(0x00);
+        let immutables = [(1, 32)];
+
+        let mut deployed = known.clone();
+        deployed[1..33].copy_from_slice(&[0xff; 32]);
+        assert!(same_code(&known, &immutables, &deployed));
+        deployed.extend([0; 33]);
+        assert!(same_code(&known, &immutables, &deployed));
+        assert!(!same_code(&known, &[], &deployed));
+
+        // a single differing instruction is another contract
+        deployed[33] = 0xfe;
+        assert!(!same_code(&known, &immutables, &deployed));
+    }
+}
@@ -0,0 +1,66 @@
+//! Callbacks of test contracts.
+//!
+//! Test contracts may implement functions that are called back during a test, e.g. by a contract
+//! under test or a cheatcode, whose names happen to start with a test prefix. Marking such a
+//! function with the `@custom:callback` NatSpec tag excludes it from the tests:
+//!
+//! ```solidity
+//! contract VaultTest is DSTest {
+//!     /// @custom:callback
+//!     function testHook(uint256 amount) external { .. }
+//! }
+//! ```
+//!
+//! Callbacks are never run or counted as tests, regardless of the filter.
+use crate::natspec::function_docs;
+use std::collections::BTreeSet;
+
+/// The NatSpec tag that marks a function as a callback
+pub const CALLBACK_TAG: &str = "@custom:callback";
+
+/// Returns the names of the functions of the contract `name` in the given source code whose doc
+/// comment has the [CALLBACK_TAG]
+pub fn parse_callbacks(source: &str, name: &str) -> BTreeSet<String> {
+    function_docs(source, name)
+        .into_iter()
+        .filter(|(_, doc)| {
+            doc.iter().any(|line| line.split_whitespace().any(|token| token == CALLBACK_TAG))
+        })
+        .map(|(function, _)| function.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_callbacks() {
+        let source = r#"
+contract Helper {
+    /// @custom:callback
+    function testHelper() external {}
+}
+
+contract VaultTest is DSTest {
+    /// @notice Called back by the vault
+    /// @custom:callback
+    function testHook(uint256 amount) external {}
+
+    /**
+     * @custom:callback
+     */
+    function onFlashLoan() external {}
+
+    /// @custom:callbacks
+    function testDeposit() external {}
+}
+"#;
+        assert_eq!(
+            parse_callbacks(source, "VaultTest"),
+            BTreeSet::from(["onFlashLoan".to_string(), "testHook".to_string()])
+        );
+        assert_eq!(parse_callbacks(source, "Helper"), BTreeSet::from(["testHelper".to_string()]));
+        assert!(parse_callbacks(source, "Missing").is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+use crate::trace::{CallTraceArena, RawOrDecodedCall};
+use ethers::{abi::Abi, types::Address};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+/// An edge of the [CallGraph]: a test called a function of a contract
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CallGraphEdge {
+    /// The test, as `<test contract>.<signature>`
+    pub test: String,
+    /// The name of the called contract
+    pub contract: String,
+    /// The signature of the called function
+    pub function: String,
+    /// How often the test called the function
+    pub calls: usize,
+}
+
+/// Tracks which public and external functions of contracts each test called, aggregated across
+/// all tests
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    /// `contract name -> selector -> signature`
+    functions: BTreeMap<String, BTreeMap<[u8; 4], String>>,
+    /// `(test, contract, signature) -> calls`
+    edges: BTreeMap<(String, String, String), usize>,
+}
+
+impl CallGraph {
+    /// Creates a call graph of the calls to the functions of the given contracts
+    pub fn new<'a>(contracts: impl IntoIterator<Item = (&'a str, &'a Abi)>) -> Self {
+        let functions = contracts
+            .into_iter()
+            .map(|(name, abi)| {
+                let functions = abi
+                    .functions()
+                    .map(|func| (func.short_signature(), func.signature()))
+                    .collect();
+                (name.to_string(), functions)
+            })
+            .collect();
+        Self { functions, edges: BTreeMap::new() }
+    }
+
+    /// Adds the calls in the trace of the test to the graph.
+    ///
+    /// `contracts` maps the addresses in the trace to contract names, see
+    /// [CallTraceDecoder::contracts](crate::trace::CallTraceDecoder). This must be called before
+    /// the trace is decoded, since the selectors are read from the raw calldata.
+    pub fn analyze(
+        &mut self,
+        test: &str,
+        trace: &CallTraceArena,
+        contracts: &BTreeMap<Address, String>,
+    ) {
+        for node in &trace.arena {
+            if node.trace.created() {
+                continue
+            }
+            let data = match node.trace.data {
+                RawOrDecodedCall::Raw(ref data) if data.len() >= 4 => data,
+                _ => continue,
+            };
+            let (contract, functions) = match contracts
+                .get(&node.trace.address)
+                .and_then(|name| Some((name, self.functions.get(name)?)))
+            {
+                Some(called) => called,
+                None => continue,
+            };
+            if let Some(signature) = functions.get(&[data[0], data[1], data[2], data[3]]) {
+                *self
+                    .edges
+                    .entry((test.to_string(), contract.clone(), signature.clone()))
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    /// Returns the edges of the graph, ordered by test
+    pub fn edges(&self) -> Vec<CallGraphEdge> {
+        self.edges
+            .iter()
+            .map(|((test, contract, function), calls)| CallGraphEdge {
+                test: test.clone(),
+                contract: contract.clone(),
+                function: function.clone(),
+                calls: *calls,
+            })
+            .collect()
+    }
+
+    /// Returns the graph in the DOT format, the edges are labeled with the number of calls
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph callgraph {\n    rankdir=LR;\n");
+        for edge in self.edges() {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}.{}\" [label=\"{}\"];",
+                escape(&edge.test),
+                escape(&edge.contract),
+                escape(&edge.function),
+                edge.calls
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Returns the graph as JSON, `{"edges": [...]}` with the [CallGraphEdge]s
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&serde_json::json!({ "edges": self.edges() }))
+    }
+
+    /// Writes the graph to the file, in the DOT format if its extension is `.dot` and as JSON
+    /// otherwise
+    pub fn write(&self, path: &Path) -> eyre::Result<()> {
+        let content = if path.extension().map_or(false, |ext| ext == "dot") {
+            self.to_dot()
+        } else {
+            self.to_json()?
+        };
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+/// Escapes the quotes of a DOT identifier
+fn escape(id: &str) -> String {
+    id.replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::CallTrace;
+    use ethers::abi::{Function, StateMutability};
+
+    #[allow(deprecated)]
+    fn function(name: &str) -> Function {
+        Function {
+            name: name.to_string(),
+            inputs: vec![],
+            outputs: vec![],
+            constant: None,
+            state_mutability: StateMutability::NonPayable,
+        }
+    }
+
+    #[test]
+    fn aggregates_calls_of_tests() {
+        let mut abi = Abi::default();
+        for name in ["deposit", "withdraw"] {
+            abi.functions.insert(name.to_string(), vec![function(name)]);
+        }
+        let mut graph = CallGraph::new([("Vault", &abi)]);
+
+        let vault = Address::random();
+        let mut arena = CallTraceArena::default();
+        arena.push_trace(0, CallTrace { address: Address::random(), ..Default::default() });
+        for _ in 0..2 {
+            arena.push_trace(
+                0,
+                CallTrace {
+                    depth: 1,
+                    address: vault,
+                    data: RawOrDecodedCall::Raw(function("deposit").short_signature().to_vec()),
+                    ..Default::default()
+                },
+            );
+        }
+        let contracts = BTreeMap::from([(vault, "Vault".to_string())]);
+        graph.analyze("VaultTest.testDeposit()", &arena, &contracts);
+        graph.analyze("VaultTest.testNothing()", &CallTraceArena::default(), &contracts);
+
+        assert_eq!(
+            graph.edges(),
+            vec![CallGraphEdge {
+                test: "VaultTest.testDeposit()".to_string(),
+                contract: "Vault".to_string(),
+                function: "deposit()".to_string(),
+                calls: 2,
+            }]
+        );
+        assert_eq!(
+            graph.to_dot(),
+            "digraph callgraph {\n    rankdir=LR;\n    \"VaultTest.testDeposit()\" -> \
+             \"Vault.deposit()\" [label=\"2\"];\n}\n"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use crate::{
+    assertions::instruction_pcs,
+    branches::same_code,
+    executor::inspector::InstructionHits,
+    trace::{CallTraceArena, RawOrDecodedCall},
+};
+use ethers::{abi::Abi, solc::sourcemap::SourceMap, types::Address};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Tracks which public and external functions of contracts were called in traces
+#[derive(Debug, Clone, Default)]
+pub struct FunctionCoverage {
+    /// `contract name -> selector -> (signature, called)`
+    functions: BTreeMap<String, BTreeMap<[u8; 4], (String, bool)>>,
+}
+
+impl FunctionCoverage {
+    /// Creates a function coverage for all functions of the given contracts
+    pub fn new<'a>(contracts: impl IntoIterator<Item = (&'a str, &'a Abi)>) -> Self {
+        let functions = contracts
+            .into_iter()
+            .map(|(name, abi)| {
+                let functions = abi
+                    .functions()
+                    .map(|func| (func.short_signature(), (func.signature(), false)))
+                    .collect();
+                (name.to_string(), functions)
+            })
+            .collect();
+        Self { functions }
+    }
+
+    /// Marks the functions called in the trace as covered.
+    ///
+    /// `contracts` maps the addresses in the trace to contract names, see
+    /// [CallTraceDecoder::contracts](crate::trace::CallTraceDecoder). This must be called before
+    /// the trace is decoded, since the selectors are read from the raw calldata.
+    pub fn analyze(&mut self, trace: &CallTraceArena, contracts: &BTreeMap<Address, String>) {
+        for node in &trace.arena {
+            if node.trace.created() {
+                continue
+            }
+            let data = match node.trace.data {
+                RawOrDecodedCall::Raw(ref data) if data.len() >= 4 => data,
+                _ => continue,
+            };
+            let functions = match contracts
+                .get(&node.trace.address)
+                .and_then(|name| self.functions.get_mut(name))
+            {
+                Some(functions) => functions,
+                None => continue,
+            };
+            let selector = [data[0], data[1], data[2], data[3]];
+            if let Some((_, called)) = functions.get_mut(&selector) {
+                *called = true;
+            }
+        }
+    }
+
+    /// Returns the number of functions
+    pub fn total(&self) -> usize {
+        self.functions.values().map(|functions| functions.len()).sum()
+    }
+
+    /// Returns the number of functions that were called
+    pub fn covered(&self) -> usize {
+        self.functions.values().flat_map(|functions| functions.values()).filter(|f| f.1).count()
+    }
+
+    /// Returns the percentage of functions that were called, this is 100 if there are none
+    pub fn percentage(&self) -> f64 {
+        match self.total() {
+            0 => 100.0,
+            total => self.covered() as f64 * 100.0 / total as f64,
+        }
+    }
+
+    /// Returns `true` if the contract has a function with the given name or signature
+    pub fn has_function(&self, contract: &str, function: &str) -> bool {
+        self.matching(contract, function).next().is_some()
+    }
+
+    /// Returns `true` if a function of the contract with the given name or signature was called
+    pub fn is_called(&self, contract: &str, function: &str) -> bool {
+        self.matching(contract, function).any(|(_, called)| *called)
+    }
+
+    /// Returns the functions of the contract with the given name or signature
+    fn matching<'a>(
+        &'a self,
+        contract: &str,
+        function: &'a str,
+    ) -> impl Iterator<Item = &'a (String, bool)> + 'a {
+        self.functions.get(contract).into_iter().flat_map(|functions| functions.values()).filter(
+            move |(signature, _)| {
+                signature == function || signature.split('(').next() == Some(function)
+            },
+        )
+    }
+
+    /// Returns the functions that were not called, as `<contract>.<signature>`
+    pub fn uncovered(&self) -> Vec<String> {
+        self.functions
+            .iter()
+            .flat_map(|(name, functions)| {
+                functions
+                    .values()
+                    .filter(|(_, called)| !called)
+                    .map(move |(signature, _)| format!("{}.{}", name, signature))
+            })
+            .collect()
+    }
+}
+
+/// Hit counts of the lines of source files, `path -> line -> hits`
+pub type LineHits = BTreeMap<PathBuf, BTreeMap<usize, u64>>;
+
+/// The runtime code of a contract and the source lines of its instructions, see
+/// [contract_lines]
+#[derive(Debug, Clone, Default)]
+pub struct ContractLines {
+    pub code: Vec<u8>,
+    /// The byte ranges of the values of immutable variables in the code, `(start, length)`
+    pub immutables: Vec<(usize, usize)>,
+    /// The path and line of every instruction in the source code, by program counter
+    pub lines: BTreeMap<usize, (PathBuf, usize)>,
+}
+
+/// Returns the source lines of the instructions of the runtime code that belong to the project
+/// sources, given its source map and the path and source code by source id
+pub fn contract_lines(
+    code: &[u8],
+    source_map: &SourceMap,
+    sources: &BTreeMap<u32, (PathBuf, String)>,
+) -> BTreeMap<usize, (PathBuf, usize)> {
+    // the offsets of the line breaks of every source, computed once per source
+    let mut line_breaks: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    instruction_pcs(code)
+        .into_iter()
+        .zip(source_map)
+        .filter_map(|(pc, element)| {
+            let index = element.index?;
+            let (path, source) = sources.get(&index)?;
+            let breaks = line_breaks
+                .entry(index)
+                .or_insert_with(|| source.match_indices('\n').map(|(offset, _)| offset).collect());
+            let line = breaks.partition_point(|offset| *offset < element.offset) + 1;
+            Some((pc, (path.clone(), line)))
+        })
+        .collect()
+}
+
+/// Returns the hit counts of the source lines of the contracts that ran, given how often their
+/// instructions were executed. `code_at` returns the runtime code at an address.
+///
+/// A line was executed as often as its most executed instruction in a contract, the executions in
+/// different contracts add up. Contracts that are not known, like the test contract, are left
+/// out.
+pub fn line_hits<K: Ord>(
+    hits: &InstructionHits,
+    code_at: impl Fn(&Address) -> Option<Vec<u8>>,
+    contracts: &BTreeMap<K, ContractLines>,
+) -> LineHits {
+    let mut line_hits = LineHits::new();
+    for (address, instructions) in hits {
+        let code = match code_at(address) {
+            Some(code) => code,
+            None => continue,
+        };
+        let contract =
+            match contracts.values().find(|known| same_code(&known.code, &known.immutables, &code))
+            {
+                Some(contract) => contract,
+                None => continue,
+            };
+        let mut lines: BTreeMap<(&Path, usize), u64> = BTreeMap::new();
+        for (pc, count) in instructions {
+            if let Some((path, line)) = contract.lines.get(pc) {
+                let hits = lines.entry((path.as_path(), *line)).or_default();
+                *hits = (*hits).max(*count);
+            }
+        }
+        for ((path, line), count) in lines {
+            *line_hits.entry(path.to_path_buf()).or_default().entry(line).or_default() += count;
+        }
+    }
+    line_hits
+}
+
+/// Tracks how often the lines of the source code of contracts were executed over all tests
+#[derive(Debug, Clone, Default)]
+pub struct LineCoverage {
+    lines: LineHits,
+}
+
+impl LineCoverage {
+    /// Creates a line coverage for all lines with instructions of the given contracts, none of
+    /// which were executed yet
+    pub fn new<'a>(contracts: impl IntoIterator<Item = &'a ContractLines>) -> Self {
+        let mut lines = LineHits::new();
+        for (path, line) in contracts.into_iter().flat_map(|contract| contract.lines.values()) {
+            lines.entry(path.clone()).or_default().entry(*line).or_default();
+        }
+        Self { lines }
+    }
+
+    /// Adds the hit counts of the lines a test executed, see [line_hits]. Lines of source files
+    /// the coverage was not created for are ignored.
+    pub fn record(&mut self, hits: &LineHits) {
+        for (path, lines) in hits {
+            let known = match self.lines.get_mut(path) {
+                Some(known) => known,
+                None => continue,
+            };
+            for (line, count) in lines {
+                *known.entry(*line).or_default() += count;
+            }
+        }
+    }
+
+    /// Returns the number of lines
+    pub fn total(&self) -> usize {
+        self.lines.values().map(|lines| lines.len()).sum()
+    }
+
+    /// Returns the number of lines that were executed
+    pub fn covered(&self) -> usize {
+        self.lines.values().flat_map(|lines| lines.values()).filter(|hits| **hits > 0).count()
+    }
+
+    /// Returns the percentage of lines that were executed, this is 100 if there are none
+    pub fn percentage(&self) -> f64 {
+        match self.total() {
+            0 => 100.0,
+            total => self.covered() as f64 * 100.0 / total as f64,
+        }
+    }
+
+    /// Returns the coverage in the lcov tracefile format, one record per source file
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        for (path, lines) in &self.lines {
+            let _ = writeln!(lcov, "TN:\nSF:{}", path.display());
+            for (line, hits) in lines {
+                let _ = writeln!(lcov, "DA:{},{}", line, hits);
+            }
+            let hit = lines.values().filter(|hits| **hits > 0).count();
+            let _ = writeln!(lcov, "LF:{}\nLH:{}\nend_of_record", lines.len(), hit);
+        }
+        lcov
+    }
+}
+
+/// The format of the line coverage report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageReportFormat {
+    /// lcov tracefiles, e.g. for Codecov, Coveralls and editors
+    Lcov,
+}
+
+impl Default for CoverageReportFormat {
+    fn default() -> Self {
+        CoverageReportFormat::Lcov
+    }
+}
+
+impl FromStr for CoverageReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lcov" => Ok(CoverageReportFormat::Lcov),
+            _ => Err(format!("Unknown coverage report format `{}`, expected lcov", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::CallTrace;
+    use ethers::{
+        abi::{Function, StateMutability},
+        solc::sourcemap::parse,
+    };
+
+    #[allow(deprecated)]
+    fn function(name: &str) -> Function {
+        Function {
+            name: name.to_string(),
+            inputs: vec![],
+            outputs: vec![],
+            constant: None,
+            state_mutability: StateMutability::NonPayable,
+        }
+    }
+
+    #[test]
+    fn tracks_called_functions() {
+        let mut abi = Abi::default();
+        for name in ["deposit", "withdraw"] {
+            abi.functions.insert(name.to_string(), vec![function(name)]);
+        }
+        let mut coverage = FunctionCoverage::new([("Vault", &abi)]);
+        assert_eq!(coverage.total(), 2);
+        assert_eq!(coverage.covered(), 0);
+
+        let vault = Address::random();
+        let mut arena = CallTraceArena::default();
+        arena.push_trace(
+            0,
+            CallTrace {
+                address: vault,
+                data: RawOrDecodedCall::Raw(function("deposit").short_signature().to_vec()),
+                ..Default::default()
+            },
+        );
+        coverage.analyze(&arena, &BTreeMap::from([(vault, "Vault".to_string())]));
+
+        assert_eq!(coverage.covered(), 1);
+        assert_eq!(coverage.percentage(), 50.0);
+        assert_eq!(coverage.uncovered(), vec!["Vault.withdraw()"]);
+
+        assert!(coverage.is_called("Vault", "deposit"));
+        assert!(coverage.is_called("Vault", "deposit()"));
+        assert!(!coverage.is_called("Vault", "withdraw"));
+        assert!(coverage.has_function("Vault", "withdraw"));
+        assert!(!coverage.has_function("Vault", "withdraw(uint256)"));
+        assert!(!coverage.has_function("Bank", "deposit"));
+    }
+
+    #[test]
+    fn can_write_lcov() {
+        let source = "contract Vault {\n    function f() external {\n        x = 1;\n    }\n}";
+        let assignment = source.find("x = 1").unwrap();
+        // PUSH1 0x01, PUSH1 0x00, SSTORE, STOP
+        let code = vec![0x60, 0x01, 0x60, 0x00, 0x55, 0x00];
+        let source_map =
+            parse(&format!("0:10:0:-;{0}:5:0:-;{0}:5:0:-;0:1:1:-", assignment)).unwrap();
+        let sources = BTreeMap::from([(0, (PathBuf::from("src/Vault.sol"), source.to_string()))]);
+        let lines = contract_lines(&code, &source_map, &sources);
+        let path = PathBuf::from("src/Vault.sol");
+        assert_eq!(
+            lines,
+            BTreeMap::from([
+                (0, (path.clone(), 1)),
+                (2, (path.clone(), 3)),
+                (4, (path.clone(), 3)),
+            ])
+        );
+
+        let contracts = BTreeMap::from([(
+            "src/Vault.sol:Vault",
+            ContractLines { code, immutables: vec![], lines },
+        )]);
+        let mut coverage = LineCoverage::new(contracts.values());
+        assert_eq!(coverage.total(), 2);
+        assert_eq!(coverage.covered(), 0);
+
+        let vault = Address::from_low_u64_be(1);
+        let hits = InstructionHits::from([(vault, BTreeMap::from([(2, 1), (4, 2)]))]);
+        let code_at = |_: &Address| Some(contracts.values().next().unwrap().code.clone());
+        coverage.record(&line_hits(&hits, code_at, &contracts));
+        coverage.record(&line_hits(&hits, code_at, &contracts));
+        assert_eq!(coverage.covered(), 1);
+        assert_eq!(coverage.percentage(), 50.0);
+        assert_eq!(
+            coverage.to_lcov(),
+            "TN:\nSF:src/Vault.sol\nDA:1,0\nDA:3,4\nLF:2\nLH:1\nend_of_record\n"
+        );
+    }
+}
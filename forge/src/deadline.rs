@@ -0,0 +1,96 @@
+//! Fuzz budgets of test runs with a deadline.
+//!
+//! Instead of stopping the run once the deadline passed, the runs of every fuzz test are reduced
+//! so that the fuzz tests that did not start yet can finish in the time left. The time left is
+//! shared evenly between these fuzz tests, as many of which run in parallel as there are threads,
+//! and converted to runs with the mean duration of a run of the fuzz tests that finished so far.
+//!
+//! Fuzz tests make their configured runs until the first fuzz test finished, and at least
+//! [MIN_RUNS] once the deadline passed.
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// The fewest runs a fuzz test makes, even after the deadline passed
+pub const MIN_RUNS: u32 = 1;
+
+/// The time left for the fuzz tests of a run, shared by all test contracts
+#[derive(Debug)]
+pub struct FuzzBudget {
+    deadline: Instant,
+    /// The number of fuzz tests that did not start yet
+    pending: AtomicUsize,
+    /// The number of runs the finished fuzz tests made
+    runs: AtomicU64,
+    /// The total duration of the finished fuzz tests, in microseconds
+    micros: AtomicU64,
+    /// How many fuzz tests run in parallel
+    threads: usize,
+}
+
+impl FuzzBudget {
+    /// Creates a budget for `fuzz_tests` fuzz tests that must finish within `deadline` from now,
+    /// running on the threads of the current rayon pool
+    pub fn new(deadline: Duration, fuzz_tests: usize) -> Self {
+        Self::with_threads(deadline, fuzz_tests, rayon::current_num_threads())
+    }
+
+    /// Creates a budget for `fuzz_tests` fuzz tests that must finish within `deadline` from now,
+    /// `threads` of which run in parallel
+    pub fn with_threads(deadline: Duration, fuzz_tests: usize, threads: usize) -> Self {
+        Self {
+            deadline: Instant::now() + deadline,
+            pending: AtomicUsize::new(fuzz_tests),
+            runs: AtomicU64::new(0),
+            micros: AtomicU64::new(0),
+            threads: threads.max(1),
+        }
+    }
+
+    /// Returns the number of runs the next fuzz test may make, at most `configured`, and counts
+    /// the test as started
+    pub fn runs(&self, configured: u32) -> u32 {
+        let pending = self
+            .pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                Some(pending.saturating_sub(1))
+            })
+            .unwrap_or_default()
+            .max(1);
+        let runs = self.runs.load(Ordering::SeqCst);
+        if runs == 0 {
+            return configured
+        }
+        let per_run = self.micros.load(Ordering::SeqCst).max(1) as f64 / runs as f64;
+        let left = self.deadline.saturating_duration_since(Instant::now()).as_micros() as f64;
+        let share = left * self.threads.min(pending) as f64 / pending as f64;
+        ((share / per_run).floor().min(configured as f64) as u32).max(MIN_RUNS.min(configured))
+    }
+
+    /// Records the runs a finished fuzz test made and how long it took
+    pub fn record(&self, runs: usize, duration: Duration) {
+        self.runs.fetch_add(runs as u64, Ordering::SeqCst);
+        self.micros.fetch_add(duration.as_micros() as u64, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_reduce_runs() {
+        let budget = FuzzBudget::with_threads(Duration::from_secs(3600), 3, 1);
+        // nothing is known about the duration of a run before the first fuzz test finished
+        assert_eq!(budget.runs(256), 256);
+        budget.record(256, Duration::from_millis(256));
+        assert_eq!(budget.runs(256), 256);
+
+        let budget = FuzzBudget::with_threads(Duration::ZERO, 3, 4);
+        assert_eq!(budget.runs(256), 256);
+        budget.record(256, Duration::from_millis(256));
+        assert_eq!(budget.runs(256), MIN_RUNS);
+        assert_eq!(budget.runs(0), 0);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{SuiteResult, TestResult};
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt};
+
+/// Identifies a test by its suite (`<path>:<contract>`) and its signature
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct TestId {
+    pub suite: String,
+    pub test: String,
+}
+
+impl fmt::Display for TestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}::{}", self.suite, self.test)
+    }
+}
+
+/// The gas usage of a test in two runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GasChange {
+    pub id: TestId,
+    pub old: u64,
+    pub new: u64,
+}
+
+impl GasChange {
+    /// The difference in gas usage between the two runs
+    pub fn delta(&self) -> i128 {
+        self.new as i128 - self.old as i128
+    }
+}
+
+/// The differences between the results of two test runs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SuiteDiff {
+    /// Tests that passed in the old run and fail in the new run
+    pub newly_failing: Vec<TestId>,
+    /// Tests that failed in the old run and pass in the new run
+    pub newly_passing: Vec<TestId>,
+    /// Tests that only exist in the new run
+    pub added: Vec<TestId>,
+    /// Tests that only exist in the old run
+    pub removed: Vec<TestId>,
+    /// Tests whose gas usage changed
+    pub gas_changes: Vec<GasChange>,
+}
+
+impl SuiteDiff {
+    /// Returns `true` if the runs did not differ
+    pub fn is_empty(&self) -> bool {
+        self.newly_failing.is_empty() &&
+            self.newly_passing.is_empty() &&
+            self.added.is_empty() &&
+            self.removed.is_empty() &&
+            self.gas_changes.is_empty()
+    }
+}
+
+/// Compares the results of an old and a new test run
+pub fn diff_suites(
+    old: &BTreeMap<String, SuiteResult>,
+    new: &BTreeMap<String, SuiteResult>,
+) -> SuiteDiff {
+    let old = flatten(old);
+    let new = flatten(new);
+    let mut diff = SuiteDiff::default();
+
+    for (id, new_result) in &new {
+        let old_result = match old.get(id) {
+            Some(old_result) => old_result,
+            None => {
+                diff.added.push(id.clone());
+                continue
+            }
+        };
+
+        match (old_result.success, new_result.success) {
+            (true, false) => diff.newly_failing.push(id.clone()),
+            (false, true) => diff.newly_passing.push(id.clone()),
+            _ => {}
+        }
+
+        let old_gas = old_result.kind.gas_used().gas();
+        let new_gas = new_result.kind.gas_used().gas();
+        if old_gas != new_gas {
+            diff.gas_changes.push(GasChange { id: id.clone(), old: old_gas, new: new_gas });
+        }
+    }
+    diff.removed = old.keys().filter(|id| !new.contains_key(id)).cloned().collect();
+
+    diff
+}
+
+/// Returns all test results by their id
+fn flatten(results: &BTreeMap<String, SuiteResult>) -> BTreeMap<TestId, &TestResult> {
+    results
+        .iter()
+        .flat_map(|(suite, result)| {
+            result.test_results.iter().map(move |(test, result)| {
+                (TestId { suite: suite.clone(), test: test.clone() }, result)
+            })
+        })
+        .collect()
+}
+
+impl fmt::Display for SuiteDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences")
+        }
+
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+        table.set_header(vec![
+            Cell::new("Change").add_attribute(Attribute::Bold),
+            Cell::new("Test").add_attribute(Attribute::Bold),
+            Cell::new("Old gas").add_attribute(Attribute::Bold),
+            Cell::new("New gas").add_attribute(Attribute::Bold),
+            Cell::new("Δ").add_attribute(Attribute::Bold),
+        ]);
+
+        let mut add_rows = |ids: &[TestId], change: &str, color: Color| {
+            for id in ids {
+                table.add_row(vec![
+                    Cell::new(change).fg(color),
+                    Cell::new(id.to_string()),
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(""),
+                ]);
+            }
+        };
+        add_rows(&self.newly_failing, "newly failing", Color::Red);
+        add_rows(&self.newly_passing, "newly passing", Color::Green);
+        add_rows(&self.added, "added", Color::Cyan);
+        add_rows(&self.removed, "removed", Color::Yellow);
+
+        for change in &self.gas_changes {
+            let delta = change.delta();
+            let color = if delta > 0 { Color::Red } else { Color::Green };
+            table.add_row(vec![
+                Cell::new("gas"),
+                Cell::new(change.id.to_string()),
+                Cell::new(change.old.to_string()),
+                Cell::new(change.new.to_string()),
+                Cell::new(format!("{:+}", delta)).fg(color),
+            ]);
+        }
+
+        writeln!(f, "{}", table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestKind;
+    use std::time::Duration;
+
+    fn result(success: bool, gas: u64) -> TestResult {
+        TestResult { success, kind: TestKind::Standard(gas), ..Default::default() }
+    }
+
+    fn suites(tests: Vec<(&str, TestResult)>) -> BTreeMap<String, SuiteResult> {
+        let tests = tests.into_iter().map(|(name, result)| (name.to_string(), result)).collect();
+        BTreeMap::from([("A.t.sol:ATest".to_string(), SuiteResult::new(Duration::ZERO, tests))])
+    }
+
+    #[test]
+    fn can_diff_suites() {
+        let old = suites(vec![
+            ("testFails()", result(true, 100)),
+            ("testPasses()", result(false, 100)),
+            ("testGas()", result(true, 100)),
+            ("testRemoved()", result(true, 100)),
+        ]);
+        let new = suites(vec![
+            ("testFails()", result(false, 100)),
+            ("testPasses()", result(true, 100)),
+            ("testGas()", result(true, 150)),
+            ("testAdded()", result(true, 100)),
+        ]);
+
+        let id = |test: &str| TestId { suite: "A.t.sol:ATest".to_string(), test: test.to_string() };
+        let diff = diff_suites(&old, &new);
+        assert_eq!(diff.newly_failing, vec![id("testFails()")]);
+        assert_eq!(diff.newly_passing, vec![id("testPasses()")]);
+        assert_eq!(diff.added, vec![id("testAdded()")]);
+        assert_eq!(diff.removed, vec![id("testRemoved()")]);
+        assert_eq!(diff.gas_changes, vec![GasChange { id: id("testGas()"), old: 100, new: 150 }]);
+        assert_eq!(diff.gas_changes[0].delta(), 50);
+
+        assert!(diff_suites(&old, &old).is_empty());
+    }
+}
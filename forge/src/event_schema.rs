@@ -0,0 +1,196 @@
+//! Checking the events emitted by a test run against a schema.
+//!
+//! The schema is a JSON file with the signatures of the events the tests must emit and of the
+//! events they may emit:
+//!
+//! ```json
+//! {
+//!   "required": ["Deposit(address,uint256)", "Withdrawal(address,uint256)"],
+//!   "allowed": ["Approval(address,address,uint256)"]
+//! }
+//! ```
+//!
+//! Every required event must be emitted by at least one test of the run, and every event emitted
+//! by any test must be required or allowed. Events are matched by the signature they were
+//! declared with in the ABIs of the project. Logs whose first topic is not the selector of an
+//! event declared in any ABI are ignored, this includes anonymous events, whose first topic is an
+//! indexed argument if any. DSTest logging events and `console.log` calls are ignored too. The
+//! events of a fuzz test are those of its last run.
+use crate::{decode::decode_console_log, SuiteResult};
+use ethers::types::H256;
+use foundry_utils::OutputEncoding;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::Path,
+};
+
+/// The events a test run must and may emit, see [event_schema](crate::event_schema)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventSchema {
+    /// The signatures of the events at least one test must emit
+    #[serde(default)]
+    pub required: BTreeSet<String>,
+    /// The signatures of the events tests may emit in addition to the required events
+    #[serde(default)]
+    pub allowed: BTreeSet<String>,
+}
+
+impl EventSchema {
+    /// Reads the schema from a JSON file
+    pub fn read(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|err| eyre::eyre!("Failed to read {}: {}", path.display(), err))?;
+        let schema: Self = serde_json::from_str(&content)
+            .map_err(|err| eyre::eyre!("Invalid event schema {}: {}", path.display(), err))?;
+        // signatures may be written with spaces, e.g. `Transfer(address, address, uint256)`
+        let normalize = |signatures: BTreeSet<String>| {
+            signatures.into_iter().map(|signature| signature.replace(' ', "")).collect()
+        };
+        Ok(Self { required: normalize(schema.required), allowed: normalize(schema.allowed) })
+    }
+
+    /// Returns the events that were emitted but are neither required nor allowed, followed by
+    /// the required events that were never emitted
+    pub fn check(&self, emitted: &EmittedEvents) -> Vec<EventViolation> {
+        let unexpected = emitted
+            .iter()
+            .filter(|(event, _)| !self.required.contains(*event) && !self.allowed.contains(*event))
+            .map(|(event, tests)| EventViolation::Unexpected {
+                event: event.clone(),
+                tests: tests.iter().cloned().collect(),
+            });
+        let missing = self
+            .required
+            .iter()
+            .filter(|event| !emitted.contains_key(*event))
+            .map(|event| EventViolation::Missing { event: event.clone() });
+        unexpected.chain(missing).collect()
+    }
+}
+
+/// The signatures of the events emitted by the tests of a run, with the tests that emitted them
+/// as `<path>:<contract>::<signature>`
+pub type EmittedEvents = BTreeMap<String, BTreeSet<String>>;
+
+/// Collects the events emitted by the tests, given the event signatures by topic 0, see
+/// [Selectors](crate::Selectors)
+///
+/// Logs whose topic 0 has no known signature, e.g. those of anonymous events, are left out.
+pub fn emitted_events(
+    results: &BTreeMap<String, SuiteResult>,
+    signatures: &BTreeMap<H256, BTreeSet<String>>,
+) -> EmittedEvents {
+    let mut emitted = EmittedEvents::new();
+    for (suite, suite_result) in results {
+        for (test, result) in &suite_result.test_results {
+            for log in &result.logs {
+                let topic = match log.topics.first() {
+                    Some(topic) if decode_console_log(log, OutputEncoding::default()).is_none() => {
+                        topic
+                    }
+                    _ => continue,
+                };
+                let event = match signatures.get(topic).and_then(|known| known.iter().next()) {
+                    Some(signature) => signature.clone(),
+                    None => continue,
+                };
+                emitted.entry(event).or_default().insert(format!("{}::{}", suite, test));
+            }
+        }
+    }
+    emitted
+}
+
+/// An event that does not match the schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventViolation {
+    /// An event that is neither required nor allowed was emitted by these tests
+    Unexpected { event: String, tests: Vec<String> },
+    /// A required event was not emitted by any test
+    Missing { event: String },
+}
+
+impl fmt::Display for EventViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventViolation::Unexpected { event, tests } => {
+                write!(f, "unexpected event {} emitted by {}", event, tests[0])?;
+                match tests.len() {
+                    1 => Ok(()),
+                    2 => write!(f, " and 1 other test"),
+                    n => write!(f, " and {} other tests", n - 1),
+                }
+            }
+            EventViolation::Missing { event } => {
+                write!(f, "required event {} was not emitted by any test", event)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TestKind, TestResult};
+    use ethers::{abi::RawLog, utils::keccak256};
+    use std::time::Duration;
+
+    fn result(events: &[&str]) -> TestResult {
+        TestResult {
+            success: true,
+            logs: events
+                .iter()
+                .map(|event| RawLog { topics: vec![keccak256(event).into()], data: vec![] })
+                .collect(),
+            kind: TestKind::Standard(21_000),
+            duration: Duration::from_millis(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_check_events() {
+        let results = BTreeMap::from([(
+            "src/Vault.t.sol:VaultTest".to_string(),
+            SuiteResult::new(
+                Duration::from_millis(20),
+                BTreeMap::from([
+                    ("testDeposit()".to_string(), result(&["Deposit(address,uint256)"])),
+                    ("testSkim()".to_string(), result(&["Skim(uint256)", "Unknown()"])),
+                ]),
+            ),
+        )]);
+        let signatures = ["Deposit(address,uint256)", "Skim(uint256)"]
+            .iter()
+            .map(|event| (H256::from(keccak256(event)), BTreeSet::from([event.to_string()])))
+            .collect();
+        // the topic of `Unknown()` stands in for an anonymous event
+        let emitted = emitted_events(&results, &signatures);
+        assert_eq!(
+            emitted.keys().cloned().collect::<Vec<_>>(),
+            vec!["Deposit(address,uint256)".to_string(), "Skim(uint256)".to_string()]
+        );
+
+        let schema = EventSchema {
+            required: BTreeSet::from([
+                "Deposit(address,uint256)".to_string(),
+                "Withdrawal(address,uint256)".to_string(),
+            ]),
+            allowed: BTreeSet::new(),
+        };
+        let violations = schema.check(&emitted);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(
+            violations[0].to_string(),
+            "unexpected event Skim(uint256) emitted by src/Vault.t.sol:VaultTest::testSkim()"
+        );
+        assert_eq!(
+            violations[1].to_string(),
+            "required event Withdrawal(address,uint256) was not emitted by any test"
+        );
+    }
+}
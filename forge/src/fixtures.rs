@@ -0,0 +1,140 @@
+use ethers::{
+    abi::Abi,
+    types::{Address, Bytes, U256},
+};
+use eyre::WrapErr;
+use foundry_evm::{
+    executor::{DatabaseRef, Executor},
+    trace::identifier::KnownAddressIdentifier,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// An account that is placed into the state before the test contracts are deployed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Fixture {
+    /// The name of the contract, used to identify the account in traces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The runtime code of the account
+    #[serde(default)]
+    pub code: Bytes,
+    /// The storage of the account, `slot -> value`
+    #[serde(default)]
+    pub storage: BTreeMap<U256, U256>,
+    /// The balance of the account
+    #[serde(default)]
+    pub balance: U256,
+    /// The nonce of the account
+    #[serde(default)]
+    pub nonce: u64,
+    /// The ABI of the contract, used to decode calls to the account in traces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abi: Option<Abi>,
+}
+
+/// Accounts that are placed into the state before the test contracts are deployed, e.g. external
+/// contracts the tests interact with, so the tests can run without a fork.
+///
+/// The fixtures file is a JSON object of [Fixture]s by address:
+///
+/// ```json
+/// {
+///   "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2": {
+///     "name": "WETH9",
+///     "code": "0x6060...",
+///     "storage": { "0x0": "0x1" },
+///     "balance": "0x0",
+///     "nonce": 1,
+///     "abi": [...]
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Fixtures {
+    accounts: BTreeMap<Address, Fixture>,
+}
+
+impl Fixtures {
+    /// Creates fixtures for the given accounts
+    pub fn new(accounts: BTreeMap<Address, Fixture>) -> Self {
+        Self { accounts }
+    }
+
+    /// Reads the fixtures from a JSON file
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let content = fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read fixtures from {}", path.display()))?;
+        serde_json::from_str(&content)
+            .wrap_err_with(|| format!("Invalid fixtures in {}", path.display()))
+    }
+
+    /// Returns the accounts, by address
+    pub fn accounts(&self) -> &BTreeMap<Address, Fixture> {
+        &self.accounts
+    }
+
+    /// Returns `true` if there are no fixtures
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Places the accounts into the state of the executor
+    pub fn apply<DB: DatabaseRef>(&self, executor: &mut Executor<DB>) {
+        for (address, fixture) in &self.accounts {
+            executor.set_balance(*address, fixture.balance);
+            executor.set_nonce(*address, fixture.nonce);
+            if !fixture.code.is_empty() {
+                executor.set_code(*address, fixture.code.0.clone());
+            }
+            for (slot, value) in &fixture.storage {
+                executor.set_storage(*address, *slot, *value);
+            }
+        }
+    }
+
+    /// Returns a trace identifier for the named accounts
+    pub fn identifier(&self) -> KnownAddressIdentifier {
+        KnownAddressIdentifier::new(
+            self.accounts
+                .iter()
+                .filter_map(|(address, fixture)| {
+                    let name = fixture.name.clone()?;
+                    Some((*address, (name, fixture.abi.clone())))
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foundry_evm::trace::identifier::TraceIdentifier;
+
+    #[test]
+    fn can_parse_fixtures() {
+        let fixtures: Fixtures = serde_json::from_str(
+            r#"{
+                "0x0000000000000000000000000000000000001234": {
+                    "name": "Oracle",
+                    "code": "0x6001",
+                    "storage": { "0x0": "0x2a" },
+                    "balance": "0x64"
+                }
+            }"#,
+        )
+        .unwrap();
+        let address = Address::from_low_u64_be(0x1234);
+        let fixture = &fixtures.accounts()[&address];
+        assert_eq!(fixture.code, Bytes::from(vec![0x60, 0x01]));
+        assert_eq!(fixture.storage[&U256::zero()], U256::from(42));
+        assert_eq!(fixture.balance, U256::from(100));
+        assert_eq!(fixture.nonce, 0);
+
+        let identities = fixtures.identifier().identify_addresses(vec![(&address, None)]);
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].label.as_deref(), Some("Oracle"));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::trace::{CallTraceArena, RawOrDecodedCall, TraceKind};
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+/// Aggregates the gas used by the call frames of tests into a flamegraph, in the folded stack
+/// format of `flamegraph.pl` and `inferno`.
+///
+/// Every line of the folded format is a stack of frames separated by `;` followed by the gas the
+/// innermost frame used itself, without the gas of its subcalls. A frame is named
+/// `<contract>::<function>`, by the label or name of the called contract and the decoded function.
+#[derive(Debug, Clone, Default)]
+pub struct Flamegraph {
+    /// `stack -> gas`
+    stacks: BTreeMap<String, u64>,
+}
+
+impl Flamegraph {
+    /// Adds the gas used by the call frames of the execution traces of a test, setup and
+    /// deployment traces are left out.
+    ///
+    /// The traces must be decoded, see
+    /// [CallTraceDecoder::decode](crate::trace::CallTraceDecoder::decode), otherwise frames are
+    /// named by address and selector.
+    pub fn analyze(&mut self, traces: &[(TraceKind, CallTraceArena)]) {
+        for (_, trace) in traces.iter().filter(|(kind, _)| *kind == TraceKind::Execution) {
+            self.analyze_node(0, trace, "");
+        }
+    }
+
+    fn analyze_node(&mut self, node_index: usize, arena: &CallTraceArena, parent: &str) {
+        let node = &arena.arena[node_index];
+        let trace = &node.trace;
+
+        let contract = trace
+            .label
+            .clone()
+            .or_else(|| trace.contract.clone())
+            .unwrap_or_else(|| format!("{:?}", trace.address));
+        let function = match trace.data {
+            _ if trace.created() => "constructor".to_string(),
+            RawOrDecodedCall::Decoded(ref func, _) => func.clone(),
+            RawOrDecodedCall::Raw(ref data) if data.len() >= 4 => {
+                format!("0x{}", hex::encode(&data[..4]))
+            }
+            RawOrDecodedCall::Raw(_) => "fallback".to_string(),
+        };
+        let frame = format!("{}::{}", contract, function).replace(';', ":");
+        let stack = if parent.is_empty() { frame } else { format!("{};{}", parent, frame) };
+
+        let subcalls =
+            node.children.iter().map(|index| arena.arena[*index].trace.gas_cost).sum::<u64>();
+        let gas = trace.gas_cost.saturating_sub(subcalls);
+        if gas > 0 {
+            *self.stacks.entry(stack.clone()).or_default() += gas;
+        }
+
+        for index in &node.children {
+            self.analyze_node(*index, arena, &stack);
+        }
+    }
+
+    /// Returns the flamegraph in the folded stack format
+    pub fn to_folded(&self) -> String {
+        let mut folded = String::new();
+        for (stack, gas) in &self.stacks {
+            let _ = writeln!(folded, "{} {}", stack, gas);
+        }
+        folded
+    }
+
+    /// Writes the flamegraph to the file in the folded stack format
+    pub fn write(&self, path: &Path) -> eyre::Result<()> {
+        fs::write(path, self.to_folded())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::CallTrace;
+    use ethers::types::Address;
+
+    #[test]
+    fn can_fold_gas_by_call_frame() {
+        let call = |depth: usize, contract: &str, func: &str, gas_cost: u64| CallTrace {
+            depth,
+            contract: Some(contract.to_string()),
+            address: Address::random(),
+            data: RawOrDecodedCall::Decoded(func.to_string(), vec![]),
+            gas_cost,
+            ..Default::default()
+        };
+        let mut arena = CallTraceArena::default();
+        arena.push_trace(0, call(0, "VaultTest", "testDeposit", 50_000));
+        arena.push_trace(0, call(1, "Vault", "deposit", 30_000));
+        arena.push_trace(1, call(2, "Token", "transferFrom", 10_000));
+        arena.push_trace(0, call(1, "Vault", "deposit", 5_000));
+
+        let mut flamegraph = Flamegraph::default();
+        flamegraph.analyze(&[(TraceKind::Setup, arena.clone()), (TraceKind::Execution, arena)]);
+        assert_eq!(
+            flamegraph.to_folded(),
+            "VaultTest::testDeposit 15000\n\
+             VaultTest::testDeposit;Vault::deposit 25000\n\
+             VaultTest::testDeposit;Vault::deposit;Token::transferFrom 10000\n"
+        );
+    }
+}
@@ -1,16 +1,147 @@
 use crate::{
     executor::{CHEATCODE_ADDRESS, HARDHAT_CONSOLE_ADDRESS},
     trace::{CallTraceArena, RawOrDecodedCall, TraceKind},
+    TestPrefixes,
 };
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
 use ethers::types::U256;
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fmt::Display};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct GasReport {
     pub report_for: Vec<String>,
     pub contracts: BTreeMap<String, ContractInfo>,
+    /// The function columns to display, all columns are displayed if empty
+    #[serde(skip)]
+    pub columns: Vec<GasColumn>,
+    /// The column to sort functions by in descending order, functions are sorted by name if unset
+    #[serde(skip)]
+    pub sort_by: Option<GasColumn>,
+    /// Whether to display gas in thousands
+    #[serde(skip)]
+    pub in_thousands: bool,
+    /// The format the report is displayed in
+    #[serde(skip)]
+    pub format: GasReportFormat,
+    /// The maximum width of the tables in characters, cells are wrapped to fit if set
+    #[serde(skip)]
+    pub width: Option<u16>,
+    /// The granularity gas is rounded to for display, see [round_gas]
+    #[serde(skip)]
+    pub granularity: Option<u64>,
+    /// The prefixes of test functions, calls to test functions are not reported
+    #[serde(skip)]
+    pub test_prefixes: TestPrefixes,
+}
+
+/// Rounds gas to the nearest multiple of `granularity`, halfway values are rounded up.
+///
+/// Rounding trades precision for stability: gas that changes by a few units, e.g. between
+/// compiler patch versions, is usually rounded to the same value. A granularity of 0 or 1 returns
+/// the gas as is.
+pub fn round_gas(gas: u64, granularity: u64) -> u64 {
+    if granularity <= 1 {
+        return gas
+    }
+    gas.saturating_add(granularity / 2) / granularity * granularity
+}
+
+/// The display format of the gas report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasReportFormat {
+    /// Tables for the terminal
+    Table,
+    /// Markdown tables, e.g. for pull request comments
+    Markdown,
+}
+
+impl Default for GasReportFormat {
+    fn default() -> Self {
+        GasReportFormat::Table
+    }
+}
+
+impl FromStr for GasReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(GasReportFormat::Table),
+            "markdown" | "md" => Ok(GasReportFormat::Markdown),
+            _ => Err(format!("Unknown gas report format `{}`, expected one of table, markdown", s)),
+        }
+    }
+}
+
+/// A function column of the gas report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasColumn {
+    Min,
+    Avg,
+    Median,
+    Max,
+    Calls,
+}
+
+impl GasColumn {
+    pub const ALL: [GasColumn; 5] =
+        [GasColumn::Min, GasColumn::Avg, GasColumn::Median, GasColumn::Max, GasColumn::Calls];
+
+    fn name(&self) -> &'static str {
+        match self {
+            GasColumn::Min => "min",
+            GasColumn::Avg => "avg",
+            GasColumn::Median => "median",
+            GasColumn::Max => "max",
+            GasColumn::Calls => "# calls",
+        }
+    }
+
+    fn header(&self) -> Cell {
+        let cell = Cell::new(self.name()).add_attribute(Attribute::Bold);
+        match self.color() {
+            Some(color) => cell.fg(color),
+            None => cell,
+        }
+    }
+
+    fn color(&self) -> Option<Color> {
+        match self {
+            GasColumn::Min => Some(Color::Green),
+            GasColumn::Avg | GasColumn::Median => Some(Color::Yellow),
+            GasColumn::Max => Some(Color::Red),
+            GasColumn::Calls => None,
+        }
+    }
+
+    fn value(&self, info: &GasInfo) -> U256 {
+        match self {
+            GasColumn::Min => info.min,
+            GasColumn::Avg => info.mean,
+            GasColumn::Median => info.median,
+            GasColumn::Max => info.max,
+            GasColumn::Calls => info.calls.len().into(),
+        }
+    }
+}
+
+impl FromStr for GasColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "min" => Ok(GasColumn::Min),
+            "avg" | "mean" => Ok(GasColumn::Avg),
+            "median" => Ok(GasColumn::Median),
+            "max" => Ok(GasColumn::Max),
+            "calls" => Ok(GasColumn::Calls),
+            _ => Err(format!(
+                "Unknown gas report column `{}`, expected one of min, avg, median, max, calls",
+                s
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -34,6 +165,98 @@ impl GasReport {
         Self { report_for, ..Default::default() }
     }
 
+    /// Only displays the given function columns
+    #[must_use]
+    pub fn with_columns(mut self, columns: Vec<GasColumn>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Sorts functions by the given column in descending order
+    #[must_use]
+    pub fn sort_by(mut self, column: Option<GasColumn>) -> Self {
+        self.sort_by = column;
+        self
+    }
+
+    /// Displays gas in thousands
+    #[must_use]
+    pub fn in_thousands(mut self, in_thousands: bool) -> Self {
+        self.in_thousands = in_thousands;
+        self
+    }
+
+    /// Displays the report in the given format
+    #[must_use]
+    pub fn with_format(mut self, format: GasReportFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Wraps the cells of the terminal tables so they fit into the given width, markdown tables
+    /// are not affected
+    #[must_use]
+    pub fn with_width(mut self, width: Option<u16>) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Rounds the displayed gas to the nearest multiple of the given granularity, the number of
+    /// calls and the deployment size are not rounded
+    #[must_use]
+    pub fn with_granularity(mut self, granularity: Option<u64>) -> Self {
+        self.granularity = granularity;
+        self
+    }
+
+    /// Leaves out calls to functions with the given test prefixes
+    #[must_use]
+    pub fn with_test_prefixes(mut self, test_prefixes: TestPrefixes) -> Self {
+        self.test_prefixes = test_prefixes;
+        self
+    }
+
+    /// Returns the displayed function columns
+    fn columns(&self) -> &[GasColumn] {
+        if self.columns.is_empty() {
+            &GasColumn::ALL[..]
+        } else {
+            &self.columns
+        }
+    }
+
+    /// Returns the functions of the contract in display order
+    fn sorted_functions<'a>(&self, contract: &'a ContractInfo) -> Vec<(&'a String, &'a GasInfo)> {
+        let mut functions = contract.functions.iter().collect::<Vec<_>>();
+        if let Some(sort_by) = self.sort_by {
+            // stable sort, so functions with equal values remain sorted by name
+            functions.sort_by(|(_, a), (_, b)| sort_by.value(b).cmp(&sort_by.value(a)));
+        }
+        functions
+    }
+
+    /// Formats the value of a function column
+    fn format_column(&self, column: GasColumn, function: &GasInfo) -> String {
+        let value = column.value(function);
+        match column {
+            GasColumn::Calls => value.to_string(),
+            _ => self.format_gas(value),
+        }
+    }
+
+    /// Formats a gas value
+    fn format_gas(&self, gas: U256) -> String {
+        let gas = match self.granularity {
+            Some(granularity) => round_gas(gas.low_u64(), granularity).into(),
+            None => gas,
+        };
+        if self.in_thousands {
+            format!("{:.1}k", gas.low_u128() as f64 / 1000.0)
+        } else {
+            gas.to_string()
+        }
+    }
+
     pub fn analyze(&mut self, traces: &[(TraceKind, CallTraceArena)]) {
         let report_for_all = self.report_for.is_empty() || self.report_for.iter().any(|s| s == "*");
         traces.iter().for_each(|(_, trace)| {
@@ -66,7 +289,7 @@ impl GasReport {
                     }
                     // TODO: More robust test contract filtering
                     RawOrDecodedCall::Decoded(func, _)
-                        if !func.starts_with("test") && func != "setUp" =>
+                        if !self.test_prefixes.is_test(func) && func != "setUp" =>
                     {
                         let function_report = contract_report
                             .functions
@@ -110,8 +333,9 @@ impl GasReport {
     }
 }
 
-impl Display for GasReport {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+impl GasReport {
+    /// Writes the report as terminal tables
+    fn fmt_table(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         for (name, contract) in self.contracts.iter() {
             if contract.functions.is_empty() {
                 continue
@@ -119,6 +343,9 @@ impl Display for GasReport {
 
             let mut table = Table::new();
             table.load_preset(UTF8_FULL).apply_modifier(UTF8_ROUND_CORNERS);
+            if let Some(width) = self.width {
+                table.set_content_arrangement(ContentArrangement::Dynamic).set_table_width(width);
+            }
             table.set_header(vec![Cell::new(format!("{} contract", name))
                 .add_attribute(Attribute::Bold)
                 .fg(Color::Green)]);
@@ -126,28 +353,209 @@ impl Display for GasReport {
                 Cell::new("Deployment Cost").add_attribute(Attribute::Bold).fg(Color::Cyan),
                 Cell::new("Deployment Size").add_attribute(Attribute::Bold).fg(Color::Cyan),
             ]);
-            table.add_row(vec![contract.gas.to_string(), contract.size.to_string()]);
+            table.add_row(vec![self.format_gas(contract.gas), contract.size.to_string()]);
 
-            table.add_row(vec![
-                Cell::new("Function Name").add_attribute(Attribute::Bold).fg(Color::Magenta),
-                Cell::new("min").add_attribute(Attribute::Bold).fg(Color::Green),
-                Cell::new("avg").add_attribute(Attribute::Bold).fg(Color::Yellow),
-                Cell::new("median").add_attribute(Attribute::Bold).fg(Color::Yellow),
-                Cell::new("max").add_attribute(Attribute::Bold).fg(Color::Red),
-                Cell::new("# calls").add_attribute(Attribute::Bold),
-            ]);
-            contract.functions.iter().for_each(|(fname, function)| {
-                table.add_row(vec![
-                    Cell::new(fname.to_string()).add_attribute(Attribute::Bold),
-                    Cell::new(function.min.to_string()).fg(Color::Green),
-                    Cell::new(function.mean.to_string()).fg(Color::Yellow),
-                    Cell::new(function.median.to_string()).fg(Color::Yellow),
-                    Cell::new(function.max.to_string()).fg(Color::Red),
-                    Cell::new(function.calls.len().to_string()),
-                ]);
+            let columns = self.columns();
+            let mut header =
+                vec![Cell::new("Function Name").add_attribute(Attribute::Bold).fg(Color::Magenta)];
+            header.extend(columns.iter().map(GasColumn::header));
+            table.add_row(header);
+
+            self.sorted_functions(contract).into_iter().for_each(|(fname, function)| {
+                let mut row = vec![Cell::new(fname.to_string()).add_attribute(Attribute::Bold)];
+                row.extend(columns.iter().map(|column| {
+                    let cell = Cell::new(self.format_column(*column, function));
+                    match column.color() {
+                        Some(color) => cell.fg(color),
+                        None => cell,
+                    }
+                }));
+                table.add_row(row);
             });
             writeln!(f, "{}", table)?
         }
         Ok(())
     }
+
+    /// Writes the report as Markdown, with a heading and two tables per contract
+    fn fmt_markdown(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        for (name, contract) in self.contracts.iter() {
+            if contract.functions.is_empty() {
+                continue
+            }
+
+            writeln!(f, "### {} contract", name)?;
+            writeln!(f)?;
+            let header = vec!["Deployment Cost".to_string(), "Deployment Size".to_string()];
+            let row = vec![self.format_gas(contract.gas), contract.size.to_string()];
+            write_markdown_table(f, header, vec![row], false)?;
+            writeln!(f)?;
+
+            let columns = self.columns();
+            let mut header = vec!["Function Name".to_string()];
+            header.extend(columns.iter().map(|column| column.name().to_string()));
+            let rows = self
+                .sorted_functions(contract)
+                .into_iter()
+                .map(|(fname, function)| {
+                    let mut row = vec![fname.to_string()];
+                    row.extend(columns.iter().map(|column| self.format_column(*column, function)));
+                    row
+                })
+                .collect();
+            write_markdown_table(f, header, rows, true)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a Markdown table whose cells are padded to the width of their column, so it is also
+/// aligned as plain text.
+///
+/// Numbers are right-aligned, if `label_column` is set the first column is left-aligned.
+fn write_markdown_table(
+    f: &mut std::fmt::Formatter,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+    label_column: bool,
+) -> Result<(), std::fmt::Error> {
+    let widths = (0..header.len())
+        .map(|i| {
+            // the separator row needs at least 3 characters
+            rows.iter().map(|row| row[i].len()).chain([header[i].len(), 3]).max().unwrap_or(3)
+        })
+        .collect::<Vec<_>>();
+    let is_label = |i: usize| label_column && i == 0;
+
+    let write_row = |f: &mut std::fmt::Formatter, row: &[String]| {
+        let cells = row
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if is_label(i) {
+                    format!("{:<width$}", cell, width = widths[i])
+                } else {
+                    format!("{:>width$}", cell, width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>();
+        writeln!(f, "| {} |", cells.join(" | "))
+    };
+
+    write_row(f, &header[..])?;
+    let separator = widths
+        .iter()
+        .enumerate()
+        .map(|(i, width)| {
+            if is_label(i) {
+                format!(":{}", "-".repeat(width - 1))
+            } else {
+                format!("{}:", "-".repeat(width - 1))
+            }
+        })
+        .collect::<Vec<_>>();
+    writeln!(f, "| {} |", separator.join(" | "))?;
+    for row in rows {
+        write_row(f, &row[..])?;
+    }
+    Ok(())
+}
+
+impl Display for GasReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self.format {
+            GasReportFormat::Table => self.fmt_table(f),
+            GasReportFormat::Markdown => self.fmt_markdown(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::CallTrace;
+    use ethers::types::Address;
+
+    #[test]
+    fn can_format_markdown() {
+        let mut report = GasReport::new(vec![]).with_format(GasReportFormat::Markdown);
+        let mut contract =
+            ContractInfo { gas: 123456.into(), size: 789.into(), ..Default::default() };
+        contract.functions.insert(
+            "increment".to_string(),
+            GasInfo { calls: vec![22000.into(), 43000.into()], ..Default::default() },
+        );
+        report.contracts.insert("Counter".to_string(), contract);
+        let report = report.with_columns(vec![GasColumn::Min, GasColumn::Calls]).finalize();
+
+        assert_eq!(
+            report.to_string(),
+            "### Counter contract
+
+| Deployment Cost | Deployment Size |
+| --------------: | --------------: |
+|          123456 |             789 |
+
+| Function Name |   min | # calls |
+| :------------ | ----: | ------: |
+| increment     | 22000 |       2 |
+
+"
+        );
+    }
+
+    #[test]
+    fn can_round_gas() {
+        assert_eq!(round_gas(21_049, 100), 21_000);
+        assert_eq!(round_gas(21_050, 100), 21_100);
+        assert_eq!(round_gas(21_049, 0), 21_049);
+        assert_eq!(round_gas(21_049, 1), 21_049);
+        assert_eq!(round_gas(u64::MAX, 100), u64::MAX / 100 * 100);
+
+        let gas = crate::TestKindGas::Fuzz { runs: 256, mean: 43_249, median: 43_251 };
+        assert_eq!(
+            gas.rounded(100),
+            crate::TestKindGas::Fuzz { runs: 256, mean: 43_200, median: 43_300 }
+        );
+
+        let mut report = GasReport::new(vec![])
+            .with_format(GasReportFormat::Markdown)
+            .with_granularity(Some(1000));
+        let mut contract =
+            ContractInfo { gas: 123_456.into(), size: 789.into(), ..Default::default() };
+        contract.functions.insert(
+            "increment".to_string(),
+            GasInfo { calls: vec![22_400.into(), 22_600.into()], ..Default::default() },
+        );
+        report.contracts.insert("Counter".to_string(), contract);
+        let report = report
+            .with_columns(vec![GasColumn::Min, GasColumn::Max, GasColumn::Calls])
+            .finalize()
+            .to_string();
+        assert!(report.contains("|          123000 |             789 |"));
+        assert!(report.contains("| increment     | 22000 | 23000 |       2 |"));
+    }
+
+    #[test]
+    fn leaves_out_test_functions() {
+        let call = |depth: usize, func: &str| CallTrace {
+            depth,
+            contract: Some("Counter".to_string()),
+            address: Address::random(),
+            data: RawOrDecodedCall::Decoded(func.to_string(), vec![]),
+            gas_cost: 1000,
+            ..Default::default()
+        };
+        let mut arena = CallTraceArena::default();
+        arena.push_trace(0, call(0, "check_increment"));
+        arena.push_trace(0, call(1, "increment"));
+        arena.push_trace(0, call(1, "testHelper"));
+
+        let mut report = GasReport::new(vec![])
+            .with_test_prefixes(TestPrefixes::new(vec!["check_".to_string()]));
+        report.analyze(&[(TraceKind::Execution, arena)]);
+        let functions = report.contracts["Counter"].functions.keys().collect::<Vec<_>>();
+        assert_eq!(functions, vec!["increment", "testHelper"]);
+    }
 }
@@ -0,0 +1,206 @@
+//! Gas targets of tests.
+//!
+//! A test can declare how much gas it is expected to use with the `@custom:gas-target` NatSpec
+//! tag, followed by the gas and optionally the tolerance in percent:
+//!
+//! ```solidity
+//! contract TransferTest is DSTest {
+//!     /// @custom:gas-target 21000 ±3%
+//!     function testTransfer() external { .. }
+//! }
+//! ```
+//!
+//! After the run, tests that used more gas than the target plus its tolerance are reported,
+//! using less gas is never reported. Solidity rejects NatSpec tags that are not `@custom:`, so
+//! the tag can not be shortened to `@gas-target`.
+use crate::{natspec::function_docs, SuiteResult};
+use ethers::prelude::ArtifactId;
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// The NatSpec tag that declares the gas target of a test
+pub const GAS_TARGET_TAG: &str = "@custom:gas-target";
+
+/// The gas a test is expected to use
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasTarget {
+    pub gas: u64,
+    /// How many percent above `gas` a test may use
+    pub tolerance: f64,
+}
+
+impl GasTarget {
+    /// Returns the most gas a test may use, the target plus its tolerance
+    pub fn max_gas(&self) -> u64 {
+        (self.gas as f64 * (1.0 + self.tolerance / 100.0)).floor() as u64
+    }
+
+    /// Returns by how many percent `gas` is above the target if it exceeds the tolerance
+    pub fn overage(&self, gas: u64) -> Option<f64> {
+        (gas > self.max_gas()).then(|| (gas as f64 / self.gas as f64 - 1.0) * 100.0)
+    }
+}
+
+impl FromStr for GasTarget {
+    type Err = eyre::Report;
+
+    /// Parses `<gas> ±<tolerance>%`, also written `<gas> +-<tolerance>%`, or just `<gas>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (gas, tolerance) = match s.split_once('±').or_else(|| s.split_once("+-")) {
+            Some((gas, tolerance)) => (gas, Some(tolerance)),
+            None => (s, None),
+        };
+        let gas = gas
+            .trim()
+            .replace('_', "")
+            .parse::<u64>()
+            .map_err(|_| eyre::eyre!("Invalid gas target `{}`", s.trim()))?;
+        let tolerance = match tolerance {
+            Some(tolerance) => tolerance
+                .trim()
+                .strip_suffix('%')
+                .and_then(|tolerance| tolerance.trim().parse::<f64>().ok())
+                .filter(|tolerance| *tolerance >= 0.0)
+                .ok_or_else(|| eyre::eyre!("Invalid tolerance of gas target `{}`", s.trim()))?,
+            None => 0.0,
+        };
+        if gas == 0 {
+            eyre::bail!("Invalid gas target `{}`, the gas must not be 0", s.trim())
+        }
+        Ok(Self { gas, tolerance })
+    }
+}
+
+impl fmt::Display for GasTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ±{}%", self.gas, self.tolerance)
+    }
+}
+
+/// A test that used more gas than its target
+#[derive(Debug, Clone, PartialEq)]
+pub struct GasTargetOverage {
+    /// The identifier of the test contract
+    pub suite: String,
+    /// The signature of the test
+    pub test: String,
+    /// The gas the test used, the median gas of fuzz tests
+    pub gas: u64,
+    pub target: GasTarget,
+}
+
+impl fmt::Display for GasTargetOverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}::{} used {} gas, {:.2}% above its target of {}",
+            self.suite,
+            self.test,
+            self.gas,
+            self.target.overage(self.gas).unwrap_or_default(),
+            self.target
+        )
+    }
+}
+
+/// Returns the gas targets of the functions of the contract `name` in the given source code, by
+/// function name. Invalid targets are skipped with a warning.
+pub fn parse_gas_targets(source: &str, name: &str) -> BTreeMap<String, GasTarget> {
+    let mut targets = BTreeMap::new();
+    for (function, doc) in function_docs(source, name) {
+        let value = doc.iter().find_map(|line| {
+            let (_, value) = line.split_once(GAS_TARGET_TAG)?;
+            (value.is_empty() || value.starts_with(char::is_whitespace))
+                .then(|| value.trim().trim_end_matches("*/"))
+        });
+        match value.map(str::parse::<GasTarget>) {
+            Some(Ok(target)) => {
+                targets.insert(function.to_string(), target);
+            }
+            Some(Err(err)) => {
+                tracing::warn!(contract = name, function, %err, "ignoring invalid gas target")
+            }
+            None => {}
+        }
+    }
+    targets
+}
+
+/// Returns the tests that used more gas than their target, in order of the results.
+///
+/// `targets` are the gas targets of the test contracts by function name. Tests that failed are
+/// skipped.
+pub fn check_gas_targets(
+    results: &BTreeMap<String, SuiteResult>,
+    targets: &BTreeMap<ArtifactId, BTreeMap<String, GasTarget>>,
+) -> Vec<GasTargetOverage> {
+    let targets =
+        targets.iter().map(|(id, targets)| (id.identifier(), targets)).collect::<BTreeMap<_, _>>();
+    let mut overages = Vec::new();
+    for (suite, suite_result) in results {
+        let suite_targets = match targets.get(suite) {
+            Some(targets) => targets,
+            None => continue,
+        };
+        for (test, result) in suite_result.test_results.iter().filter(|(_, r)| r.success) {
+            let name = test.split('(').next().unwrap_or_default();
+            let target = match suite_targets.get(name) {
+                Some(target) => *target,
+                None => continue,
+            };
+            let gas = result.kind.gas_used().gas();
+            if target.overage(gas).is_some() {
+                overages.push(GasTargetOverage {
+                    suite: suite.clone(),
+                    test: test.clone(),
+                    gas,
+                    target,
+                });
+            }
+        }
+    }
+    overages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_gas_targets() {
+        let target = |gas, tolerance| GasTarget { gas, tolerance };
+        assert_eq!("21000 ±3%".parse::<GasTarget>().unwrap(), target(21000, 3.0));
+        assert_eq!("21_000 +- 0.5 %".parse::<GasTarget>().unwrap(), target(21000, 0.5));
+        assert_eq!("50000".parse::<GasTarget>().unwrap(), target(50000, 0.0));
+        assert!("21000 ±3".parse::<GasTarget>().is_err());
+        assert!("lots".parse::<GasTarget>().is_err());
+        assert!("0".parse::<GasTarget>().is_err());
+
+        let source = r#"
+contract TransferTest is DSTest {
+    /// @notice Transfers once
+    /// @custom:gas-target 21000 ±3%
+    function testTransfer() external {}
+
+    /** @custom:gas-target lots */
+    function testInvalid() external {}
+
+    /// @custom:gas-targets 100
+    function testOther() external {}
+}
+"#;
+        assert_eq!(
+            parse_gas_targets(source, "TransferTest"),
+            BTreeMap::from([("testTransfer".to_string(), target(21000, 3.0))])
+        );
+    }
+
+    #[test]
+    fn can_detect_overages() {
+        let target = GasTarget { gas: 20000, tolerance: 5.0 };
+        assert_eq!(target.max_gas(), 21000);
+        assert_eq!(target.overage(21000), None);
+        assert_eq!(target.overage(19000), None);
+        assert_eq!(target.overage(30000), Some(50.0));
+        assert_eq!(target.to_string(), "20000 ±5%");
+    }
+}
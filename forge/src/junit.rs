@@ -0,0 +1,125 @@
+//! JUnit XML reports of test runs.
+//!
+//! Every test contract is a `<testsuite>` named by its identifier (`<path>:<contract>`), every
+//! test a `<testcase>` named by its signature. Failed tests have a `<failure>` element with the
+//! revert reason as message, tests skipped at runtime a `<skipped/>` element. Times are in
+//! seconds.
+use crate::{report::TestReport, TestResult};
+use std::fmt::Write;
+
+/// Serializes the results of a test run as a JUnit XML report
+pub fn to_junit(report: &TestReport) -> String {
+    let count = |f: fn(&TestResult) -> bool| {
+        report.values().flat_map(|suite| suite.test_results.values()).filter(|r| f(r)).count()
+    };
+    let tests = count(|_| true);
+    let failures = count(|r| !r.success);
+    let skipped = count(|r| r.skipped);
+    let time = report.values().map(|suite| suite.duration.as_secs_f64()).sum::<f64>();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"forge tests\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" \
+         time=\"{:.3}\">",
+        tests, failures, skipped, time
+    );
+    for (name, suite) in report {
+        let results = &suite.test_results;
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            escape(name),
+            results.len(),
+            results.values().filter(|r| !r.success).count(),
+            results.values().filter(|r| r.skipped).count(),
+            suite.duration.as_secs_f64()
+        );
+        for (test, result) in results {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape(test),
+                escape(name),
+                result.duration.as_secs_f64()
+            );
+            if result.success && !result.skipped {
+                xml.push_str("/>\n");
+                continue
+            }
+            xml.push_str(">\n");
+            if result.skipped {
+                xml.push_str("      <skipped/>\n");
+            } else {
+                let reason = result.reason.as_deref().unwrap_or("test failed");
+                let _ = writeln!(xml, "      <failure message=\"{}\"/>", escape(reason));
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Escapes the characters that are not allowed in XML attribute values
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SuiteResult, TestKind};
+    use std::{collections::BTreeMap, time::Duration};
+
+    fn result(success: bool, skipped: bool, reason: Option<&str>) -> TestResult {
+        TestResult {
+            success,
+            skipped,
+            reason: reason.map(str::to_string),
+            kind: TestKind::Standard(21_000),
+            duration: Duration::from_millis(5),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_write_junit() {
+        let report = TestReport::from([(
+            "src/Counter.t.sol:CounterTest".to_string(),
+            SuiteResult::new(
+                Duration::from_millis(20),
+                BTreeMap::from([
+                    ("testIncrement()".to_string(), result(true, false, None)),
+                    ("testFork()".to_string(), result(true, true, Some("FOUNDRY::SKIP"))),
+                    ("testLimit()".to_string(), result(false, false, Some("a < \"b\""))),
+                ]),
+            ),
+        )]);
+
+        let xml = to_junit(&report);
+        assert!(xml.contains(
+            "<testsuites name=\"forge tests\" tests=\"3\" failures=\"1\" skipped=\"1\" \
+             time=\"0.020\">"
+        ));
+        assert!(xml.contains(
+            "<testcase name=\"testIncrement()\" classname=\"src/Counter.t.sol:CounterTest\" \
+             time=\"0.005\"/>"
+        ));
+        assert!(xml.contains("<failure message=\"a &lt; &quot;b&quot;\"/>"));
+        assert!(xml.contains("<skipped/>"));
+    }
+}
@@ -1,13 +1,76 @@
 /// Gas reports
 pub mod gas_report;
 
+/// Comparing the results of test runs
+pub mod diff;
+
+/// Decoding the storage changes of tests
+pub mod storage;
+
+/// Function and line coverage of test runs
+pub mod coverage;
+
+/// Call graphs of the functions tests call
+pub mod callgraph;
+
+/// Flamegraphs of the gas used by the call frames of tests
+pub mod flamegraph;
+
+/// Caching the results of passing tests across runs
+pub mod result_cache;
+
+/// The format of the JSON test results
+pub mod report;
+
+/// JUnit XML reports of test runs
+pub mod junit;
+
+/// Compiling and testing a project without the CLI
+pub mod run;
+
+/// Accounts placed into the state before the tests run
+pub mod fixtures;
+
+/// Counting the assertions executed by tests
+pub mod assertions;
+
+/// Functions of test contracts that are not tests
+pub mod callbacks;
+
+/// The NatSpec of test contracts and their functions
+mod natspec;
+
+/// Mutation testing of the contracts the tests call
+pub mod mutation;
+
+/// The gas tests are expected to use, declared in their NatSpec
+pub mod gas_targets;
+
+/// Branch coverage of fuzz tests
+pub mod branches;
+
+/// Reducing the runs of fuzz tests to finish before a deadline
+pub mod deadline;
+
+/// Running test contracts once per set of constructor arguments
+pub mod variants;
+
+/// Checking the events emitted by a test run against a schema
+pub mod event_schema;
+
 /// The Forge test runner
 mod runner;
-pub use runner::{ContractRunner, SuiteResult, TestKind, TestKindGas, TestResult};
+pub use runner::{
+    ContractRunner, ReturnValue, SuiteResult, TestKind, TestKindGas, TestPrefixes, TestResult,
+    TestSelection, CODE_SIZE_LIMIT, DEFAULT_TEST_PREFIX,
+};
 
 /// Forge test runners for multiple contracts
 mod multi_runner;
-pub use multi_runner::{MultiContractRunner, MultiContractRunnerBuilder};
+pub use multi_runner::{
+    ArtifactTransform, ContractFork, ForkBlock, GasThreshold, MultiContractRunner,
+    MultiContractRunnerBuilder, Selectors, TestEnv,
+};
 
 pub trait TestFilter {
     fn matches_test(&self, test_name: impl AsRef<str>) -> bool;
@@ -59,6 +122,7 @@ pub mod test_helpers {
         sender: Address::from_str("00a329c0648769a73afac7f9381e08fb43dbea72").unwrap(),
         initial_balance: U256::MAX,
         ffi: true,
+        memory_limit: 2u64.pow(24),
         ..Default::default()
     });
 
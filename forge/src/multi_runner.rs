@@ -1,18 +1,54 @@
-use crate::{ContractRunner, SuiteResult, TestFilter};
+use crate::{
+    assertions,
+    branches::{contract_branches, ContractBranches},
+    callbacks::parse_callbacks,
+    coverage::{contract_lines, ContractLines},
+    deadline::FuzzBudget,
+    fixtures::Fixtures,
+    gas_targets::{parse_gas_targets, GasTarget},
+    natspec::{contract_docs, function_docs},
+    variants::{parse_variants, SuiteVariant},
+    ContractRunner, SuiteResult, TestFilter, TestPrefixes, TestResult, TestSelection,
+};
 use ethers::{
-    abi::Abi,
-    prelude::{artifacts::CompactContractBytecode, ArtifactId, ArtifactOutput},
+    abi::{Abi, Event, Function, ParamType, Token},
+    prelude::{
+        artifacts::{
+            BytecodeObject, CompactContractBytecode, CompactDeployedBytecode, StorageLayout,
+        },
+        ArtifactId, ArtifactOutput,
+    },
     solc::{Artifact, ProjectCompileOutput},
-    types::{Address, Bytes, U256},
+    types::{Address, Bytes, H256, U256},
+    utils::{get_create2_address, keccak256},
 };
 use eyre::Result;
-use foundry_evm::executor::{
-    builder::Backend, opts::EvmOpts, DatabaseRef, Executor, ExecutorBuilder, Fork, SpecId,
+use foundry_evm::{
+    executor::{
+        builder::Backend,
+        inspector::{CustomPrecompiles, PrecompileStub},
+        opts::EvmOpts,
+        DatabaseRef, Env, Executor, ExecutorBuilder, Fork, SpecId,
+    },
+    fuzz::{CustomStrategies, FuzzWeights},
 };
-use foundry_utils::{PostLinkInput, RuntimeOrHandle};
-use proptest::test_runner::TestRunner;
+use foundry_utils::{AbiNames, OutputEncoding, PostLinkInput, RuntimeOrHandle};
+use proptest::{strategy::BoxedStrategy, test_runner::TestRunner};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use rayon::prelude::*;
-use std::{collections::BTreeMap, marker::Sync, path::Path, sync::mpsc::Sender};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    marker::Sync,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 /// Builder used for instantiating the multi-contract runner
 #[derive(Debug, Default)]
@@ -28,10 +64,340 @@ pub struct MultiContractRunnerBuilder {
     pub evm_spec: Option<SpecId>,
     /// The fork config
     pub fork: Option<Fork>,
+    /// The fork blocks to run the tests against, each in a separate run
+    pub fork_blocks: Vec<u64>,
+    /// The memory limit of the EVM in bytes
+    pub memory_limit: Option<u64>,
+    /// The weights of the input strategies of fuzz tests
+    pub fuzz_weights: FuzzWeights,
+    /// The percentage of random `address` inputs of fuzz tests drawn from the known addresses
+    pub fuzz_address_pool_rate: u32,
+    /// Custom strategies for the inputs of fuzz tests
+    pub fuzz_strategies: CustomStrategies,
+    /// The directory of artifacts to read the deployed bytecode from, for contracts whose compiler
+    /// output lacks it
+    pub deployed_bytecode_dir: Option<PathBuf>,
+    /// The kinds of tests to run
+    pub test_selection: TestSelection,
+    /// The prefixes of test functions
+    pub test_prefixes: TestPrefixes,
+    /// Whether to stop the entire run after the first failing test
+    pub fail_fast: bool,
+    /// If set, the entire run is stopped after this many failing tests
+    pub bail: Option<usize>,
+    /// The seed used to shuffle the execution order of contracts and tests, if any
+    pub shuffle_seed: Option<u64>,
+    /// If set, calls to contracts that were not deployed in the test and are not in this set
+    /// revert
+    pub external_call_allowlist: Option<BTreeSet<Address>>,
+    /// The block base fee, overrides the base fee of the environment or fork
+    pub base_fee: Option<U256>,
+    /// The transaction gas price, overrides the gas price of the environment or fork
+    pub gas_price: Option<U256>,
+    /// The chain id, overrides the chain id of the environment or fork
+    pub chain_id: Option<u64>,
+    /// The block coinbase, overrides the coinbase of the environment or fork
+    pub coinbase: Option<Address>,
+    /// The `tx.origin` of the calls of the tests, the sender if not set
+    pub tx_origin: Option<Address>,
+    /// The block prevrandao, overrides the difficulty or prevrandao of the environment or fork
+    pub prevrandao: Option<U256>,
+    /// If set, the storage changes of unit tests are recorded for these addresses, or for the
+    /// test contract if empty
+    pub storage_diff: Option<Vec<Address>>,
+    /// If set, only these tests are run, by suite identifier and test signature
+    pub only_tests: Option<BTreeMap<String, BTreeSet<String>>>,
+    /// If set, only the tests that used more gas than the threshold in a previous run are run
+    pub gas_threshold: Option<GasThreshold>,
+    /// Whether to record the names of the cheatcodes invoked by each test
+    pub record_cheatcodes: bool,
+    /// Whether to record the maximum call depth reached by each test
+    pub record_call_depth: bool,
+    /// Whether to count the assertions executed by each test
+    pub record_assertions: bool,
+    /// Whether to record the gas refunded to each test
+    pub record_gas_refunds: bool,
+    /// Whether to count how often each test executed the source lines of the contracts
+    pub record_line_coverage: bool,
+    /// Whether to only deploy the test contracts and run their `setUp`
+    pub setup_only: bool,
+    /// If set, the setup of test contracts whose runtime code is larger fails
+    pub code_size_limit: Option<usize>,
+    /// If set, the test contracts are deployed with `CREATE2` and this salt
+    pub deploy_salt: Option<H256>,
+    /// How often a failed `setUp` is retried
+    pub setup_retries: u32,
+    /// Whether unit tests fail if a contract other than the test contract executed `SELFDESTRUCT`
+    pub no_selfdestruct: bool,
+    /// If set, fuzz tests whose branch coverage is lower fail, in percent
+    pub fuzz_min_coverage: Option<f64>,
+    /// If set, the runs of fuzz tests are reduced so the tests finish within this duration
+    pub deadline: Option<Duration>,
+    /// Accounts placed into the state before the test contracts are deployed
+    pub fixtures: Fixtures,
+    /// Custom precompiles available to the tests
+    pub precompiles: CustomPrecompiles,
+    /// Stubs of precompiles for snapshot tests, by address
+    pub precompile_stubs: BTreeMap<Address, PrecompileStub>,
+    /// ABIs of contracts that are not part of the project, only used for decoding
+    pub extra_abis: Vec<Abi>,
+    /// Opcodes that are not available to the tests
+    pub disabled_opcodes: BTreeSet<u8>,
+    /// If set, no further test contracts are run once this is raised, e.g. on Ctrl-C
+    pub interrupt: Option<Arc<AtomicBool>>,
+    /// The EVM environment, resolved from the EVM options if not set
+    pub env: Option<Env>,
+    /// Applied to the compiled contracts before they are linked
+    pub artifact_transform: Option<ArtifactTransform>,
+    /// How addresses and bytes are printed in Hardhat-style logs
+    pub output_encoding: OutputEncoding,
+}
+
+/// A transformation of the compiled contracts before they are linked, see
+/// [MultiContractRunnerBuilder::with_artifact_transform()]
+#[derive(Clone)]
+pub struct ArtifactTransform(
+    Arc<dyn Fn(&ArtifactId, &mut CompactContractBytecode) + Send + Sync + 'static>,
+);
+
+impl ArtifactTransform {
+    pub fn new(
+        transform: impl Fn(&ArtifactId, &mut CompactContractBytecode) + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(transform))
+    }
+
+    /// Applies the transformation to the contract
+    pub fn apply(&self, id: &ArtifactId, contract: &mut CompactContractBytecode) {
+        (self.0)(id, contract)
+    }
+}
+
+impl fmt::Debug for ArtifactTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ArtifactTransform")
+    }
 }
 
 pub type DeployableContracts = BTreeMap<ArtifactId, (Abi, Bytes, Vec<Bytes>)>;
 
+/// The signatures of functions, errors and events by selector, see
+/// [MultiContractRunner::selectors()]
+///
+/// A selector can belong to more than one signature if they collide.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selectors {
+    /// Function signatures by 4 byte selector, e.g. `transfer(address,uint256)`
+    pub functions: BTreeMap<[u8; 4], BTreeSet<String>>,
+    /// Custom error signatures by 4 byte selector, e.g. `Unauthorized(address)`
+    pub errors: BTreeMap<[u8; 4], BTreeSet<String>>,
+    /// Event signatures by topic 0, e.g. `Transfer(address,address,uint256)`
+    pub events: BTreeMap<H256, BTreeSet<String>>,
+}
+
+impl Selectors {
+    /// Collects the selectors of the functions, errors and events of the ABIs
+    pub fn from_abis<'a>(abis: impl IntoIterator<Item = &'a Abi>) -> Self {
+        let signature = |name: &str, params: Vec<String>| format!("{}({})", name, params.join(","));
+        let mut selectors = Self::default();
+        for abi in abis {
+            for func in abi.functions() {
+                selectors
+                    .functions
+                    .entry(func.short_signature())
+                    .or_default()
+                    .insert(func.signature());
+            }
+            for error in abi.errors() {
+                let mut selector = [0u8; 4];
+                selector.copy_from_slice(&error.signature()[..4]);
+                let params = error.inputs.iter().map(|param| param.kind.to_string()).collect();
+                selectors
+                    .errors
+                    .entry(selector)
+                    .or_default()
+                    .insert(signature(&error.name, params));
+            }
+            for event in abi.events() {
+                let params = event.inputs.iter().map(|param| param.kind.to_string()).collect();
+                selectors
+                    .events
+                    .entry(event.signature())
+                    .or_default()
+                    .insert(signature(&event.name, params));
+            }
+        }
+        selectors
+    }
+}
+
+/// The NatSpec tag that declares the URL of the node a test contract forks from
+pub const FORK_URL_TAG: &str = "@custom:fork-url";
+
+/// The NatSpec tag that declares the block a test contract forks from, the latest block if unset
+pub const FORK_BLOCK_TAG: &str = "@custom:fork-block";
+
+/// The fork configuration of a single test contract, declared in the contract's NatSpec:
+///
+/// ```solidity
+/// /// @custom:fork-url https://eth-mainnet.alchemyapi.io/v2/<key>
+/// /// @custom:fork-block 14000000
+/// contract ForkTest is DSTest { .. }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractFork {
+    /// The URL of the node to fork from
+    pub url: String,
+    /// The block to fork from, if any
+    pub block: Option<u64>,
+}
+
+impl ContractFork {
+    /// Parses the [FORK_URL_TAG] and [FORK_BLOCK_TAG] tags from the doc comment of the contract
+    /// `name` in the given source code
+    ///
+    /// Returns `None` if the contract does not exist or has no [FORK_URL_TAG] tag, and an error if
+    /// a tag has no value or the block is not a number.
+    pub fn parse(source: &str, name: &str) -> eyre::Result<Option<Self>> {
+        let mut url = None;
+        let mut block = None;
+        for line in contract_docs(source, name) {
+            let mut tokens = line.split_whitespace().map(|token| token.trim_end_matches("*/"));
+            while let Some(token) = tokens.next() {
+                if token == FORK_URL_TAG {
+                    let value = tokens.next().filter(|value| !value.is_empty());
+                    let value = value.ok_or_else(|| {
+                        eyre::eyre!("Missing value of `{}` of contract {}", FORK_URL_TAG, name)
+                    })?;
+                    url = Some(value.to_string());
+                } else if token == FORK_BLOCK_TAG {
+                    let value = tokens.next().unwrap_or_default();
+                    let value = value.replace('_', "").parse::<u64>().map_err(|_| {
+                        eyre::eyre!(
+                            "Invalid value `{}` of `{}` of contract {}, expected a block number",
+                            value,
+                            FORK_BLOCK_TAG,
+                            name
+                        )
+                    })?;
+                    block = Some(value);
+                }
+            }
+        }
+
+        Ok(url.map(|url| ContractFork { url, block }))
+    }
+}
+
+/// The NatSpec tag that marks a test contract as a base of other test contracts.
+///
+/// Test contracts run every test function they declare or inherit, so the tests of a base test
+/// contract that is not `abstract` would run once for the base itself and once for every contract
+/// that inherits it. Marked contracts are not run themselves, their tests still run as part of
+/// the contracts that inherit them, unless these are marked too:
+///
+/// ```solidity
+/// /// @custom:abstract
+/// contract VaultTestBase is DSTest {
+///     function testDeposit() public { .. }
+/// }
+///
+/// contract WethVaultTest is VaultTestBase { .. }
+/// ```
+pub const ABSTRACT_TEST_TAG: &str = "@custom:abstract";
+
+/// Returns `true` if the contract `name` in the given source code is marked with
+/// [ABSTRACT_TEST_TAG]
+pub fn is_abstract_test(source: &str, name: &str) -> bool {
+    contract_docs(source, name).iter().any(|line| {
+        line.split_whitespace().any(|token| token.trim_end_matches("*/") == ABSTRACT_TEST_TAG)
+    })
+}
+
+/// The parts of the EVM environment that affect test outcomes, see
+/// [MultiContractRunner::evm_env()]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestEnv {
+    /// The chain id
+    pub chain_id: U256,
+    /// The block number
+    pub block_number: U256,
+    /// The block timestamp
+    pub timestamp: U256,
+    /// The block base fee
+    pub base_fee: U256,
+    /// The block gas limit
+    pub gas_limit: U256,
+    /// The block coinbase
+    pub coinbase: Address,
+}
+
+impl From<&Env> for TestEnv {
+    fn from(env: &Env) -> Self {
+        Self {
+            chain_id: env.cfg.chain_id,
+            block_number: env.block.number,
+            timestamp: env.block.timestamp,
+            base_fee: env.block.basefee,
+            gas_limit: env.block.gas_limit,
+            coinbase: env.block.coinbase,
+        }
+    }
+}
+
+/// The block a forked test run reads the remote state at, see
+/// [MultiContractRunner::fork_block()]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ForkBlock {
+    /// The block number
+    pub number: u64,
+    /// The block timestamp
+    pub timestamp: U256,
+}
+
+/// Restricts a run to the tests that used more gas than a threshold in a previous run, see
+/// [MultiContractRunnerBuilder::only_expensive()]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GasThreshold {
+    /// The gas used by the tests of the previous run, by suite identifier and test signature
+    pub gas: BTreeMap<String, BTreeMap<String, u64>>,
+    /// Tests that used more gas than this are run
+    pub threshold: u64,
+    /// Whether tests that are not part of the previous run are run as well
+    pub include_new: bool,
+}
+
+impl GasThreshold {
+    /// Reads the gas used by the tests of a previous run, the median gas for fuzz tests
+    pub fn new(results: &BTreeMap<String, SuiteResult>, threshold: u64) -> Self {
+        let gas = results
+            .iter()
+            .map(|(suite, result)| {
+                let tests = result
+                    .test_results
+                    .iter()
+                    .filter(|(test, _)| *test != "setUp()")
+                    .map(|(test, result)| (test.clone(), result.kind.gas_used().gas()))
+                    .collect();
+                (suite.clone(), tests)
+            })
+            .collect();
+        Self { gas, threshold, include_new: false }
+    }
+
+    /// Sets whether tests that are not part of the previous run are run as well
+    #[must_use]
+    pub fn include_new(mut self, include_new: bool) -> Self {
+        self.include_new = include_new;
+        self
+    }
+
+    /// Returns the gas the test used in the previous run, if it was part of it
+    fn gas_used(&self, suite: &str, test: &str) -> Option<u64> {
+        self.gas.get(suite).and_then(|tests| tests.get(test)).copied()
+    }
+}
+
 impl MultiContractRunnerBuilder {
     /// Given an EVM, proceeds to return a runner which is able to execute all tests
     /// against that evm
@@ -44,12 +410,72 @@ impl MultiContractRunnerBuilder {
     where
         A: ArtifactOutput,
     {
+        let root = root.as_ref().to_path_buf();
+
         // This is just the contracts compiled, but we need to merge this with the read cached
         // artifacts
-        let contracts = output
-            .with_stripped_file_prefixes(root)
-            .into_artifacts()
-            .map(|(i, c)| (i, c.into_contract_bytecode()))
+        let mut storage_layouts = BTreeMap::new();
+        let mut source_maps = BTreeMap::new();
+        let mut immutables = BTreeMap::new();
+        let (artifacts, sources) =
+            output.with_stripped_file_prefixes(&root).into_artifacts_with_sources();
+        let contracts = artifacts
+            .into_iter()
+            .map(|(id, artifact)| {
+                if self.storage_diff.is_some() {
+                    if let Some(layout) = read_storage_layout(&artifact) {
+                        storage_layouts.insert(id.clone(), layout);
+                    }
+                }
+                (id, artifact.into_contract_bytecode())
+            })
+            .map(|(id, contract)| {
+                if self.record_assertions ||
+                    self.fuzz_min_coverage.is_some() ||
+                    self.record_line_coverage
+                {
+                    let source_map = contract
+                        .deployed_bytecode
+                        .as_ref()
+                        .and_then(|code| code.bytecode.as_ref())
+                        .and_then(|code| code.source_map())
+                        .and_then(|source_map| source_map.ok());
+                    if let Some(source_map) = source_map {
+                        source_maps.insert(id.clone(), source_map);
+                    }
+                    // the values of immutable variables are only set on deployment
+                    if let Some(code) = contract.deployed_bytecode.as_ref() {
+                        let ranges = code
+                            .immutable_references
+                            .values()
+                            .flatten()
+                            .map(|offsets| (offsets.start as usize, offsets.length as usize))
+                            .collect::<Vec<_>>();
+                        immutables.insert(id.clone(), ranges);
+                    }
+                }
+                (id, contract)
+            })
+            .map(|(id, mut contract)| {
+                if !has_deployed_bytecode(&contract) {
+                    if let Some(ref dir) = self.deployed_bytecode_dir {
+                        contract.deployed_bytecode = read_deployed_bytecode(dir, &id);
+                    }
+                    if !has_deployed_bytecode(&contract) {
+                        tracing::warn!(
+                            contract = %id.identifier(),
+                            "missing deployed bytecode, traces of this contract can not be decoded"
+                        );
+                    }
+                }
+                (id, contract)
+            })
+            .map(|(id, mut contract)| {
+                if let Some(ref transform) = self.artifact_transform {
+                    transform.apply(&id, &mut contract);
+                }
+                (id, contract)
+            })
             .collect::<Vec<(ArtifactId, CompactContractBytecode)>>();
 
         let mut known_contracts: BTreeMap<ArtifactId, (Abi, Vec<u8>)> = Default::default();
@@ -61,6 +487,7 @@ impl MultiContractRunnerBuilder {
         // create a mapping of name => (abi, deployment code, Vec<library deployment code>)
         let mut deployable_contracts = DeployableContracts::default();
 
+        let start = Instant::now();
         foundry_utils::link(
             BTreeMap::from_iter(contracts),
             &mut known_contracts,
@@ -85,10 +512,10 @@ impl MultiContractRunnerBuilder {
                     };
 
                 let abi = contract.abi.expect("We should have an abi by now");
-                // if its a test, add it to deployable contracts
-                if abi.constructor.as_ref().map(|c| c.inputs.is_empty()).unwrap_or(true) &&
-                    abi.functions().any(|func| func.name.starts_with("test"))
-                {
+                // if its a test, add it to deployable contracts. Base test contracts and test
+                // contracts whose constructor takes arguments but declare no variants are removed
+                // again below
+                if abi.functions().any(|func| self.test_prefixes.is_test(&func.name)) {
                     deployable_contracts
                         .insert(id.clone(), (abi.clone(), bytecode, dependencies.to_vec()));
                 }
@@ -101,8 +528,189 @@ impl MultiContractRunnerBuilder {
                 Ok(())
             },
         )?;
+        tracing::debug!(
+            contracts = known_contracts.len(),
+            elapsed = ?start.elapsed(),
+            "linked contracts"
+        );
+
+        // collect the fork configurations, callbacks, gas targets, variants and base test contracts
+        // declared in the NatSpec of the test contracts, and the functions they declare themselves
+        let mut contents = BTreeMap::new();
+        let mut read_source = |id: &ArtifactId| {
+            contents
+                .entry(id.source.clone())
+                .or_insert_with(|| fs::read_to_string(root.join(&id.source)).ok())
+                .clone()
+        };
+        let mut contract_forks = BTreeMap::new();
+        let mut callbacks = BTreeMap::new();
+        let mut gas_targets = BTreeMap::new();
+        let mut variants = BTreeMap::new();
+        let mut abstract_tests = BTreeSet::new();
+        let mut declared_functions = BTreeMap::new();
+        for (id, (abi, _, _)) in &deployable_contracts {
+            let source = match read_source(id) {
+                Some(source) => source,
+                None => continue,
+            };
+            let functions = function_docs(&source, &id.name).into_iter();
+            declared_functions
+                .insert(id.clone(), functions.map(|(function, _)| function.to_string()).collect());
+            if let Some(fork) = ContractFork::parse(&source, &id.name)? {
+                contract_forks.insert(id.clone(), fork);
+            }
+            let contract_callbacks = parse_callbacks(&source, &id.name);
+            if !contract_callbacks.is_empty() {
+                callbacks.insert(id.clone(), contract_callbacks);
+            }
+            let contract_gas_targets = parse_gas_targets(&source, &id.name);
+            if !contract_gas_targets.is_empty() {
+                gas_targets.insert(id.clone(), contract_gas_targets);
+            }
+            if let Some(constructor) = abi.constructor.as_ref().filter(|c| !c.inputs.is_empty()) {
+                let contract_variants = parse_variants(&source, &id.name, constructor);
+                if !contract_variants.is_empty() {
+                    variants.insert(id.clone(), contract_variants);
+                }
+            }
+            if is_abstract_test(&source, &id.name) {
+                tracing::debug!(contract = %id.identifier(), "skipping base test contract");
+                abstract_tests.insert(id.clone());
+            }
+        }
+        deployable_contracts.retain(|id, (abi, _, _)| {
+            !abstract_tests.contains(id) &&
+                (abi.constructor.as_ref().map_or(true, |c| c.inputs.is_empty()) ||
+                    variants.contains_key(id))
+        });
+
+        // intersect the tests the run is restricted to with the expensive tests
+        let mut only_tests = self.only_tests;
+        if let Some(ref threshold) = self.gas_threshold {
+            let mut expensive = BTreeMap::<String, BTreeSet<String>>::new();
+            for (id, (abi, _, _)) in &deployable_contracts {
+                for suite in suite_names(id, variants.get(id)) {
+                    let tests = abi
+                        .functions()
+                        .map(|func| func.signature())
+                        .filter(|test| match threshold.gas_used(&suite, test) {
+                            Some(gas) => gas > threshold.threshold,
+                            None => threshold.include_new,
+                        })
+                        .filter(|test| {
+                            only_tests.as_ref().map_or(true, |only| {
+                                only.get(&suite).map_or(false, |tests| tests.contains(test))
+                            })
+                        })
+                        .collect::<BTreeSet<_>>();
+                    if !tests.is_empty() {
+                        expensive.insert(suite, tests);
+                    }
+                }
+            }
+            only_tests = Some(expensive);
+        }
+
+        // ethabi drops the names of struct fields, so they are read from the artifacts instead.
+        // The artifacts are read in parallel and merged in order, so later contracts take
+        // precedence as if they were read one after another
+        let abi_names = known_contracts
+            .par_iter()
+            .filter_map(|(id, _)| {
+                let content = fs::read_to_string(root.join(&id.path)).ok()?;
+                let artifact = serde_json::from_str::<serde_json::Value>(&content).ok()?;
+                Some(AbiNames::from_json(&artifact))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .fold(AbiNames::default(), |mut abi_names, names| {
+                abi_names.merge(names);
+                abi_names
+            });
+
+        let source_files = if source_maps.is_empty() {
+            BTreeMap::new()
+        } else {
+            sources
+                .into_ids()
+                .filter_map(|(id, path)| {
+                    let source = fs::read_to_string(root.join(&path)).ok()?;
+                    Some((id, (PathBuf::from(path), source)))
+                })
+                .collect::<BTreeMap<u32, (PathBuf, String)>>()
+        };
+
+        // find the jumps into assertion functions in the runtime code of the test contracts
+        let mut assertion_jumps = BTreeMap::new();
+        if self.record_assertions {
+            let source_code = source_files
+                .iter()
+                .map(|(id, (_, source))| (*id, source.clone()))
+                .collect::<BTreeMap<u32, String>>();
+            for id in deployable_contracts.keys() {
+                if let (Some(source_map), Some((_, code))) =
+                    (source_maps.get(id), known_contracts.get(id))
+                {
+                    let jumps = assertions::assertion_jumps(code, source_map, &source_code);
+                    assertion_jumps.insert(id.clone(), jumps);
+                }
+            }
+        }
+
+        // find the conditional jumps in the runtime code of the contracts under test
+        let mut branches = BTreeMap::new();
+        if self.fuzz_min_coverage.is_some() {
+            for (id, (_, code)) in
+                known_contracts.iter().filter(|(id, _)| !deployable_contracts.contains_key(*id))
+            {
+                if let Some(source_map) = source_maps.get(id) {
+                    let contract = ContractBranches {
+                        name: id.name.clone(),
+                        code: code.clone(),
+                        immutables: immutables.get(id).cloned().unwrap_or_default(),
+                        branches: contract_branches(code, source_map, &source_files),
+                    };
+                    branches.insert(id.clone(), contract);
+                }
+            }
+        }
+
+        // map the instructions in the runtime code of the contracts under test to source lines
+        let mut lines = BTreeMap::new();
+        if self.record_line_coverage {
+            for (id, (_, code)) in
+                known_contracts.iter().filter(|(id, _)| !deployable_contracts.contains_key(*id))
+            {
+                if let Some(source_map) = source_maps.get(id) {
+                    let contract = ContractLines {
+                        code: code.clone(),
+                        immutables: immutables.get(id).cloned().unwrap_or_default(),
+                        lines: contract_lines(code, source_map, &source_files),
+                    };
+                    lines.insert(id.clone(), contract);
+                }
+            }
+        }
+
+        let mut precompiles = self.precompiles;
+        for (address, stub) in self.precompile_stubs {
+            precompiles.register_stub(address, stub);
+        }
+
+        let (_, mut events, mut errors) = foundry_utils::flatten_known_contracts(&known_contracts);
+        for abi in &self.extra_abis {
+            for event in abi.events() {
+                events.entry(event.signature()).or_insert_with(|| event.clone());
+            }
+            for error in abi.errors() {
+                let known = errors.errors().any(|known| known.signature() == error.signature());
+                if !known {
+                    errors.errors.entry(error.name.clone()).or_default().push(error.clone());
+                }
+            }
+        }
 
-        let execution_info = foundry_utils::flatten_known_contracts(&known_contracts);
         Ok(MultiContractRunner {
             contracts: deployable_contracts,
             known_contracts,
@@ -110,9 +718,61 @@ impl MultiContractRunnerBuilder {
             evm_spec: self.evm_spec.unwrap_or(SpecId::LONDON),
             sender: self.sender,
             fuzzer: self.fuzzer,
-            errors: Some(execution_info.2),
+            errors: Some(errors),
+            events: Arc::new(events),
+            abi_names: Arc::new(abi_names),
+            extra_abis: self.extra_abis,
             source_paths,
             fork: self.fork,
+            fork_blocks: self.fork_blocks,
+            memory_limit: self.memory_limit,
+            fuzz_weights: self.fuzz_weights,
+            fuzz_address_pool_rate: self.fuzz_address_pool_rate,
+            fuzz_strategies: self.fuzz_strategies,
+            contract_forks,
+            callbacks,
+            declared_functions,
+            gas_targets,
+            variants,
+            test_selection: self.test_selection,
+            test_prefixes: self.test_prefixes,
+            fail_fast: self.fail_fast,
+            bail: self.bail,
+            shuffle_seed: self.shuffle_seed,
+            external_call_allowlist: self.external_call_allowlist,
+            base_fee: self.base_fee,
+            gas_price: self.gas_price,
+            chain_id: self.chain_id,
+            coinbase: self.coinbase,
+            tx_origin: self.tx_origin,
+            prevrandao: self.prevrandao,
+            storage_diff: self.storage_diff,
+            storage_layouts,
+            only_tests,
+            gas_threshold: self.gas_threshold,
+            record_cheatcodes: self.record_cheatcodes,
+            record_call_depth: self.record_call_depth,
+            record_assertions: self.record_assertions,
+            assertion_jumps,
+            record_gas_refunds: self.record_gas_refunds,
+            record_line_coverage: self.record_line_coverage,
+            lines,
+            setup_only: self.setup_only,
+            code_size_limit: self.code_size_limit,
+            deploy_salt: self.deploy_salt,
+            setup_retries: self.setup_retries,
+            no_selfdestruct: self.no_selfdestruct,
+            fuzz_min_coverage: self.fuzz_min_coverage,
+            branches,
+            deadline: self.deadline,
+            fuzz_budget: None,
+            fixtures: self.fixtures,
+            precompiles,
+            disabled_opcodes: self.disabled_opcodes,
+            interrupt: self.interrupt,
+            cached_results: BTreeMap::new(),
+            env: self.env,
+            output_encoding: self.output_encoding,
         })
     }
 
@@ -134,227 +794,2430 @@ impl MultiContractRunnerBuilder {
         self
     }
 
+    /// Sets the weight of boundary value inputs for fuzz tests, see
+    /// [FuzzedExecutor::with_boundary_weight()](foundry_evm::fuzz::FuzzedExecutor::with_boundary_weight)
     #[must_use]
-    pub fn evm_spec(mut self, spec: SpecId) -> Self {
-        self.evm_spec = Some(spec);
+    pub fn fuzz_boundary_weight(mut self, weight: u32) -> Self {
+        self.fuzz_weights.boundary = weight;
         self
     }
 
+    /// Sets the weights of all input strategies of fuzz tests, see [FuzzWeights]
     #[must_use]
-    pub fn with_fork(mut self, fork: Option<Fork>) -> Self {
-        self.fork = fork;
+    pub fn fuzz_weights(mut self, weights: FuzzWeights) -> Self {
+        self.fuzz_weights = weights;
         self
     }
-}
 
-/// A multi contract runner receives a set of contracts deployed in an EVM instance and proceeds
-/// to run all test functions in these contracts.
-pub struct MultiContractRunner {
-    /// Mapping of contract name to Abi, creation bytecode and library bytecode which
-    /// needs to be deployed & linked against
-    pub contracts: DeployableContracts,
-    /// Compiled contracts by name that have an Abi and runtime bytecode
-    pub known_contracts: BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
-    /// The EVM instance used in the test runner
-    pub evm_opts: EvmOpts,
-    /// The EVM spec
-    pub evm_spec: SpecId,
-    /// All known errors, used for decoding reverts
-    pub errors: Option<Abi>,
-    /// The fuzzer which will be used to run parametric tests (w/ non-0 solidity args)
-    fuzzer: Option<TestRunner>,
-    /// The address which will be used as the `from` field in all EVM calls
-    sender: Option<Address>,
-    /// A map of contract names to absolute source file paths
-    pub source_paths: BTreeMap<String, String>,
-    /// The fork config
-    pub fork: Option<Fork>,
-}
+    /// Sets the percentage of random `address` inputs of fuzz tests that are drawn from the known
+    /// addresses, see
+    /// [FuzzedExecutor::with_address_pool_rate()](foundry_evm::fuzz::FuzzedExecutor::with_address_pool_rate)
+    #[must_use]
+    pub fn fuzz_address_pool_rate(mut self, rate: u32) -> Self {
+        self.fuzz_address_pool_rate = rate;
+        self
+    }
 
-impl MultiContractRunner {
-    pub fn count_filtered_tests(&self, filter: &(impl TestFilter + Send + Sync)) -> usize {
-        self.contracts
-            .iter()
-            .filter(|(id, _)| {
-                filter.matches_path(id.source.to_string_lossy()) &&
-                    filter.matches_contract(&id.name)
-            })
-            .flat_map(|(_, (abi, _, _))| {
-                abi.functions().filter(|func| filter.matches_test(func.signature()))
-            })
-            .count()
+    /// Registers a custom strategy for all fuzzed parameters of the given type, see
+    /// [CustomStrategies::add_param_strategy()]
+    ///
+    /// Tests without parameters of a type with a custom strategy use the default strategies.
+    #[must_use]
+    pub fn fuzz_param_strategy(
+        mut self,
+        param: &ParamType,
+        strategy: impl Fn(&ParamType) -> BoxedStrategy<Token> + Send + Sync + 'static,
+    ) -> Self {
+        self.fuzz_strategies.add_param_strategy(param, strategy);
+        self
     }
 
-    pub fn test(
-        &mut self,
-        filter: &(impl TestFilter + Send + Sync),
-        stream_result: Option<Sender<(String, SuiteResult)>>,
-        include_fuzz_tests: bool,
-    ) -> Result<BTreeMap<String, SuiteResult>> {
-        let runtime = RuntimeOrHandle::new();
-        let env = runtime.block_on(self.evm_opts.evm_env());
+    /// Registers a custom strategy for all inputs of the fuzz test with the given signature, see
+    /// [CustomStrategies::add_test_strategy()]
+    #[must_use]
+    pub fn fuzz_test_strategy(
+        mut self,
+        signature: impl Into<String>,
+        strategy: impl Fn(&Function) -> BoxedStrategy<Vec<Token>> + Send + Sync + 'static,
+    ) -> Self {
+        self.fuzz_strategies.add_test_strategy(signature, strategy);
+        self
+    }
 
-        // the db backend that serves all the data
-        let db = runtime.block_on(Backend::new(self.fork.take(), &env));
+    /// Sets the prefixes of test functions, see [TestPrefixes]
+    #[must_use]
+    pub fn test_prefixes(mut self, prefixes: TestPrefixes) -> Self {
+        self.test_prefixes = prefixes;
+        self
+    }
 
-        let results = self
-            .contracts
-            .par_iter()
-            .filter(|(id, _)| {
-                filter.matches_path(id.source.to_string_lossy()) &&
-                    filter.matches_contract(&id.name)
-            })
-            .filter(|(_, (abi, _, _))| abi.functions().any(|func| filter.matches_test(&func.name)))
-            .map(|(id, (abi, deploy_code, libs))| {
-                let mut builder = ExecutorBuilder::new()
-                    .with_cheatcodes(self.evm_opts.ffi)
-                    .with_config(env.clone())
-                    .with_spec(self.evm_spec)
-                    .with_gas_limit(self.evm_opts.gas_limit());
+    /// Sets the kinds of tests to run, see [TestSelection]
+    #[must_use]
+    pub fn test_selection(mut self, selection: TestSelection) -> Self {
+        self.test_selection = selection;
+        self
+    }
 
-                if self.evm_opts.verbosity >= 3 {
-                    builder = builder.with_tracing();
-                }
+    /// Stops the entire run after the first failing test, skipping all remaining tests
+    #[must_use]
+    pub fn fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
 
-                let executor = builder.build(db.clone());
-                let result = self.run_tests(
-                    &id.identifier(),
-                    abi,
-                    executor,
-                    deploy_code.clone(),
-                    libs,
-                    (filter, include_fuzz_tests),
-                )?;
-                Ok((id.identifier(), result))
-            })
-            .filter_map(Result::<_>::ok)
-            .filter(|(_, results)| !results.is_empty())
-            .map_with(stream_result, |stream_result, (name, result)| {
-                if let Some(stream_result) = stream_result.as_ref() {
-                    stream_result.send((name.clone(), result.clone())).unwrap();
-                }
-                (name, result)
-            })
-            .collect::<BTreeMap<_, _>>();
-        Ok(results)
+    /// Stops launching further test contracts once `interrupt` is raised, the contracts already
+    /// running are run to completion
+    #[must_use]
+    pub fn with_interrupt(mut self, interrupt: Arc<AtomicBool>) -> Self {
+        self.interrupt = Some(interrupt);
+        self
     }
 
-    // The _name field is unused because we only want it for tracing
-    #[tracing::instrument(
-        name = "contract",
-        skip_all,
-        err,
-        fields(name = %_name)
-    )]
-    fn run_tests<DB: DatabaseRef + Send + Sync>(
-        &self,
-        _name: &str,
-        contract: &Abi,
-        executor: Executor<DB>,
-        deploy_code: Bytes,
-        libs: &[Bytes],
-        (filter, include_fuzz_tests): (&impl TestFilter, bool),
-    ) -> Result<SuiteResult> {
-        let mut runner = ContractRunner::new(
-            executor,
-            contract,
-            deploy_code,
-            self.evm_opts.initial_balance,
-            self.sender,
-            self.errors.as_ref(),
-            libs,
-        );
-        runner.run_tests(filter, self.fuzzer.clone(), include_fuzz_tests)
+    /// Stops the entire run after the given number of failing tests, skipping all remaining tests
+    #[must_use]
+    pub fn bail(mut self, bail: Option<usize>) -> Self {
+        self.bail = bail;
+        self
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        decode::decode_console_logs,
-        test_helpers::{filter::Filter, COMPILED, EVM_OPTS, PROJECT},
-    };
-    use foundry_evm::trace::TraceKind;
+    /// Randomizes the order in which contracts and their tests are run, using the given seed.
+    ///
+    /// A shuffled run runs one test at a time, so that the tests actually run in that order.
+    #[must_use]
+    pub fn shuffle(mut self, seed: Option<u64>) -> Self {
+        self.shuffle_seed = seed;
+        self
+    }
 
-    /// Builds a base runner
-    fn base_runner() -> MultiContractRunnerBuilder {
-        MultiContractRunnerBuilder::default().sender(EVM_OPTS.sender)
+    /// If set, calls to contracts that were not deployed in the test revert, unless they are in
+    /// the allowlist
+    #[must_use]
+    pub fn external_call_allowlist(mut self, allowlist: Option<BTreeSet<Address>>) -> Self {
+        self.external_call_allowlist = allowlist;
+        self
     }
 
-    /// Builds a non-tracing runner
-    fn runner() -> MultiContractRunner {
-        base_runner().build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone()).unwrap()
+    /// Records the storage changes of unit tests for the given addresses, or for the test contract
+    /// if empty. The storage layout must be part of the compiler output to decode the changes.
+    #[must_use]
+    pub fn storage_diff(mut self, addresses: Option<Vec<Address>>) -> Self {
+        self.storage_diff = addresses;
+        self
     }
 
-    /// Builds a tracing runner
-    fn tracing_runner() -> MultiContractRunner {
-        let mut opts = EVM_OPTS.clone();
-        opts.verbosity = 5;
-        base_runner().build(&(*PROJECT).paths.root, (*COMPILED).clone(), opts).unwrap()
+    /// Restricts the run to the given tests, by suite identifier (`<path>:<contract>`, or
+    /// `<path>:<contract>[<variant>]` for the [variants](crate::variants) of a test contract) and
+    /// test signature. Tests that are not matched by the filter are still skipped.
+    #[must_use]
+    pub fn only_tests(mut self, tests: Option<BTreeMap<String, BTreeSet<String>>>) -> Self {
+        self.only_tests = tests;
+        self
     }
 
-    /// A helper to assert the outcome of multiple tests with helpful assert messages
-    fn assert_multiple(
-        actuals: &BTreeMap<String, SuiteResult>,
-        expecteds: BTreeMap<&str, Vec<(&str, bool, Option<String>, Option<Vec<String>>)>>,
-    ) {
-        assert_eq!(
-            actuals.len(),
-            expecteds.len(),
+    /// Restricts the run to the tests that used more gas than the threshold in a previous run.
+    /// Combined with [Self::only_tests()], only the tests that are in both sets are run.
+    #[must_use]
+    pub fn only_expensive(mut self, threshold: Option<GasThreshold>) -> Self {
+        self.gas_threshold = threshold;
+        self
+    }
+
+    /// Records the names of the cheatcodes invoked by each test (or its setup), see
+    /// [TestResult::used_cheatcodes](crate::TestResult)
+    #[must_use]
+    pub fn record_cheatcodes(mut self, record: bool) -> Self {
+        self.record_cheatcodes = record;
+        self
+    }
+
+    /// Records the maximum call depth reached by each test, see
+    /// [TestResult::max_call_depth](crate::TestResult)
+    #[must_use]
+    pub fn record_call_depth(mut self, record: bool) -> Self {
+        self.record_call_depth = record;
+        self
+    }
+
+    /// Counts the assertions executed by each test, see
+    /// [TestResult::assertions](crate::TestResult) and [assertions](crate::assertions)
+    #[must_use]
+    pub fn record_assertions(mut self, record: bool) -> Self {
+        self.record_assertions = record;
+        self
+    }
+
+    /// Records the gas refunded to each test, e.g. for clearing storage, see
+    /// [TestResult::gas_refund](crate::TestResult). Refunds of reverted calls are void, so they
+    /// are not reported.
+    #[must_use]
+    pub fn record_gas_refunds(mut self, record: bool) -> Self {
+        self.record_gas_refunds = record;
+        self
+    }
+
+    /// Counts how often each test executed the source lines of the contracts of the project, see
+    /// [TestResult::line_hits](crate::TestResult) and
+    /// [LineCoverage](crate::coverage::LineCoverage).
+    ///
+    /// The lines are mapped to the instructions of the runtime code of the contracts with the
+    /// source maps, the test contracts themselves and their `setUp` are not counted.
+    #[must_use]
+    pub fn record_line_coverage(mut self, record: bool) -> Self {
+        self.record_line_coverage = record;
+        self
+    }
+
+    /// Checks the size of the runtime code of the test contracts.
+    ///
+    /// The setup of test contracts whose runtime code is larger than `limit` bytes fails, the
+    /// sizes are reported as [SuiteResult::code_size](crate::SuiteResult). Contracts larger than
+    /// [CODE_SIZE_LIMIT](crate::CODE_SIZE_LIMIT) can not be deployed regardless of the limit.
+    #[must_use]
+    pub fn code_size_limit(mut self, limit: Option<usize>) -> Self {
+        self.code_size_limit = limit;
+        self
+    }
+
+    /// Deploys the test contracts with `CREATE2` and the given salt instead of `CREATE`.
+    ///
+    /// The address of a test contract then only depends on the sender and its bytecode, see
+    /// [MultiContractRunner::deploy_address()], and is reported as
+    /// [SuiteResult::address](crate::SuiteResult). Unlike with `CREATE`, the address does not
+    /// depend on the nonce of the sender, so it does not change with the number of libraries
+    /// that are deployed first. The libraries and any contracts created by the test contract are
+    /// still deployed with `CREATE`, their addresses depend on the nonce of their deployer as
+    /// usual.
+    #[must_use]
+    pub fn deploy_salt(mut self, salt: Option<H256>) -> Self {
+        self.deploy_salt = salt;
+        self
+    }
+
+    /// Fails unit tests in which a contract other than the test contract executed `SELFDESTRUCT`,
+    /// e.g. to catch accidental self-destruct paths of contracts under test.
+    ///
+    /// The reason of the failure names the address of the contract and the call depth of the
+    /// call frame. The execution is traced to find these call frames. Fuzz tests are not checked.
+    #[must_use]
+    pub fn no_selfdestruct(mut self, enabled: bool) -> Self {
+        self.no_selfdestruct = enabled;
+        self
+    }
+
+    /// Fails fuzz tests that took less than `percent` of the branches of the functions they ran,
+    /// see [branches](crate::branches).
+    ///
+    /// The coverage and the branches that were never taken are reported as
+    /// [TestResult::branch_coverage](crate::TestResult). Only contracts of the project count, the
+    /// test contracts themselves do not.
+    #[must_use]
+    pub fn fuzz_min_coverage(mut self, percent: Option<f64>) -> Self {
+        self.fuzz_min_coverage = percent;
+        self
+    }
+
+    /// Reduces the runs of fuzz tests as the run progresses, so that all tests finish within
+    /// `deadline` instead of being cut off, see [deadline](crate::deadline).
+    ///
+    /// Fuzz tests that made fewer runs than configured report the configured runs as
+    /// [TestResult::configured_runs](crate::TestResult).
+    #[must_use]
+    pub fn deadline(mut self, deadline: Option<Duration>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Retries a failed `setUp` up to `retries` times before the setup of the test contract
+    /// fails, e.g. if `setUp` intermittently fails because a fork is flaky.
+    ///
+    /// Every attempt runs on the state right after the deployment of the test contract, the number
+    /// of attempts is reported as [SuiteResult::setup_attempts](crate::SuiteResult).
+    #[must_use]
+    pub fn setup_retries(mut self, retries: u32) -> Self {
+        self.setup_retries = retries;
+        self
+    }
+
+    /// Only deploys the matched test contracts and runs their `setUp`, without running any tests.
+    ///
+    /// Every contract is reported as a single `setUp()` result.
+    #[must_use]
+    pub fn setup_only(mut self, setup_only: bool) -> Self {
+        self.setup_only = setup_only;
+        self
+    }
+
+    /// Sets the block base fee, this takes precedence over the base fee of a fork
+    #[must_use]
+    pub fn with_base_fee(mut self, base_fee: U256) -> Self {
+        self.base_fee = Some(base_fee);
+        self
+    }
+
+    /// Sets the transaction gas price, this takes precedence over the gas price of a fork
+    #[must_use]
+    pub fn with_gas_price(mut self, gas_price: U256) -> Self {
+        self.gas_price = Some(gas_price);
+        self
+    }
+
+    /// Sets the chain id returned by `block.chainid`, this takes precedence over the chain id of a
+    /// fork
+    #[must_use]
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Sets the EVM environment the tests are run in instead of resolving it from the EVM options,
+    /// e.g. the environment of a recorded run.
+    ///
+    /// The base fee, gas price, chain id, coinbase and prevrandao overrides are not applied to it.
+    #[must_use]
+    pub fn with_env(mut self, env: Env) -> Self {
+        self.env = Some(env);
+        self
+    }
+
+    /// Sets the address returned by `block.coinbase`, this takes precedence over the coinbase of
+    /// the forked block
+    #[must_use]
+    pub fn with_coinbase(mut self, coinbase: Address) -> Self {
+        self.coinbase = Some(coinbase);
+        self
+    }
+
+    /// Sets the value returned by `block.prevrandao` (`block.difficulty` before the merge), this
+    /// takes precedence over the prevrandao of the forked block
+    #[must_use]
+    pub fn with_prevrandao(mut self, prevrandao: U256) -> Self {
+        self.prevrandao = Some(prevrandao);
+        self
+    }
+
+    /// Places the accounts of the fixtures into the state before the test contracts are deployed,
+    /// on top of the state of the fork if there is one
+    #[must_use]
+    pub fn with_fixtures(mut self, fixtures: Fixtures) -> Self {
+        self.fixtures = fixtures;
+        self
+    }
+
+    /// Answers calls of the tests to the addresses of the custom precompiles with their
+    /// [PrecompileHandler](foundry_evm::executor::inspector::PrecompileHandler)s, e.g. to test
+    /// contracts that call the precompiles of an L2.
+    ///
+    /// This only affects the EVM the tests run in. Results of tests are not cached while custom
+    /// precompiles are set, since the handlers can not be hashed.
+    #[must_use]
+    pub fn with_precompiles(mut self, precompiles: CustomPrecompiles) -> Self {
+        self.precompiles = precompiles;
+        self
+    }
+
+    /// Stubs the precompile at `address` with fixed outputs, e.g. `ecrecover` at `0x01`, so that
+    /// snapshots of the test output, like traces and logs, are stable across environments.
+    ///
+    /// This changes the semantics of the EVM: the precompile no longer computes its output, so
+    /// tests may pass that fail on any chain. Only use this for snapshot tests, never to check the
+    /// correctness of contracts. Stubs take precedence over custom precompiles at the same
+    /// address, see [with_precompiles()](Self::with_precompiles). `keccak256` is an opcode, not a
+    /// precompile, and can not be stubbed.
+    #[must_use]
+    pub fn with_precompile_stub(mut self, address: Address, stub: PrecompileStub) -> Self {
+        self.precompile_stubs.insert(address, stub);
+        self
+    }
+
+    /// Adds the ABIs of contracts that are not part of the project, e.g. of contracts on a forked
+    /// chain, to decode their reverts, events and calls in traces.
+    ///
+    /// The ABIs of the project take precedence: functions, events and errors of these ABIs are
+    /// only used if no project contract declares one with the same selector.
+    #[must_use]
+    pub fn with_extra_abis(mut self, abis: impl IntoIterator<Item = Abi>) -> Self {
+        self.extra_abis.extend(abis);
+        self
+    }
+
+    /// Sets `tx.origin` of the calls of the tests independently of the sender, e.g. to test
+    /// access control that depends on whether a call is initiated by a contract or an EOA.
+    ///
+    /// `msg.sender` of the top-level calls is still the sender, see
+    /// [TxOrigin](foundry_evm::executor::inspector::TxOrigin).
+    #[must_use]
+    pub fn with_tx_origin(mut self, origin: Address) -> Self {
+        self.tx_origin = Some(origin);
+        self
+    }
+
+    /// Makes these opcodes unavailable to the tests, e.g. `PUSH0` to check that contracts can be
+    /// deployed to chains that did not activate Shanghai yet.
+    ///
+    /// Tests that execute a disabled opcode fail with
+    /// `opcode <name> not available under this configuration`, see
+    /// [OpcodeGuard](foundry_evm::executor::inspector::OpcodeGuard).
+    #[must_use]
+    pub fn disabled_opcodes(mut self, opcodes: BTreeSet<u8>) -> Self {
+        self.disabled_opcodes = opcodes;
+        self
+    }
+
+    /// Applies `transform` to every compiled contract, including libraries, e.g. to instrument
+    /// the bytecode or to change the ABI of test contracts.
+    ///
+    /// The transform runs in [build()](Self::build) after the artifacts are read, and after the
+    /// storage layouts, source maps and deployed bytecode were taken from them, but before the
+    /// contracts are linked: the bytecode still contains the placeholders of the libraries, which
+    /// must be kept at the offsets of the link references. Everything derived from the linked
+    /// contracts, i.e. the deployed test contracts and the contracts known to the trace decoder,
+    /// sees the transformed contracts.
+    #[must_use]
+    pub fn with_artifact_transform(
+        mut self,
+        transform: impl Fn(&ArtifactId, &mut CompactContractBytecode) + Send + Sync + 'static,
+    ) -> Self {
+        self.artifact_transform = Some(ArtifactTransform::new(transform));
+        self
+    }
+
+    #[must_use]
+    pub fn evm_spec(mut self, spec: SpecId) -> Self {
+        self.evm_spec = Some(spec);
+        self
+    }
+
+    #[must_use]
+    pub fn with_fork(mut self, fork: Option<Fork>) -> Self {
+        self.fork = fork;
+        self
+    }
+
+    /// Runs the tests against each of these blocks of the fork, instead of a single block.
+    ///
+    /// The results of every block are reported separately, as `<path>:<contract>@<block>`.
+    /// Contracts that declare their own fork run once against their fork. Each block is forked
+    /// with a backend of its own that fetches the state of that block, so the number of RPC calls
+    /// grows with the number of blocks, and the state is not cached on disk. Results of previous
+    /// runs are not reused.
+    ///
+    /// This has no effect without a fork.
+    #[must_use]
+    pub fn fork_blocks(mut self, blocks: Vec<u64>) -> Self {
+        self.fork_blocks = blocks;
+        self
+    }
+
+    /// Sets the directory of artifacts to read the deployed bytecode from, for contracts whose
+    /// compiler output lacks it.
+    ///
+    /// The artifacts are expected at `<dir>/<source file name>/<contract name>.json`.
+    #[must_use]
+    pub fn with_deployed_bytecode_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.deployed_bytecode_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the memory limit of the EVM in bytes, see [ExecutorBuilder::with_memory_limit()]
+    #[must_use]
+    pub fn with_memory_limit(mut self, memory_limit: u64) -> Self {
+        self.memory_limit = Some(memory_limit);
+        self
+    }
+
+    /// Sets how addresses and bytes are printed in Hardhat-style logs, see
+    /// [ExecutorBuilder::with_output_encoding()]
+    #[must_use]
+    pub fn with_output_encoding(mut self, encoding: OutputEncoding) -> Self {
+        self.output_encoding = encoding;
+        self
+    }
+}
+
+/// Returns the identifiers of the suites of the test contract, `<path>:<contract>[<variant>]` for
+/// each of its variants or `<path>:<contract>` if it has none
+fn suite_names(id: &ArtifactId, variants: Option<&Vec<SuiteVariant>>) -> Vec<String> {
+    match variants {
+        Some(variants) => {
+            variants.iter().map(|variant| variant.suite_name(&id.identifier())).collect()
+        }
+        None => vec![id.identifier()],
+    }
+}
+
+/// Returns `true` if the contract has non-empty deployed bytecode
+fn has_deployed_bytecode(contract: &CompactContractBytecode) -> bool {
+    contract
+        .deployed_bytecode
+        .as_ref()
+        .and_then(|d| d.bytecode.as_ref())
+        .map(|b| match b.object {
+            BytecodeObject::Bytecode(ref bytes) => !bytes.is_empty(),
+            BytecodeObject::Unlinked(ref code) => !code.is_empty(),
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the storage layout of the artifact, if it was requested from the compiler
+fn read_storage_layout(artifact: &impl Serialize) -> Option<StorageLayout> {
+    let mut artifact = serde_json::to_value(artifact).ok()?;
+    serde_json::from_value(artifact.get_mut("storageLayout")?.take()).ok()
+}
+
+/// Reads the deployed bytecode of the contract from its artifact in `dir`, if any
+fn read_deployed_bytecode(dir: &Path, id: &ArtifactId) -> Option<CompactDeployedBytecode> {
+    let file = id.source.file_name()?;
+    let path = dir.join(file).join(format!("{}.json", id.name));
+    let content = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str::<CompactContractBytecode>(&content) {
+        Ok(artifact) => artifact.deployed_bytecode,
+        Err(err) => {
+            tracing::warn!(?path, ?err, "could not parse artifact");
+            None
+        }
+    }
+}
+
+/// A multi contract runner receives a set of contracts deployed in an EVM instance and proceeds
+/// to run all test functions in these contracts.
+pub struct MultiContractRunner {
+    /// Mapping of contract name to Abi, creation bytecode and library bytecode which
+    /// needs to be deployed & linked against
+    pub contracts: DeployableContracts,
+    /// Compiled contracts by name that have an Abi and runtime bytecode
+    pub known_contracts: BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
+    /// The EVM instance used in the test runner
+    pub evm_opts: EvmOpts,
+    /// The EVM spec
+    pub evm_spec: SpecId,
+    /// All known errors, used for decoding reverts
+    pub errors: Option<Abi>,
+    /// All known events by signature, used for decoding `vm.expectEmit` failures
+    pub events: Arc<BTreeMap<H256, Event>>,
+    /// The parameter names of all known functions and events, including the fields of structs
+    pub abi_names: Arc<AbiNames>,
+    /// ABIs of contracts that are not part of the project, see
+    /// [MultiContractRunnerBuilder::with_extra_abis()]
+    pub extra_abis: Vec<Abi>,
+    /// The fuzzer which will be used to run parametric tests (w/ non-0 solidity args)
+    fuzzer: Option<TestRunner>,
+    /// The address which will be used as the `from` field in all EVM calls
+    sender: Option<Address>,
+    /// A map of contract names to absolute source file paths
+    pub source_paths: BTreeMap<String, String>,
+    /// The fork config
+    pub fork: Option<Fork>,
+    /// The fork blocks to run the tests against, see [MultiContractRunnerBuilder::fork_blocks()]
+    pub fork_blocks: Vec<u64>,
+    /// The memory limit of the EVM in bytes, if any
+    pub memory_limit: Option<u64>,
+    /// The weights of the input strategies of fuzz tests
+    fuzz_weights: FuzzWeights,
+    /// The percentage of random `address` inputs of fuzz tests drawn from the known addresses
+    fuzz_address_pool_rate: u32,
+    /// Custom strategies for the inputs of fuzz tests
+    fuzz_strategies: CustomStrategies,
+    /// The fork configurations declared by test contracts, these take precedence over `fork`
+    pub contract_forks: BTreeMap<ArtifactId, ContractFork>,
+    /// The names of the functions of test contracts marked as callbacks, which are not tests, see
+    /// [callbacks](crate::callbacks)
+    pub callbacks: BTreeMap<ArtifactId, BTreeSet<String>>,
+    /// The names of the functions the test contracts declare themselves instead of inheriting
+    /// them, for the contracts whose source could be read
+    pub declared_functions: BTreeMap<ArtifactId, BTreeSet<String>>,
+    /// The gas targets of the tests by function name, see [gas_targets](crate::gas_targets)
+    pub gas_targets: BTreeMap<ArtifactId, BTreeMap<String, GasTarget>>,
+    /// The variants of the test contracts whose constructor takes arguments, see
+    /// [variants](crate::variants)
+    pub variants: BTreeMap<ArtifactId, Vec<SuiteVariant>>,
+    /// The kinds of tests to run
+    pub test_selection: TestSelection,
+    /// The prefixes of test functions
+    pub test_prefixes: TestPrefixes,
+    /// Whether to stop the entire run after the first failing test
+    pub fail_fast: bool,
+    /// If set, the entire run is stopped after this many failing tests
+    pub bail: Option<usize>,
+    /// The seed used to shuffle the execution order of contracts and tests, if any
+    pub shuffle_seed: Option<u64>,
+    /// If set, calls to contracts that were not deployed in the test and are not in this set
+    /// revert
+    pub external_call_allowlist: Option<BTreeSet<Address>>,
+    /// The block base fee, overrides the base fee of the environment or fork
+    pub base_fee: Option<U256>,
+    /// The transaction gas price, overrides the gas price of the environment or fork
+    pub gas_price: Option<U256>,
+    /// The chain id, overrides the chain id of the environment or fork
+    pub chain_id: Option<u64>,
+    /// The block coinbase, overrides the coinbase of the environment or fork
+    pub coinbase: Option<Address>,
+    /// The `tx.origin` of the calls of the tests, the sender if not set
+    pub tx_origin: Option<Address>,
+    /// The block prevrandao, overrides the difficulty or prevrandao of the environment or fork
+    pub prevrandao: Option<U256>,
+    /// If set, the storage changes of unit tests are recorded for these addresses, or for the
+    /// test contract if empty
+    pub storage_diff: Option<Vec<Address>>,
+    /// The storage layouts of the compiled contracts, if storage diffs are enabled
+    pub storage_layouts: BTreeMap<ArtifactId, StorageLayout>,
+    /// If set, only these tests are run, by suite identifier and test signature
+    pub only_tests: Option<BTreeMap<String, BTreeSet<String>>>,
+    /// If set, only the tests that used more gas than the threshold in a previous run are run
+    pub gas_threshold: Option<GasThreshold>,
+    /// Whether to record the names of the cheatcodes invoked by each test
+    pub record_cheatcodes: bool,
+    /// Whether to record the maximum call depth reached by each test
+    pub record_call_depth: bool,
+    /// Whether to count the assertions executed by each test
+    pub record_assertions: bool,
+    /// The program counters of the jumps into assertion functions in the runtime code of the test
+    /// contracts, if assertions are counted
+    pub assertion_jumps: BTreeMap<ArtifactId, BTreeSet<usize>>,
+    /// Whether to record the gas refunded to each test
+    pub record_gas_refunds: bool,
+    /// Whether to count how often each test executed the source lines of the contracts
+    pub record_line_coverage: bool,
+    /// The source lines of the instructions in the runtime code of the contracts, if line
+    /// coverage is recorded
+    pub lines: BTreeMap<ArtifactId, ContractLines>,
+    /// Whether to only deploy the test contracts and run their `setUp`
+    pub setup_only: bool,
+    /// If set, the setup of test contracts whose runtime code is larger fails
+    pub code_size_limit: Option<usize>,
+    /// If set, the test contracts are deployed with `CREATE2` and this salt
+    pub deploy_salt: Option<H256>,
+    /// How often a failed `setUp` is retried
+    pub setup_retries: u32,
+    /// Whether unit tests fail if a contract other than the test contract executed `SELFDESTRUCT`
+    pub no_selfdestruct: bool,
+    /// If set, fuzz tests whose branch coverage is lower fail, in percent
+    pub fuzz_min_coverage: Option<f64>,
+    /// The conditional jumps in the runtime code of the contracts, if the branch coverage of fuzz
+    /// tests is recorded
+    pub branches: BTreeMap<ArtifactId, ContractBranches>,
+    /// If set, the runs of fuzz tests are reduced so the tests finish within this duration
+    pub deadline: Option<Duration>,
+    /// The time left for the fuzz tests of the current run, if there is a deadline
+    fuzz_budget: Option<FuzzBudget>,
+    /// Accounts placed into the state before the test contracts are deployed
+    pub fixtures: Fixtures,
+    /// Custom precompiles available to the tests
+    pub precompiles: CustomPrecompiles,
+    /// Opcodes that are not available to the tests
+    pub disabled_opcodes: BTreeSet<u8>,
+    /// If set, no further test contracts are run once this is raised
+    pub interrupt: Option<Arc<AtomicBool>>,
+    /// Results of tests that are reused instead of running the tests, by contract identifier and
+    /// test signature, see [TestResultCache](crate::result_cache::TestResultCache)
+    pub cached_results: BTreeMap<String, BTreeMap<String, TestResult>>,
+    /// The EVM environment, resolved on first use
+    env: Option<Env>,
+    /// How addresses and bytes are printed in Hardhat-style logs
+    pub output_encoding: OutputEncoding,
+}
+
+impl MultiContractRunner {
+    /// Returns the signatures of the functions, errors and events of all known contracts by
+    /// selector, the same signatures traces are decoded with
+    pub fn selectors(&self) -> Selectors {
+        Selectors::from_abis(self.known_contracts.values().map(|(abi, _)| abi))
+    }
+
+    /// Returns the number of test functions that match the filter
+    pub fn count_filtered_tests(&self, filter: &(impl TestFilter + Send + Sync)) -> usize {
+        self.filtered_tests(filter).count()
+    }
+
+    /// Returns the tests that match the filter with the contract they belong to, without running
+    /// them, in the order of the contracts
+    pub fn filtered_tests<'a>(
+        &'a self,
+        filter: &'a (impl TestFilter + Send + Sync),
+    ) -> impl Iterator<Item = (&'a ArtifactId, &'a Function)> + 'a {
+        self.contracts
+            .iter()
+            .filter(|(id, _)| {
+                filter.matches_path(id.source.to_string_lossy()) &&
+                    filter.matches_contract(&id.name)
+            })
+            .flat_map(move |(id, (abi, _, _))| {
+                abi.functions()
+                    .filter(move |func| {
+                        self.test_prefixes.is_test(&func.name) &&
+                            filter.matches_test(func.signature()) &&
+                            self.test_selection.matches(func, true) &&
+                            self.suite_names(id)
+                                .iter()
+                                .any(|suite| self.is_only_test(suite, func)) &&
+                            !self.is_callback(id, func)
+                    })
+                    .map(move |func| (id, func))
+            })
+    }
+
+    /// Returns the signatures of the tests that match the filter in more than one contract, with
+    /// the contracts that declare them
+    ///
+    /// Tests that contracts inherit are left out, tests of a shared base contract are expected to
+    /// run once for every contract that inherits them.
+    pub fn duplicate_tests(
+        &self,
+        filter: &(impl TestFilter + Send + Sync),
+    ) -> BTreeMap<String, Vec<ArtifactId>> {
+        let mut duplicates = BTreeMap::<String, Vec<ArtifactId>>::new();
+        let declared = |id: &ArtifactId, func: &Function| {
+            self.declared_functions.get(id).map_or(true, |functions| functions.contains(&func.name))
+        };
+        for (id, func) in self.filtered_tests(filter).filter(|(id, func)| declared(*id, *func)) {
+            duplicates.entry(func.signature()).or_default().push(id.clone());
+        }
+        duplicates.retain(|_, ids| ids.len() > 1);
+        duplicates
+    }
+
+    /// Returns the tests the run is restricted to that do not exist (anymore), as
+    /// `<path>:<contract>::<signature>`
+    pub fn missing_tests(&self) -> Vec<String> {
+        let only_tests = match self.only_tests {
+            Some(ref tests) => tests,
+            None => return Vec::new(),
+        };
+        let existing = self
+            .contracts
+            .iter()
+            .flat_map(|(id, (abi, _, _))| {
+                let tests = abi.functions().map(|func| func.signature()).collect::<BTreeSet<_>>();
+                self.suite_names(id).into_iter().map(move |suite| (suite, tests.clone()))
+            })
+            .collect::<BTreeMap<String, BTreeSet<String>>>();
+        only_tests
+            .iter()
+            .flat_map(|(suite, tests)| {
+                let existing = existing.get(suite);
+                tests
+                    .iter()
+                    .filter(move |test| !existing.map(|e| e.contains(*test)).unwrap_or_default())
+                    .map(move |test| format!("{}::{}", suite, test))
+            })
+            .collect()
+    }
+
+    /// Returns the tests that match the filter but are not part of the previous run the gas
+    /// threshold was read from, as `<path>:<contract>::<signature>`, see
+    /// [MultiContractRunnerBuilder::only_expensive()]
+    pub fn tests_without_gas(&self, filter: &(impl TestFilter + Send + Sync)) -> Vec<String> {
+        let threshold = match self.gas_threshold {
+            Some(ref threshold) => threshold,
+            None => return Vec::new(),
+        };
+        self.contracts
+            .iter()
+            .filter(|(id, _)| {
+                filter.matches_path(id.source.to_string_lossy()) &&
+                    filter.matches_contract(&id.name)
+            })
+            .flat_map(|(id, (abi, _, _))| {
+                let suite = id.identifier();
+                abi.functions()
+                    .filter(|func| {
+                        self.test_prefixes.is_test(&func.name) &&
+                            filter.matches_test(func.signature()) &&
+                            !self.is_callback(id, func)
+                    })
+                    .map(|func| func.signature())
+                    .filter(|test| threshold.gas_used(&suite, test).is_none())
+                    .map(|test| format!("{}::{}", suite, test))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns `true` if the function is marked as a callback of the test contract, see
+    /// [callbacks](crate::callbacks)
+    fn is_callback(&self, id: &ArtifactId, func: &Function) -> bool {
+        self.callbacks.get(id).map(|callbacks| callbacks.contains(&func.name)).unwrap_or_default()
+    }
+
+    /// Returns `true` if the run is not restricted to certain tests or includes the test of the
+    /// suite
+    fn is_only_test(&self, suite: &str, func: &Function) -> bool {
+        self.only_tests
+            .as_ref()
+            .map(|tests| {
+                tests.get(suite).map(|t| t.contains(&func.signature())).unwrap_or_default()
+            })
+            .unwrap_or(true)
+    }
+
+    /// Returns the identifiers of the suites of the test contract, one per variant if it declares
+    /// [variants](crate::variants)
+    fn suite_names(&self, id: &ArtifactId) -> Vec<String> {
+        suite_names(id, self.variants.get(id))
+    }
+
+    /// Returns a hash of everything the results of each test contract depend on, by suite
+    /// identifier: its bytecode, which includes the code of all contracts it creates, the linked
+    /// libraries and the EVM and fuzzer options. Each [variant](crate::variants) of a test
+    /// contract is hashed with its constructor arguments.
+    ///
+    /// Contracts whose tests run against a fork are left out, since the forked state can change.
+    pub fn suite_hashes(&self) -> BTreeMap<String, H256> {
+        let mut options = serde_json::to_vec(&self.evm_opts).unwrap_or_default();
+        options.extend(
+            format!(
+                "{:?}",
+                (
+                    self.evm_spec,
+                    self.base_fee,
+                    self.gas_price,
+                    self.chain_id,
+                    self.coinbase,
+                    self.tx_origin,
+                    self.prevrandao,
+                    self.fuzz_weights,
+                    self.fuzz_address_pool_rate,
+                    self.fuzzer.as_ref().map(|fuzzer| fuzzer.config()),
+                    self.deploy_salt,
+                    &self.disabled_opcodes,
+                )
+            )
+            .bytes(),
+        );
+        options.extend(serde_json::to_vec(&self.fixtures).unwrap_or_default());
+        options.push(self.no_selfdestruct as u8);
+        options.extend(format!("{:?}", self.fuzz_min_coverage).bytes());
+
+        let hash = |deploy_code: &[u8], libs: &[Bytes]| {
+            let mut data = deploy_code.to_vec();
+            libs.iter().for_each(|lib| data.extend_from_slice(lib));
+            data.extend_from_slice(&options);
+            H256::from(keccak256(data))
+        };
+        self.contracts
+            .iter()
+            .filter(|(id, _)| self.fork.is_none() && !self.contract_forks.contains_key(*id))
+            .flat_map(|(id, (_, deploy_code, libs))| match self.variants.get(id) {
+                // the constructor arguments of a variant are part of its deployment code
+                Some(variants) => variants
+                    .iter()
+                    .map(|variant| {
+                        let deploy_code = [deploy_code.as_ref(), variant.args.as_ref()].concat();
+                        (variant.suite_name(&id.identifier()), hash(&deploy_code, libs))
+                    })
+                    .collect(),
+                None => vec![(id.identifier(), hash(deploy_code, libs))],
+            })
+            .collect()
+    }
+
+    /// Returns the cached results of the matched tests of the contract, if all of them are cached
+    fn cached_suite(
+        &self,
+        id: &ArtifactId,
+        suite: &str,
+        abi: &Abi,
+        filter: &impl TestFilter,
+        include_fuzz_tests: bool,
+    ) -> Option<SuiteResult> {
+        // cached results do not report the code size, the assertions, the gas refunds or the line
+        // coverage and may depend on custom precompiles
+        if self.setup_only ||
+            self.code_size_limit.is_some() ||
+            self.record_assertions ||
+            self.record_gas_refunds ||
+            self.record_line_coverage ||
+            !self.precompiles.is_empty()
+        {
+            return None
+        }
+        let cached = self.cached_results.get(suite)?;
+        let test_results = abi
+            .functions()
+            .filter(|func| {
+                self.test_prefixes.is_test(&func.name) &&
+                    filter.matches_test(func.signature()) &&
+                    self.test_selection.matches(func, include_fuzz_tests) &&
+                    self.is_only_test(suite, func) &&
+                    !self.is_callback(id, func)
+            })
+            .map(|func| {
+                let signature = func.signature();
+                cached.get(&signature).map(|result| (signature, result.clone()))
+            })
+            .collect::<Option<BTreeMap<_, _>>>()?;
+        Some(SuiteResult::new(Duration::ZERO, test_results).with_address(self.deploy_address(id)))
+    }
+
+    /// Returns the address the test contract is deployed at, if the test contracts are deployed
+    /// with `CREATE2`, see [MultiContractRunnerBuilder::deploy_salt()]
+    pub fn deploy_address(&self, id: &ArtifactId) -> Option<Address> {
+        let salt = self.deploy_salt?;
+        let (_, deploy_code, _) = self.contracts.get(id)?;
+        Some(get_create2_address(
+            self.sender.unwrap_or_default(),
+            salt.as_bytes().to_vec(),
+            deploy_code.clone(),
+        ))
+    }
+
+    /// Returns the EVM environment the tests are run in, with the overrides of the builder
+    /// applied.
+    ///
+    /// Contracts with their own fork configuration run in the environment of their fork instead.
+    pub fn evm_env(&mut self) -> &Env {
+        if self.env.is_none() {
+            let mut env = RuntimeOrHandle::new().block_on(self.evm_opts.evm_env());
+            self.override_env(&mut env);
+            self.env = Some(env);
+        }
+        self.env.as_ref().expect("env was resolved")
+    }
+
+    /// Returns the block the tests run against if they run against a fork, this must be called
+    /// before the tests are run.
+    ///
+    /// If no fork block number is configured, this is the latest block at the time the
+    /// environment is resolved. The fork is pinned to it for the whole run, so passing it as the
+    /// fork block number runs the tests against the same state again.
+    pub fn fork_block(&mut self) -> Option<ForkBlock> {
+        self.fork.as_ref()?;
+        let env = self.evm_env();
+        Some(ForkBlock { number: env.block.number.as_u64(), timestamp: env.block.timestamp })
+    }
+
+    /// Runs the matched tests, by test contract.
+    ///
+    /// If [fork blocks](MultiContractRunnerBuilder::fork_blocks()) are configured, the tests run
+    /// once against each block and the results are keyed by `<path>:<contract>@<block>`. The fork,
+    /// environment and cached results of the runner are restored afterwards, so it can run again.
+    pub fn test(
+        &mut self,
+        filter: &(impl TestFilter + Send + Sync),
+        stream_result: Option<Sender<(String, SuiteResult)>>,
+        include_fuzz_tests: bool,
+    ) -> Result<BTreeMap<String, SuiteResult>> {
+        // tests that run in parallel do not run in the shuffled order, a shuffled run goes
+        // through a single thread that takes the contracts and tests one after another
+        if self.shuffle_seed.is_some() && rayon::current_num_threads() > 1 {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build()?;
+            return pool.install(|| self.test(filter, stream_result, include_fuzz_tests))
+        }
+
+        self.fuzz_budget = self.deadline.filter(|_| include_fuzz_tests).map(|deadline| {
+            let fuzz_tests =
+                self.filtered_tests(filter).filter(|(_, func)| !func.inputs.is_empty()).count();
+            FuzzBudget::new(deadline, fuzz_tests * self.fork_blocks.len().max(1))
+        });
+
+        let fork = match self.fork.take() {
+            Some(fork) if !self.fork_blocks.is_empty() => fork,
+            fork => {
+                self.fork = fork;
+                return self.test_at(filter, stream_result, include_fuzz_tests, None)
+            }
+        };
+
+        // every block is run with an environment of its own, the cached results are not specific
+        // to a block
+        let fork_block_number = self.evm_opts.fork_block_number;
+        let env = self.env.take();
+        let cached_results = std::mem::take(&mut self.cached_results);
+        let results = self.test_fork_blocks(&fork, filter, stream_result, include_fuzz_tests);
+        self.evm_opts.fork_block_number = fork_block_number;
+        self.env = env;
+        self.cached_results = cached_results;
+        self.fork = Some(fork);
+        results
+    }
+
+    /// Runs the matched tests against each of the fork blocks of the `fork`, one after another
+    fn test_fork_blocks(
+        &mut self,
+        fork: &Fork,
+        filter: &(impl TestFilter + Send + Sync),
+        stream_result: Option<Sender<(String, SuiteResult)>>,
+        include_fuzz_tests: bool,
+    ) -> Result<BTreeMap<String, SuiteResult>> {
+        let mut results = BTreeMap::new();
+        for (idx, block) in self.fork_blocks.clone().into_iter().enumerate() {
+            self.evm_opts.fork_block_number = Some(block);
+            self.env = None;
+            self.fork = Some(Fork {
+                cache_path: None,
+                url: fork.url.clone(),
+                pin_block: Some(block),
+                chain_id: fork.chain_id,
+            });
+            results.extend(self.test_at(
+                filter,
+                stream_result.clone(),
+                include_fuzz_tests,
+                Some((block, idx == 0)),
+            )?);
+            if self.is_interrupted() {
+                break
+            }
+        }
+        Ok(results)
+    }
+
+    /// Runs the matched tests against a single block.
+    ///
+    /// `fork_block` is the block of the fork blocks this run is for and whether it is the first,
+    /// contracts with their own fork only run with the first.
+    fn test_at(
+        &mut self,
+        filter: &(impl TestFilter + Send + Sync),
+        stream_result: Option<Sender<(String, SuiteResult)>>,
+        include_fuzz_tests: bool,
+        fork_block: Option<(u64, bool)>,
+    ) -> Result<BTreeMap<String, SuiteResult>> {
+        let runtime = RuntimeOrHandle::new();
+        let env = self.evm_env().clone();
+
+        // the db backend that serves all the data, the fork is pinned to the block the environment
+        // was resolved at, so that all state is read from the same block even if "latest" moves
+        let fork = self.fork.take().map(|mut fork| {
+            fork.pin_block.get_or_insert(env.block.number.as_u64());
+            fork
+        });
+        let db = runtime.block_on(Backend::new(fork, &env));
+
+        // contracts that declare their own fork get a backend per fork, contracts that declare the
+        // same fork share the state fetched from it. Writes never reach the backend, every
+        // contract's executor keeps them in its own database on top of the backend
+        let mut fork_backends = BTreeMap::new();
+        let contract_backends = self
+            .contract_forks
+            .iter()
+            .filter(|_| fork_block.map_or(true, |(_, first)| first))
+            .filter(|(id, _)| {
+                filter.matches_path(id.source.to_string_lossy()) &&
+                    filter.matches_contract(&id.name)
+            })
+            .map(|(id, fork)| {
+                let backend =
+                    fork_backends.entry((fork.url.clone(), fork.block)).or_insert_with(|| {
+                        let mut evm_opts = self.evm_opts.clone();
+                        evm_opts.fork_url = Some(fork.url.clone());
+                        evm_opts.fork_block_number = fork.block;
+
+                        let mut env = runtime.block_on(evm_opts.evm_env());
+                        self.override_env(&mut env);
+                        let fork = Fork {
+                            cache_path: None,
+                            url: fork.url.clone(),
+                            pin_block: fork.block.or_else(|| Some(env.block.number.as_u64())),
+                            chain_id: evm_opts.get_chain_id(),
+                        };
+                        let db = runtime.block_on(Backend::new(Some(fork), &env));
+                        (env, db)
+                    });
+                (id.clone(), backend.clone())
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        // raised by the first failing test if fail-fast is enabled, or once `bail` tests failed
+        let failed =
+            (self.fail_fast || self.bail.is_some()).then(|| Arc::new(AtomicBool::new(false)));
+        let failures = self.bail.map(|_| Arc::new(AtomicUsize::new(0)));
+
+        let mut contracts = self.contracts.iter().collect::<Vec<_>>();
+        if let Some(seed) = self.shuffle_seed {
+            contracts.shuffle(&mut StdRng::seed_from_u64(seed));
+        }
+        // test contracts with variants run once per variant
+        let contracts = contracts
+            .into_iter()
+            .flat_map(|(id, contract)| match self.variants.get(id) {
+                Some(variants) => {
+                    variants.iter().map(|variant| (id, contract, Some(variant))).collect()
+                }
+                None => vec![(id, contract, None)],
+            })
+            .map(|(id, contract, variant)| {
+                let suite = match variant {
+                    Some(variant) => variant.suite_name(&id.identifier()),
+                    None => id.identifier(),
+                };
+                (id, contract, variant, suite)
+            })
+            .collect::<Vec<_>>();
+
+        let results = contracts
+            .into_par_iter()
+            .filter(|(id, _, _, _)| {
+                filter.matches_path(id.source.to_string_lossy()) &&
+                    filter.matches_contract(&id.name)
+            })
+            .filter(|(id, _, _, _)| {
+                // contracts with their own fork are not run against every block
+                fork_block
+                    .map_or(true, |(_, first)| first || !self.contract_forks.contains_key(*id))
+            })
+            .filter(|(id, (abi, _, _), _, suite)| {
+                abi.functions().any(|func| {
+                    filter.matches_test(&func.name) &&
+                        self.test_selection.matches(func, include_fuzz_tests) &&
+                        self.is_only_test(suite, func) &&
+                        !self.is_callback(id, func)
+                })
+            })
+            .filter(|_| {
+                failed.as_ref().map(|failed| !failed.load(Ordering::Relaxed)).unwrap_or(true) &&
+                    !self.is_interrupted()
+            })
+            .map(|(id, (abi, deploy_code, libs), variant, suite)| {
+                // The contract does not need to be deployed if all matched tests are cached, the
+                // deploy address of a variant depends on its arguments so variants are deployed
+                if variant.is_none() {
+                    let cached = self.cached_suite(id, &suite, abi, filter, include_fuzz_tests);
+                    if let Some(result) = cached {
+                        return Ok((suite, result))
+                    }
+                }
+
+                let (name, env, db) = match (contract_backends.get(id), fork_block) {
+                    (Some((env, db)), _) => (suite.clone(), env, db),
+                    (None, Some((block, _))) => (format!("{}@{}", suite, block), &env, &db),
+                    (None, None) => (suite.clone(), &env, &db),
+                };
+                let deploy_code = match variant {
+                    Some(variant) => [deploy_code.as_ref(), variant.args.as_ref()].concat().into(),
+                    None => deploy_code.clone(),
+                };
+                let mut builder = ExecutorBuilder::new()
+                    .with_cheatcodes(self.evm_opts.ffi)
+                    .with_config(env.clone())
+                    .with_spec(self.evm_spec)
+                    .with_gas_limit(self.evm_opts.gas_limit())
+                    .with_known_events(self.events.clone())
+                    .with_output_encoding(self.output_encoding);
+
+                // selfdestructs are found in the traces
+                if self.evm_opts.verbosity >= 3 || self.no_selfdestruct {
+                    builder = builder.with_tracing();
+                }
+
+                if self.record_cheatcodes {
+                    builder = builder.with_cheatcode_recording();
+                }
+
+                if self.record_call_depth {
+                    builder = builder.with_call_depth_recording();
+                }
+
+                if self.fuzz_min_coverage.is_some() {
+                    builder = builder.with_branch_recording();
+                }
+
+                if self.record_gas_refunds {
+                    builder = builder.with_gas_refund_recording();
+                }
+
+                if self.record_line_coverage {
+                    builder = builder.with_instruction_recording();
+                }
+
+                if let Some(memory_limit) = self.memory_limit {
+                    builder = builder.with_memory_limit(memory_limit);
+                }
+
+                if let Some(ref allowed) = self.external_call_allowlist {
+                    builder = builder.with_call_guard(allowed.clone());
+                }
+
+                if !self.precompiles.is_empty() {
+                    builder = builder.with_precompiles(self.precompiles.clone());
+                }
+
+                if !self.disabled_opcodes.is_empty() {
+                    builder = builder.with_disabled_opcodes(self.disabled_opcodes.clone());
+                }
+
+                if let Some(origin) = self.tx_origin {
+                    builder = builder.with_tx_origin(origin);
+                }
+
+                let mut executor = builder.build(db.clone());
+                self.fixtures.apply(&mut executor);
+                let result = self.run_tests(
+                    id,
+                    &suite,
+                    abi,
+                    executor,
+                    deploy_code,
+                    libs,
+                    (filter, include_fuzz_tests),
+                    (failed.clone(), failures.clone()),
+                )?;
+                Ok((name, result))
+            })
+            .filter_map(Result::<_>::ok)
+            .filter(|(_, results)| !results.is_empty())
+            .map_with(stream_result, |stream_result, (name, result)| {
+                if let Some(stream_result) = stream_result.as_ref() {
+                    stream_result.send((name.clone(), result.clone())).unwrap();
+                }
+                (name, result)
+            })
+            .collect::<BTreeMap<_, _>>();
+        Ok(results)
+    }
+
+    /// Returns `true` if the run was interrupted, see
+    /// [MultiContractRunnerBuilder::with_interrupt()]
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupt.as_ref().map(|flag| flag.load(Ordering::Relaxed)).unwrap_or_default()
+    }
+
+    /// Applies the configured base fee, gas price, chain id, coinbase and prevrandao to the
+    /// environment
+    fn override_env(&self, env: &mut Env) {
+        if let Some(base_fee) = self.base_fee {
+            env.block.basefee = base_fee;
+        }
+        if let Some(gas_price) = self.gas_price {
+            env.tx.gas_price = gas_price;
+        }
+        if let Some(chain_id) = self.chain_id {
+            env.cfg.chain_id = chain_id.into();
+            env.tx.chain_id = Some(chain_id);
+        }
+        if let Some(coinbase) = self.coinbase {
+            env.block.coinbase = coinbase;
+        }
+        if let Some(prevrandao) = self.prevrandao {
+            // the `DIFFICULTY` opcode returns the prevrandao after the merge
+            env.block.difficulty = prevrandao;
+        }
+    }
+
+    #[tracing::instrument(
+        name = "contract",
+        skip_all,
+        err,
+        fields(name = %suite)
+    )]
+    fn run_tests<DB: DatabaseRef + Clone + Send + Sync>(
+        &self,
+        id: &ArtifactId,
+        suite: &str,
+        contract: &Abi,
+        executor: Executor<DB>,
+        deploy_code: Bytes,
+        libs: &[Bytes],
+        (filter, include_fuzz_tests): (&impl TestFilter, bool),
+        (fail_fast, failures): (Option<Arc<AtomicBool>>, Option<Arc<AtomicUsize>>),
+    ) -> Result<SuiteResult> {
+        let mut runner = ContractRunner::new(
+            executor,
+            contract,
+            deploy_code,
+            self.evm_opts.initial_balance,
+            self.sender,
+            self.errors.as_ref(),
+            libs,
+        );
+        runner.fuzz_weights = self.fuzz_weights;
+        runner.fuzz_address_pool_rate = self.fuzz_address_pool_rate;
+        runner.fuzz_strategies = self.fuzz_strategies.clone();
+        runner.test_selection = self.test_selection;
+        runner.test_prefixes = self.test_prefixes.clone();
+        runner.fail_fast = fail_fast;
+        runner.failures = failures;
+        runner.max_failures = self.bail.unwrap_or(1);
+        runner.shuffle_seed = self.shuffle_seed;
+        runner.storage_diff = self.storage_diff.as_deref();
+        runner.storage_layout = self.storage_layouts.get(id);
+        runner.only_tests = self.only_tests.as_ref().and_then(|tests| tests.get(suite));
+        runner.callbacks = self.callbacks.get(id);
+        if !self.record_assertions && !self.record_gas_refunds && !self.record_line_coverage {
+            runner.cached_results = self.cached_results.get(suite);
+        }
+        runner.setup_only = self.setup_only;
+        runner.code_size_limit = self.code_size_limit;
+        runner.gas_stipend = self.evm_opts.gas_stipend;
+        runner.deploy_salt = self.deploy_salt;
+        runner.setup_retries = self.setup_retries;
+        runner.no_selfdestruct = self.no_selfdestruct;
+        if self.fuzz_min_coverage.is_some() {
+            runner.branches = Some(&self.branches);
+            runner.fuzz_min_coverage = self.fuzz_min_coverage;
+        }
+        runner.fuzz_budget = self.fuzz_budget.as_ref();
+        if self.record_line_coverage {
+            runner.lines = Some(&self.lines);
+        }
+        runner.assertion_jumps = self.assertion_jumps.get(id);
+        runner.abi_names = Some(self.abi_names.as_ref());
+        let mut result = runner.run_tests(filter, self.fuzzer.clone(), include_fuzz_tests)?;
+        if self.code_size_limit.is_some() && result.code_size.is_none() {
+            // the contract could not be deployed, report the size of its compiled runtime code
+            result.code_size = self.known_contracts.get(id).map(|(_, code)| code.len());
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        coverage::LineCoverage,
+        decode::decode_console_logs,
+        fixtures::Fixture,
+        gas_targets::check_gas_targets,
+        mutation::{contract_mutations, run_mutants, sample, tests_calling},
+        test_helpers::{filter::Filter, COMPILED, EVM_OPTS, PROJECT},
+        ReturnValue, TestKind, CODE_SIZE_LIMIT,
+    };
+    use ethers::abi::AbiParser;
+    use foundry_evm::{executor::inspector::parse_opcode, trace::TraceKind};
+
+    /// Builds a base runner
+    fn base_runner() -> MultiContractRunnerBuilder {
+        MultiContractRunnerBuilder::default().sender(EVM_OPTS.sender)
+    }
+
+    /// Builds a non-tracing runner
+    fn runner() -> MultiContractRunner {
+        base_runner().build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone()).unwrap()
+    }
+
+    /// Builds a tracing runner
+    fn tracing_runner() -> MultiContractRunner {
+        let mut opts = EVM_OPTS.clone();
+        opts.verbosity = 5;
+        base_runner().build(&(*PROJECT).paths.root, (*COMPILED).clone(), opts).unwrap()
+    }
+
+    /// A helper to assert the outcome of multiple tests with helpful assert messages
+    fn assert_multiple(
+        actuals: &BTreeMap<String, SuiteResult>,
+        expecteds: BTreeMap<&str, Vec<(&str, bool, Option<String>, Option<Vec<String>>)>>,
+    ) {
+        assert_eq!(
+            actuals.len(),
+            expecteds.len(),
             "We did not run as many contracts as we expected"
         );
-        for (contract_name, tests) in &expecteds {
-            assert_eq!(
-                actuals[*contract_name].len(),
-                expecteds[contract_name].len(),
-                "We did not run as many test functions as we expected for {}",
-                contract_name
-            );
-            for (test_name, should_pass, reason, expected_logs) in tests {
-                let logs =
-                    decode_console_logs(&actuals[*contract_name].test_results[*test_name].logs);
+        for (contract_name, tests) in &expecteds {
+            assert_eq!(
+                actuals[*contract_name].len(),
+                expecteds[contract_name].len(),
+                "We did not run as many test functions as we expected for {}",
+                contract_name
+            );
+            for (test_name, should_pass, reason, expected_logs) in tests {
+                let logs = decode_console_logs(
+                    &actuals[*contract_name].test_results[*test_name].logs,
+                    OutputEncoding::default(),
+                );
+
+                if *should_pass {
+                    assert!(
+                        actuals[*contract_name].test_results[*test_name].success,
+                        "Test {} did not pass as expected.\nReason: {:?}\nLogs:\n{}",
+                        test_name,
+                        actuals[*contract_name].test_results[*test_name].reason,
+                        logs.join("\n")
+                    );
+                } else {
+                    assert!(
+                        !actuals[*contract_name].test_results[*test_name].success,
+                        "Test {} did not fail as expected.\nLogs:\n{}",
+                        test_name,
+                        logs.join("\n")
+                    );
+                    assert_eq!(
+                        actuals[*contract_name].test_results[*test_name].reason, *reason,
+                        "Failure reason for test {} did not match what we expected.",
+                        test_name
+                    );
+                }
+
+                if let Some(expected_logs) = expected_logs {
+                    assert!(
+                        logs.iter().eq(expected_logs.iter()),
+                        "Logs did not match for test {}.\nExpected:\n{}\n\nGot:\n{}",
+                        test_name,
+                        logs.join("\n"),
+                        expected_logs.join("\n")
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_is_deterministic() {
+        // contracts are linked in parallel, which must not change the result
+        let (a, b) = (runner(), runner());
+        assert_eq!(a.contracts, b.contracts);
+        assert_eq!(a.known_contracts, b.known_contracts);
+        assert_eq!(a.events, b.events);
+        assert_eq!(a.errors, b.errors);
+    }
+
+    #[test]
+    fn test_core() {
+        let mut runner = runner();
+        let results = runner.test(&Filter::new(".*", ".*", ".*core"), None, true).unwrap();
+
+        assert_multiple(
+            &results,
+            BTreeMap::from([
+                (
+                    "core/FailingSetup.t.sol:FailingSetupTest",
+                    vec![(
+                        "setUp()",
+                        false,
+                        Some("Setup failed: setup failed predictably".to_string()),
+                        None,
+                    )],
+                ),
+                (
+                    "core/Reverting.t.sol:RevertingTest",
+                    vec![("testFailRevert()", true, None, None)],
+                ),
+                (
+                    "core/SetupConsistency.t.sol:SetupConsistencyCheck",
+                    vec![("testAdd()", true, None, None), ("testMultiply()", true, None, None)],
+                ),
+                (
+                    "core/DSStyle.t.sol:DSStyleTest",
+                    vec![("testFailingAssertions()", true, None, None)],
+                ),
+                (
+                    "core/DappToolsParity.t.sol:DappToolsParityTest",
+                    vec![
+                        ("testAddresses()", true, None, None),
+                        ("testEnvironment()", true, None, None),
+                    ],
+                ),
+                (
+                    "core/PaymentFailure.t.sol:PaymentFailureTest",
+                    vec![("testCantPay()", false, Some("Revert".to_string()), None)],
+                ),
+                (
+                    "core/LibraryLinking.t.sol:LibraryLinkingTest",
+                    vec![("testDirect()", true, None, None), ("testNested()", true, None, None)],
+                ),
+                ("core/Abstract.t.sol:AbstractTest", vec![("testSomething()", true, None, None)]),
+                ("core/Abstract.t.sol:MarkedTest", vec![("testInherited()", true, None, None)]),
+                (
+                    "core/SetUpTest.t.sol:SetUpTestTest",
+                    vec![
+                        ("testSetUpTestRunsBeforeTest()", true, None, None),
+                        ("testSetUpTestStateIsReset()", true, None, None),
+                    ],
+                ),
+                // `testOnDeposit(uint256)` is a callback
+                ("core/Callbacks.t.sol:CallbacksTest", vec![("testDeposit()", true, None, None)]),
+            ]),
+        );
+    }
+
+    #[test]
+    fn test_logs() {
+        let mut runner = runner();
+        let results = runner.test(&Filter::new(".*", ".*", ".*logs"), None, true).unwrap();
+
+        assert_multiple(
+            &results,
+            BTreeMap::from([
+                (
+                    "logs/DebugLogs.t.sol:DebugLogsTest",
+                    vec![
+                        ("test1()", true, None, Some(vec!["0".into(), "1".into(), "2".into()])),
+                        ("test2()", true, None, Some(vec!["0".into(), "1".into(), "3".into()])),
+                        (
+                            "testFailWithRequire()",
+                            true,
+                            None,
+                            Some(vec!["0".into(), "1".into(), "5".into()]),
+                        ),
+                        (
+                            "testFailWithRevert()",
+                            true,
+                            None,
+                            Some(vec!["0".into(), "1".into(), "4".into(), "100".into()]),
+                        ),
+                    ],
+                ),
+                (
+                    "logs/HardhatLogs.t.sol:HardhatLogsTest",
+                    vec![
+                        (
+                            "testInts()",
+                            true,
+                            None,
+                            Some(vec![
+                                "constructor".into(),
+                                "0".into(),
+                                "1".into(),
+                                "2".into(),
+                                "3".into(),
+                            ]),
+                        ),
+                        (
+                            "testMisc()",
+                            true,
+                            None,
+                            Some(vec![
+                                "constructor".into(),
+                                "testMisc, 0x0000000000000000000000000000000000000001".into(),
+                                "testMisc, 42".into(),
+                            ]),
+                        ),
+                        (
+                            "testStrings()",
+                            true,
+                            None,
+                            Some(vec!["constructor".into(), "testStrings".into()]),
+                        ),
+                    ],
+                ),
+            ]),
+        );
+    }
 
-                if *should_pass {
-                    assert!(
-                        actuals[*contract_name].test_results[*test_name].success,
+    #[test]
+    fn test_cheats() {
+        let mut runner = runner();
+        let suite_result = runner.test(&Filter::new(".*", ".*", ".*cheats"), None, true).unwrap();
+
+        for (_, SuiteResult { test_results, .. }) in suite_result {
+            for (test_name, result) in test_results {
+                let logs = decode_console_logs(&result.logs, OutputEncoding::default());
+                assert!(
+                    result.success,
+                    "Test {} did not pass as expected.\nReason: {:?}\nLogs:\n{}",
+                    test_name,
+                    result.reason,
+                    logs.join("\n")
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_skip() {
+        let mut runner = runner();
+        let results = runner.test(&Filter::new(".*", ".*", ".*cheats/Skip"), None, true).unwrap();
+        let results = &results["cheats/Skip.t.sol:SkipTest"].test_results;
+
+        for test in ["testSkipOffMainnet()", "testSkipFuzz(uint256)"] {
+            assert!(results[test].success && results[test].skipped, "{} was not skipped", test);
+            assert!(results[test].counterexample.is_none());
+        }
+        assert!(results["testNoSkip()"].success);
+        assert!(!results["testNoSkip()"].skipped);
+    }
+
+    #[test]
+    fn test_fuzz() {
+        let mut runner = runner();
+        let suite_result = runner.test(&Filter::new(".*", ".*", ".*fuzz"), None, true).unwrap();
+
+        for (_, SuiteResult { test_results, .. }) in suite_result {
+            for (test_name, result) in test_results {
+                let logs = decode_console_logs(&result.logs, OutputEncoding::default());
+
+                match test_name.as_ref() {
+                    "testPositive(uint256)" | "testSuccessfulFuzz(uint128,uint128)" => assert!(
+                        result.success,
                         "Test {} did not pass as expected.\nReason: {:?}\nLogs:\n{}",
                         test_name,
-                        actuals[*contract_name].test_results[*test_name].reason,
+                        result.reason,
                         logs.join("\n")
-                    );
-                } else {
-                    assert!(
-                        !actuals[*contract_name].test_results[*test_name].success,
-                        "Test {} did not fail as expected.\nLogs:\n{}",
+                    ),
+                    _ => assert!(
+                        !result.success,
+                        "Test {} did not fail as expected.\nReason: {:?}\nLogs:\n{}",
                         test_name,
+                        result.reason,
                         logs.join("\n")
-                    );
-                    assert_eq!(
-                        actuals[*contract_name].test_results[*test_name].reason, *reason,
-                        "Failure reason for test {} did not match what we expected.",
-                        test_name
-                    );
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fuzz_weights() {
+        let run = |weights| {
+            let mut runner = base_runner()
+                .fuzzer(TestRunner::default())
+                .fuzz_weights(weights)
+                .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+                .unwrap();
+            let filter = Filter::new("testNegative(0|Max)", ".*", ".*fuzz/FuzzNumbers");
+            let results = runner.test(&filter, None, true).unwrap();
+            let results = &results["fuzz/FuzzNumbers.t.sol:FuzzNumbersTest"].test_results;
+            (results["testNegative0(uint256)"].success, results["testNegativeMax(uint256)"].success)
+        };
+        let only = |zero, max| FuzzWeights { random: 0, state: 0, boundary: 0, zero, max };
+
+        // only zero inputs, or only maximum inputs
+        assert_eq!(run(only(1, 0)), (false, true));
+        assert_eq!(run(only(0, 1)), (true, false));
+        assert_eq!(run(only(1, 1)), (false, false));
+    }
+
+    #[test]
+    fn test_fuzz_fixtures() {
+        let mut runner = base_runner()
+            .fuzzer(TestRunner::default())
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results =
+            runner.test(&Filter::new(".*", ".*", ".*fuzz/FuzzFixtures"), None, true).unwrap();
+        let results = &results["fuzz/FuzzFixtures.t.sol:FuzzFixturesTest"].test_results;
+
+        // the failing inputs are only drawn from the fixtures
+        let counterexample = |test: &str| {
+            let result = &results[test];
+            assert!(!result.success, "{} did not fail", test);
+            result.counterexample.as_ref().unwrap().args[0].clone()
+        };
+        assert_eq!(counterexample("testFixtureAmount(uint256)"), Token::Uint(555.into()));
+        assert_eq!(counterexample("testFixtureSmall(uint8)"), Token::Uint(7.into()));
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut runner = base_runner()
+            .test_selection(TestSelection::OnlyUnit)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*fuzz"), None, true).unwrap();
+        assert!(results.values().all(|suite| suite.is_empty()));
+
+        let mut runner = base_runner()
+            .fuzzer(TestRunner::default())
+            .test_selection(TestSelection::OnlyFuzz)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*core"), None, true).unwrap();
+        assert!(results.values().all(|suite| suite.is_empty()));
+        let results = runner.test(&Filter::new(".*", ".*", ".*fuzz"), None, false).unwrap();
+        assert!(!results.is_empty());
+        assert!(results
+            .values()
+            .flat_map(|suite| suite.test_results.values())
+            .all(|result| result.is_fuzz()));
+    }
+
+    #[test]
+    fn test_custom_prefixes() {
+        let filter = Filter::new(".*", ".*", ".*core/TestPrefix");
+        let prefixes = TestPrefixes::new(vec!["check_".to_string(), "prove_".to_string()]);
+        let mut runner = base_runner()
+            .fuzzer(TestRunner::default())
+            .test_prefixes(prefixes)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        let results = &results["core/TestPrefix.t.sol:TestPrefixTest"].test_results;
+        assert_eq!(
+            results.keys().collect::<Vec<_>>(),
+            vec!["check_FailReverts()", "check_Passes()", "prove_Commutative(uint128,uint128)"]
+        );
+        assert!(results.values().all(|result| result.success));
+        assert!(results["prove_Commutative(uint128,uint128)"].is_fuzz());
+
+        // there are no tests with the default prefix
+        let results = runner().test(&filter, None, true).unwrap();
+        assert!(!results.contains_key("core/TestPrefix.t.sol:TestPrefixTest"));
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let filter = Filter::new(".*", ".*", ".*core");
+        let expected = runner().test(&filter, None, true).unwrap();
+
+        let mut runner = base_runner()
+            .shuffle(Some(42))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let results = runner.test(&filter, Some(tx), true).unwrap();
+        assert_eq!(results.keys().collect::<Vec<_>>(), expected.keys().collect::<Vec<_>>());
+        for (name, suite) in &results {
+            for (test, result) in &suite.test_results {
+                assert_eq!(
+                    result.success, expected[name].test_results[test].success,
+                    "{} in {} changed its outcome when shuffled",
+                    test, name
+                );
+            }
+        }
+
+        // the contracts finished in the order they were shuffled into
+        let mut order = runner.contracts.keys().map(|id| id.identifier()).collect::<Vec<_>>();
+        order.shuffle(&mut StdRng::seed_from_u64(42));
+        order.retain(|name| results.contains_key(name));
+        assert_eq!(rx.iter().map(|(name, _)| name).collect::<Vec<_>>(), order);
+    }
+
+    #[test]
+    fn test_base_fee_and_gas_price() {
+        let mut runner = base_runner()
+            .with_base_fee(10.into())
+            .with_gas_price(20.into())
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*env/BlockEnv"), None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "env/BlockEnv.t.sol:BlockEnvTest",
+                vec![("testBaseFee()", true, None, None), ("testGasPrice()", true, None, None)],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_chain_id() {
+        let mut runner = base_runner()
+            .with_chain_id(1337)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*env/ChainId"), None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "env/ChainId.t.sol:ChainIdTest",
+                vec![("testChainId()", true, None, None)],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_disabled_opcodes() {
+        let mut runner = base_runner()
+            .disabled_opcodes(BTreeSet::from([parse_opcode("CHAINID").unwrap()]))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let filter = Filter::new(".*", ".*", ".*env/DisabledOpcodes");
+        let results = runner.test(&filter, None, true).unwrap();
+        let reason = Some("opcode CHAINID not available under this configuration".to_string());
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "env/DisabledOpcodes.t.sol:DisabledOpcodesTest",
+                vec![
+                    ("testChainId()", false, reason.clone(), None),
+                    ("testChainIdOfContract()", false, reason, None),
+                    ("testNoChainId()", true, None, None),
+                ],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_selectors() {
+        let selectors = runner().selectors();
+        let failed = ethers::utils::id("failed()");
+        assert!(selectors.functions[&failed].contains("failed()"));
+        let error = ethers::utils::id("CustomError()");
+        assert_eq!(selectors.errors[&error], BTreeSet::from(["CustomError()".to_string()]));
+        let log = H256::from(keccak256("log_named_uint(string,uint256)"));
+        assert!(selectors.events[&log].contains("log_named_uint(string,uint256)"));
+    }
+
+    #[test]
+    fn test_duplicate_tests() {
+        let runner = runner();
+        let duplicates = runner.duplicate_tests(&Filter::new("testChainId", ".*", ".*env"));
+        let names =
+            duplicates["testChainId()"].iter().map(|id| id.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"ChainIdTest") && names.contains(&"DisabledOpcodesTest"));
+
+        let filter = Filter::new("testChainId", "ChainIdTest", ".*env");
+        assert!(runner.duplicate_tests(&filter).is_empty());
+
+        // tests inherited from a shared base are expected in every contract that inherits them
+        let filter = Filter::new(".*", ".*", ".*env/SharedBase");
+        assert_eq!(runner.filtered_tests(&filter).count(), 2);
+        assert!(runner.duplicate_tests(&filter).is_empty());
+    }
+
+    #[test]
+    fn test_extra_abis() {
+        let extra = AbiParser::default()
+            .parse(&[
+                "error CustomError()",
+                "error Unauthorized(address caller)",
+                "event Swap(address indexed sender, uint256 amount)",
+            ])
+            .unwrap();
+        let project_errors = runner().errors.unwrap();
+        let runner = base_runner()
+            .with_extra_abis([extra])
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+
+        // errors declared by the project are not added again
+        let errors = runner.errors.as_ref().unwrap();
+        assert_eq!(errors.errors["CustomError"], project_errors.errors["CustomError"]);
+        assert_eq!(errors.errors["Unauthorized"].len(), 1);
+        let swap = H256::from(keccak256("Swap(address,uint256)"));
+        assert_eq!(runner.events[&swap].name, "Swap");
+        assert_eq!(runner.extra_abis.len(), 1);
+    }
+
+    #[test]
+    fn test_artifact_transform() {
+        let transformed = Arc::new(AtomicUsize::new(0));
+        let counter = transformed.clone();
+        let mut runner = base_runner()
+            .with_artifact_transform(move |id, contract| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                if id.name == "RevertingTest" {
+                    if let Some(ref mut abi) = contract.abi {
+                        abi.functions.remove("testFailRevert");
+                    }
+                }
+            })
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        assert!(transformed.load(Ordering::SeqCst) > 0);
+
+        let filter = Filter::new(".*", ".*", ".*core/Reverting");
+        assert!(runner.test(&filter, None, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mutants() {
+        let mutable = Arc::new(std::sync::Mutex::new(BTreeMap::new()));
+        let recorded = mutable.clone();
+        let mut opts = EVM_OPTS.clone();
+        opts.verbosity = 3;
+        let mut runner = base_runner()
+            .with_artifact_transform(move |id, contract| {
+                if id.name == "Threshold" {
+                    recorded.lock().unwrap().insert(id.clone(), contract_mutations(contract));
                 }
+            })
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), opts)
+            .unwrap();
+        let filter = Filter::new(".*", ".*", ".*mutation/Threshold");
+        let results = runner.test(&filter, None, true).unwrap();
+        let tests = tests_calling(&results, &runner.known_contracts);
+        assert_eq!(
+            tests["mutation/Threshold.t.sol:Threshold"],
+            BTreeMap::from([(
+                "mutation/Threshold.t.sol:ThresholdTest".to_string(),
+                BTreeSet::from(["testExceeds()".to_string()])
+            )])
+        );
+
+        let mutants = mutable
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|(id, mutations)| mutations.iter().map(move |m| (id.clone(), *m)))
+            .collect::<Vec<_>>();
+        let run = |transform: ArtifactTransform, tests| {
+            base_runner()
+                .with_artifact_transform(move |id, contract| transform.apply(id, contract))
+                .only_tests(Some(tests))
+                .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())?
+                .test(&filter, None, true)
+        };
+        let mutants =
+            run_mutants(sample(mutants, 10), &runner.known_contracts, &tests, run).unwrap();
+        assert!(!mutants.is_empty());
+        let contract = "mutation/Threshold.t.sol:Threshold";
+        assert!(mutants.iter().all(|mutant| mutant.contract == contract));
+        assert!(mutants.iter().any(|mutant| {
+            mutant.killed_by == vec!["mutation/Threshold.t.sol:ThresholdTest::testExceeds()"]
+        }));
+    }
+
+    #[test]
+    fn test_gas_targets() {
+        let mut runner = runner();
+        let filter = Filter::new(".*", ".*", ".*gas/GasTargets");
+        let results = runner.test(&filter, None, true).unwrap();
+        let overages = check_gas_targets(&results, &runner.gas_targets);
+        assert_eq!(overages.len(), 1);
+        assert_eq!(overages[0].suite, "gas/GasTargets.t.sol:GasTargetsTest");
+        assert_eq!(overages[0].test, "testAboveTarget()");
+        assert_eq!(overages[0].target, GasTarget { gas: 100, tolerance: 0.0 });
+    }
+
+    #[test]
+    fn test_record_gas_refunds() {
+        let filter = Filter::new(".*", ".*", ".*env/GasRefund");
+        let results = runner().test(&filter, None, true).unwrap();
+        let results = &results["env/GasRefund.t.sol:GasRefundTest"].test_results;
+        assert_eq!(results["testClear()"].gas_refund, None);
+
+        let mut runner = base_runner()
+            .record_gas_refunds(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        let results = &results["env/GasRefund.t.sol:GasRefundTest"].test_results;
+        // clearing a storage slot is refunded 4800 gas since London
+        assert_eq!(results["testClear()"].gas_refund, Some(4800));
+        assert_eq!(results["testOverwrite()"].gas_refund, Some(0));
+    }
+
+    #[test]
+    fn test_out_of_gas_reason() {
+        let mut runner = runner();
+        let results = runner.test(&Filter::new(".*", ".*", ".*env/OutOfGas"), None, true).unwrap();
+        let results = &results["env/OutOfGas.t.sol:OutOfGasTest"].test_results;
+
+        let reason = results["testStarvedCall()"].reason.as_deref().unwrap();
+        assert!(reason.starts_with("out of gas (gas limit: 50000, gas used: "));
+        assert!(reason.contains(") in call to 0x"));
+        // reverts with a reason keep it, even if a call ran out of gas before
+        assert_eq!(results["testRevert()"].reason.as_deref(), Some("explicit revert"));
+        assert_eq!(results["testCaughtOutOfGas()"].reason.as_deref(), Some("after the call"));
+        // a caught call that ran out of gas is not the cause of a later empty revert
+        assert_eq!(results["testCaughtOutOfGasEmptyRevert()"].reason.as_deref(), Some("Revert"));
+    }
+
+    #[test]
+    fn test_gas_stipend() {
+        let filter = Filter::new(".*", ".*", ".*env/OutOfGas");
+        let results = runner().test(&filter, None, true).unwrap();
+        let results = &results["env/OutOfGas.t.sol:GasStipendTest"].test_results;
+        assert!(results["testWritesSlots()"].success);
+
+        // writing 10 storage slots takes more than 200k gas
+        let mut opts = EVM_OPTS.clone();
+        opts.gas_stipend = Some(100_000);
+        let mut runner =
+            base_runner().build(&(*PROJECT).paths.root, (*COMPILED).clone(), opts).unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        let result =
+            &results["env/OutOfGas.t.sol:GasStipendTest"].test_results["testWritesSlots()"];
+        assert!(!result.success);
+        assert!(result.reason.as_deref().unwrap().starts_with("out of gas (gas limit: "));
+    }
+
+    #[test]
+    fn test_record_line_coverage() {
+        let filter = Filter::new(".*", ".*", ".*env/LineCoverage");
+        let mut runner = base_runner()
+            .record_line_coverage(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let mut coverage = LineCoverage::new(runner.lines.values());
+        let results = runner.test(&filter, None, true).unwrap();
+        let results = &results["env/LineCoverage.t.sol:LineCoverageTest"].test_results;
+        let hits = results["testIncrement()"].line_hits.as_ref().unwrap();
+        coverage.record(hits);
+
+        let (path, lines) = hits
+            .iter()
+            .find(|(path, _)| path.ends_with("env/LineCoverage.t.sol"))
+            .expect("no lines of the tally were executed");
+        // `count += 1` in `increment`, the test contract itself is not counted
+        assert!(lines[&10] >= 2);
+        assert!(!lines.contains_key(&26));
+
+        let lcov = coverage.to_lcov();
+        assert!(lcov.contains(&format!("SF:{}\n", path.display())));
+        assert!(lcov.contains("DA:10,"));
+        // `count = 0` in `reset` was never executed
+        assert!(lcov.contains("DA:14,0\n"));
+        assert!(coverage.percentage() < 100.0);
+    }
+
+    #[test]
+    fn test_variants() {
+        let mut runner = runner();
+        let filter = Filter::new(".*", ".*", ".*env/Variants");
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_eq!(
+            results.keys().collect::<Vec<_>>(),
+            vec![
+                "env/Variants.t.sol:VariantsTest[large]",
+                "env/Variants.t.sol:VariantsTest[small]"
+            ]
+        );
+        let passed = |suite: &str| results[suite].test_results["testAmount()"].success;
+        assert!(!passed("env/Variants.t.sol:VariantsTest[large]"));
+        assert!(passed("env/Variants.t.sol:VariantsTest[small]"));
+
+        // every variant is hashed with its arguments
+        let hashes = runner.suite_hashes();
+        assert_ne!(
+            hashes["env/Variants.t.sol:VariantsTest[large]"],
+            hashes["env/Variants.t.sol:VariantsTest[small]"]
+        );
+
+        // the run can be restricted to the tests of a single variant
+        let only_tests = BTreeMap::from([(
+            "env/Variants.t.sol:VariantsTest[small]".to_string(),
+            BTreeSet::from(["testAmount()".to_string()]),
+        )]);
+        let mut runner = base_runner()
+            .only_tests(Some(only_tests))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_eq!(
+            results.keys().collect::<Vec<_>>(),
+            vec!["env/Variants.t.sol:VariantsTest[small]"]
+        );
+        assert!(runner.missing_tests().is_empty());
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let interrupt = Arc::new(AtomicBool::new(true));
+        let mut runner = base_runner()
+            .with_interrupt(interrupt.clone())
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let filter = Filter::new(".*", ".*", ".*core");
+        assert!(runner.test(&filter, None, true).unwrap().is_empty());
+
+        interrupt.store(false, Ordering::Relaxed);
+        assert!(!runner.test(&filter, None, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_coinbase() {
+        let mut runner = base_runner()
+            .with_coinbase(Address::from_low_u64_be(0xc014ba5e))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*env/Coinbase"), None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "env/Coinbase.t.sol:CoinbaseTest",
+                vec![("testCoinbase()", true, None, None)],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_tx_origin() {
+        let mut runner = base_runner()
+            .with_tx_origin(Address::from_low_u64_be(0x1337))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*env/TxOrigin"), None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "env/TxOrigin.t.sol:TxOriginTest",
+                vec![
+                    ("testTxOrigin()", true, None, None),
+                    ("testTxOriginOfNestedCalls()", true, None, None),
+                ],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_prevrandao() {
+        let mut runner = base_runner()
+            .with_prevrandao(U256::from(0x5eed))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let filter = Filter::new(".*", ".*", ".*env/Prevrandao");
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "env/Prevrandao.t.sol:PrevrandaoTest",
+                vec![("testPrevrandao()", true, None, None)],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_fixtures() {
+        // the runtime code returns the value of slot 0
+        let oracle = Fixture {
+            code: "0x60005460005260206000f3".parse().unwrap(),
+            storage: BTreeMap::from([(U256::zero(), U256::from(42))]),
+            balance: U256::from(1000),
+            ..Default::default()
+        };
+        let fixtures = Fixtures::new(BTreeMap::from([(Address::from_low_u64_be(0xf1f1), oracle)]));
+        let mut runner = base_runner()
+            .with_fixtures(fixtures)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*env/Fixtures"), None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "env/Fixtures.t.sol:FixturesTest",
+                vec![("testFixture()", true, None, None)],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_precompiles() {
+        let mut precompiles = CustomPrecompiles::default();
+        precompiles.register(Address::from_low_u64_be(0x100), |input, _| {
+            if input.len() != 32 {
+                return Err(Default::default())
+            }
+            let doubled = U256::from_big_endian(input) * 2;
+            let mut output = [0u8; 32];
+            doubled.to_big_endian(&mut output);
+            Ok((100, output.to_vec().into()))
+        });
+        let filter = Filter::new(".*", ".*", ".*env/Precompiles");
+        let mut runner = base_runner()
+            .with_precompiles(precompiles)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "env/Precompiles.t.sol:PrecompilesTest",
+                vec![
+                    ("testPrecompile()", true, None, None),
+                    ("testPrecompileRevert()", true, None, None),
+                ],
+            )]),
+        );
+
+        // without the precompile the call succeeds without output
+        let results = runner().test(&filter, None, true).unwrap();
+        let results = &results["env/Precompiles.t.sol:PrecompilesTest"].test_results;
+        assert!(!results["testPrecompile()"].success);
+    }
+
+    #[test]
+    fn test_precompile_stubs() {
+        let word = |value: u64| {
+            let mut word = [0u8; 32];
+            U256::from(value).to_big_endian(&mut word);
+            word.to_vec()
+        };
+        let filter = Filter::new(".*", ".*", ".*env/PrecompileStubs");
+        let mut runner = base_runner()
+            .with_precompile_stub(
+                Address::from_low_u64_be(1),
+                PrecompileStub { gas: 3000, ..PrecompileStub::constant(word(0x1234)) },
+            )
+            .with_precompile_stub(
+                Address::from_low_u64_be(2),
+                PrecompileStub::constant(word(0)).with_fixture(b"abc".to_vec(), word(1)),
+            )
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "env/PrecompileStubs.t.sol:PrecompileStubsTest",
+                vec![
+                    ("testStubbedEcrecover()", true, None, None),
+                    ("testStubbedSha256()", true, None, None),
+                ],
+            )]),
+        );
+
+        // the real precompiles compute their outputs
+        let results = runner().test(&filter, None, true).unwrap();
+        let results = &results["env/PrecompileStubs.t.sol:PrecompileStubsTest"].test_results;
+        assert!(!results["testStubbedEcrecover()"].success);
+        assert!(!results["testStubbedSha256()"].success);
+    }
 
-                if let Some(expected_logs) = expected_logs {
-                    assert!(
-                        logs.iter().eq(expected_logs.iter()),
-                        "Logs did not match for test {}.\nExpected:\n{}\n\nGot:\n{}",
-                        test_name,
-                        logs.join("\n"),
-                        expected_logs.join("\n")
-                    );
-                }
+    #[test]
+    fn test_no_selfdestruct() {
+        let filter = Filter::new(".*", ".*", ".*env/Selfdestruct");
+        let results = tracing_runner().test(&filter, None, true).unwrap();
+        let results = &results["env/Selfdestruct.t.sol:SelfdestructTest"].test_results;
+        assert!(results["testDestroy()"].success);
+        assert!(results["testDestroy()"].traces.iter().any(|(_, arena)| {
+            arena.arena.iter().any(|node| node.trace.selfdestruct.is_some())
+        }));
+
+        let mut runner = base_runner()
+            .no_selfdestruct(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        let results = &results["env/Selfdestruct.t.sol:SelfdestructTest"].test_results;
+        assert!(!results["testDestroy()"].success);
+        assert!(results["testDestroy()"]
+            .reason
+            .as_deref()
+            .unwrap_or_default()
+            .starts_with("SELFDESTRUCT in"));
+        assert!(results["testNoDestroy()"].success);
+    }
+
+    #[test]
+    fn test_fuzz_min_coverage() {
+        let filter = Filter::new(".*", ".*", ".*env/FuzzCoverage");
+        let run = |min| {
+            let mut runner = base_runner()
+                .fuzzer(TestRunner::default())
+                .fuzz_min_coverage(Some(min))
+                .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+                .unwrap();
+            let mut results = runner.test(&filter, None, true).unwrap();
+            let mut suite = results.remove("env/FuzzCoverage.t.sol:FuzzCoverageTest").unwrap();
+            suite.test_results.remove("testOpen(uint256)").unwrap()
+        };
+
+        // the fuzzer does not guess the code, so the lock is never opened
+        let result = run(0.0);
+        assert!(result.success);
+        let coverage = result.branch_coverage.unwrap();
+        assert!(coverage.hit > 0);
+        assert!(coverage.percentage() < 100.0);
+        assert!(coverage.uncovered.iter().any(|branch| {
+            branch.starts_with("Lock at ") && branch.contains("FuzzCoverage.t.sol:")
+        }));
+
+        let result = run(100.0);
+        assert!(!result.success);
+        assert!(result.reason.unwrap().starts_with("branch coverage of"));
+
+        let mut runner = base_runner()
+            .fuzzer(TestRunner::default())
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        let results = &results["env/FuzzCoverage.t.sol:FuzzCoverageTest"].test_results;
+        assert!(results["testOpen(uint256)"].branch_coverage.is_none());
+    }
+
+    #[test]
+    fn test_deadline() {
+        let filter = Filter::new(".*", ".*", ".*fuzz/FuzzNumbers");
+        let mut runner = base_runner()
+            .fuzzer(TestRunner::default())
+            .deadline(Some(Duration::ZERO))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        // the tests run one after another, so all fuzz tests but the first start after the
+        // deadline passed and make a single run
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let results = pool.install(|| runner.test(&filter, None, true)).unwrap();
+        let results = &results["fuzz/FuzzNumbers.t.sol:FuzzNumbersTest"].test_results;
+        let configured = TestRunner::default().config().cases;
+        let reduced =
+            results.values().filter(|result| result.configured_runs.is_some()).collect::<Vec<_>>();
+        assert_eq!(reduced.len(), results.len() - 1);
+        for result in reduced {
+            assert_eq!(result.configured_runs, Some(configured));
+            match result.kind {
+                TestKind::Fuzz(ref cases) => assert!(cases.cases().len() <= 1),
+                _ => panic!("expected a fuzz test"),
             }
         }
     }
 
     #[test]
-    fn test_core() {
-        let mut runner = runner();
-        let results = runner.test(&Filter::new(".*", ".*", ".*core"), None, true).unwrap();
+    fn test_setup_retries() {
+        // a precompile that fails the first two calls, the calls are not part of the state that
+        // is restored between attempts
+        let runner_with_retries = |retries| {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let mut precompiles = CustomPrecompiles::default();
+            precompiles.register(Address::from_low_u64_be(0x101), move |_, _| {
+                if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                    return Err(Default::default())
+                }
+                Ok((100, Default::default()))
+            });
+            base_runner()
+                .with_precompiles(precompiles)
+                .setup_retries(retries)
+                .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+                .unwrap()
+        };
+        let filter = Filter::new(".*", ".*", ".*env/FlakySetup");
+
+        let results = runner_with_retries(2).test(&filter, None, true).unwrap();
+        let suite = &results["env/FlakySetup.t.sol:FlakySetupTest"];
+        assert_eq!(suite.setup_attempts, 3);
+        assert!(suite.test_results["testSetupSucceeded()"].success);
+
+        let results = runner_with_retries(1).test(&filter, None, true).unwrap();
+        let suite = &results["env/FlakySetup.t.sol:FlakySetupTest"];
+        assert_eq!(suite.setup_attempts, 2);
+        assert!(!suite.test_results["setUp()"].success);
+    }
+
+    #[test]
+    fn test_setup_failure() {
+        let filter = Filter::new(".*", ".*", "core/(FailingSetup|SetupConsistency)");
+        let results = runner().test(&filter, None, true).unwrap();
+        assert_eq!(
+            results["core/FailingSetup.t.sol:FailingSetupTest"].setup_failure.as_deref(),
+            Some("setup failed predictably")
+        );
+        let suite = &results["core/SetupConsistency.t.sol:SetupConsistencyCheck"];
+        assert_eq!(suite.setup_failure, None);
+        assert!(suite.test_results.values().all(|result| result.success));
+    }
+
+    #[test]
+    fn test_only_tests() {
+        let only_tests = BTreeMap::from([
+            (
+                "core/SetupConsistency.t.sol:SetupConsistencyCheck".to_string(),
+                BTreeSet::from(["testAdd()".to_string(), "testRemoved()".to_string()]),
+            ),
+            ("core/Removed.t.sol:RemovedTest".to_string(), BTreeSet::from(["testA()".to_string()])),
+        ]);
+        let mut runner = base_runner()
+            .only_tests(Some(only_tests))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+
+        let filter = Filter::new(".*", ".*", ".*core");
+        assert_eq!(runner.count_filtered_tests(&filter), 1);
+        assert_eq!(
+            runner.missing_tests(),
+            vec![
+                "core/Removed.t.sol:RemovedTest::testA()",
+                "core/SetupConsistency.t.sol:SetupConsistencyCheck::testRemoved()"
+            ]
+        );
+
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "core/SetupConsistency.t.sol:SetupConsistencyCheck",
+                vec![("testAdd()", true, None, None)],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_only_expensive() {
+        let suite = "core/SetupConsistency.t.sol:SetupConsistencyCheck";
+        let threshold = GasThreshold {
+            gas: BTreeMap::from([(
+                suite.to_string(),
+                BTreeMap::from([
+                    ("testAdd()".to_string(), 50_000),
+                    ("testMultiply()".to_string(), 500),
+                ]),
+            )]),
+            threshold: 1_000,
+            include_new: false,
+        };
+        let filter = Filter::new(".*", ".*", ".*core/(SetupConsistency|Reverting)");
+        let mut runner = base_runner()
+            .only_expensive(Some(threshold.clone()))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        assert_eq!(
+            runner.tests_without_gas(&filter),
+            vec!["core/Reverting.t.sol:RevertingTest::testFailRevert()"]
+        );
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_multiple(&results, BTreeMap::from([(suite, vec![("testAdd()", true, None, None)])]));
+
+        // tests that are not part of the previous run are included on request
+        let runner = base_runner()
+            .only_expensive(Some(threshold.include_new(true)))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        assert_eq!(runner.count_filtered_tests(&filter), 2);
+    }
+
+    #[test]
+    fn test_count_filtered_tests() {
+        let runner = runner();
+        assert_eq!(runner.count_filtered_tests(&Filter::new(".*", ".*", ".*SetupConsistency")), 2);
+        assert_eq!(
+            runner.count_filtered_tests(&Filter::new("testAdd", ".*", ".*SetupConsistency")),
+            1
+        );
+    }
+
+    #[test]
+    fn test_deploy_salt() {
+        let filter = Filter::new(".*", ".*", "core/SetupConsistency");
+        let suite = "core/SetupConsistency.t.sol:SetupConsistencyCheck";
+        let mut runner = base_runner()
+            .deploy_salt(Some(H256::from_low_u64_be(1)))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let id = runner.contracts.keys().find(|id| id.identifier() == suite).unwrap().clone();
+        let address = runner.deploy_address(&id).unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_eq!(results[suite].address, Some(address));
+        assert!(results[suite].test_results.values().all(|result| result.success));
+
+        let mut runner = base_runner()
+            .deploy_salt(Some(H256::from_low_u64_be(2)))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_ne!(results[suite].address, Some(address));
+
+        let results = runner().test(&filter, None, true).unwrap();
+        assert_eq!(results[suite].address, None);
+    }
+
+    #[test]
+    fn test_code_size_limit() {
+        let filter = Filter::new(".*", ".*", "core/SetupConsistency");
+        let mut runner = base_runner()
+            .code_size_limit(Some(CODE_SIZE_LIMIT))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        let suite = &results["core/SetupConsistency.t.sol:SetupConsistencyCheck"];
+        let code_size = suite.code_size.unwrap();
+        assert!(code_size > 0 && code_size <= CODE_SIZE_LIMIT);
+        assert!(suite.test_results.values().all(|result| result.success));
+
+        let mut runner = base_runner()
+            .code_size_limit(Some(code_size - 1))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        let suite = &results["core/SetupConsistency.t.sol:SetupConsistencyCheck"];
+        assert_eq!(suite.code_size, Some(code_size));
+        assert_eq!(
+            suite.test_results["setUp()"].reason,
+            Some(format!(
+                "Setup failed: the runtime code is {} bytes, more than the code size limit of {} \
+                 bytes",
+                code_size,
+                code_size - 1
+            ))
+        );
 
+        let results = base_runner()
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap()
+            .test(&filter, None, true)
+            .unwrap();
+        assert_eq!(results["core/SetupConsistency.t.sol:SetupConsistencyCheck"].code_size, None);
+    }
+
+    #[test]
+    fn test_setup_only() {
+        let mut runner = base_runner()
+            .setup_only(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let filter = Filter::new(".*", ".*", "core/(FailingSetup|SetupConsistency)");
+        let results = runner.test(&filter, None, true).unwrap();
         assert_multiple(
             &results,
             BTreeMap::from([
@@ -367,148 +3230,168 @@ mod tests {
                         None,
                     )],
                 ),
-                (
-                    "core/Reverting.t.sol:RevertingTest",
-                    vec![("testFailRevert()", true, None, None)],
-                ),
                 (
                     "core/SetupConsistency.t.sol:SetupConsistencyCheck",
-                    vec![("testAdd()", true, None, None), ("testMultiply()", true, None, None)],
-                ),
-                (
-                    "core/DSStyle.t.sol:DSStyleTest",
-                    vec![("testFailingAssertions()", true, None, None)],
-                ),
-                (
-                    "core/DappToolsParity.t.sol:DappToolsParityTest",
-                    vec![
-                        ("testAddresses()", true, None, None),
-                        ("testEnvironment()", true, None, None),
-                    ],
-                ),
-                (
-                    "core/PaymentFailure.t.sol:PaymentFailureTest",
-                    vec![("testCantPay()", false, Some("Revert".to_string()), None)],
-                ),
-                (
-                    "core/LibraryLinking.t.sol:LibraryLinkingTest",
-                    vec![("testDirect()", true, None, None), ("testNested()", true, None, None)],
+                    vec![("setUp()", true, None, None)],
                 ),
-                ("core/Abstract.t.sol:AbstractTest", vec![("testSomething()", true, None, None)]),
             ]),
         );
     }
 
     #[test]
-    fn test_logs() {
-        let mut runner = runner();
-        let results = runner.test(&Filter::new(".*", ".*", ".*logs"), None, true).unwrap();
+    fn test_storage_diff() {
+        let mut runner = base_runner()
+            .storage_diff(Some(Vec::new()))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*storage"), None, true).unwrap();
+        let results = &results["storage/StorageDiff.t.sol:StorageDiffTest"].test_results;
 
-        assert_multiple(
-            &results,
-            BTreeMap::from([
-                (
-                    "logs/DebugLogs.t.sol:DebugLogsTest",
-                    vec![
-                        ("test1()", true, None, Some(vec!["0".into(), "1".into(), "2".into()])),
-                        ("test2()", true, None, Some(vec!["0".into(), "1".into(), "3".into()])),
-                        (
-                            "testFailWithRequire()",
-                            true,
-                            None,
-                            Some(vec!["0".into(), "1".into(), "5".into()]),
-                        ),
-                        (
-                            "testFailWithRevert()",
-                            true,
-                            None,
-                            Some(vec!["0".into(), "1".into(), "4".into(), "100".into()]),
-                        ),
-                    ],
-                ),
-                (
-                    "logs/HardhatLogs.t.sol:HardhatLogsTest",
-                    vec![
-                        (
-                            "testInts()",
-                            true,
-                            None,
-                            Some(vec![
-                                "constructor".into(),
-                                "0".into(),
-                                "1".into(),
-                                "2".into(),
-                                "3".into(),
-                            ]),
-                        ),
-                        (
-                            "testMisc()",
-                            true,
-                            None,
-                            Some(vec![
-                                "constructor".into(),
-                                "testMisc, 0x0000000000000000000000000000000000000001".into(),
-                                "testMisc, 42".into(),
-                            ]),
-                        ),
-                        (
-                            "testStrings()",
-                            true,
-                            None,
-                            Some(vec!["constructor".into(), "testStrings".into()]),
-                        ),
-                    ],
-                ),
-            ]),
-        );
+        let changes = &results["testIncrement()"].storage_diff;
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].before, changes[0].after), (0.into(), 2.into()));
+        assert!(results["testRead()"].storage_diff.is_empty());
     }
 
     #[test]
-    fn test_cheats() {
+    fn test_record_cheatcodes() {
+        let mut runner = base_runner()
+            .record_cheatcodes(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*cheats/Warp"), None, true).unwrap();
+        let results = &results["cheats/Warp.t.sol:WarpTest"].test_results;
+        assert_eq!(results["testWarp()"].used_cheatcodes, BTreeSet::from(["warp".to_string()]));
+        assert_eq!(
+            results["testWarpFuzzed(uint128)"].used_cheatcodes,
+            BTreeSet::from(["warp".to_string()])
+        );
+
+        // nothing is recorded by default
         let mut runner = runner();
-        let suite_result = runner.test(&Filter::new(".*", ".*", ".*cheats"), None, true).unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*cheats/Warp"), None, true).unwrap();
+        assert!(results["cheats/Warp.t.sol:WarpTest"].test_results["testWarp()"]
+            .used_cheatcodes
+            .is_empty());
+    }
 
-        for (_, SuiteResult { test_results, .. }) in suite_result {
-            for (test_name, result) in test_results {
-                let logs = decode_console_logs(&result.logs);
-                assert!(
-                    result.success,
-                    "Test {} did not pass as expected.\nReason: {:?}\nLogs:\n{}",
-                    test_name,
-                    result.reason,
-                    logs.join("\n")
+    #[test]
+    fn test_external_call_guard_allows_local_contracts() {
+        let filter = Filter::new(".*", ".*", ".*core");
+        let expected = runner().test(&filter, None, true).unwrap();
+
+        let mut runner = base_runner()
+            .external_call_allowlist(Some(BTreeSet::new()))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        for (name, suite) in &results {
+            for (test, result) in &suite.test_results {
+                assert_eq!(
+                    result.success, expected[name].test_results[test].success,
+                    "{} in {} changed its outcome with the external call guard",
+                    test, name
                 );
             }
         }
     }
 
     #[test]
-    fn test_fuzz() {
+    fn test_external_call_guard() {
+        // the fixture was not deployed in the test, the runtime code returns the value of slot 0
+        let oracle =
+            Fixture { code: "0x60005460005260206000f3".parse().unwrap(), ..Default::default() };
+        let fixtures = Fixtures::new(BTreeMap::from([(Address::from_low_u64_be(0xf1f1), oracle)]));
+        let mut runner = base_runner()
+            .with_fixtures(fixtures)
+            .external_call_allowlist(Some(BTreeSet::new()))
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let results = runner.test(&Filter::new(".*", ".*", ".*env/CallGuard"), None, true).unwrap();
+        let results = &results["env/CallGuard.t.sol:CallGuardTest"].test_results;
+        assert!(results["testCallEtched()"].success);
+
+        let blocked = &results["testCallFixture()"];
+        assert!(!blocked.success);
+        assert!(blocked.reason.as_deref().unwrap().starts_with("Call to external contract"));
+    }
+
+    #[test]
+    fn test_record_call_depth() {
+        let mut runner = base_runner()
+            .record_call_depth(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let filter = Filter::new(".*", ".*", ".*trace/CallDepth");
+        let results = runner.test(&filter, None, true).unwrap();
+        let results = &results["trace/CallDepth.t.sol:CallDepthTest"].test_results;
+        assert_eq!(results["testNoCalls()"].max_call_depth, Some(0));
+        assert_eq!(results["testRecurse()"].max_call_depth, Some(3));
+        let fuzzed = results["testRecurseFuzzed(uint8)"].max_call_depth.unwrap();
+        assert!((1..=4).contains(&fuzzed));
+
+        // nothing is recorded by default
         let mut runner = runner();
-        let suite_result = runner.test(&Filter::new(".*", ".*", ".*fuzz"), None, true).unwrap();
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_eq!(
+            results["trace/CallDepth.t.sol:CallDepthTest"].test_results["testRecurse()"]
+                .max_call_depth,
+            None
+        );
+    }
 
-        for (_, SuiteResult { test_results, .. }) in suite_result {
-            for (test_name, result) in test_results {
-                let logs = decode_console_logs(&result.logs);
+    #[test]
+    fn test_record_assertions() {
+        let mut runner = base_runner()
+            .record_assertions(true)
+            .build(&(*PROJECT).paths.root, (*COMPILED).clone(), EVM_OPTS.clone())
+            .unwrap();
+        let filter = Filter::new(".*", ".*", ".*trace/Assertions");
+        let results = runner.test(&filter, None, true).unwrap();
+        let results = &results["trace/Assertions.t.sol:AssertionsTest"].test_results;
+        assert_eq!(results["testNoAssertions()"].assertions, Some(0));
+        assert_eq!(results["testAssertions()"].assertions, Some(3));
+        assert_eq!(results["testAssertionsInLoop()"].assertions, Some(4));
+        assert_eq!(results["testFuzzAssertions(uint8)"].assertions, Some(1));
 
-                match test_name.as_ref() {
-                    "testPositive(uint256)" | "testSuccessfulFuzz(uint128,uint128)" => assert!(
-                        result.success,
-                        "Test {} did not pass as expected.\nReason: {:?}\nLogs:\n{}",
-                        test_name,
-                        result.reason,
-                        logs.join("\n")
-                    ),
-                    _ => assert!(
-                        !result.success,
-                        "Test {} did not fail as expected.\nReason: {:?}\nLogs:\n{}",
-                        test_name,
-                        result.reason,
-                        logs.join("\n")
-                    ),
-                }
-            }
-        }
+        // nothing is counted by default
+        let mut runner = runner();
+        let results = runner.test(&filter, None, true).unwrap();
+        assert_eq!(
+            results["trace/Assertions.t.sol:AssertionsTest"].test_results["testAssertions()"]
+                .assertions,
+            None
+        );
+    }
+
+    #[test]
+    fn test_return_values() {
+        let mut runner = runner();
+        let filter = Filter::new(".*", ".*", ".*returns/Returns");
+        let results = runner.test(&filter, None, true).unwrap();
+        let results = &results["returns/Returns.t.sol:ReturnsTest"].test_results;
+        assert!(results["testNoReturn()"].returns.is_empty());
+        assert_eq!(
+            results["testReturnsGas()"].returns,
+            vec![
+                ReturnValue {
+                    name: "gas".to_string(),
+                    kind: "uint256".to_string(),
+                    value: "21000".to_string()
+                },
+                ReturnValue {
+                    name: "ok".to_string(),
+                    kind: "bool".to_string(),
+                    value: "true".to_string()
+                },
+            ]
+        );
+        let unnamed = &results["testReturnsUnnamed()"].returns;
+        assert_eq!(unnamed.len(), 1);
+        assert_eq!(unnamed[0].to_string(), "\"table\"");
+        let reverted = &results["testRevertsWithReturn()"];
+        assert!(!reverted.success);
+        assert!(reverted.returns.is_empty());
     }
 
     #[test]
@@ -548,6 +3431,112 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_contract_fork() {
+        let source = r#"
+            import "ds-test/test.sol";
+
+            /// @custom:fork-url https://mainnet.example.com
+            /// @custom:fork-block 14_000_000
+            contract ForkTest is DSTest {}
+
+            /// @notice No fork here
+            contract NoForkTest is DSTest {}
+
+            /**
+             * @custom:fork-url https://rinkeby.example.com
+             */
+            abstract contract AbstractForkTest {}
+
+            /// @custom:fork-url https://mainnet.example.com
+            /// @custom:fork-block latest
+            contract InvalidBlockForkTest is DSTest {}
+
+            /// @fork-url https://mainnet.example.com
+            contract UntaggedForkTest is DSTest {}
+        "#;
+
+        assert_eq!(
+            ContractFork::parse(source, "ForkTest").unwrap(),
+            Some(ContractFork {
+                url: "https://mainnet.example.com".to_string(),
+                block: Some(14000000)
+            })
+        );
+        assert_eq!(ContractFork::parse(source, "NoForkTest").unwrap(), None);
+        assert_eq!(ContractFork::parse(source, "Missing").unwrap(), None);
+        assert_eq!(
+            ContractFork::parse(source, "AbstractForkTest").unwrap(),
+            Some(ContractFork { url: "https://rinkeby.example.com".to_string(), block: None })
+        );
+        assert!(ContractFork::parse(source, "InvalidBlockForkTest").is_err());
+        assert_eq!(ContractFork::parse(source, "UntaggedForkTest").unwrap(), None);
+    }
+
+    #[test]
+    fn test_contract_fork() {
+        // solc only accepts `@custom:` tags, an unknown tag would fail the compilation
+        assert!(!COMPILED.has_compiler_errors());
+
+        let mut runner = runner();
+        let fork = runner
+            .contract_forks
+            .iter()
+            .find(|(id, _)| id.name == "ForkTest")
+            .map(|(_, fork)| fork.clone());
+        assert_eq!(
+            fork,
+            Some(ContractFork {
+                url: "https://mainnet.infura.io/v3/c60b0bb42f8a4c6481ecd229eddaca27".to_string(),
+                block: Some(14000000)
+            })
+        );
+
+        let results = runner.test(&Filter::new(".*", ".*", ".*fork/Fork"), None, true).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([(
+                "fork/Fork.t.sol:ForkTest",
+                vec![
+                    ("testForkBlock()", true, None, None),
+                    ("testReadForkedState()", true, None, None),
+                ],
+            )]),
+        );
+    }
+
+    #[test]
+    fn test_contracts_share_fork_reads_but_not_writes() {
+        let filter = Filter::new(".*", ".*", ".*fork/SharedFork");
+        let mut runner = runner();
+        let forks = runner
+            .contract_forks
+            .iter()
+            .filter(|(id, _)| id.name.starts_with("SharedFork"))
+            .map(|(_, fork)| fork)
+            .collect::<Vec<_>>();
+        assert_eq!(forks.len(), 2);
+        assert_eq!(forks[0], forks[1]);
+
+        // the tests run one after another, so the slot is overwritten before the other contract
+        // reads it from the backend both contracts share
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let results = pool.install(|| runner.test(&filter, None, true)).unwrap();
+        assert_multiple(
+            &results,
+            BTreeMap::from([
+                (
+                    "fork/SharedFork.t.sol:SharedForkOverwriteTest",
+                    vec![("testOverwriteForkedSlot()", true, None, None)],
+                ),
+                (
+                    "fork/SharedFork.t.sol:SharedForkReadTest",
+                    vec![("testReadOriginalSlot()", true, None, None)],
+                ),
+            ]),
+        );
+    }
+
     #[test]
     fn test_doesnt_run_abstract_contract() {
         let mut runner = runner();
@@ -555,5 +3544,29 @@ mod tests {
             runner.test(&Filter::new(".*", ".*", ".*core/Abstract.t.sol"), None, true).unwrap();
         assert!(results.get("core/Abstract.t.sol:AbstractTestBase").is_none());
         assert!(results.get("core/Abstract.t.sol:AbstractTest").is_some());
+        // base test contracts marked with `@custom:abstract` only run as part of derived contracts
+        assert!(results.get("core/Abstract.t.sol:MarkedTestBase").is_none());
+        let marked = &results["core/Abstract.t.sol:MarkedTest"];
+        assert!(marked.test_results.contains_key("testInherited()"));
+    }
+
+    #[test]
+    fn can_parse_abstract_tests() {
+        let source = r#"
+/// @custom:abstract
+contract VaultTestBase is DSTest {}
+
+/**
+ * @notice Runs against WETH
+ * @custom:abstract */
+contract WethVaultTestBase is VaultTestBase {}
+
+/// @custom:abstractness is not the tag
+contract WethVaultTest is WethVaultTestBase {}
+"#;
+        assert!(is_abstract_test(source, "VaultTestBase"));
+        assert!(is_abstract_test(source, "WethVaultTestBase"));
+        assert!(!is_abstract_test(source, "WethVaultTest"));
+        assert!(!is_abstract_test(source, "Missing"));
     }
 }
@@ -0,0 +1,468 @@
+//! Mutation testing.
+//!
+//! A mutant is a copy of a compiled contract with a single changed byte in its runtime code: a
+//! flipped comparison, e.g. `LT` instead of `GT`, or an altered constant. Mutants are deployed in
+//! place of the original contract via an [ArtifactTransform] and the tests that call the contract
+//! are run against them. A mutant is killed if any of these tests fails, mutants that survive
+//! point at behavior the tests do not check.
+//!
+//! Contracts that link libraries are not mutated, and neither are copies of a mutated contract
+//! embedded in contracts that link libraries, e.g. to deploy it with `new`.
+use crate::{assertions::instruction_pcs, trace::TraceKind, ArtifactTransform, SuiteResult};
+use ethers::{
+    abi::Abi,
+    prelude::ArtifactId,
+    solc::artifacts::{BytecodeObject, CompactContractBytecode},
+    types::Bytes,
+};
+use foundry_evm::executor::inspector::opcode_name;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+const LT: u8 = 0x10;
+const GT: u8 = 0x11;
+const SLT: u8 = 0x12;
+const SGT: u8 = 0x13;
+const JUMP: u8 = 0x56;
+const JUMPI: u8 = 0x57;
+const JUMPDEST: u8 = 0x5b;
+const PUSH1: u8 = 0x60;
+const PUSH32: u8 = 0x7f;
+
+/// How a mutant differs from the original contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MutationKind {
+    /// A comparison is inverted, e.g. `LT` instead of `GT`
+    FlipComparison,
+    /// The last byte of a constant pushed onto the stack is changed
+    AlterConstant,
+}
+
+/// A change of a single byte of the runtime code of a contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mutation {
+    /// The program counter of the mutated instruction
+    pub pc: usize,
+    /// The offset of the changed byte in the runtime code, the instruction itself or its data
+    pub offset: usize,
+    /// The byte of the original contract
+    pub original: u8,
+    /// The byte of the mutant
+    pub mutated: u8,
+    pub kind: MutationKind,
+}
+
+impl fmt::Display for Mutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            MutationKind::FlipComparison => write!(
+                f,
+                "{} -> {} at pc {}",
+                opcode_name(self.original),
+                opcode_name(self.mutated),
+                self.pc
+            ),
+            MutationKind::AlterConstant => write!(
+                f,
+                "constant 0x{:02x} -> 0x{:02x} at pc {}",
+                self.original, self.mutated, self.pc
+            ),
+        }
+    }
+}
+
+/// A mutant and the tests that killed it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mutant {
+    /// The identifier of the mutated contract
+    pub contract: String,
+    pub mutation: Mutation,
+    /// The tests that failed against the mutant, as `<suite>::<signature>`, empty if it survived
+    pub killed_by: Vec<String>,
+}
+
+impl Mutant {
+    /// Returns `true` if no test failed against the mutant
+    pub fn survived(&self) -> bool {
+        self.killed_by.is_empty()
+    }
+}
+
+/// Returns the mutations of the runtime code, in order of their program counters.
+///
+/// Every comparison is flipped and the last byte of every constant is altered, except for jump
+/// destinations, which would only make the code jump to an invalid instruction. Constants that
+/// are the offset of a `JUMPDEST` are jump destinations, e.g. the return address of an internal
+/// function, as well as constants that are jumped to right away. The metadata the compiler
+/// appends to the code is not mutated.
+pub fn mutations(code: &[u8]) -> Vec<Mutation> {
+    let code = &code[..code.len() - metadata_len(code)];
+    let jumpdests = instruction_pcs(code)
+        .into_iter()
+        .filter(|pc| code[*pc] == JUMPDEST)
+        .collect::<BTreeSet<_>>();
+    let mut mutations = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let op = code[pc];
+        let next = pc + 1 + push_size(op);
+        if let Some(flipped) = flip_comparison(op) {
+            mutations.push(Mutation {
+                pc,
+                offset: pc,
+                original: op,
+                mutated: flipped,
+                kind: MutationKind::FlipComparison,
+            });
+        } else if push_size(op) > 0 &&
+            next <= code.len() &&
+            !matches!(code.get(next), Some(&JUMP) | Some(&JUMPI)) &&
+            !push_value(&code[pc + 1..next]).map_or(false, |value| jumpdests.contains(&value))
+        {
+            let original = code[next - 1];
+            mutations.push(Mutation {
+                pc,
+                offset: next - 1,
+                original,
+                mutated: if original == u8::MAX { original - 1 } else { original + 1 },
+                kind: MutationKind::AlterConstant,
+            });
+        }
+        pc = next;
+    }
+    mutations
+}
+
+/// Returns the mutations of the contract, see [mutations()], none if it can not be mutated
+pub fn contract_mutations(contract: &CompactContractBytecode) -> Vec<Mutation> {
+    runtime_code(contract).map(|(code, _)| mutations(code)).unwrap_or_default()
+}
+
+/// Applies the mutation of the runtime code `code` to every copy of the code in the creation and
+/// deployed bytecode of the contract.
+///
+/// This covers the contract itself as well as contracts that deploy it, whose code embeds its
+/// creation code. Returns `false` if the contract contains no copy of the code.
+pub fn apply_mutation(
+    contract: &mut CompactContractBytecode,
+    code: &[u8],
+    mutation: &Mutation,
+) -> bool {
+    if code.get(mutation.offset) != Some(&mutation.original) {
+        return false
+    }
+    let mut applied = false;
+    if let Some(ref mut bytecode) = contract.bytecode {
+        applied |= mutate_copies(&mut bytecode.object, code, mutation);
+    }
+    if let Some(bytecode) = contract.deployed_bytecode.as_mut().and_then(|d| d.bytecode.as_mut()) {
+        applied |= mutate_copies(&mut bytecode.object, code, mutation);
+    }
+    applied
+}
+
+/// Returns the tests that passed and called a contract, by the identifier of the contract, see
+/// [ArtifactId::identifier()], then by suite and signature.
+///
+/// The contracts are identified by the code they were created with in any trace of the test, only
+/// the calls in the execution traces count, calls made by setUp do not.
+pub fn tests_calling(
+    results: &BTreeMap<String, SuiteResult>,
+    known_contracts: &BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
+) -> BTreeMap<String, BTreeMap<String, BTreeSet<String>>> {
+    let mut tests: BTreeMap<String, BTreeMap<String, BTreeSet<String>>> = BTreeMap::new();
+    for (suite, suite_result) in results {
+        for (test, result) in &suite_result.test_results {
+            if !result.success || test == "setUp()" {
+                continue
+            }
+            let mut contracts = BTreeMap::new();
+            for (_, trace) in &result.traces {
+                for (address, code) in trace.addresses() {
+                    if let Some(id) = code.and_then(|code| identify(code, known_contracts)) {
+                        contracts.insert(*address, id.identifier());
+                    }
+                }
+            }
+            for (_, trace) in result.traces.iter().filter(|(kind, _)| *kind == TraceKind::Execution)
+            {
+                for node in &trace.arena {
+                    if let Some(contract) = contracts.get(&node.trace.address) {
+                        tests
+                            .entry(contract.clone())
+                            .or_default()
+                            .entry(suite.clone())
+                            .or_default()
+                            .insert(test.clone());
+                    }
+                }
+            }
+        }
+    }
+    tests
+}
+
+/// Returns the known contract with the runtime code.
+///
+/// The code of a contract with immutable variables differs from the known code in their values,
+/// so the known contract of the same size with the fewest differing bytes is picked, as long as
+/// less than 10% of the bytes differ.
+fn identify<'a>(
+    code: &[u8],
+    known_contracts: &'a BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
+) -> Option<&'a ArtifactId> {
+    known_contracts
+        .iter()
+        .filter(|(_, (_, known))| !known.is_empty() && known.len() == code.len())
+        .map(|(id, (_, known))| (id, known.iter().zip(code).filter(|(a, b)| a != b).count()))
+        .filter(|(_, differing)| differing * 10 < code.len())
+        .min_by_key(|(_, differing)| *differing)
+        .map(|(id, _)| id)
+}
+
+/// Returns at most `max` of the items, spread evenly over all of them
+pub fn sample<T>(items: Vec<T>, max: usize) -> Vec<T> {
+    let len = items.len();
+    if len <= max {
+        return items
+    }
+    let picked = (0..max).map(|i| i * len / max).collect::<BTreeSet<_>>();
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| picked.contains(i))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+/// Runs the tests that call the mutated contracts against each mutant.
+///
+/// The runtime code of the mutated contracts is taken from `known_contracts`. `tests` are the
+/// tests that passed against the original contracts, by the identifier of the contract they call,
+/// see [tests_calling()]. `run` builds a runner with the given transform, runs the given tests by
+/// suite and signature and returns their results. Mutants of contracts that no test calls survive
+/// without running anything.
+pub fn run_mutants(
+    mutants: Vec<(ArtifactId, Mutation)>,
+    known_contracts: &BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
+    tests: &BTreeMap<String, BTreeMap<String, BTreeSet<String>>>,
+    mut run: impl FnMut(
+        ArtifactTransform,
+        BTreeMap<String, BTreeSet<String>>,
+    ) -> eyre::Result<BTreeMap<String, SuiteResult>>,
+) -> eyre::Result<Vec<Mutant>> {
+    mutants
+        .into_iter()
+        .map(|(id, mutation)| {
+            let code = known_contracts.get(&id).map(|(_, code)| code.clone());
+            let killed_by = match (code, tests.get(&id.identifier())) {
+                (Some(code), Some(tests)) => {
+                    let transform = ArtifactTransform::new(move |_, contract| {
+                        apply_mutation(contract, &code, &mutation);
+                    });
+                    failed_tests(&run(transform, tests.clone())?)
+                }
+                _ => Vec::new(),
+            };
+            Ok(Mutant { contract: id.identifier(), mutation, killed_by })
+        })
+        .collect()
+}
+
+/// Returns the tests that failed, as `<suite>::<signature>`
+fn failed_tests(results: &BTreeMap<String, SuiteResult>) -> Vec<String> {
+    results
+        .iter()
+        .flat_map(|(suite, result)| {
+            result
+                .test_results
+                .iter()
+                .filter(|(_, result)| !result.success)
+                .map(move |(test, _)| format!("{}::{}", suite, test))
+        })
+        .collect()
+}
+
+/// Returns the linked runtime code of the contract and its offset in the linked creation code
+fn runtime_code(contract: &CompactContractBytecode) -> Option<(&[u8], usize)> {
+    let creation = linked(&contract.bytecode.as_ref()?.object)?;
+    let runtime = linked(&contract.deployed_bytecode.as_ref()?.bytecode.as_ref()?.object)?;
+    if runtime.is_empty() {
+        return None
+    }
+    let offset = creation.windows(runtime.len()).position(|code| code == runtime)?;
+    Some((runtime, offset))
+}
+
+/// Returns the code of the bytecode object, unless it still contains link placeholders
+fn linked(object: &BytecodeObject) -> Option<&[u8]> {
+    match object {
+        BytecodeObject::Bytecode(ref bytes) => Some(bytes.as_ref()),
+        BytecodeObject::Unlinked(_) => None,
+    }
+}
+
+/// Applies the mutation of the runtime code `code` to every copy of the code in the bytecode
+/// object, returns `false` if there is none
+fn mutate_copies(object: &mut BytecodeObject, code: &[u8], mutation: &Mutation) -> bool {
+    let mutated = match linked(object) {
+        Some(bytes) if !code.is_empty() => {
+            let copies = bytes
+                .windows(code.len())
+                .enumerate()
+                .filter(|(_, window)| *window == code)
+                .map(|(start, _)| start)
+                .collect::<Vec<_>>();
+            if copies.is_empty() {
+                return false
+            }
+            let mut mutated = bytes.to_vec();
+            for start in copies {
+                mutated[start + mutation.offset] = mutation.mutated;
+            }
+            mutated
+        }
+        _ => return false,
+    };
+    *object = BytecodeObject::Bytecode(Bytes::from(mutated));
+    true
+}
+
+/// Returns the length of the CBOR encoded metadata at the end of the code, including the two
+/// bytes of its length, or 0 if there is none
+fn metadata_len(code: &[u8]) -> usize {
+    if code.len() < 2 {
+        return 0
+    }
+    let len = u16::from_be_bytes([code[code.len() - 2], code[code.len() - 1]]) as usize + 2;
+    // the metadata is a CBOR map
+    match code.len().checked_sub(len) {
+        Some(start) if (0xa1..=0xa5).contains(&code[start]) => len,
+        _ => 0,
+    }
+}
+
+/// Returns the value of the data of a `PUSH` instruction, if it fits into a `usize`
+fn push_value(data: &[u8]) -> Option<usize> {
+    let start = data.iter().position(|byte| *byte != 0).unwrap_or(data.len());
+    let data = &data[start..];
+    if data.len() > std::mem::size_of::<usize>() {
+        return None
+    }
+    Some(data.iter().fold(0, |value, byte| value << 8 | *byte as usize))
+}
+
+fn push_size(op: u8) -> usize {
+    if (PUSH1..=PUSH32).contains(&op) {
+        (op - PUSH1 + 1) as usize
+    } else {
+        0
+    }
+}
+
+fn flip_comparison(op: u8) -> Option<u8> {
+    match op {
+        LT => Some(GT),
+        GT => Some(LT),
+        SLT => Some(SGT),
+        SGT => Some(SLT),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::solc::artifacts::{CompactBytecode, CompactDeployedBytecode};
+
+    // CALLDATASIZE, PUSH1 0x04, GT, PUSH2 0x0010, JUMPI, PUSH1 0x2a, PUSH1 0x0d, STOP, JUMPDEST,
+    // metadata `a1 00`
+    const CODE: [u8; 18] = [
+        0x36, 0x60, 0x04, 0x11, 0x61, 0x00, 0x10, 0x57, 0x60, 0x2a, 0x60, 0x0d, 0x00, 0x5b, 0xa1,
+        0x00, 0x00, 0x02,
+    ];
+
+    fn contract(creation: &[u8], runtime: &[u8]) -> CompactContractBytecode {
+        let bytecode = |code: &[u8]| CompactBytecode {
+            object: BytecodeObject::Bytecode(code.to_vec().into()),
+            source_map: None,
+            link_references: Default::default(),
+        };
+        CompactContractBytecode {
+            abi: None,
+            bytecode: Some(bytecode(creation)),
+            deployed_bytecode: Some(CompactDeployedBytecode {
+                bytecode: Some(bytecode(runtime)),
+                immutable_references: Default::default(),
+            }),
+        }
+    }
+
+    #[test]
+    fn can_find_mutations() {
+        let mutations = mutations(&CODE);
+        assert_eq!(
+            mutations,
+            vec![
+                Mutation {
+                    pc: 1,
+                    offset: 2,
+                    original: 0x04,
+                    mutated: 0x05,
+                    kind: MutationKind::AlterConstant
+                },
+                Mutation {
+                    pc: 3,
+                    offset: 3,
+                    original: GT,
+                    mutated: LT,
+                    kind: MutationKind::FlipComparison
+                },
+                Mutation {
+                    pc: 8,
+                    offset: 9,
+                    original: 0x2a,
+                    mutated: 0x2b,
+                    kind: MutationKind::AlterConstant
+                },
+            ]
+        );
+        // `PUSH1 0x0d` pushes the offset of the `JUMPDEST`, e.g. a return address
+        assert!(mutations.iter().all(|mutation| mutation.pc != 10));
+        assert_eq!(mutations[1].to_string(), "GT -> LT at pc 3");
+        assert_eq!(mutations[2].to_string(), "constant 0x2a -> 0x2b at pc 8");
+    }
+
+    #[test]
+    fn can_apply_mutations() {
+        let creation = [&[0x60, 0x00][..], &CODE].concat();
+        let mut mutant = contract(&creation, &CODE);
+        let mutation = contract_mutations(&mutant)[1];
+        assert!(apply_mutation(&mut mutant, &CODE, &mutation));
+
+        let mut expected = CODE;
+        expected[3] = LT;
+        assert_eq!(runtime_code(&mutant), Some((&expected[..], 2)));
+
+        // a contract deploying the mutated contract embeds its creation code
+        let mut deployer = contract(&[0x00], &[&[0x60, 0x01][..], &creation, &[0x00]].concat());
+        assert!(apply_mutation(&mut deployer, &CODE, &mutation));
+        assert_eq!(
+            linked(&deployer.deployed_bytecode.unwrap().bytecode.unwrap().object),
+            Some(&[&[0x60, 0x01, 0x60, 0x00][..], &expected, &[0x00]].concat()[..])
+        );
+
+        // the runtime code is not part of the creation code
+        let mut other = contract(&[0x60, 0x00], &[0x5b]);
+        assert!(contract_mutations(&other).is_empty());
+        assert!(!apply_mutation(&mut other, &CODE, &mutation));
+    }
+
+    #[test]
+    fn can_sample_evenly() {
+        assert_eq!(sample((0..4).collect(), 10), vec![0, 1, 2, 3]);
+        assert_eq!(sample((0..10).collect(), 3), vec![0, 3, 6]);
+    }
+}
@@ -0,0 +1,106 @@
+//! The NatSpec of test contracts and their functions.
+//!
+//! Tests are configured with NatSpec tags, e.g. [CALLBACK_TAG](crate::callbacks::CALLBACK_TAG) or
+//! [ABSTRACT_TEST_TAG](crate::ABSTRACT_TEST_TAG). The doc comments are read from the source code,
+//! a doc comment consists of the `///` or `/** .. */` comment lines directly above a declaration.
+
+/// Returns the functions of the contract `name` in the given source code with the lines of their
+/// doc comments, in order of declaration
+pub(crate) fn function_docs<'a>(source: &'a str, name: &str) -> Vec<(&'a str, Vec<&'a str>)> {
+    let lines = source.lines().map(str::trim).collect::<Vec<_>>();
+    let mut functions = Vec::new();
+    let mut in_contract = false;
+    for (decl, &line) in lines.iter().enumerate() {
+        if let Some(contract) = declared_contract(line) {
+            in_contract = contract == name;
+            continue
+        }
+        let function = match declared_function(line) {
+            Some(function) if in_contract => function,
+            _ => continue,
+        };
+
+        functions.push((function, doc_above(&lines, decl)));
+    }
+    functions
+}
+
+/// Returns the lines of the doc comment of the contract `name` in the given source code, empty
+/// if the contract does not exist
+pub(crate) fn contract_docs<'a>(source: &'a str, name: &str) -> Vec<&'a str> {
+    let lines = source.lines().map(str::trim).collect::<Vec<_>>();
+    lines
+        .iter()
+        .position(|line| declared_contract(line) == Some(name))
+        .map(|decl| doc_above(&lines, decl))
+        .unwrap_or_default()
+}
+
+/// Returns the doc comment of the declaration in line `decl`, which consists of the comment
+/// lines directly above it
+fn doc_above<'a>(lines: &[&'a str], decl: usize) -> Vec<&'a str> {
+    let mut doc = lines[..decl]
+        .iter()
+        .rev()
+        .take_while(|line| {
+            line.starts_with("///") || line.starts_with("/**") || line.starts_with('*')
+        })
+        .copied()
+        .collect::<Vec<_>>();
+    doc.reverse();
+    doc
+}
+
+/// Returns the name of the contract, library or interface declared in the line, if any
+fn declared_contract(line: &str) -> Option<&str> {
+    let mut tokens = line.split_whitespace().skip_while(|token| *token == "abstract");
+    match tokens.next() {
+        Some("contract") | Some("library") | Some("interface") => {
+            tokens.next().map(|token| token.trim_end_matches('{'))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the name of the function declared in the line, if any
+fn declared_function(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("function ")?;
+    let name = rest.split('(').next()?.trim();
+    (!name.is_empty()).then(|| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_read_docs() {
+        let source = r#"
+/// @custom:fork-url http://localhost:8545
+abstract contract VaultTest is DSTest {
+    /// @notice Deposits are credited
+    function testDeposit() external {}
+
+    /**
+     * @custom:callback
+     */
+    function onFlashLoan() external {}
+
+    function testWithdraw(uint256 amount) external {}
+}
+"#;
+        assert_eq!(
+            contract_docs(source, "VaultTest"),
+            vec!["/// @custom:fork-url http://localhost:8545"]
+        );
+        assert!(contract_docs(source, "Missing").is_empty());
+        assert_eq!(
+            function_docs(source, "VaultTest"),
+            vec![
+                ("testDeposit", vec!["/// @notice Deposits are credited"]),
+                ("onFlashLoan", vec!["/**", "* @custom:callback", "*/"]),
+                ("testWithdraw", vec![]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! The results `forge test --json` prints.
+//!
+//! The output is a single JSON object that maps the identifier of each test contract
+//! (`<path>:<contract>`) to its [SuiteResult], which in turn maps the signature of each test to
+//! its [TestResult]. All types the output consists of are re-exported here, [from_json()] parses
+//! the output back into them.
+//!
+//! Fields are only ever added to the format, new fields are optional when parsing so the output of
+//! older versions can still be read. Changes that are not backwards compatible increment
+//! [REPORT_VERSION], which the object holds next to the test contracts as `"version"`. Output
+//! without a version is read as version 1, output of a newer version is rejected.
+//!
+//! `forge test --json-stream` prints the same results as newline-delimited JSON instead, one line
+//! per test contract as soon as its tests completed. Every line is an object with the identifier
+//! of the test contract as its single key, see [suite_to_json()], [from_json_lines()] parses the
+//! lines back into one [TestReport].
+//!
+//! Logs and the decoded arguments of counterexamples are not part of the output, they are empty
+//! in parsed results.
+use eyre::WrapErr;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+pub use crate::{storage::StorageChange, ReturnValue, SuiteResult, TestKind, TestResult};
+pub use foundry_evm::{
+    fuzz::{CounterExample, FuzzCase, FuzzedCases},
+    trace::{CallTraceArena, TraceKind},
+};
+
+/// The version of the format, incremented whenever a change is not backwards compatible
+pub const REPORT_VERSION: u32 = 1;
+
+/// The results of a test run, by test contract identifier
+pub type TestReport = BTreeMap<String, SuiteResult>;
+
+/// The output of `forge test --json`, the results of a test run with the version of the format
+#[derive(Serialize)]
+struct VersionedReport<'a> {
+    version: u32,
+    #[serde(flatten)]
+    suites: &'a TestReport,
+}
+
+/// Serializes the results of a test run the way `forge test --json` prints them
+pub fn to_json(report: &TestReport) -> eyre::Result<String> {
+    Ok(serde_json::to_string(&VersionedReport { version: REPORT_VERSION, suites: report })?)
+}
+
+/// Parses the output of `forge test --json`
+pub fn from_json(json: &str) -> eyre::Result<TestReport> {
+    let mut object: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(json).wrap_err("Invalid test results")?;
+    if let Some(version) = object.remove("version") {
+        let version: u32 =
+            serde_json::from_value(version).wrap_err("Invalid version of the test results")?;
+        if version > REPORT_VERSION {
+            eyre::bail!(
+                "The test results are of version {}, only versions up to {} are supported",
+                version,
+                REPORT_VERSION
+            )
+        }
+    }
+    serde_json::from_value(object.into()).wrap_err("Invalid test results")
+}
+
+/// Serializes the results of a test contract the way `forge test --json-stream` prints them, as
+/// a single line `{"<identifier>": <results>}`
+pub fn suite_to_json(contract: &str, result: &SuiteResult) -> eyre::Result<String> {
+    Ok(serde_json::to_string(&BTreeMap::from([(contract, result)]))?)
+}
+
+/// Parses the output of `forge test --json-stream`, empty lines are skipped
+pub fn from_json_lines(lines: &str) -> eyre::Result<TestReport> {
+    let mut report = TestReport::new();
+    for (number, line) in lines.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let suites: TestReport = serde_json::from_str(line)
+            .wrap_err_with(|| format!("Invalid test results in line {}", number + 1))?;
+        report.extend(suites);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Bytes;
+    use std::time::Duration;
+
+    fn result(kind: TestKind, counterexample: Option<CounterExample>) -> TestResult {
+        TestResult {
+            success: counterexample.is_none(),
+            reason: counterexample.as_ref().map(|_| "assertion failed".to_string()),
+            counterexample,
+            kind,
+            used_cheatcodes: ["prank".to_string()].into(),
+            duration: Duration::from_millis(3),
+            max_call_depth: Some(2),
+            assertions: Some(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn can_roundtrip_json() {
+        let counterexample =
+            CounterExample { calldata: Bytes::from(vec![1, 2, 3, 4]), args: vec![], names: vec![] };
+        let cases = FuzzedCases::new(vec![FuzzCase {
+            calldata: Bytes::from(vec![1, 2, 3, 4]),
+            gas: 30_000,
+            stipend: 21_000,
+        }]);
+        let report = TestReport::from([(
+            "test/Vault.t.sol:VaultTest".to_string(),
+            SuiteResult::new(
+                Duration::from_millis(10),
+                BTreeMap::from([
+                    ("testDeposit()".to_string(), result(TestKind::Standard(42_000), None)),
+                    (
+                        "testWithdraw(uint256)".to_string(),
+                        result(TestKind::Fuzz(cases), Some(counterexample)),
+                    ),
+                ]),
+            )
+            .with_setup_rpc_calls(5),
+        )]);
+
+        let json = to_json(&report).unwrap();
+        assert!(json.starts_with(&format!("{{\"version\":{},", REPORT_VERSION)));
+        let parsed = from_json(&json).unwrap();
+        assert_eq!(to_json(&parsed).unwrap(), json);
+
+        let suite = &parsed["test/Vault.t.sol:VaultTest"];
+        assert_eq!(suite.setup_rpc_calls, 5);
+        assert!(matches!(suite.test_results["testDeposit()"].kind, TestKind::Standard(42_000)));
+        let fuzz = &suite.test_results["testWithdraw(uint256)"];
+        assert_eq!(fuzz.kind.fuzz_runs(), Some(1));
+        assert_eq!(fuzz.counterexample.as_ref().unwrap().calldata.as_ref(), &[1, 2, 3, 4]);
+        assert_eq!(fuzz.max_call_depth, Some(2));
+        assert_eq!(fuzz.assertions, Some(3));
+
+        assert!(from_json("[]").is_err());
+        assert!(from_json("{}").unwrap().is_empty());
+        assert!(from_json(&format!("{{\"version\":{}}}", REPORT_VERSION + 1)).is_err());
+
+        let lines = report
+            .iter()
+            .map(|(contract, result)| suite_to_json(contract, result).unwrap() + "\n")
+            .collect::<String>();
+        assert_eq!(lines.lines().count(), 1);
+        assert_eq!(to_json(&from_json_lines(&lines).unwrap()).unwrap(), json);
+        assert!(from_json_lines("{}\n[]").is_err());
+    }
+}
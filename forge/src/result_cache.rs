@@ -0,0 +1,143 @@
+use crate::{SuiteResult, TestResult};
+use ethers::types::H256;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// The name of the file in the cache directory the results are stored in
+pub const TEST_RESULTS_CACHE_FILE: &str = "test-results.json";
+
+/// Cheatcodes that read from outside of the EVM, the results of tests that invoke them are never
+/// cached
+const UNCACHEABLE_CHEATCODES: &[&str] = &["ffi", "getCode"];
+
+/// The cached results of a test contract
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CachedSuite {
+    /// The hash of the test contract the results belong to, see
+    /// [MultiContractRunner::suite_hashes()](crate::MultiContractRunner::suite_hashes)
+    hash: H256,
+    /// The results of passing tests, by signature
+    results: BTreeMap<String, TestResult>,
+}
+
+/// Caches the results of passing tests across runs.
+///
+/// The results are stored per test contract together with a hash of the contract's bytecode, its
+/// libraries and the EVM options, so they are invalidated as soon as the contract or any contract
+/// it depends on changes. Only the results of passing tests that did not invoke cheatcodes which
+/// read from outside of the EVM, like `ffi`, are cached, failing tests are always run again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestResultCache {
+    /// Cached results by contract identifier
+    suites: BTreeMap<String, CachedSuite>,
+}
+
+impl TestResultCache {
+    /// Reads the cache from the file, a missing or invalid file is treated as an empty cache
+    pub fn read(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the cache to the file
+    pub fn write(&self, path: &Path) -> eyre::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Returns the cached results of the contracts whose hash did not change, by contract
+    /// identifier and test signature
+    pub fn results(
+        &self,
+        hashes: &BTreeMap<String, H256>,
+    ) -> BTreeMap<String, BTreeMap<String, TestResult>> {
+        hashes
+            .iter()
+            .filter_map(|(suite, hash)| {
+                let cached = self.suites.get(suite)?;
+                (cached.hash == *hash && !cached.results.is_empty())
+                    .then(|| (suite.clone(), cached.results.clone()))
+            })
+            .collect()
+    }
+
+    /// Caches the results of passing tests of a run, the results of contracts whose hash changed
+    /// are dropped
+    pub fn update(
+        &mut self,
+        hashes: &BTreeMap<String, H256>,
+        results: &BTreeMap<String, SuiteResult>,
+    ) {
+        for (suite, hash) in hashes {
+            let cached = self.suites.entry(suite.clone()).or_default();
+            if cached.hash != *hash {
+                *cached = CachedSuite { hash: *hash, results: BTreeMap::new() };
+            }
+            let test_results = match results.get(suite) {
+                Some(result) => &result.test_results,
+                None => continue,
+            };
+            for (test, result) in test_results {
+                if is_cacheable(result) {
+                    cached.results.insert(test.clone(), result.clone());
+                } else {
+                    cached.results.remove(test);
+                }
+            }
+        }
+        self.suites.retain(|_, cached| !cached.results.is_empty());
+    }
+}
+
+/// Returns `true` if the test passed and did not read from outside of the EVM
+fn is_cacheable(result: &TestResult) -> bool {
+    result.success &&
+        !result.used_ffi &&
+        !UNCACHEABLE_CHEATCODES
+            .iter()
+            .any(|cheatcode| result.used_cheatcodes.contains(*cheatcode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn result(success: bool) -> TestResult {
+        TestResult { success, ..Default::default() }
+    }
+
+    #[test]
+    fn caches_passing_results_until_the_hash_changes() {
+        let suite = "A.t.sol:ATest".to_string();
+        let mut ffi = result(true);
+        ffi.used_ffi = true;
+        let results = BTreeMap::from([(
+            suite.clone(),
+            SuiteResult::new(
+                Duration::ZERO,
+                BTreeMap::from([
+                    ("testPasses()".to_string(), result(true)),
+                    ("testFails()".to_string(), result(false)),
+                    ("testFfi()".to_string(), ffi),
+                ]),
+            ),
+        )]);
+
+        let hashes = BTreeMap::from([(suite.clone(), H256::from_low_u64_be(1))]);
+        let mut cache = TestResultCache::default();
+        cache.update(&hashes, &results);
+        let cached = cache.results(&hashes);
+        assert_eq!(cached[&suite].keys().collect::<Vec<_>>(), vec!["testPasses()"]);
+
+        let changed = BTreeMap::from([(suite, H256::from_low_u64_be(2))]);
+        assert!(cache.results(&changed).is_empty());
+        cache.update(&changed, &BTreeMap::new());
+        assert!(cache.results(&hashes).is_empty());
+    }
+}
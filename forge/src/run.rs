@@ -0,0 +1,514 @@
+//! Compiling and testing a project without the CLI.
+//!
+//! [run_tests()] compiles the project of a [Config], builds the test runner and runs the tests
+//! that match the filter the way `forge test` does, including the options of [RunOptions] like
+//! repeated runs, cached results and the files the results are written to:
+//!
+//! ```no_run
+//! use forge::run::{run_tests, RunOptions};
+//! # fn run(config: &foundry_config::Config, filter: impl forge::TestFilter + Send + Sync + 'static) {
+//! let run = run_tests(config, filter, RunOptions::default()).unwrap();
+//! # }
+//! ```
+//!
+//! Embedders that need more control use the steps of [run_tests()] separately, like `forge test`
+//! does: [build_runner()], which customizes the builder with the options of the config before the
+//! runner is built, [Reruns::run()], [RunCache::load()], [stream()] and [write_output_dir()].
+use crate::{
+    fuzz::FuzzWeights,
+    junit,
+    report::{self, TestReport},
+    result_cache::{TestResultCache, TEST_RESULTS_CACHE_FILE},
+    MultiContractRunner, MultiContractRunnerBuilder, SuiteResult, TestFilter, TestPrefixes,
+};
+use ethers::{
+    solc::{EvmVersion, ProjectCompileOutput},
+    types::H256,
+};
+use eyre::WrapErr;
+use foundry_config::{caching::StorageCachingConfig, figment::Figment, Config};
+use foundry_evm::executor::{opts::EvmOpts, Fork, SpecId};
+use proptest::test_runner::TestRunner;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    path::{Component, Path, PathBuf},
+    sync::mpsc::channel,
+    thread,
+};
+
+/// The options of [run_tests()] that are not part of the config
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Whether fuzz tests are run
+    pub include_fuzz_tests: bool,
+    /// The EVM options, read from the config if not set, see [evm_opts()]
+    pub evm_opts: Option<EvmOpts>,
+    /// The compiler output of the project, the project is compiled if not set, see [compile()]
+    pub output: Option<ProjectCompileOutput>,
+    /// The blocks of the fork the tests run against, once per block, see
+    /// [MultiContractRunnerBuilder::fork_blocks()]
+    pub fork_blocks: Vec<u64>,
+    /// How often the tests are run, the results of all runs but the last are
+    /// [Reruns::repeated_results]
+    pub repeat: usize,
+    /// Whether the tests are run once more up front and unit tests whose gas usage differs
+    /// between the runs fail, see [Reruns::gas_baseline]
+    pub gas_determinism_check: bool,
+    /// Whether the results of passing tests that did not change since the last run are reused,
+    /// see [RunCache]
+    pub cache_results: bool,
+    /// The file the results of every test contract are appended to as soon as it completed, see
+    /// [ResultsFile]
+    pub results_file: Option<PathBuf>,
+    /// The directory the results are written to once all tests completed, see
+    /// [write_output_dir()]
+    pub output_dir: Option<PathBuf>,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            include_fuzz_tests: true,
+            evm_opts: None,
+            output: None,
+            fork_blocks: Vec::new(),
+            repeat: 1,
+            gas_determinism_check: false,
+            cache_results: false,
+            results_file: None,
+            output_dir: None,
+        }
+    }
+}
+
+/// The results of [run_tests()]
+#[derive(Default)]
+pub struct TestRun {
+    /// The results of the last run
+    pub results: TestReport,
+    /// The results of the runs before the last one, see [RunOptions::repeat]
+    pub repeated_results: Vec<TestReport>,
+    /// The files written to the output directory, see [RunOptions::output_dir]
+    pub written: Vec<PathBuf>,
+}
+
+/// Compiles the project of the config, builds the test runner and runs the tests that match the
+/// filter
+pub fn run_tests(
+    config: &Config,
+    filter: impl TestFilter + Send + Sync + 'static,
+    mut opts: RunOptions,
+) -> eyre::Result<TestRun> {
+    // compiled once, every rerun builds its runner from the same output
+    if opts.output.is_none() {
+        opts.output = Some(compile(config)?);
+    }
+    let build = || build_runner(config, opts.clone(), Ok);
+
+    let mut runner = build()?;
+    let reruns = Reruns::run(build, &filter, &opts)?;
+    let cache = opts.cache_results.then(|| RunCache::load(config, &mut runner));
+    let results = stream(runner, filter, &opts, reruns.gas_baseline.as_ref(), |_, _| Ok(()))?;
+    if let Some(cache) = cache {
+        cache.update(&results)?;
+    }
+    let written = match opts.output_dir {
+        Some(ref dir) => write_output_dir(dir, &results)?,
+        None => Vec::new(),
+    };
+    Ok(TestRun { results, repeated_results: reruns.repeated_results, written })
+}
+
+/// Compiles the project of the config unless the output is part of the options and builds the
+/// test runner.
+///
+/// The builder with the options of the config, see [runner_builder()], is passed through
+/// `customize` before the runner is built. The options of the run take precedence over the
+/// customization.
+pub fn build_runner(
+    config: &Config,
+    opts: RunOptions,
+    customize: impl FnOnce(MultiContractRunnerBuilder) -> eyre::Result<MultiContractRunnerBuilder>,
+) -> eyre::Result<MultiContractRunner> {
+    let evm_opts = match opts.evm_opts {
+        Some(evm_opts) => evm_opts,
+        None => evm_opts(config)?,
+    };
+    if !opts.fork_blocks.is_empty() && evm_opts.fork_url.is_none() {
+        eyre::bail!("Running the tests against fork blocks requires a fork URL to fork from");
+    }
+    let output = match opts.output {
+        Some(output) => output,
+        None => compile(config)?,
+    };
+    let mut builder = customize(runner_builder(config, &evm_opts)?)?.fork_blocks(opts.fork_blocks);
+    // tests that invoked cheatcodes which read from outside of the EVM are never cached
+    if opts.cache_results {
+        builder = builder.record_cheatcodes(true);
+    }
+    builder.build(&config.__root.0, output, evm_opts)
+}
+
+/// The runs of the tests ahead of the actual run
+#[derive(Default)]
+pub struct Reruns {
+    /// The results the gas usage of the actual run is compared against, see
+    /// [RunOptions::gas_determinism_check]
+    pub gas_baseline: Option<TestReport>,
+    /// The results of the repeated runs, see [RunOptions::repeat]
+    pub repeated_results: Vec<TestReport>,
+}
+
+impl Reruns {
+    /// Runs the tests that match the filter ahead of the actual run, every run with a fresh runner
+    /// built by `build`
+    pub fn run(
+        build: impl Fn() -> eyre::Result<MultiContractRunner>,
+        filter: &(impl TestFilter + Send + Sync),
+        opts: &RunOptions,
+    ) -> eyre::Result<Self> {
+        let include_fuzz_tests = opts.include_fuzz_tests;
+        let gas_baseline = if opts.gas_determinism_check {
+            Some(build()?.test(filter, None, include_fuzz_tests)?)
+        } else {
+            None
+        };
+        let repeated_results = (1..opts.repeat)
+            .map(|_| build()?.test(filter, None, include_fuzz_tests))
+            .collect::<eyre::Result<Vec<_>>>()?;
+        Ok(Self { gas_baseline, repeated_results })
+    }
+}
+
+/// The cached results of the project, see [RunOptions::cache_results]
+#[derive(Debug)]
+pub struct RunCache {
+    /// Where the cache is stored
+    path: PathBuf,
+    cache: TestResultCache,
+    /// The hashes of the test contracts of the runner, see
+    /// [MultiContractRunner::suite_hashes()]
+    hashes: BTreeMap<String, H256>,
+}
+
+impl RunCache {
+    /// Reads the cached results of the project and sets those that are still valid as the cached
+    /// results of the runner, matching tests are not run again
+    pub fn load(config: &Config, runner: &mut MultiContractRunner) -> Self {
+        let path = config.__root.0.join(&config.cache_path).join(TEST_RESULTS_CACHE_FILE);
+        let cache = TestResultCache::read(&path);
+        let hashes = runner.suite_hashes();
+        runner.cached_results = cache.results(&hashes);
+        Self { path, cache, hashes }
+    }
+
+    /// Caches the results of passing tests of the run and writes the cache
+    pub fn update(mut self, results: &TestReport) -> eyre::Result<()> {
+        self.cache.update(&self.hashes, results);
+        self.cache.write(&self.path)
+    }
+}
+
+/// Runs the tests of the runner that match the filter and returns their results.
+///
+/// As soon as a test contract completed, its unit tests fail if their gas usage differs from
+/// `gas_baseline`, see [RunOptions::gas_determinism_check], its results are passed to `on_suite`
+/// and appended to the results file, see [RunOptions::results_file].
+pub fn stream<F>(
+    mut runner: MultiContractRunner,
+    filter: F,
+    opts: &RunOptions,
+    gas_baseline: Option<&TestReport>,
+    mut on_suite: impl FnMut(&str, &mut SuiteResult) -> eyre::Result<()>,
+) -> eyre::Result<TestReport>
+where
+    F: TestFilter + Send + Sync + 'static,
+{
+    let mut results_file = opts.results_file.as_deref().map(ResultsFile::create).transpose()?;
+    let include_fuzz_tests = opts.include_fuzz_tests;
+    let (tx, rx) = channel::<(String, SuiteResult)>();
+    let handle = thread::spawn(move || runner.test(&filter, Some(tx), include_fuzz_tests));
+
+    let mut results = BTreeMap::new();
+    for (contract_name, mut suite_result) in rx {
+        if let Some(baseline) = gas_baseline.and_then(|baseline| baseline.get(&contract_name)) {
+            check_gas_determinism(baseline, &mut suite_result);
+        }
+        on_suite(&contract_name, &mut suite_result)?;
+        if let Some(ref mut results_file) = results_file {
+            results_file.append(&contract_name, &suite_result)?;
+        }
+        results.insert(contract_name, suite_result);
+    }
+    handle.join().expect("test thread panicked")?;
+    Ok(results)
+}
+
+/// Fails every unit test in `suite` whose gas usage differs from the same test in `baseline`
+fn check_gas_determinism(baseline: &SuiteResult, suite: &mut SuiteResult) {
+    for (name, result) in &mut suite.test_results {
+        let expected = match baseline.test_results.get(name) {
+            Some(expected) if result.is_unit() && expected.is_unit() => expected,
+            _ => continue,
+        };
+        let (expected_gas, gas) = (expected.kind.gas_used().gas(), result.kind.gas_used().gas());
+        if expected_gas != gas {
+            result.success = false;
+            result.reason = Some(format!(
+                "Non-deterministic gas usage: {} in the first run, {} in the second run. The test likely depends on the time or on randomness",
+                expected_gas, gas
+            ));
+        }
+    }
+}
+
+/// Writes test results to a file as newline delimited JSON
+#[derive(Debug)]
+pub struct ResultsFile {
+    file: fs::File,
+}
+
+impl ResultsFile {
+    /// Creates the file at `path`, truncating it if it exists
+    pub fn create(path: &Path) -> eyre::Result<Self> {
+        let file = fs::File::create(path)
+            .wrap_err_with(|| format!("Failed to create results file {}", path.display()))?;
+        Ok(Self { file })
+    }
+
+    /// Appends a line for every test of the suite and flushes the file
+    pub fn append(&mut self, contract_name: &str, suite_result: &SuiteResult) -> eyre::Result<()> {
+        for (name, result) in &suite_result.test_results {
+            let line =
+                serde_json::json!({ "contract": contract_name, "test": name, "result": result });
+            writeln!(self.file, "{}", line)?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes the results of the run into `dir` and returns the paths of the written files: the
+/// results as JSON and JUnit XML, the gas used by every test and the traces of failed tests
+pub fn write_output_dir(dir: &Path, results: &TestReport) -> eyre::Result<Vec<PathBuf>> {
+    let traces_dir = dir.join("traces");
+    fs::create_dir_all(&traces_dir)
+        .wrap_err_with(|| format!("Failed to create output directory {}", dir.display()))?;
+
+    let mut written = Vec::new();
+    let mut write = |path: PathBuf, content: String| -> eyre::Result<()> {
+        fs::write(&path, content)
+            .wrap_err_with(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+        Ok(())
+    };
+
+    write(dir.join("results.json"), report::to_json(results)?)?;
+    write(dir.join("report.junit.xml"), junit::to_junit(results))?;
+
+    let gas = results
+        .iter()
+        .map(|(suite, suite_result)| {
+            let tests = suite_result
+                .test_results
+                .iter()
+                .map(|(test, result)| (test.as_str(), result.kind.gas_used().gas()))
+                .collect::<BTreeMap<_, _>>();
+            (suite.as_str(), tests)
+        })
+        .collect::<BTreeMap<_, _>>();
+    write(dir.join("gas.json"), serde_json::to_string_pretty(&gas)?)?;
+
+    // the traces are placed by the source path of the contract, contracts of the same name in
+    // different files do not overwrite each other's traces
+    for (suite, suite_result) in results {
+        let (source, contract) = suite.rsplit_once(':').unwrap_or(("", suite));
+        let suite_dir = traces_dir.join(
+            Path::new(source)
+                .components()
+                .filter(|component| matches!(component, Component::Normal(_)))
+                .collect::<PathBuf>(),
+        );
+        for (test, result) in suite_result.test_results.iter().filter(|(_, r)| !r.success) {
+            fs::create_dir_all(&suite_dir).wrap_err_with(|| {
+                format!("Failed to create output directory {}", suite_dir.display())
+            })?;
+            let path = suite_dir.join(format!("{}.{}.json", contract, test));
+            write(path, serde_json::to_string_pretty(&result.traces)?)?;
+        }
+    }
+    Ok(written)
+}
+
+/// Reads the EVM options from the config
+pub fn evm_opts(config: &Config) -> eyre::Result<EvmOpts> {
+    Ok(Figment::from(config.clone()).extract()?)
+}
+
+/// Compiles the project of the config, returns an error if the compiler reported errors
+pub fn compile(config: &Config) -> eyre::Result<ProjectCompileOutput> {
+    let output = config.project()?.compile()?;
+    if output.has_compiler_errors() {
+        eyre::bail!("{}", output)
+    }
+    Ok(output)
+}
+
+/// Returns a builder of the test runner with the options of the config and the EVM options
+pub fn runner_builder(
+    config: &Config,
+    evm_opts: &EvmOpts,
+) -> eyre::Result<MultiContractRunnerBuilder> {
+    Ok(MultiContractRunnerBuilder::default()
+        .fuzzer(TestRunner::new(fuzz_config(config)))
+        .fuzz_weights(fuzz_weights(config)?)
+        .fuzz_address_pool_rate(config.fuzz_address_pool_rate)
+        .initial_balance(evm_opts.initial_balance)
+        .evm_spec(evm_spec(&config.evm_version))
+        .sender(evm_opts.sender)
+        .with_fork(get_fork(evm_opts, &config.rpc_storage_caching))
+        .with_memory_limit(evm_opts.memory_limit)
+        .with_output_encoding(config.output_encoding)
+        .test_prefixes(TestPrefixes::new(config.test_prefixes.clone())))
+}
+
+/// Returns the configuration of the fuzzer
+pub fn fuzz_config(config: &Config) -> proptest::test_runner::Config {
+    proptest::test_runner::Config {
+        failure_persistence: None,
+        cases: config.fuzz_runs,
+        max_local_rejects: config.fuzz_max_local_rejects,
+        max_global_rejects: config.fuzz_max_global_rejects,
+        ..Default::default()
+    }
+}
+
+/// Returns the weights of the input strategies of fuzz tests, `fuzz.boundary` takes precedence
+/// over `fuzz_boundary_weight`
+pub fn fuzz_weights(config: &Config) -> eyre::Result<FuzzWeights> {
+    let weights = FuzzWeights {
+        random: config.fuzz.random,
+        state: config.fuzz.state,
+        boundary: config.fuzz.boundary.unwrap_or(config.fuzz_boundary_weight),
+        zero: config.fuzz.zero,
+        max: config.fuzz.max,
+    };
+    weights.validate().wrap_err("Invalid `fuzz` weights in the config")?;
+    Ok(weights)
+}
+
+/// Returns the [SpecId] of the EVM version
+pub fn evm_spec(evm: &EvmVersion) -> SpecId {
+    match evm {
+        EvmVersion::Istanbul => SpecId::ISTANBUL,
+        EvmVersion::Berlin => SpecId::BERLIN,
+        EvmVersion::London => SpecId::LONDON,
+        _ => panic!("Unsupported EVM version"),
+    }
+}
+
+/// Helper function that returns the [Fork] to use, if any.
+///
+/// storage caching for the [Fork] will be enabled if
+///   - `fork_url` is present
+///   - `fork_block_number` is present
+///   - [StorageCachingConfig] allows the `fork_url` +  chain id pair
+///   - storage is allowed (`no_storage_caching = false`)
+///
+/// If all these criteria are met, then storage caching is enabled and storage info will be written
+/// to [Config::foundry_cache_dir()]/<str(chainid)>/<block>/storage.json
+///
+/// for `mainnet` and `--fork-block-number 14435000` on mac the corresponding storage cache will be
+/// at `~/.foundry/cache/mainnet/14435000/storage.json`
+pub fn get_fork(evm_opts: &EvmOpts, config: &StorageCachingConfig) -> Option<Fork> {
+    /// Returns the path where the cache file should be stored
+    ///
+    /// or `None` if caching should not be enabled
+    ///
+    /// See also [ Config::foundry_block_cache_file()]
+    fn get_block_storage_path(
+        evm_opts: &EvmOpts,
+        config: &StorageCachingConfig,
+        chain_id: u64,
+    ) -> Option<PathBuf> {
+        if evm_opts.no_storage_caching {
+            // storage caching explicitly opted out of
+            return None
+        }
+        let url = evm_opts.fork_url.as_ref()?;
+        // cache only if block explicitly pinned
+        let block = evm_opts.fork_block_number?;
+
+        if config.enable_for_endpoint(url) && config.enable_for_chain_id(chain_id) {
+            return Config::foundry_block_cache_file(chain_id, block)
+        }
+
+        None
+    }
+
+    if let Some(ref url) = evm_opts.fork_url {
+        let chain_id = evm_opts.get_chain_id();
+        let cache_storage = get_block_storage_path(evm_opts, config, chain_id);
+        let fork = Fork {
+            url: url.clone(),
+            pin_block: evm_opts.fork_block_number,
+            cache_path: cache_storage,
+            chain_id,
+        };
+        return Some(fork)
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::{filter::Filter, COMPILED, EVM_OPTS, PROJECT};
+    use ethers::types::Address;
+
+    #[test]
+    fn can_read_evm_opts() {
+        let config = Config { sender: Address::from_low_u64_be(0x1337), ..Default::default() };
+        assert_eq!(evm_opts(&config).unwrap().sender, config.sender);
+    }
+
+    #[test]
+    fn can_run_with_config_options() {
+        let config = Config { __root: PROJECT.paths.root.clone().into(), ..Default::default() };
+        let opts = RunOptions {
+            evm_opts: Some(EVM_OPTS.clone()),
+            output: Some((*COMPILED).clone()),
+            ..Default::default()
+        };
+        let mut runner = build_runner(&config, opts, Ok).unwrap();
+        let filter = Filter::new(".*", ".*", ".*core/SetupConsistency");
+        let results = runner.test(&filter, None, true).unwrap();
+        let results = results.values().flat_map(|suite| suite.test_results.values());
+        assert_eq!(results.clone().count(), 2);
+        assert!(results.into_iter().all(|result| result.success));
+    }
+
+    #[test]
+    fn can_run_tests_repeatedly() {
+        let config = Config { __root: PROJECT.paths.root.clone().into(), ..Default::default() };
+        let opts = RunOptions {
+            evm_opts: Some(EVM_OPTS.clone()),
+            output: Some((*COMPILED).clone()),
+            repeat: 3,
+            gas_determinism_check: true,
+            ..Default::default()
+        };
+        let filter = Filter::new(".*", ".*", ".*core/SetupConsistency");
+        let run = run_tests(&config, filter, opts).unwrap();
+        assert_eq!(run.repeated_results.len(), 2);
+        assert!(run.written.is_empty());
+        for results in run.repeated_results.iter().chain(std::iter::once(&run.results)) {
+            let results = results.values().flat_map(|suite| suite.test_results.values());
+            assert_eq!(results.clone().count(), 2);
+            assert!(results.into_iter().all(|result| result.success));
+        }
+    }
+}
@@ -1,36 +1,140 @@
-use crate::TestFilter;
+use crate::{
+    branches::{BranchCoverage, ContractBranches},
+    coverage::{line_hits, ContractLines, LineHits},
+    deadline::FuzzBudget,
+    gas_report::round_gas,
+    storage::{diff_storage, StorageChange},
+    TestFilter,
+};
 use ethers::{
-    abi::{Abi, Function, RawLog},
-    types::{Address, Bytes, U256},
+    abi::{Abi, Function, ParamType, RawLog, Token},
+    prelude::ArtifactId,
+    solc::artifacts::StorageLayout,
+    types::{Address, Bytes, H256, U256},
 };
 use eyre::Result;
 use foundry_evm::{
-    executor::{CallResult, DatabaseRef, DeployResult, EvmError, Executor},
-    fuzz::{CounterExample, FuzzedCases, FuzzedExecutor},
+    executor::{
+        fork::count_rpc_calls, inspector::SKIP_REASON, CallResult, DatabaseRef, DeployResult,
+        EvmError, Executor,
+    },
+    fuzz::{
+        fit_fixture, fixture_name, CounterExample, CustomStrategies, FuzzConfig, FuzzFixtures,
+        FuzzWeights, FuzzedCases, FuzzedExecutor,
+    },
     trace::{CallTraceArena, TraceKind},
     CALLER,
 };
+use foundry_utils::{format_token, AbiNames};
 use proptest::test_runner::TestRunner;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 
 /// Results and duration for a set of tests included in the same test contract
-#[derive(Clone, Serialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SuiteResult {
     /// Total duration of the test run for this block of tests
     pub duration: Duration,
+    /// Time spent deploying the test contract and its libraries and running `setUp`
+    #[serde(default)]
+    pub setup_duration: Duration,
+    /// The number of RPC calls made by a fork while deploying the test contract and running
+    /// `setUp`
+    #[serde(default)]
+    pub setup_rpc_calls: usize,
+    /// The size of the runtime code of the test contract, if code sizes are checked. If the
+    /// contract could not be deployed, this is the size of its compiled runtime code, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_size: Option<usize>,
+    /// The address the test contract was deployed at, if it was deployed with `CREATE2`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<Address>,
+    /// The number of times `setUp` was run, more than once if it was retried after it failed
+    #[serde(default)]
+    pub setup_attempts: usize,
+    /// Why `setUp` failed, if it did. The tests of the contract are then not run and the only
+    /// test result is the failed `setUp()`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub setup_failure: Option<String>,
     /// Individual test results. `test method name -> TestResult`
     pub test_results: BTreeMap<String, TestResult>,
 }
 
 impl SuiteResult {
     pub fn new(duration: Duration, test_results: BTreeMap<String, TestResult>) -> Self {
-        Self { duration, test_results }
+        Self {
+            duration,
+            setup_duration: Duration::ZERO,
+            setup_rpc_calls: 0,
+            code_size: None,
+            address: None,
+            setup_attempts: 0,
+            setup_failure: None,
+            test_results,
+        }
+    }
+
+    /// Sets the time spent deploying the test contract and running `setUp`
+    #[must_use]
+    pub fn with_setup_duration(mut self, setup_duration: Duration) -> Self {
+        self.setup_duration = setup_duration;
+        self
+    }
+
+    /// Sets the number of RPC calls made while deploying the test contract and running `setUp`
+    #[must_use]
+    pub fn with_setup_rpc_calls(mut self, setup_rpc_calls: usize) -> Self {
+        self.setup_rpc_calls = setup_rpc_calls;
+        self
+    }
+
+    /// Sets the size of the runtime code of the test contract
+    #[must_use]
+    pub fn with_code_size(mut self, code_size: Option<usize>) -> Self {
+        self.code_size = code_size;
+        self
+    }
+
+    /// Sets the address the test contract was deployed at
+    #[must_use]
+    pub fn with_address(mut self, address: Option<Address>) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Sets the number of times `setUp` was run
+    #[must_use]
+    pub fn with_setup_attempts(mut self, setup_attempts: usize) -> Self {
+        self.setup_attempts = setup_attempts;
+        self
+    }
+
+    /// Sets why `setUp` failed
+    #[must_use]
+    pub fn with_setup_failure(mut self, setup_failure: Option<String>) -> Self {
+        self.setup_failure = setup_failure;
+        self
+    }
+
+    /// Returns the time spent executing the tests, summed over all tests
+    pub fn execution_duration(&self) -> Duration {
+        self.test_results.values().map(|result| result.duration).sum()
+    }
+
+    /// Returns the number of RPC calls made by a fork for the setup and all tests
+    pub fn rpc_calls(&self) -> usize {
+        let test_rpc_calls: usize = self.test_results.values().map(|result| result.rpc_calls).sum();
+        self.setup_rpc_calls + test_rpc_calls
     }
 
     pub fn is_empty(&self) -> bool {
@@ -43,13 +147,18 @@ impl SuiteResult {
 }
 
 /// The result of an executed solidity test
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct TestResult {
     /// Whether the test case was successful. This means that the transaction executed
     /// properly, or that there was a revert and that the test was expected to fail
     /// (prefixed with `testFail`)
     pub success: bool,
 
+    /// Whether the test skipped itself with the `skip` cheatcode. Skipped tests are successful,
+    /// but are counted separately from passing tests
+    #[serde(default)]
+    pub skipped: bool,
+
     /// If there was a revert, this field will be populated. Note that the test can
     /// still be successful (i.e self.success == true) when it's expected to fail.
     pub reason: Option<String>,
@@ -68,14 +177,131 @@ pub struct TestResult {
     /// Traces
     pub traces: Vec<(TraceKind, CallTraceArena)>,
 
-    /// Labeled addresses
+    /// Labeled addresses, these are part of the JSON output so traces can be rendered with the
+    /// labels
     pub labeled_addresses: BTreeMap<Address, String>,
+
+    /// Whether the test (or its setup) invoked the FFI cheatcode
+    pub used_ffi: bool,
+
+    /// The names of the cheatcodes the test (or its setup) invoked, if they were recorded
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub used_cheatcodes: BTreeSet<String>,
+
+    /// Structured fields extracted from the console logs by a
+    /// [LogProcessor](foundry_evm::decode::LogProcessor)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub log_fields: BTreeMap<String, String>,
+
+    /// The calls leading to the call a revert originated in, if the test failed and was traced
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub revert_path: Vec<String>,
+
+    /// The storage values changed by the test, if storage diffs were enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub storage_diff: Vec<StorageChange>,
+
+    /// Time spent executing the test, including all fuzz runs
+    #[serde(default)]
+    pub duration: Duration,
+
+    /// The number of RPC calls a fork made to fetch the state the test read
+    #[serde(default)]
+    pub rpc_calls: usize,
+
+    /// The maximum call depth the test reached, if it was recorded. The call to the test function
+    /// is at depth 0, see [CallDepth](foundry_evm::executor::inspector::CallDepth)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_call_depth: Option<usize>,
+
+    /// The number of assertions the test executed, if they were counted. For fuzz tests this is
+    /// the fewest assertions of any fuzz case, see [assertions](crate::assertions)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assertions: Option<usize>,
+
+    /// The branch coverage of the functions the fuzz test called, if it was recorded, see
+    /// [branches](crate::branches)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_coverage: Option<BranchCoverage>,
+
+    /// The runs the fuzz test was configured with, if it made fewer to finish before the
+    /// deadline, see [deadline](crate::deadline)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub configured_runs: Option<u32>,
+
+    /// The gas refunded to the test, e.g. for clearing storage, if refunds were recorded. The gas
+    /// of the test is net of it. For fuzz tests this is the mean refund of the passing fuzz cases
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_refund: Option<u64>,
+
+    /// The hit counts of the source lines of the contracts the test called, if line coverage was
+    /// recorded, see [LineCoverage](crate::coverage::LineCoverage). They are not part of the JSON
+    /// output
+    #[serde(skip)]
+    pub line_hits: Option<LineHits>,
+
+    /// The decoded values the test function returned, if it is a unit test with outputs that did
+    /// not revert
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub returns: Vec<ReturnValue>,
+}
+
+/// A value returned by a test function
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReturnValue {
+    /// The name of the output, empty if it is unnamed
+    pub name: String,
+    /// The Solidity type of the output
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The value, formatted like the arguments of counterexamples
+    pub value: String,
+}
+
+impl ReturnValue {
+    /// Pairs the decoded return data of the function with its outputs
+    pub fn decode(func: &Function, result: Token) -> Vec<Self> {
+        let tokens = match (func.outputs.len(), result) {
+            (1, token) => vec![token],
+            (_, Token::Tuple(tokens)) => tokens,
+            (_, token) => vec![token],
+        };
+        func.outputs
+            .iter()
+            .zip(tokens)
+            .map(|(output, token)| ReturnValue {
+                name: output.name.clone(),
+                kind: output.kind.to_string(),
+                value: format_token(&token),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for ReturnValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            f.write_str(&self.value)
+        } else {
+            write!(f, "{}: {}", self.name, self.value)
+        }
+    }
 }
 
 impl TestResult {
     /// Returns `true` if this is the result of a fuzz test
     pub fn is_fuzz(&self) -> bool {
-        matches!(self.kind, TestKind::Fuzz(_))
+        self.kind.is_fuzz()
+    }
+
+    /// Returns `true` if this is the result of a standard unit test
+    pub fn is_unit(&self) -> bool {
+        self.kind.is_unit()
+    }
+
+    /// Returns the number of executed fuzz cases if this is the result of a fuzz test
+    pub fn fuzz_runs(&self) -> Option<usize> {
+        self.kind.fuzz_runs()
     }
 }
 
@@ -108,6 +334,18 @@ impl TestKindGas {
             TestKindGas::Fuzz { median, .. } => *median,
         }
     }
+
+    /// Returns the gas rounded to the nearest multiple of `granularity`, see [round_gas]
+    pub fn rounded(&self, granularity: u64) -> Self {
+        match *self {
+            TestKindGas::Standard(gas) => TestKindGas::Standard(round_gas(gas, granularity)),
+            TestKindGas::Fuzz { runs, mean, median } => TestKindGas::Fuzz {
+                runs,
+                mean: round_gas(mean, granularity),
+                median: round_gas(median, granularity),
+            },
+        }
+    }
 }
 
 /// Various types of tests
@@ -121,7 +359,31 @@ pub enum TestKind {
     Fuzz(FuzzedCases),
 }
 
+impl Default for TestKind {
+    fn default() -> Self {
+        TestKind::Standard(0)
+    }
+}
+
 impl TestKind {
+    /// Returns `true` if this is a fuzz test
+    pub fn is_fuzz(&self) -> bool {
+        matches!(self, TestKind::Fuzz(_))
+    }
+
+    /// Returns `true` if this is a standard unit test
+    pub fn is_unit(&self) -> bool {
+        matches!(self, TestKind::Standard(_))
+    }
+
+    /// Returns the number of executed cases if this is a fuzz test
+    pub fn fuzz_runs(&self) -> Option<usize> {
+        match self {
+            TestKind::Fuzz(fuzzed) => Some(fuzzed.cases().len()),
+            _ => None,
+        }
+    }
+
     /// The gas consumed by this test
     pub fn gas_used(&self) -> TestKindGas {
         match self {
@@ -135,6 +397,86 @@ impl TestKind {
     }
 }
 
+/// Selects the kinds of tests to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestSelection {
+    /// Run unit tests, and fuzz tests if fuzz tests are included
+    All,
+    /// Only run fuzz tests, i.e. test functions with parameters.
+    ///
+    /// This takes precedence over excluding fuzz tests.
+    OnlyFuzz,
+    /// Only run unit tests, i.e. test functions without parameters
+    OnlyUnit,
+}
+
+impl Default for TestSelection {
+    fn default() -> Self {
+        TestSelection::All
+    }
+}
+
+impl TestSelection {
+    /// Returns `true` if the test function is selected
+    pub fn matches(&self, func: &Function, include_fuzz_tests: bool) -> bool {
+        match self {
+            TestSelection::All => include_fuzz_tests || func.inputs.is_empty(),
+            TestSelection::OnlyFuzz => !func.inputs.is_empty(),
+            TestSelection::OnlyUnit => func.inputs.is_empty(),
+        }
+    }
+}
+
+/// The maximum size of the runtime code of a contract in bytes, see
+/// [EIP-170](https://eips.ethereum.org/EIPS/eip-170)
+pub const CODE_SIZE_LIMIT: usize = 24576;
+
+/// The prefix of test functions if no other prefixes are configured
+pub const DEFAULT_TEST_PREFIX: &str = "test";
+
+/// The prefixes of the names of test functions, [DEFAULT_TEST_PREFIX] by default.
+///
+/// The `testFail` semantics apply relative to the prefix: a test is expected to fail if its name
+/// continues with `Fail` after the prefix, e.g. `testFailOverflow` or `check_FailOverflow`.
+/// Whether a test is a fuzz test only depends on whether it takes parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestPrefixes(Vec<String>);
+
+impl Default for TestPrefixes {
+    fn default() -> Self {
+        Self(vec![DEFAULT_TEST_PREFIX.to_string()])
+    }
+}
+
+impl TestPrefixes {
+    /// Creates the prefixes, the default prefix is used if none are given
+    pub fn new(prefixes: Vec<String>) -> Self {
+        if prefixes.is_empty() {
+            Self::default()
+        } else {
+            Self(prefixes)
+        }
+    }
+
+    /// Returns the prefixes
+    pub fn prefixes(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Returns `true` if the function name starts with one of the prefixes
+    pub fn is_test(&self, name: &str) -> bool {
+        self.0.iter().any(|prefix| name.starts_with(prefix.as_str()))
+    }
+
+    /// Returns `true` if the function name starts with one of the prefixes followed by `Fail`,
+    /// i.e. the test is expected to fail
+    pub fn is_test_fail(&self, name: &str) -> bool {
+        self.0.iter().any(|prefix| {
+            name.strip_prefix(prefix.as_str()).map(|rest| rest.starts_with("Fail")).unwrap_or(false)
+        })
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct TestSetup {
     /// The address at which the test contract was deployed
@@ -149,6 +491,14 @@ pub struct TestSetup {
     pub setup_failed: bool,
     /// The reason the setup failed
     pub reason: Option<String>,
+    /// Whether the setup invoked the FFI cheatcode
+    pub used_ffi: bool,
+    /// The cheatcodes invoked during setup, if they are recorded
+    pub used_cheatcodes: BTreeSet<String>,
+    /// The size of the runtime code of the deployed test contract
+    pub code_size: Option<usize>,
+    /// The number of times `setUp` was run
+    pub attempts: usize,
 }
 
 pub struct ContractRunner<'a, DB: DatabaseRef> {
@@ -168,6 +518,72 @@ pub struct ContractRunner<'a, DB: DatabaseRef> {
     pub initial_balance: U256,
     /// The address which will be used as the `from` field in all EVM calls
     pub sender: Address,
+    /// The weights of the input strategies of fuzz tests, see [FuzzedExecutor::with_weights()]
+    pub fuzz_weights: FuzzWeights,
+    /// The percentage of random `address` inputs of fuzz tests drawn from the known addresses,
+    /// see [FuzzedExecutor::with_address_pool_rate()]
+    pub fuzz_address_pool_rate: u32,
+    /// Custom strategies for the inputs of fuzz tests, see
+    /// [FuzzedExecutor::with_custom_strategies()]
+    pub fuzz_strategies: CustomStrategies,
+    /// The kinds of tests to run
+    pub test_selection: TestSelection,
+    /// The prefixes of test functions
+    pub test_prefixes: TestPrefixes,
+    /// If set, the flag is raised once a test fails and the remaining tests are skipped
+    pub fail_fast: Option<Arc<AtomicBool>>,
+    /// If set, counts the failed tests of all runners sharing the counter, and the fail-fast flag
+    /// is only raised once `max_failures` tests failed
+    pub failures: Option<Arc<AtomicUsize>>,
+    /// The number of failed tests after which the fail-fast flag is raised, if `failures` is set
+    pub max_failures: usize,
+    /// If set, the tests are run in an order shuffled with this seed, they only run in that
+    /// order if the current rayon pool has a single thread
+    pub shuffle_seed: Option<u64>,
+    /// If set, the storage changes of each unit test are recorded for these addresses, or for the
+    /// test contract if empty
+    pub storage_diff: Option<&'a [Address]>,
+    /// The storage layout of the test contract, used to decode its storage changes
+    pub storage_layout: Option<&'a StorageLayout>,
+    /// If set, only the tests with these signatures are run
+    pub only_tests: Option<&'a BTreeSet<String>>,
+    /// The names of the functions marked as callbacks, which are not run as tests
+    pub callbacks: Option<&'a BTreeSet<String>>,
+    /// Results of tests that are reused instead of running the tests, by signature
+    pub cached_results: Option<&'a BTreeMap<String, TestResult>>,
+    /// Whether to only deploy the contract and run `setUp`, without running any tests
+    pub setup_only: bool,
+    /// If set, the setup fails if the runtime code of the test contract is larger than this many
+    /// bytes. Contracts larger than [CODE_SIZE_LIMIT] can not be deployed either way
+    pub code_size_limit: Option<usize>,
+    /// If set, the gas the calls of tests are given instead of the gas limit of the executor, the
+    /// deployment and `setUp` still get the gas limit
+    pub gas_stipend: Option<u64>,
+    /// If set, the test contract is deployed with `CREATE2` and this salt instead of `CREATE`, so
+    /// its address only depends on the sender and its bytecode
+    pub deploy_salt: Option<H256>,
+    /// How often a failed `setUp` is retried, on the state right after the deployment of the test
+    /// contract
+    pub setup_retries: u32,
+    /// If set, the assertions of the tests are counted, given the program counters of the jumps
+    /// into assertion functions in the runtime code of the test contract
+    pub assertion_jumps: Option<&'a BTreeSet<usize>>,
+    /// The parameter names of the test contract's functions, used to print the struct fields of
+    /// counterexamples
+    pub abi_names: Option<&'a AbiNames>,
+    /// Whether unit tests fail if a contract other than the test contract executed
+    /// `SELFDESTRUCT`, requires tracing
+    pub no_selfdestruct: bool,
+    /// If set, the branch coverage of fuzz tests is computed for these contracts, requires
+    /// branch recording
+    pub branches: Option<&'a BTreeMap<ArtifactId, ContractBranches>>,
+    /// If set, fuzz tests whose branch coverage is lower fail, in percent
+    pub fuzz_min_coverage: Option<f64>,
+    /// If set, the runs of fuzz tests are reduced to finish before the deadline of the budget
+    pub fuzz_budget: Option<&'a FuzzBudget>,
+    /// If set, the lines of these contracts that tests execute are counted, requires instruction
+    /// recording
+    pub lines: Option<&'a BTreeMap<ArtifactId, ContractLines>>,
 }
 
 impl<'a, DB: DatabaseRef> ContractRunner<'a, DB> {
@@ -189,11 +605,42 @@ impl<'a, DB: DatabaseRef> ContractRunner<'a, DB> {
             sender: sender.unwrap_or_default(),
             errors,
             predeploy_libs,
+            fuzz_weights: FuzzWeights::default(),
+            fuzz_address_pool_rate: 0,
+            fuzz_strategies: CustomStrategies::default(),
+            test_selection: TestSelection::All,
+            test_prefixes: TestPrefixes::default(),
+            fail_fast: None,
+            failures: None,
+            max_failures: 1,
+            shuffle_seed: None,
+            storage_diff: None,
+            storage_layout: None,
+            only_tests: None,
+            callbacks: None,
+            cached_results: None,
+            setup_only: false,
+            code_size_limit: None,
+            gas_stipend: None,
+            deploy_salt: None,
+            setup_retries: 0,
+            assertion_jumps: None,
+            abi_names: None,
+            no_selfdestruct: false,
+            branches: None,
+            fuzz_min_coverage: None,
+            fuzz_budget: None,
+            lines: None,
         }
     }
+
+    /// Returns `true` if the function is marked as a callback, see [callbacks](crate::callbacks)
+    fn is_callback(&self, func: &Function) -> bool {
+        self.callbacks.map(|callbacks| callbacks.contains(&func.name)).unwrap_or_default()
+    }
 }
 
-impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
+impl<'a, DB: DatabaseRef + Clone + Send + Sync> ContractRunner<'a, DB> {
     /// Deploys the test contract inside the runner from the sending account, and optionally runs
     /// the `setUp` function on the test contract.
     pub fn setup(&mut self, setup: bool) -> Result<TestSetup> {
@@ -220,12 +667,49 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             .collect();
 
         // Deploy an instance of the contract
-        let DeployResult { address, mut logs, traces: constructor_traces, .. } = self
-            .executor
-            .deploy(self.sender, self.code.0.clone(), 0u32.into())
-            .expect("couldn't deploy");
+        let deployed = match self.deploy_salt {
+            Some(salt) => {
+                self.executor.deploy_create2(self.sender, self.code.0.clone(), 0u32.into(), salt)
+            }
+            None => self.executor.deploy(self.sender, self.code.0.clone(), 0u32.into()),
+        };
+        let DeployResult { address, mut logs, traces: constructor_traces, .. } = match deployed {
+            Ok(deployed) => deployed,
+            Err(err) => {
+                return Ok(TestSetup {
+                    traces,
+                    setup_failed: true,
+                    reason: Some(format!("Setup failed: {}", err)),
+                    ..Default::default()
+                })
+            }
+        };
         traces.extend(constructor_traces.map(|traces| (TraceKind::Deployment, traces)).into_iter());
 
+        let code_size = self.executor.db.basic(address).code.map(|code| code.len());
+        if let Some(limit) = self.code_size_limit {
+            let size = code_size.unwrap_or_default();
+            if size > limit {
+                return Ok(TestSetup {
+                    address,
+                    logs,
+                    traces,
+                    setup_failed: true,
+                    reason: Some(format!(
+                        "Setup failed: the runtime code is {} bytes, more than the code size limit \
+                         of {} bytes",
+                        size, limit
+                    )),
+                    code_size,
+                    ..Default::default()
+                })
+            }
+        }
+
+        if let Some(jumps) = self.assertion_jumps {
+            self.executor.count_assertions(address, jumps.clone());
+        }
+
         // Now we set the contracts initial balance, and we also reset `self.sender`s balance to
         // the initial balance we want
         self.executor.set_balance(address, self.initial_balance);
@@ -234,28 +718,75 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         // Optionally call the `setUp` function
         Ok(if setup {
             tracing::trace!("setting up");
-            let (setup_failed, setup_logs, setup_traces, labeled_addresses, reason) = match self
-                .executor
-                .setup(address)
-            {
-                Ok(CallResult { traces, labels, logs, .. }) => (false, logs, traces, labels, None),
-                Err(EvmError::Execution { traces, labels, logs, reason, .. }) => {
-                    (true, logs, traces, labels, Some(format!("Setup failed: {}", reason)))
+            // the state after the deployment, restored before a failed `setUp` is retried
+            let deployed = (self.setup_retries > 0).then(|| self.executor.clone());
+            let mut attempts = 0;
+            let (
+                setup_failed,
+                setup_logs,
+                setup_traces,
+                labeled_addresses,
+                used_ffi,
+                used_cheatcodes,
+                reason,
+            ) = loop {
+                attempts += 1;
+                let result = match self.executor.setup(address) {
+                    Ok(CallResult { traces, labels, logs, used_ffi, used_cheatcodes, .. }) => {
+                        (false, logs, traces, labels, used_ffi, used_cheatcodes, None)
+                    }
+                    Err(EvmError::Execution {
+                        traces,
+                        labels,
+                        logs,
+                        used_ffi,
+                        used_cheatcodes,
+                        reason,
+                        ..
+                    }) => (
+                        true,
+                        logs,
+                        traces,
+                        labels,
+                        used_ffi,
+                        used_cheatcodes,
+                        Some(format!("Setup failed: {}", reason)),
+                    ),
+                    Err(e) => (
+                        true,
+                        Vec::new(),
+                        None,
+                        BTreeMap::new(),
+                        false,
+                        BTreeSet::new(),
+                        Some(format!("Setup failed: {}", &e.to_string())),
+                    ),
+                };
+                match deployed {
+                    Some(ref deployed) if result.0 && attempts <= self.setup_retries as usize => {
+                        tracing::debug!(attempts, reason = ?result.6, "retrying setup");
+                        self.executor = deployed.clone();
+                    }
+                    _ => break result,
                 }
-                Err(e) => (
-                    true,
-                    Vec::new(),
-                    None,
-                    BTreeMap::new(),
-                    Some(format!("Setup failed: {}", &e.to_string())),
-                ),
             };
             traces.extend(setup_traces.map(|traces| (TraceKind::Setup, traces)).into_iter());
             logs.extend_from_slice(&setup_logs);
 
-            TestSetup { address, logs, traces, labeled_addresses, setup_failed, reason }
+            TestSetup {
+                address,
+                logs,
+                traces,
+                labeled_addresses,
+                setup_failed,
+                reason,
+                used_ffi,
+                used_cheatcodes,
+                code_size,
+                attempts,
+            }
         } else {
-            TestSetup { address, logs, traces, ..Default::default() }
+            TestSetup { address, logs, traces, code_size, ..Default::default() }
         })
     }
 
@@ -270,54 +801,154 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         let start = Instant::now();
         let needs_setup = self.contract.functions().any(|func| func.name == "setUp");
 
-        let setup = self.setup(needs_setup)?;
-        if setup.setup_failed {
-            // The setup failed, so we return a single test result for `setUp`
+        let (setup, setup_rpc_calls) = count_rpc_calls(|| self.setup(needs_setup));
+        let setup = setup?;
+        let setup_duration = start.elapsed();
+        // the code size is only reported if it is checked
+        let code_size = self.code_size_limit.and(setup.code_size);
+        // the address is only reported if it is deterministic
+        let address = self.deploy_salt.and(Some(setup.address)).filter(|addr| !addr.is_zero());
+        if setup.setup_failed || self.setup_only {
+            let revert_path = if setup.setup_failed {
+                self.cancel();
+                revert_path(&setup.traces, TraceKind::Setup)
+            } else {
+                Vec::new()
+            };
+            let setup_failure = setup.setup_failed.then(|| {
+                let reason = setup.reason.as_deref().unwrap_or("setup failed");
+                reason.strip_prefix("Setup failed: ").unwrap_or(reason).to_string()
+            });
+            // The setup failed or only the setup is run, so we return a single test result for
+            // `setUp`
             return Ok(SuiteResult::new(
                 start.elapsed(),
                 [(
                     "setUp()".to_string(),
                     TestResult {
-                        success: false,
+                        success: !setup.setup_failed,
                         reason: setup.reason,
-                        counterexample: None,
                         logs: setup.logs,
-                        kind: TestKind::Standard(0),
                         traces: setup.traces,
                         labeled_addresses: setup.labeled_addresses,
+                        used_ffi: setup.used_ffi,
+                        used_cheatcodes: setup.used_cheatcodes,
+                        revert_path,
+                        ..Default::default()
                     },
                 )]
                 .into(),
-            ))
+            )
+            .with_setup_duration(start.elapsed())
+            .with_setup_rpc_calls(setup_rpc_calls)
+            .with_code_size(code_size)
+            .with_address(address)
+            .with_setup_attempts(setup.attempts)
+            .with_setup_failure(setup_failure))
+        }
+
+        // the deployment and `setUp` ran with the gas limit
+        if let Some(stipend) = self.gas_stipend {
+            self.executor.set_gas_limit(stipend.into());
         }
 
         // Collect valid test functions
-        let tests: Vec<_> = self
+        let mut tests: Vec<_> = self
             .contract
             .functions()
             .into_iter()
             .filter(|func| {
-                func.name.starts_with("test") &&
+                self.test_prefixes.is_test(&func.name) &&
                     filter.matches_test(func.signature()) &&
-                    (include_fuzz_tests || func.inputs.is_empty())
+                    self.test_selection.matches(func, include_fuzz_tests) &&
+                    self.only_tests
+                        .map(|tests| tests.contains(&func.signature()))
+                        .unwrap_or(true) &&
+                    !self.is_callback(func)
             })
-            .map(|func| (func, func.name.starts_with("testFail")))
+            .map(|func| (func, self.test_prefixes.is_test_fail(&func.name)))
             .collect();
+        if let Some(seed) = self.shuffle_seed {
+            tests.shuffle(&mut StdRng::seed_from_u64(seed));
+        }
 
-        let test_results = tests
+        // Reuse the cached results of tests instead of running them again
+        let mut cached_results = BTreeMap::new();
+        if let Some(cached) = self.cached_results {
+            tests.retain(|(func, _)| match cached.get(&func.signature()) {
+                Some(result) => {
+                    cached_results.insert(func.signature(), result.clone());
+                    false
+                }
+                None => true,
+            });
+        }
+
+        // An optional `setUpTest()` or `setUpTest(bytes4)` function runs before every test
+        let setup_test = self.contract.functions().find(|func| {
+            func.name == "setUpTest" &&
+                (func.inputs.is_empty() ||
+                    (func.inputs.len() == 1 &&
+                        func.inputs[0].kind == ParamType::FixedBytes(4)))
+        });
+
+        let mut test_results = tests
             .par_iter()
             .filter_map(|(func, should_fail)| {
-                let result = if func.inputs.is_empty() {
-                    Some(self.run_test(func, *should_fail, setup.clone()))
-                } else {
-                    fuzzer.as_ref().map(|fuzzer| {
-                        self.run_fuzz_test(func, *should_fail, fuzzer.clone(), setup.clone())
-                    })
+                if self.is_cancelled() {
+                    return None
+                }
+
+                let mut setup = setup.clone();
+                let test_executor;
+                let executor = match setup_test {
+                    Some(setup_test) => match self.setup_test(setup_test, func, &mut setup) {
+                        Ok(executor) => {
+                            test_executor = executor;
+                            &test_executor
+                        }
+                        Err(reason) => {
+                            self.cancel();
+                            let revert_path = revert_path(&setup.traces, TraceKind::Setup);
+                            return Some(Ok((
+                                func.signature(),
+                                TestResult {
+                                    reason: Some(reason),
+                                    logs: setup.logs,
+                                    traces: setup.traces,
+                                    labeled_addresses: setup.labeled_addresses,
+                                    used_ffi: setup.used_ffi,
+                                    used_cheatcodes: setup.used_cheatcodes,
+                                    revert_path,
+                                    ..Default::default()
+                                },
+                            )))
+                        }
+                    },
+                    None => &self.executor,
                 };
 
-                result.map(|result| Ok((func.signature(), result?)))
+                let (result, rpc_calls) = count_rpc_calls(|| {
+                    if func.inputs.is_empty() {
+                        Some(self.run_test(executor, func, *should_fail, setup))
+                    } else {
+                        fuzzer.as_ref().map(|fuzzer| {
+                            self.run_fuzz_test(executor, func, *should_fail, fuzzer.clone(), setup)
+                        })
+                    }
+                });
+
+                result.map(|result| {
+                    let mut result = result?;
+                    result.rpc_calls = rpc_calls;
+                    if !result.success {
+                        self.cancel();
+                    }
+                    Ok((func.signature(), result))
+                })
             })
             .collect::<Result<BTreeMap<_, _>>>()?;
+        test_results.extend(cached_results);
 
         let duration = start.elapsed();
         if !test_results.is_empty() {
@@ -329,37 +960,163 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
                 test_results.len()
             );
         }
-        Ok(SuiteResult::new(duration, test_results))
+        Ok(SuiteResult::new(duration, test_results)
+            .with_setup_duration(setup_duration)
+            .with_setup_rpc_calls(setup_rpc_calls)
+            .with_code_size(code_size)
+            .with_address(address)
+            .with_setup_attempts(setup.attempts))
+    }
+
+    /// Returns `true` if fail-fast is enabled and enough tests already failed
+    fn is_cancelled(&self) -> bool {
+        self.fail_fast.as_ref().map(|failed| failed.load(Ordering::Relaxed)).unwrap_or_default()
+    }
+
+    /// Signals a failed test to all runners sharing the fail-fast flag, the flag is raised once
+    /// `max_failures` tests failed if failures are counted
+    fn cancel(&self) {
+        if let Some(failed) = &self.fail_fast {
+            let stop = match &self.failures {
+                Some(failures) => failures.fetch_add(1, Ordering::Relaxed) + 1 >= self.max_failures,
+                None => true,
+            };
+            if stop {
+                failed.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Runs `setUpTest` before the test function `func` on a copy of the state after `setUp`.
+    ///
+    /// The logs, traces and labels of the call are added to `setup`. Returns the executor holding
+    /// the resulting state, or the reason `setUpTest` failed.
+    fn setup_test(
+        &self,
+        setup_test: &Function,
+        func: &Function,
+        setup: &mut TestSetup,
+    ) -> std::result::Result<Executor<DB>, String> {
+        let selector =
+            if setup_test.inputs.is_empty() { None } else { Some(func.short_signature()) };
+
+        let mut executor = self.executor.clone();
+        let (setup_logs, setup_traces, labels, used_ffi, used_cheatcodes, reason) =
+            match executor.setup_test(setup.address, selector) {
+                Ok(CallResult { traces, labels, logs, used_ffi, used_cheatcodes, .. }) => {
+                    (logs, traces, labels, used_ffi, used_cheatcodes, None)
+                }
+                Err(EvmError::Execution {
+                    traces,
+                    labels,
+                    logs,
+                    used_ffi,
+                    used_cheatcodes,
+                    reason,
+                    ..
+                }) => (
+                    logs,
+                    traces,
+                    labels,
+                    used_ffi,
+                    used_cheatcodes,
+                    Some(format!("setUpTest failed: {}", reason)),
+                ),
+                Err(err) => (
+                    Vec::new(),
+                    None,
+                    BTreeMap::new(),
+                    false,
+                    BTreeSet::new(),
+                    Some(format!("setUpTest failed: {}", err)),
+                ),
+            };
+        setup.logs.extend(setup_logs);
+        setup.traces.extend(setup_traces.map(|traces| (TraceKind::Setup, traces)).into_iter());
+        setup.labeled_addresses.extend(labels);
+        setup.used_ffi |= used_ffi;
+        setup.used_cheatcodes.extend(used_cheatcodes);
+
+        match reason {
+            Some(reason) => Err(reason),
+            None => Ok(executor),
+        }
     }
 
     #[tracing::instrument(name = "test", skip_all, fields(name = %func.signature(), %should_fail))]
     pub fn run_test(
         &self,
+        executor: &Executor<DB>,
         func: &Function,
         should_fail: bool,
         setup: TestSetup,
     ) -> Result<TestResult> {
-        let TestSetup { address, mut logs, mut traces, mut labeled_addresses, .. } = setup;
+        let TestSetup {
+            address,
+            mut logs,
+            mut traces,
+            mut labeled_addresses,
+            mut used_cheatcodes,
+            ..
+        } = setup;
 
         // Run unit test
         let start = Instant::now();
-        let (reverted, reason, gas, stipend, execution_traces, state_changeset) = match self
-            .executor
-            .call::<(), _, _>(self.sender, address, func.clone(), (), 0.into(), self.errors)
-        {
+        let (
+            reverted,
+            reason,
+            gas,
+            stipend,
+            execution_traces,
+            state_changeset,
+            used_ffi,
+            max_call_depth,
+            assertions,
+            gas_refund,
+            instructions,
+            returns,
+        ) = match executor.call::<Token, _, _>(
+            self.sender,
+            address,
+            func.clone(),
+            (),
+            0.into(),
+            self.errors,
+        ) {
             Ok(CallResult {
                 reverted,
+                result,
                 gas,
                 stipend,
                 logs: execution_logs,
                 traces: execution_trace,
                 labels: new_labels,
+                used_ffi,
+                used_cheatcodes: new_cheatcodes,
+                max_call_depth,
+                assertions,
+                gas_refund,
+                instructions,
                 state_changeset,
                 ..
             }) => {
                 labeled_addresses.extend(new_labels);
                 logs.extend(execution_logs);
-                (reverted, None, gas, stipend, execution_trace, state_changeset)
+                used_cheatcodes.extend(new_cheatcodes);
+                (
+                    reverted,
+                    None,
+                    gas,
+                    stipend,
+                    execution_trace,
+                    state_changeset,
+                    used_ffi,
+                    max_call_depth,
+                    assertions,
+                    gas_refund,
+                    instructions,
+                    ReturnValue::decode(func, result),
+                )
             }
             Err(EvmError::Execution {
                 reverted,
@@ -369,12 +1126,32 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
                 logs: execution_logs,
                 traces: execution_trace,
                 labels: new_labels,
+                used_ffi,
+                used_cheatcodes: new_cheatcodes,
+                max_call_depth,
+                assertions,
+                instructions,
                 state_changeset,
                 ..
             }) => {
                 labeled_addresses.extend(new_labels);
                 logs.extend(execution_logs);
-                (reverted, Some(reason), gas, stipend, execution_trace, state_changeset)
+                used_cheatcodes.extend(new_cheatcodes);
+                (
+                    reverted,
+                    Some(reason),
+                    gas,
+                    stipend,
+                    execution_trace,
+                    state_changeset,
+                    used_ffi,
+                    max_call_depth,
+                    assertions,
+                    // the refunds of reverted calls are void
+                    None,
+                    instructions,
+                    Vec::new(),
+                )
             }
             Err(err) => {
                 tracing::error!(?err);
@@ -383,12 +1160,51 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
         };
         traces.extend(execution_traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
 
-        let success = self.executor.is_success(
-            setup.address,
-            reverted,
-            state_changeset.expect("we should have a state changeset"),
-            should_fail,
-        );
+        let state_changeset = state_changeset.expect("we should have a state changeset");
+        let storage_diff = match self.storage_diff {
+            Some(addresses) if addresses.is_empty() => {
+                diff_storage(&executor.db, &state_changeset, address, self.storage_layout)
+            }
+            Some(addresses) => addresses
+                .iter()
+                .flat_map(|addr| {
+                    let layout = if *addr == address { self.storage_layout } else { None };
+                    diff_storage(&executor.db, &state_changeset, *addr, layout)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let line_hits = match (self.lines, instructions.as_ref()) {
+            (Some(lines), Some(hits)) => {
+                // the code of contracts the test created is only part of the changeset
+                let code_at = |address: &Address| {
+                    state_changeset
+                        .get(address)
+                        .and_then(|account| account.info.code.clone())
+                        .or_else(|| executor.db.basic(*address).code)
+                        .map(|code| code.to_vec())
+                };
+                Some(line_hits(hits, code_at, lines))
+            }
+            _ => None,
+        };
+
+        // a test that skipped itself reverted with the reason of the `skip` cheatcode
+        let skipped = reason.as_deref() == Some(SKIP_REASON);
+        let mut reason = reason;
+        let mut success =
+            skipped || executor.is_success(setup.address, reverted, state_changeset, should_fail);
+
+        if self.no_selfdestruct && !skipped {
+            if let Some(selfdestruct) = selfdestruct_frame(&traces, address) {
+                success = false;
+                reason = Some(selfdestruct);
+            }
+        }
+
+        let revert_path =
+            if success { Vec::new() } else { revert_path(&traces, TraceKind::Execution) };
 
         // Record test execution time
         tracing::debug!(
@@ -399,38 +1215,111 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
 
         Ok(TestResult {
             success,
+            skipped,
             reason,
-            counterexample: None,
             logs,
             kind: TestKind::Standard(gas.overflowing_sub(stipend).0),
             traces,
             labeled_addresses,
+            used_ffi: used_ffi || setup.used_ffi,
+            used_cheatcodes,
+            revert_path,
+            storage_diff,
+            duration: start.elapsed(),
+            max_call_depth,
+            assertions,
+            gas_refund,
+            line_hits,
+            returns,
+            ..Default::default()
         })
     }
 
     #[tracing::instrument(name = "fuzz-test", skip_all, fields(name = %func.signature(), %should_fail))]
     pub fn run_fuzz_test(
         &self,
+        executor: &Executor<DB>,
         func: &Function,
         should_fail: bool,
-        runner: TestRunner,
+        mut runner: TestRunner,
         setup: TestSetup,
     ) -> Result<TestResult> {
-        let TestSetup { address, mut logs, mut traces, mut labeled_addresses, .. } = setup;
+        let TestSetup {
+            address,
+            mut logs,
+            mut traces,
+            mut labeled_addresses,
+            mut used_cheatcodes,
+            ..
+        } = setup;
+
+        let mut configured_runs = None;
+        if let Some(budget) = self.fuzz_budget {
+            let configured = runner.config().cases;
+            let runs = budget.runs(configured);
+            if runs < configured {
+                let mut config: FuzzConfig = runner.config().clone();
+                config.cases = runs;
+                // keep the RNG so the inputs are the ones of the first runs without a deadline
+                let rng = runner.rng().clone();
+                runner = TestRunner::new_with_rng(config, rng);
+                configured_runs = Some(configured);
+            }
+        }
 
         // Run fuzz test
         let start = Instant::now();
-        let mut result = FuzzedExecutor::new(&self.executor, runner, self.sender).fuzz(
-            func,
-            address,
-            should_fail,
-            self.errors,
-        );
+        let mut result = FuzzedExecutor::new(executor, runner, self.sender)
+            .with_weights(self.fuzz_weights)
+            .with_address_pool_rate(self.fuzz_address_pool_rate)
+            .with_custom_strategies(self.fuzz_strategies.clone())
+            .with_fixtures(self.fuzz_fixtures(executor, func, address))
+            .fuzz(func, address, should_fail, self.errors);
+        if let Some(budget) = self.fuzz_budget {
+            budget.record(result.cases.cases().len(), start.elapsed());
+        }
 
         // Record logs, labels and traces
         logs.append(&mut result.logs);
         labeled_addresses.append(&mut result.labeled_addresses);
+        used_cheatcodes.append(&mut result.used_cheatcodes);
         traces.extend(result.traces.map(|traces| (TraceKind::Execution, traces)).into_iter());
+        // a fuzz test skips itself in its first case, which then fails with the skip reason
+        let skipped = !result.success && result.reason.as_deref() == Some(SKIP_REASON);
+        if skipped {
+            result.success = true;
+            result.counterexample = None;
+        }
+        let revert_path =
+            if result.success { Vec::new() } else { revert_path(&traces, TraceKind::Execution) };
+
+        let code_at = |address: &Address| {
+            result
+                .contract_code
+                .get(address)
+                .cloned()
+                .or_else(|| executor.db.basic(*address).code.map(|code| code.to_vec()))
+        };
+        let branch_coverage = match (self.branches, result.branches.as_ref()) {
+            (Some(branches), Some(hits)) => {
+                Some(BranchCoverage::new(hits, code_at, branches, address))
+            }
+            _ => None,
+        };
+        let line_hits = match (self.lines, result.instructions.as_ref()) {
+            (Some(lines), Some(hits)) => Some(line_hits(hits, code_at, lines)),
+            _ => None,
+        };
+        if let (Some(coverage), Some(min)) = (&branch_coverage, self.fuzz_min_coverage) {
+            if result.success && !skipped && coverage.percentage() < min {
+                result.success = false;
+                result.reason = Some(format!(
+                    "branch coverage of {:.2}% is below the minimum of {}%",
+                    coverage.percentage(),
+                    min
+                ));
+            }
+        }
 
         // Record test execution time
         tracing::debug!(
@@ -438,14 +1327,135 @@ impl<'a, DB: DatabaseRef + Send + Sync> ContractRunner<'a, DB> {
             success = %result.success
         );
 
+        let counterexample = result.counterexample.map(|counterexample| {
+            match self.abi_names.and_then(|names| names.function_inputs(func)) {
+                Some(names) => counterexample.with_names(names.to_vec()),
+                None => counterexample,
+            }
+        });
+
         Ok(TestResult {
             success: result.success,
+            skipped,
             reason: result.reason,
-            counterexample: result.counterexample,
+            counterexample,
             logs,
             kind: TestKind::Fuzz(result.cases),
             traces,
             labeled_addresses,
+            used_ffi: result.used_ffi || setup.used_ffi,
+            used_cheatcodes,
+            revert_path,
+            duration: start.elapsed(),
+            max_call_depth: result.max_call_depth,
+            assertions: result.assertions,
+            branch_coverage,
+            configured_runs,
+            gas_refund: result.gas_refund,
+            line_hits,
+            ..Default::default()
         })
     }
+
+    /// Reads the fixtures of the parameters of the fuzz test from the deployed test contract.
+    ///
+    /// The fixture of a parameter `amount` is either a `fixtureAmount()` function returning an
+    /// array or the getter of a public array `fixtureAmount`. Values that do not fit the type of
+    /// the parameter are skipped with a warning.
+    fn fuzz_fixtures(
+        &self,
+        executor: &Executor<DB>,
+        func: &Function,
+        address: Address,
+    ) -> FuzzFixtures {
+        let mut fixtures = FuzzFixtures::default();
+        for input in func.inputs.iter().filter(|input| !input.name.is_empty()) {
+            let name = fixture_name(&input.name);
+            let values = match self.contract.functions.get(&name).and_then(|overloads| {
+                overloads.iter().find_map(|fixture| self.read_fixture(executor, fixture, address))
+            }) {
+                Some(values) => values,
+                None => continue,
+            };
+            let total = values.len();
+            let values = values
+                .iter()
+                .filter_map(|value| fit_fixture(value, &input.kind))
+                .collect::<Vec<_>>();
+            if values.len() < total {
+                tracing::warn!(
+                    fixture = %name,
+                    param = %input.kind,
+                    skipped = total - values.len(),
+                    "fixture values do not fit the type of the parameter"
+                );
+            }
+            fixtures.insert(input.name.clone(), values);
+        }
+        fixtures
+    }
+
+    /// Returns the values of a fixture, if it is a function without inputs returning an array or
+    /// the getter of a public array
+    fn read_fixture(
+        &self,
+        executor: &Executor<DB>,
+        fixture: &Function,
+        address: Address,
+    ) -> Option<Vec<Token>> {
+        let call = |args: &[Token]| {
+            let calldata = fixture.encode_input(args).ok()?;
+            let call = executor.call_raw(self.sender, address, calldata.into(), 0.into()).ok()?;
+            if call.reverted {
+                return None
+            }
+            fixture.decode_output(&call.result).ok()?.pop()
+        };
+        match (fixture.inputs.as_slice(), fixture.outputs.as_slice()) {
+            ([], [_]) => match call(&[])? {
+                Token::Array(values) | Token::FixedArray(values) => Some(values),
+                _ => None,
+            },
+            ([index], [_]) if index.kind == ParamType::Uint(256) => {
+                // the getter of a public array reverts once the index is out of bounds
+                let values = (0..MAX_FIXTURE_VALUES)
+                    .map_while(|idx| call(&[Token::Uint(idx.into())]))
+                    .collect::<Vec<_>>();
+                (!values.is_empty()).then(|| values)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The maximum number of values read from the getter of a public array fixture
+const MAX_FIXTURE_VALUES: u64 = 1024;
+
+/// Describes the first call frame of the execution traces in which a contract other than the test
+/// contract at `address` executed `SELFDESTRUCT`, if any
+fn selfdestruct_frame(traces: &[(TraceKind, CallTraceArena)], address: Address) -> Option<String> {
+    traces
+        .iter()
+        .filter(|(kind, _)| *kind == TraceKind::Execution)
+        .flat_map(|(_, trace)| trace.arena.iter())
+        .find_map(|node| {
+            let beneficiary = node.trace.selfdestruct?;
+            (node.trace.address != address).then(|| {
+                format!(
+                    "SELFDESTRUCT in {:?} at call depth {}, beneficiary {:?} (--no-selfdestruct)",
+                    node.trace.address, node.trace.depth, beneficiary
+                )
+            })
+        })
+}
+
+/// Returns the revert path of the last trace of the given kind, see
+/// [CallTraceArena::revert_path()]
+fn revert_path(traces: &[(TraceKind, CallTraceArena)], kind: TraceKind) -> Vec<String> {
+    traces
+        .iter()
+        .rev()
+        .find(|(trace_kind, _)| *trace_kind == kind)
+        .map(|(_, trace)| trace.revert_path())
+        .unwrap_or_default()
 }
@@ -0,0 +1,191 @@
+use ethers::{
+    solc::artifacts::{Storage, StorageLayout},
+    types::{Address, U256},
+};
+use foundry_evm::executor::{DatabaseRef, StateChangeset};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A storage value that was changed by a test
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageChange {
+    /// The address of the changed contract
+    pub address: Address,
+    /// The changed slot
+    pub slot: U256,
+    /// The variable in the slot and its type, e.g. `owner (address)`, if it is known from the
+    /// storage layout of the contract
+    pub label: Option<String>,
+    /// The value before the test, only the bits of the variable if it is known
+    pub before: U256,
+    /// The value after the test, only the bits of the variable if it is known
+    pub after: U256,
+}
+
+impl fmt::Display for StorageChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.label {
+            Some(ref label) => write!(f, "{} (slot {:#x})", label, self.slot)?,
+            None => write!(f, "slot {:#x}", self.slot)?,
+        }
+        write!(f, ": {:#x} -> {:#x}", self.before, self.after)
+    }
+}
+
+/// Returns the storage values of `address` that differ between `db` and the `changeset`.
+///
+/// If the storage layout of the contract is given, slots that hold known variables are split into
+/// one change per changed variable, otherwise every changed slot is returned as is.
+pub fn diff_storage<DB: DatabaseRef>(
+    db: &DB,
+    changeset: &StateChangeset,
+    address: Address,
+    layout: Option<&StorageLayout>,
+) -> Vec<StorageChange> {
+    let account = match changeset.get(&address) {
+        Some(account) => account,
+        None => return Vec::new(),
+    };
+
+    let mut slots = account.storage.iter().collect::<Vec<_>>();
+    slots.sort_by_key(|(slot, _)| **slot);
+    slots
+        .into_iter()
+        .flat_map(|(slot, after)| {
+            decode_slot_change(address, *slot, db.storage(address, *slot), *after, layout)
+        })
+        .collect()
+}
+
+/// Decodes the change of a single slot against the storage layout
+fn decode_slot_change(
+    address: Address,
+    slot: U256,
+    before: U256,
+    after: U256,
+    layout: Option<&StorageLayout>,
+) -> Vec<StorageChange> {
+    if before == after {
+        return Vec::new()
+    }
+
+    let variables = layout
+        .map(|layout| {
+            layout.storage.iter().filter(|var| var.slot == slot.to_string()).collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let layout = match layout {
+        Some(layout) if !variables.is_empty() => layout,
+        _ => return vec![StorageChange { address, slot, label: None, before, after }],
+    };
+
+    variables
+        .into_iter()
+        .filter_map(|var| {
+            let (ty, size) = variable_type(layout, var);
+            let before = extract_bytes(before, var.offset, size);
+            let after = extract_bytes(after, var.offset, size);
+            (before != after).then(|| StorageChange {
+                address,
+                slot,
+                label: Some(format!("{} ({})", var.label, ty)),
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+/// Returns the name and the size in bytes of the type of the variable
+fn variable_type<'a>(layout: &'a StorageLayout, var: &'a Storage) -> (&'a str, usize) {
+    match layout.types.get(&var.storage_type) {
+        Some(ty) => (ty.label.as_str(), ty.number_of_bytes.parse().unwrap_or(32)),
+        None => (var.storage_type.as_str(), 32),
+    }
+}
+
+/// Extracts `size` bytes at the byte `offset` of a storage word, where variables packed into the
+/// same slot are stored
+fn extract_bytes(word: U256, offset: i64, size: usize) -> U256 {
+    let word = word >> (offset.max(0) as usize * 8);
+    if size >= 32 {
+        word
+    } else {
+        word & ((U256::one() << (size * 8)) - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> StorageLayout {
+        serde_json::from_value(serde_json::json!({
+            "storage": [
+                {
+                    "astId": 1,
+                    "contract": "A.sol:A",
+                    "label": "owner",
+                    "offset": 0,
+                    "slot": "0",
+                    "type": "t_address"
+                },
+                {
+                    "astId": 2,
+                    "contract": "A.sol:A",
+                    "label": "paused",
+                    "offset": 20,
+                    "slot": "0",
+                    "type": "t_bool"
+                },
+                {
+                    "astId": 3,
+                    "contract": "A.sol:A",
+                    "label": "count",
+                    "offset": 0,
+                    "slot": "1",
+                    "type": "t_uint256"
+                }
+            ],
+            "types": {
+                "t_address": { "encoding": "inplace", "label": "address", "numberOfBytes": "20" },
+                "t_bool": { "encoding": "inplace", "label": "bool", "numberOfBytes": "1" },
+                "t_uint256": { "encoding": "inplace", "label": "uint256", "numberOfBytes": "32" }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn decodes_packed_slot_changes() {
+        let layout = layout();
+        let address = Address::random();
+
+        // only `paused` changed in the packed slot
+        let owner = U256::from(0xbeef);
+        let changes =
+            decode_slot_change(address, 0.into(), owner, owner | U256::one() << 160, Some(&layout));
+        assert_eq!(
+            changes,
+            vec![StorageChange {
+                address,
+                slot: 0.into(),
+                label: Some("paused (bool)".to_string()),
+                before: 0.into(),
+                after: 1.into(),
+            }]
+        );
+
+        let changes = decode_slot_change(address, 1.into(), 1.into(), 2.into(), Some(&layout));
+        assert_eq!(changes[0].label.as_deref(), Some("count (uint256)"));
+        assert_eq!(changes[0].to_string(), "count (uint256) (slot 0x1): 0x1 -> 0x2");
+
+        // slots that are not in the layout, e.g. of mappings, are kept as is
+        let changes = decode_slot_change(address, 7.into(), 0.into(), 3.into(), Some(&layout));
+        assert_eq!(changes[0].label, None);
+        assert_eq!(changes[0].after, 3.into());
+
+        let changes = decode_slot_change(address, 1.into(), 2.into(), 2.into(), Some(&layout));
+        assert!(changes.is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+//! Parameterized test contracts.
+//!
+//! A test contract whose constructor takes arguments runs once for every variant declared with
+//! the `@custom:variant` NatSpec tag, followed by the name of the variant and the constructor
+//! arguments, separated by whitespace:
+//!
+//! ```solidity
+//! /// @custom:variant weth 0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2 18
+//! /// @custom:variant usdc 0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48 6
+//! contract VaultTest is DSTest {
+//!     constructor(address token, uint8 decimals) { .. }
+//! }
+//! ```
+//!
+//! Every variant is a separate suite, keyed by `<path>:<contract>[<variant>]`. Test contracts
+//! whose constructor takes arguments but that declare no variants are not run.
+use crate::natspec::contract_docs;
+use ethers::{
+    abi::{self, Constructor},
+    types::Bytes,
+};
+use foundry_utils::parse_tokens;
+
+/// The NatSpec tag that declares a variant of a test contract
+pub const VARIANT_TAG: &str = "@custom:variant";
+
+/// A set of constructor arguments a test contract is deployed with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteVariant {
+    /// The name of the variant, appended to the identifier of the suite
+    pub name: String,
+    /// The ABI-encoded constructor arguments
+    pub args: Bytes,
+}
+
+impl SuiteVariant {
+    /// Returns the identifier of the suite of this variant of the test contract `identifier`
+    pub fn suite_name(&self, identifier: &str) -> String {
+        format!("{}[{}]", identifier, self.name)
+    }
+}
+
+/// Returns the variants of the contract `name` in the given source code, in order of
+/// declaration. Variants whose arguments do not match the constructor and variants with a name
+/// that was already declared are skipped with a warning.
+pub fn parse_variants(source: &str, name: &str, constructor: &Constructor) -> Vec<SuiteVariant> {
+    let mut variants: Vec<SuiteVariant> = Vec::new();
+    for line in contract_docs(source, name) {
+        let mut tokens = line.split_whitespace().skip_while(|token| *token != VARIANT_TAG).skip(1);
+        let variant = match tokens.next() {
+            Some(variant) => variant.trim_end_matches("*/"),
+            None => continue,
+        };
+        let args = tokens.filter(|arg| *arg != "*/").collect::<Vec<_>>();
+        if variants.iter().any(|known| known.name == variant) {
+            tracing::warn!(contract = name, variant, "ignoring duplicate variant");
+            continue
+        }
+        match encode_args(constructor, &args) {
+            Ok(args) => variants.push(SuiteVariant { name: variant.to_string(), args }),
+            Err(err) => tracing::warn!(contract = name, variant, %err, "ignoring invalid variant"),
+        }
+    }
+    variants
+}
+
+/// ABI-encodes the arguments of the constructor
+fn encode_args(constructor: &Constructor, args: &[&str]) -> eyre::Result<Bytes> {
+    if args.len() != constructor.inputs.len() {
+        eyre::bail!("expected {} arguments, got {}", constructor.inputs.len(), args.len())
+    }
+    let params = constructor.inputs.iter().map(|input| &input.kind).zip(args.iter().copied());
+    let tokens = parse_tokens(params, true)?;
+    Ok(abi::encode(&tokens).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{Param, ParamType, Token};
+
+    #[test]
+    fn can_parse_variants() {
+        let source = r#"
+/// @notice Runs against every token
+/// @custom:variant small 1 0x0000000000000000000000000000000000000001
+/// @custom:variant large 1000 0x0000000000000000000000000000000000000002
+/// @custom:variant small 2 0x0000000000000000000000000000000000000003
+/// @custom:variant missing 3
+contract VaultTest is DSTest {
+    constructor(uint256 amount, address token) {}
+}
+"#;
+        let param = |name: &str, kind| Param { name: name.to_string(), kind, internal_type: None };
+        let constructor = Constructor {
+            inputs: vec![param("amount", ParamType::Uint(256)), param("token", ParamType::Address)],
+        };
+        let variants = parse_variants(source, "VaultTest", &constructor);
+        let names = variants.iter().map(|variant| variant.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["small", "large"]);
+        assert_eq!(
+            variants[1].args,
+            Bytes::from(abi::encode(&[
+                Token::Uint(1000.into()),
+                Token::Address(ethers::types::Address::from_low_u64_be(2)),
+            ]))
+        );
+        assert_eq!(
+            variants[0].suite_name("src/Vault.t.sol:VaultTest"),
+            "src/Vault.t.sol:VaultTest[small]"
+        );
+        assert!(parse_variants(source, "Missing", &constructor).is_empty());
+    }
+}
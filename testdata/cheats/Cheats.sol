@@ -60,6 +60,8 @@ interface Cheats {
     function label(address, string calldata) external;
     // If the condition is false, discard this run's fuzz inputs and generate new ones
     function assume(bool) external;
+    // If the condition is true, stop the test and mark it as skipped
+    function skip(bool) external;
     // Set nonce for an account
     function setNonce(address,uint64) external;
     // Get nonce for an account
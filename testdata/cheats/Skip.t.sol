@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "./Cheats.sol";
+
+contract SkipTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testSkipOffMainnet() public {
+        cheats.skip(block.chainid != 1);
+        assertTrue(false, "did not skip");
+    }
+
+    function testNoSkip() public {
+        cheats.skip(false);
+        assertTrue(true);
+    }
+
+    function testSkipFuzz(uint256 x) public {
+        cheats.skip(true);
+        assertEq(x, x + 1, "did not skip");
+    }
+}
@@ -20,3 +20,11 @@ contract AbstractTest is AbstractTestBase {
         fixture = new TestFixture();
     }
 }
+
+/// @notice Runs as part of the contracts that inherit it only
+/// @custom:abstract
+contract MarkedTestBase {
+    function testInherited() public {}
+}
+
+contract MarkedTest is MarkedTestBase {}
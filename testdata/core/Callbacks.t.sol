@@ -0,0 +1,38 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+interface DepositCallback {
+    function testOnDeposit(uint256 amount) external;
+}
+
+contract Vault {
+    uint256 public deposits;
+
+    function deposit(uint256 amount) external {
+        deposits += amount;
+        DepositCallback(msg.sender).testOnDeposit(amount);
+    }
+}
+
+contract CallbacksTest is DSTest {
+    Vault vault;
+    uint256 notified;
+
+    function setUp() public {
+        vault = new Vault();
+    }
+
+    /// @notice Called back by the vault, this is not a test
+    /// @custom:callback
+    function testOnDeposit(uint256 amount) external {
+        require(msg.sender == address(vault), "not the vault");
+        notified += amount;
+    }
+
+    function testDeposit() public {
+        vault.deposit(10);
+        assertEq(notified, 10);
+    }
+}
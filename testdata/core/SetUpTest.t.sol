@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract SetUpTestTest is DSTest {
+    uint256 setUpCalls;
+    uint256 setUpTestCalls;
+    bytes4 lastSelector;
+
+    function setUp() public {
+        setUpCalls += 1;
+    }
+
+    function setUpTest(bytes4 selector) public {
+        setUpTestCalls += 1;
+        lastSelector = selector;
+    }
+
+    function testSetUpTestRunsBeforeTest() public {
+        assertEq(setUpCalls, 1);
+        assertEq(setUpTestCalls, 1);
+        assertEq(lastSelector, this.testSetUpTestRunsBeforeTest.selector);
+    }
+
+    function testSetUpTestStateIsReset() public {
+        assertEq(setUpCalls, 1);
+        assertEq(setUpTestCalls, 1);
+        assertEq(lastSelector, this.testSetUpTestStateIsReset.selector);
+    }
+}
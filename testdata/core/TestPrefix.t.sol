@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+// Only runs with the `check_` and `prove_` test prefixes
+contract TestPrefixTest is DSTest {
+    function check_Passes() public {
+        assertTrue(true);
+    }
+
+    function check_FailReverts() public {
+        require(false, "expected");
+    }
+
+    function prove_Commutative(uint128 a, uint128 b) public {
+        assertEq(uint256(a) + b, uint256(b) + a);
+    }
+}
@@ -0,0 +1,14 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract BlockEnvTest is DSTest {
+    function testBaseFee() public {
+        assertEq(block.basefee, 10);
+    }
+
+    function testGasPrice() public {
+        assertEq(tx.gasprice, 20);
+    }
+}
@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "../cheats/Cheats.sol";
+
+interface Oracle {
+    function price() external view returns (uint256);
+}
+
+contract CallGuardTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testCallEtched() public {
+        Oracle oracle = Oracle(address(uint160(0xe7c4)));
+        // the runtime code returns the value of slot 0
+        cheats.etch(address(oracle), hex"60005460005260206000f3");
+        assertEq(oracle.price(), 0);
+    }
+
+    function testCallFixture() public {
+        Oracle(address(uint160(0xf1f1))).price();
+    }
+}
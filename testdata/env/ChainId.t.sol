@@ -0,0 +1,10 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract ChainIdTest is DSTest {
+    function testChainId() public {
+        assertEq(block.chainid, 1337);
+    }
+}
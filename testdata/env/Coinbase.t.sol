@@ -0,0 +1,10 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract CoinbaseTest is DSTest {
+    function testCoinbase() public {
+        assertEq(block.coinbase, address(uint160(0xc014ba5e)));
+    }
+}
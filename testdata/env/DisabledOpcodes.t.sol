@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract ChainIdReader {
+    function chainId() external view returns (uint256) {
+        return block.chainid;
+    }
+}
+
+contract DisabledOpcodesTest is DSTest {
+    ChainIdReader reader;
+
+    function setUp() public {
+        reader = new ChainIdReader();
+    }
+
+    function testChainId() public {
+        assertTrue(block.chainid > 0);
+    }
+
+    function testChainIdOfContract() public {
+        assertTrue(reader.chainId() > 0);
+    }
+
+    function testNoChainId() public {
+        assertTrue(address(reader) != address(0));
+    }
+}
@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+interface Oracle {
+    function price() external view returns (uint256);
+}
+
+contract FixturesTest is DSTest {
+    Oracle constant ORACLE = Oracle(address(uint160(0xf1f1)));
+
+    function testFixture() public {
+        assertEq(ORACLE.price(), 42);
+        assertEq(address(ORACLE).balance, 1000);
+    }
+}
@@ -0,0 +1,26 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract Lock {
+    uint256 public opened;
+
+    function open(uint256 code) external {
+        if (code == 0xdeadbeefcafe) {
+            opened += 1;
+        }
+    }
+}
+
+contract FuzzCoverageTest is DSTest {
+    Lock lock;
+
+    function setUp() public {
+        lock = new Lock();
+    }
+
+    function testOpen(uint256 code) public {
+        lock.open(code);
+    }
+}
@@ -0,0 +1,16 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract GasRefundTest is DSTest {
+    uint256 value = 1;
+
+    function testClear() public {
+        value = 0;
+    }
+
+    function testOverwrite() public {
+        value = 2;
+    }
+}
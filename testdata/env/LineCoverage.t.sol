@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract Tally {
+    uint256 public count;
+
+    function increment() external {
+        count += 1;
+    }
+
+    function reset() external {
+        count = 0;
+    }
+}
+
+contract LineCoverageTest is DSTest {
+    Tally tally;
+
+    function setUp() public {
+        tally = new Tally();
+    }
+
+    function testIncrement() public {
+        tally.increment();
+        tally.increment();
+    }
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract Burner {
+    uint256 public burned;
+
+    function burn() external {
+        while (true) {
+            burned += 1;
+        }
+    }
+}
+
+contract OutOfGasTest is DSTest {
+    Burner burner;
+
+    function setUp() public {
+        burner = new Burner();
+    }
+
+    function testStarvedCall() public {
+        burner.burn{gas: 50000}();
+    }
+
+    function testRevert() public {
+        revert("explicit revert");
+    }
+
+    function testCaughtOutOfGas() public {
+        try burner.burn{gas: 50000}() {} catch {}
+        revert("after the call");
+    }
+
+    function testCaughtOutOfGasEmptyRevert() public {
+        try burner.burn{gas: 50000}() {} catch {}
+        revert();
+    }
+}
+
+contract GasStipendTest is DSTest {
+    uint256[10] slots;
+
+    function testWritesSlots() public {
+        for (uint256 i = 0; i < 10; i++) {
+            slots[i] = i + 1;
+        }
+    }
+}
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract PrecompilesTest is DSTest {
+    // doubles the number it is called with, reverts without input
+    address constant DOUBLER = address(0x100);
+
+    function testPrecompile() public {
+        (bool success, bytes memory data) = DOUBLER.staticcall(abi.encode(21));
+        assertTrue(success);
+        assertEq(abi.decode(data, (uint256)), 42);
+    }
+
+    function testPrecompileRevert() public {
+        (bool success, ) = DOUBLER.staticcall("");
+        assertTrue(!success);
+    }
+}
@@ -0,0 +1,11 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract PrevrandaoTest is DSTest {
+    function testPrevrandao() public {
+        // `block.difficulty` reads the prevrandao after the merge
+        assertEq(block.difficulty, 0x5eed);
+    }
+}
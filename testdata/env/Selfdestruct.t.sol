@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract Destructible {
+    function destroy() external {
+        selfdestruct(payable(msg.sender));
+    }
+
+    function ping() external pure returns (bool) {
+        return true;
+    }
+}
+
+contract SelfdestructTest is DSTest {
+    function testDestroy() public {
+        new Destructible().destroy();
+    }
+
+    function testNoDestroy() public {
+        assertTrue(new Destructible().ping());
+    }
+}
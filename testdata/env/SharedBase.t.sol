@@ -0,0 +1,10 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+abstract contract SharedBase {
+    function testShared() public {}
+}
+
+contract SharedBaseA is SharedBase {}
+
+contract SharedBaseB is SharedBase {}
@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract OriginReader {
+    function origin() external view returns (address) {
+        return tx.origin;
+    }
+}
+
+contract TxOriginTest is DSTest {
+    // the configured `tx.origin`, distinct from the sender
+    address constant ORIGIN = address(0x1337);
+
+    function testTxOrigin() public {
+        assertEq(tx.origin, ORIGIN);
+        assertTrue(msg.sender != tx.origin);
+    }
+
+    function testTxOriginOfNestedCalls() public {
+        assertEq(new OriginReader().origin(), ORIGIN);
+    }
+}
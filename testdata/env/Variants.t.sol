@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+/// @custom:variant small 1
+/// @custom:variant large 1000
+contract VariantsTest is DSTest {
+    uint256 immutable amount;
+
+    constructor(uint256 _amount) {
+        amount = _amount;
+    }
+
+    function testAmount() public {
+        assertTrue(amount < 100);
+    }
+}
+
+contract NoVariantsTest is DSTest {
+    constructor(uint256) {}
+
+    function testNothing() public {}
+}
@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+interface ERC20 {
+    function totalSupply() external view returns (uint256);
+}
+
+/// @custom:fork-url https://mainnet.infura.io/v3/c60b0bb42f8a4c6481ecd229eddaca27
+/// @custom:fork-block 14000000
+contract ForkTest is DSTest {
+    ERC20 constant DAI = ERC20(0x6B175474E89094C44Da98b954EedeAC495271d0F);
+
+    function testForkBlock() public {
+        assertEq(block.number, 14000000);
+    }
+
+    function testReadForkedState() public {
+        assertGt(DAI.totalSupply(), 0);
+    }
+}
@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+import "../cheats/Cheats.sol";
+
+address constant DAI = 0x6B175474E89094C44Da98b954EedeAC495271d0F;
+// the `totalSupply` of DAI
+bytes32 constant SLOT = bytes32(uint256(1));
+bytes32 constant OVERWRITTEN = bytes32(uint256(0xdead));
+
+/// @custom:fork-url https://mainnet.infura.io/v3/c60b0bb42f8a4c6481ecd229eddaca27
+/// @custom:fork-block 14000000
+contract SharedForkOverwriteTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testOverwriteForkedSlot() public {
+        assertTrue(cheats.load(DAI, SLOT) != bytes32(0));
+        cheats.store(DAI, SLOT, OVERWRITTEN);
+        assertEq(cheats.load(DAI, SLOT), OVERWRITTEN);
+    }
+}
+
+/// @custom:fork-url https://mainnet.infura.io/v3/c60b0bb42f8a4c6481ecd229eddaca27
+/// @custom:fork-block 14000000
+contract SharedForkReadTest is DSTest {
+    Cheats constant cheats = Cheats(HEVM_ADDRESS);
+
+    function testReadOriginalSlot() public {
+        bytes32 supply = cheats.load(DAI, SLOT);
+        assertTrue(supply != bytes32(0));
+        assertTrue(supply != OVERWRITTEN);
+    }
+}
@@ -0,0 +1,28 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract FuzzFixturesTest is DSTest {
+    uint256[] public fixtureAmount;
+
+    function setUp() public {
+        fixtureAmount.push(1);
+        fixtureAmount.push(555);
+    }
+
+    // `300` does not fit into `uint8` and is skipped
+    function fixtureSmall() public pure returns (uint256[] memory small) {
+        small = new uint256[](2);
+        small[0] = 300;
+        small[1] = 7;
+    }
+
+    function testFixtureAmount(uint256 amount) public {
+        assertTrue(amount != 555);
+    }
+
+    function testFixtureSmall(uint8 small) public {
+        assertTrue(small != 7);
+    }
+}
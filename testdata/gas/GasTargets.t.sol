@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract GasTargetsTest is DSTest {
+    uint256 value;
+
+    /// @custom:gas-target 1000000 ±10%
+    function testWithinTarget() public {
+        value = 1;
+    }
+
+    /// @custom:gas-target 100
+    function testAboveTarget() public {
+        value = 2;
+    }
+
+    function testWithoutTarget() public {
+        value = 3;
+    }
+}
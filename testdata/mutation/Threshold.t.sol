@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract Threshold {
+    function exceeds(uint256 value) external pure returns (bool) {
+        return value > 10;
+    }
+}
+
+contract ThresholdTest is DSTest {
+    Threshold threshold;
+
+    function setUp() public {
+        threshold = new Threshold();
+    }
+
+    function testExceeds() public {
+        assertTrue(threshold.exceeds(11));
+        assertTrue(!threshold.exceeds(10));
+    }
+}
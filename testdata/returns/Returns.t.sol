@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract ReturnsTest is DSTest {
+    function testNoReturn() public {
+        assertTrue(true);
+    }
+
+    function testReturnsGas() public pure returns (uint256 gas, bool ok) {
+        gas = 21000;
+        ok = true;
+    }
+
+    function testReturnsUnnamed() public pure returns (string memory) {
+        return "table";
+    }
+
+    function testRevertsWithReturn() public pure returns (uint256) {
+        revert("no data");
+    }
+}
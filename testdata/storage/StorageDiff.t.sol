@@ -0,0 +1,16 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract StorageDiffTest is DSTest {
+    uint256 count;
+
+    function testIncrement() public {
+        count += 2;
+    }
+
+    function testRead() public {
+        assertEq(count, 0);
+    }
+}
@@ -0,0 +1,24 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract AssertionsTest is DSTest {
+    function testNoAssertions() public {}
+
+    function testAssertions() public {
+        assertEq(uint256(1), uint256(1));
+        assertTrue(true);
+        assertEq(uint256(2), uint256(2), "two");
+    }
+
+    function testAssertionsInLoop() public {
+        for (uint256 i = 0; i < 4; i++) {
+            assertTrue(i < 4);
+        }
+    }
+
+    function testFuzzAssertions(uint8 n) public {
+        assertTrue(n <= type(uint8).max);
+    }
+}
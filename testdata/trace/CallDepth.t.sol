@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: Unlicense
+pragma solidity >=0.8.0;
+
+import "ds-test/test.sol";
+
+contract Recursive {
+    function recurse(uint256 n) public {
+        if (n > 0) {
+            this.recurse(n - 1);
+        }
+    }
+}
+
+contract CallDepthTest is DSTest {
+    Recursive recursive;
+
+    function setUp() public {
+        recursive = new Recursive();
+    }
+
+    function testNoCalls() public {
+        assertTrue(true);
+    }
+
+    function testRecurse() public {
+        recursive.recurse(2);
+    }
+
+    function testRecurseFuzzed(uint8 n) public {
+        recursive.recurse(n % 4);
+    }
+}
@@ -14,11 +14,13 @@ use ethers_solc::{
     ArtifactId,
 };
 use eyre::{Result, WrapErr};
+use rayon::prelude::*;
 use serde::Deserialize;
 use std::{
     collections::{BTreeMap, HashSet},
     env::VarError,
     fmt,
+    str::FromStr,
 };
 
 use tokio::runtime::{Handle, Runtime};
@@ -134,21 +136,20 @@ pub fn link<T, U>(
         .map(|(i, c)| (i.slug(), c.clone()))
         .collect::<BTreeMap<String, CompactContractBytecode>>();
 
-    for (id, contract) in contracts.into_iter() {
-        let (abi, maybe_deployment_bytes, maybe_runtime) = (
-            contract.abi.as_ref(),
-            contract.bytecode.as_ref(),
-            contract.deployed_bytecode.as_ref(),
-        );
+    // Contracts are linked independently of each other, so they are linked in parallel. The
+    // linked contracts are collected in the order of their ids and passed to `post_link` in that
+    // order, so the result does not depend on the order in which linking finishes.
+    let linked = contracts
+        .into_par_iter()
+        .filter_map(|(id, contract)| {
+            let (abi, bytecode, runtime) =
+                (contract.abi?, contract.bytecode?, contract.deployed_bytecode?);
 
-        if let (Some(abi), Some(bytecode), Some(runtime)) =
-            (abi, maybe_deployment_bytes, maybe_runtime)
-        {
             // we are going to mutate, but library contract addresses may change based on
             // the test so we clone
             let mut target_bytecode = bytecode.clone();
-            let mut rt = runtime.clone();
-            let mut target_bytecode_runtime = rt.bytecode.expect("No target runtime").clone();
+            let mut rt = runtime;
+            let mut target_bytecode_runtime = rt.bytecode.take().expect("No target runtime");
 
             // instantiate a vector that gets filled with library deployment bytecode
             let mut dependencies = vec![];
@@ -169,23 +170,23 @@ pub fn link<T, U>(
                 BytecodeObject::Bytecode(ref bytes) => {
                     if bytes.as_ref().is_empty() {
                         // abstract, skip
-                        continue
+                        return None
                     }
                 }
             }
 
             rt.bytecode = Some(target_bytecode_runtime);
             let tc = CompactContractBytecode {
-                abi: Some(abi.clone()),
+                abi: Some(abi),
                 bytecode: Some(target_bytecode),
                 deployed_bytecode: Some(rt),
             };
+            Some((id, tc, dependencies))
+        })
+        .collect::<Vec<_>>();
 
-            let post_link_input =
-                PostLinkInput { contract: tc, known_contracts, id, extra, dependencies };
-
-            post_link(post_link_input)?;
-        }
+    for (id, contract, dependencies) in linked {
+        post_link(PostLinkInput { contract, known_contracts, id, extra, dependencies })?;
     }
     Ok(())
 }
@@ -289,33 +290,36 @@ impl<'a> IntoFunction for &'a str {
 pub fn flatten_known_contracts(
     contracts: &BTreeMap<ArtifactId, (Abi, Vec<u8>)>,
 ) -> (BTreeMap<[u8; 4], Function>, BTreeMap<H256, Event>, Abi) {
-    let flattened_funcs: BTreeMap<[u8; 4], Function> = contracts
-        .iter()
-        .flat_map(|(_name, (abi, _code))| {
-            abi.functions()
+    // The ABIs are processed in parallel and merged in the order of the contracts, so that later
+    // contracts take precedence regardless of the order in which processing finishes
+    let processed = contracts
+        .par_iter()
+        .map(|(_name, (abi, _code))| {
+            let functions = abi
+                .functions()
                 .map(|func| (func.short_signature(), func.clone()))
-                .collect::<BTreeMap<[u8; 4], Function>>()
-        })
-        .collect();
-
-    let flattened_events: BTreeMap<H256, Event> = contracts
-        .iter()
-        .flat_map(|(_name, (abi, _code))| {
-            abi.events()
+                .collect::<Vec<([u8; 4], Function)>>();
+            let events = abi
+                .events()
                 .map(|event| (event.signature(), event.clone()))
-                .collect::<BTreeMap<H256, Event>>()
+                .collect::<Vec<(H256, Event)>>();
+            (functions, events, abi.errors().cloned().collect::<Vec<_>>())
         })
-        .collect();
+        .collect::<Vec<_>>();
 
+    let mut flattened_funcs: BTreeMap<[u8; 4], Function> = BTreeMap::new();
+    let mut flattened_events: BTreeMap<H256, Event> = BTreeMap::new();
     // We need this for better revert decoding, and want it in abi form
     let mut errors_abi = Abi::default();
-    contracts.iter().for_each(|(_name, (abi, _code))| {
-        abi.errors().for_each(|error| {
+    for (functions, events, errors) in processed {
+        flattened_funcs.extend(functions);
+        flattened_events.extend(events);
+        errors.into_iter().for_each(|error| {
             let entry =
                 errors_abi.errors.entry(error.name.clone()).or_insert_with(Default::default);
-            entry.push(error.clone());
+            entry.push(error);
         });
-    });
+    }
     (flattened_funcs, flattened_events, errors_abi)
 }
 
@@ -735,35 +739,283 @@ pub fn format_tokens(tokens: &[Token]) -> impl Iterator<Item = String> + '_ {
 
 // Gets pretty print strings for tokens
 pub fn format_token(param: &Token) -> String {
-    match param {
-        Token::Address(addr) => format!("{:?}", addr),
-        Token::FixedBytes(bytes) => format!("0x{}", hex::encode(&bytes)),
-        Token::Bytes(bytes) => format!("0x{}", hex::encode(&bytes)),
-        Token::Int(mut num) => {
-            if num.bit(255) {
-                num = num - 1;
-                format!("-{}", num.overflowing_neg().0)
-            } else {
-                num.to_string()
+    OutputEncoding::default().token(param)
+}
+
+/// How addresses and bytes are printed in logs and traces, e.g. `checksum,base64`.
+///
+/// The default prints addresses in lowercase and bytes as `0x` prefixed hex.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputEncoding {
+    /// Print addresses with their EIP-55 checksum
+    pub checksum_addresses: bool,
+    /// Print bytes in base64 instead of hex
+    pub base64_bytes: bool,
+}
+
+impl OutputEncoding {
+    /// Formats the address in this encoding
+    pub fn address(&self, addr: &Address) -> String {
+        if self.checksum_addresses {
+            ethers_core::utils::to_checksum(addr, None)
+        } else {
+            format!("{:?}", addr)
+        }
+    }
+
+    /// Formats the bytes in this encoding
+    pub fn bytes(&self, bytes: &[u8]) -> String {
+        if self.base64_bytes {
+            base64::encode(bytes)
+        } else {
+            format!("0x{}", hex::encode(bytes))
+        }
+    }
+
+    /// Pretty prints the token with its addresses and bytes in this encoding
+    pub fn token(&self, token: &Token) -> String {
+        let tokens = |tokens: &[Token]| {
+            tokens.iter().map(|token| self.token(token)).collect::<Vec<_>>().join(", ")
+        };
+        match token {
+            Token::Address(addr) => self.address(addr),
+            Token::FixedBytes(bytes) => self.bytes(bytes),
+            Token::Bytes(bytes) => self.bytes(bytes),
+            Token::Int(num) => {
+                let mut num = *num;
+                if num.bit(255) {
+                    num = num - 1;
+                    format!("-{}", num.overflowing_neg().0)
+                } else {
+                    num.to_string()
+                }
             }
+            Token::Uint(num) => num.to_string(),
+            Token::Bool(b) => format!("{}", b),
+            Token::String(s) => format!("{:?}", s),
+            Token::FixedArray(elements) => format!("[{}]", tokens(elements)),
+            Token::Array(elements) => format!("[{}]", tokens(elements)),
+            Token::Tuple(elements) => format!("({})", tokens(elements)),
         }
-        Token::Uint(num) => num.to_string(),
-        Token::Bool(b) => format!("{}", b),
-        Token::String(s) => format!("{:?}", s),
-        Token::FixedArray(tokens) => {
-            let string = tokens.iter().map(format_token).collect::<Vec<String>>().join(", ");
-            format!("[{}]", string)
+    }
+
+    /// Pretty prints the token like [OutputEncoding::token()], but prints tuples with known
+    /// component names, i.e. structs, as `{name: value, ..}`, recursively
+    pub fn token_named(&self, token: &Token, names: &ParamNames) -> String {
+        match token {
+            Token::Tuple(tokens) if tokens.len() == names.components.len() => {
+                let fields = tokens
+                    .iter()
+                    .zip(&names.components)
+                    .map(|(token, names)| {
+                        if names.name.is_empty() {
+                            self.token_named(token, names)
+                        } else {
+                            format!("{}: {}", names.name, self.token_named(token, names))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", fields.join(", "))
+            }
+            // the components of an array of tuples are the components of its elements
+            Token::Array(tokens) | Token::FixedArray(tokens) if !names.components.is_empty() => {
+                let elements =
+                    tokens.iter().map(|token| self.token_named(token, names)).collect::<Vec<_>>();
+                format!("[{}]", elements.join(", "))
+            }
+            _ => self.token(token),
         }
-        Token::Array(tokens) => {
-            let string = tokens.iter().map(format_token).collect::<Vec<String>>().join(", ");
-            format!("[{}]", string)
+    }
+}
+
+impl FromStr for OutputEncoding {
+    type Err = eyre::Report;
+
+    /// Parses a comma separated list of `checksum` or `lowercase` for addresses and `hex` or
+    /// `base64` for bytes, omitted kinds keep their default
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut encoding = Self::default();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            match part.to_lowercase().as_str() {
+                "checksum" => encoding.checksum_addresses = true,
+                "lowercase" => encoding.checksum_addresses = false,
+                "hex" => encoding.base64_bytes = false,
+                "base64" => encoding.base64_bytes = true,
+                _ => eyre::bail!(
+                    "Unknown output encoding `{}`, expected `checksum`, `lowercase`, `hex` or \
+                     `base64`",
+                    part
+                ),
+            }
         }
-        Token::Tuple(tokens) => {
-            let string = tokens.iter().map(format_token).collect::<Vec<String>>().join(", ");
-            format!("({})", string)
+        Ok(encoding)
+    }
+}
+
+impl fmt::Display for OutputEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{}",
+            if self.checksum_addresses { "checksum" } else { "lowercase" },
+            if self.base64_bytes { "base64" } else { "hex" }
+        )
+    }
+}
+
+impl serde::Serialize for OutputEncoding {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for OutputEncoding {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The names of a parameter and of its tuple components.
+///
+/// ethabi only keeps the types of tuple components, their names are read from the JSON ABI
+/// instead, see [AbiNames].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParamNames {
+    /// The name of the parameter, this is empty for unnamed parameters
+    pub name: String,
+    /// The names of the tuple components, this is empty if the parameter is not a tuple or an
+    /// array of tuples
+    pub components: Vec<ParamNames>,
+}
+
+impl ParamNames {
+    /// Reads the names of a parameter of a JSON ABI
+    fn from_json(param: &serde_json::Value) -> Self {
+        let name = param.get("name").and_then(|name| name.as_str()).unwrap_or_default();
+        let components = param
+            .get("components")
+            .and_then(|components| components.as_array())
+            .map(|components| components.iter().map(Self::from_json).collect())
+            .unwrap_or_default();
+        Self { name: name.to_string(), components }
+    }
+}
+
+/// The names of the parameters of functions and events, including the fields of structs
+#[derive(Debug, Clone, Default)]
+pub struct AbiNames {
+    /// The names of the inputs of functions, by signature
+    functions: BTreeMap<String, Vec<ParamNames>>,
+    /// The names of the inputs of events, by signature
+    events: BTreeMap<String, Vec<ParamNames>>,
+}
+
+impl AbiNames {
+    /// Reads the parameter names of the functions and events in a JSON ABI, or in an artifact
+    /// with an `abi` field
+    pub fn from_json(abi: &serde_json::Value) -> Self {
+        let mut names = Self::default();
+        names.extend_from_json(abi);
+        names
+    }
+
+    /// Adds the parameter names of the functions and events in a JSON ABI, or in an artifact
+    /// with an `abi` field
+    pub fn extend_from_json(&mut self, abi: &serde_json::Value) {
+        let items = match abi.get("abi").unwrap_or(abi).as_array() {
+            Some(items) => items,
+            None => return,
+        };
+        for item in items {
+            let names = match item.get("type").and_then(|kind| kind.as_str()) {
+                Some("function") => &mut self.functions,
+                Some("event") => &mut self.events,
+                _ => continue,
+            };
+            let (name, inputs) = match (
+                item.get("name").and_then(|name| name.as_str()),
+                item.get("inputs").and_then(|inputs| inputs.as_array()),
+            ) {
+                (Some(name), Some(inputs)) => (name, inputs),
+                _ => continue,
+            };
+            let types = match inputs.iter().map(canonical_type).collect::<Option<Vec<_>>>() {
+                Some(types) => types,
+                None => continue,
+            };
+            names.insert(
+                format!("{}({})", name, types.join(",")),
+                inputs.iter().map(ParamNames::from_json).collect(),
+            );
+        }
+    }
+
+    /// Adds the parameter names of another ABI, these take precedence over the existing ones
+    pub fn merge(&mut self, other: AbiNames) {
+        self.functions.extend(other.functions);
+        self.events.extend(other.events);
+    }
+
+    /// Returns the names of the inputs of the function, if known
+    pub fn function_inputs(&self, func: &Function) -> Option<&[ParamNames]> {
+        let signature = input_signature(&func.name, func.inputs.iter().map(|param| &param.kind));
+        self.functions.get(&signature).map(Vec::as_slice)
+    }
+
+    /// Returns the names of the inputs of the event, if known
+    pub fn event_inputs(&self, event: &Event) -> Option<&[ParamNames]> {
+        let signature = input_signature(&event.name, event.inputs.iter().map(|param| &param.kind));
+        self.events.get(&signature).map(Vec::as_slice)
+    }
+}
+
+/// Returns the signature of a function or event without its outputs, e.g. `fill((address,uint256))`
+fn input_signature<'a>(name: &str, kinds: impl Iterator<Item = &'a ParamType>) -> String {
+    format!("{}({})", name, kinds.map(ToString::to_string).collect::<Vec<_>>().join(","))
+}
+
+/// Returns the canonical type of a parameter of a JSON ABI, e.g. `(address,uint256)[]` for an
+/// array of structs
+fn canonical_type(param: &serde_json::Value) -> Option<String> {
+    let kind = param.get("type")?.as_str()?;
+    match kind.strip_prefix("tuple") {
+        Some(suffix) => {
+            let components = param
+                .get("components")?
+                .as_array()?
+                .iter()
+                .map(canonical_type)
+                .collect::<Option<Vec<_>>>()?;
+            Some(format!("({}){}", components.join(","), suffix))
         }
+        None => Some(kind.to_string()),
+    }
+}
+
+/// Pretty prints tokens like [format_tokens()], but prints the fields of structs with their names
+/// if the names of the parameters are known, see [format_token_named()]
+pub fn format_tokens_named(tokens: &[Token], names: Option<&[ParamNames]>) -> Vec<String> {
+    match names.filter(|names| names.len() == tokens.len()) {
+        Some(names) => tokens
+            .iter()
+            .zip(names)
+            .map(|(token, names)| format_token_named(token, names))
+            .collect(),
+        None => format_tokens(tokens).collect(),
     }
 }
+
+/// Pretty prints a token like [format_token()], but prints tuples with known component names,
+/// i.e. structs, as `{name: value, ..}`, recursively
+pub fn format_token_named(token: &Token, names: &ParamNames) -> String {
+    OutputEncoding::default().token_named(token, names)
+}
 /// Reads the `ETHERSCAN_API_KEY` env variable
 pub fn etherscan_api_key() -> eyre::Result<String> {
     std::env::var("ETHERSCAN_API_KEY").map_err(|err| match err {
@@ -1216,4 +1468,82 @@ mod tests {
             abi_to_solidity(&contract_abi, "").unwrap()
         );
     }
+
+    #[test]
+    fn formats_struct_fields() {
+        let abi = serde_json::json!([{
+            "type": "function",
+            "name": "testOrder",
+            "inputs": [
+                {
+                    "name": "order",
+                    "type": "tuple",
+                    "components": [
+                        { "name": "amount", "type": "uint256" },
+                        {
+                            "name": "fills",
+                            "type": "tuple[]",
+                            "components": [
+                                { "name": "taker", "type": "address" },
+                                { "name": "ok", "type": "bool" }
+                            ]
+                        }
+                    ]
+                },
+                { "name": "", "type": "uint8" }
+            ],
+            "outputs": [],
+            "stateMutability": "nonpayable"
+        }]);
+        let names = AbiNames::from_json(&serde_json::json!({ "abi": abi }));
+        let fill = ParamType::Tuple(vec![ParamType::Address, ParamType::Bool]);
+        let order = ParamType::Tuple(vec![ParamType::Uint(256), ParamType::Array(Box::new(fill))]);
+        #[allow(deprecated)]
+        let func = Function {
+            name: "testOrder".to_string(),
+            inputs: [order, ParamType::Uint(8)]
+                .into_iter()
+                .map(|kind| Param { name: String::new(), kind, internal_type: None })
+                .collect(),
+            outputs: vec![],
+            constant: None,
+            state_mutability: abi::StateMutability::NonPayable,
+        };
+        let inputs = names.function_inputs(&func);
+        assert_eq!(inputs.unwrap().len(), 2);
+
+        let order = Token::Tuple(vec![
+            Token::Uint(5.into()),
+            Token::Array(vec![Token::Tuple(vec![
+                Token::Address(Address::zero()),
+                Token::Bool(true),
+            ])]),
+        ]);
+        let taker = format!("{:?}", Address::zero());
+        assert_eq!(
+            format_tokens_named(&[order.clone(), Token::Uint(1.into())], inputs),
+            vec![
+                format!("{{amount: 5, fills: [{{taker: {}, ok: true}}]}}", taker),
+                "1".to_string()
+            ]
+        );
+        // structs without known field names are formatted as plain tuples
+        assert_eq!(format_tokens_named(&[order], None), vec![format!("(5, [({}, true)])", taker)]);
+    }
+
+    #[test]
+    fn formats_in_output_encoding() {
+        let addr: Address = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed".parse().unwrap();
+        let default = OutputEncoding::default();
+        assert_eq!(default.address(&addr), "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(default.bytes(&[0xde, 0xad]), "0xdead");
+        assert_eq!("lowercase,hex".parse::<OutputEncoding>().unwrap(), default);
+
+        let encoding = "checksum, base64".parse::<OutputEncoding>().unwrap();
+        assert_eq!(encoding.address(&addr), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        assert_eq!(encoding.bytes(&[0xde, 0xad]), "3q0=");
+        assert_eq!(encoding.to_string(), "checksum,base64");
+        assert_eq!(encoding.token(&Token::Bytes(vec![0xde, 0xad])), "3q0=");
+        assert!("utf8".parse::<OutputEncoding>().is_err());
+    }
 }